@@ -1 +1,635 @@
+//! An interactive calculator for bitwise and arithmetic operations on
+//! integers, which can be entered in decimal, hexadecimal (`0x` prefix), or
+//! binary (`0b` prefix).
+//!
+//! `calculator` prompts for a single operation, while `repl` evaluates whole
+//! expressions, parsed by the `expression` module, one line at a time. Both
+//! take a `Config` choosing the width and signedness of the numbers, and what
+//! happens when a result doesn't fit (see the `integer` module).
 
+mod expression;
+mod integer;
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::num::IntErrorKind;
+
+use expression::evaluate;
+use integer::{shift_left, shift_right, with_integer_type, ArithmeticError, Integer};
+pub use integer::{Config, Overflow, Width};
+
+/// Every operation the calculator supports.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Operators {
+    And,
+    Or,
+    Xor,
+    Not,
+    Nand,
+    Nor,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operators {
+    /// Parses either the symbol for an operator (e.g. `&`) or its case
+    /// insensitive english name (e.g. `and`).
+    fn parse(input: &str) -> Result<Operators, InputError> {
+        let operator = match input.trim().to_lowercase().as_str() {
+            "&" | "and" => Operators::And,
+            "|" | "or" => Operators::Or,
+            "^" | "xor" => Operators::Xor,
+            "!" | "~" | "not" => Operators::Not,
+            "nand" => Operators::Nand,
+            "nor" => Operators::Nor,
+            "<<" | "shl" => Operators::ShiftLeft,
+            ">>" | "shr" => Operators::ShiftRight,
+            "+" | "add" | "plus" => Operators::Add,
+            "-" | "sub" | "minus" => Operators::Subtract,
+            "*" | "mul" | "times" => Operators::Multiply,
+            "/" | "div" => Operators::Divide,
+            "" => return Err(InputError::Empty),
+            other => {
+                return Err(InputError::UnknownOperator {
+                    input: other.to_owned(),
+                })
+            }
+        };
+        Ok(operator)
+    }
+
+    /// The symbol used when printing an expression with this operator.
+    fn symbol(self) -> &'static str {
+        match self {
+            Operators::And => "&",
+            Operators::Or => "|",
+            Operators::Xor => "^",
+            Operators::Not => "!",
+            Operators::Nand => "NAND",
+            Operators::Nor => "NOR",
+            Operators::ShiftLeft => "<<",
+            Operators::ShiftRight => ">>",
+            Operators::Add => "+",
+            Operators::Subtract => "-",
+            Operators::Multiply => "*",
+            Operators::Divide => "/",
+        }
+    }
+
+    /// Whether the operator only takes a single operand.
+    fn is_unary(self) -> bool {
+        matches!(self, Operators::Not)
+    }
+
+    /// Applies the operator to `lhs` and `rhs`. Unary operators ignore `rhs`.
+    ///
+    /// `overflow` decides what happens when arithmetic or a left shift gives
+    /// a result too large for `T`. Shifting right by the width of `T` or more
+    /// shifts every bit out, and shifting by a negative amount is an error.
+    fn apply<T: Integer>(self, lhs: T, rhs: T, overflow: Overflow) -> Result<T, ArithmeticError> {
+        let result = match self {
+            Operators::And => lhs & rhs,
+            Operators::Or => lhs | rhs,
+            Operators::Xor => lhs ^ rhs,
+            Operators::Not => !lhs,
+            Operators::Nand => !(lhs & rhs),
+            Operators::Nor => !(lhs | rhs),
+            Operators::ShiftLeft => {
+                let amount = rhs.shift_amount().ok_or(ArithmeticError::NegativeShift)?;
+                shift_left(lhs, amount, overflow)?
+            }
+            Operators::ShiftRight => {
+                let amount = rhs.shift_amount().ok_or(ArithmeticError::NegativeShift)?;
+                shift_right(lhs, amount)
+            }
+            Operators::Add => overflow.resolve(
+                lhs.checked_add(rhs),
+                || lhs.wrapping_add(rhs),
+                || lhs.saturating_add(rhs),
+            )?,
+            Operators::Subtract => overflow.resolve(
+                lhs.checked_sub(rhs),
+                || lhs.wrapping_sub(rhs),
+                || lhs.saturating_sub(rhs),
+            )?,
+            Operators::Multiply => overflow.resolve(
+                lhs.checked_mul(rhs),
+                || lhs.wrapping_mul(rhs),
+                || lhs.saturating_mul(rhs),
+            )?,
+            Operators::Divide => {
+                if rhs == T::ZERO {
+                    return Err(ArithmeticError::DivideByZero);
+                }
+                // The only division that can overflow is MIN / -1
+                overflow.resolve(
+                    lhs.checked_div(rhs),
+                    || lhs.wrapping_div(rhs),
+                    || lhs.saturating_div(rhs),
+                )?
+            }
+        };
+        Ok(result)
+    }
+}
+
+/// Why a number or operator entered by the user couldn't be parsed.
+#[derive(Debug, PartialEq)]
+pub enum InputError {
+    /// Nothing was entered.
+    Empty,
+    /// `input` has a digit that isn't valid in its base, e.g. `0b2`.
+    InvalidNumber { input: String, radix: u32 },
+    /// `input` is a valid number, but doesn't fit in the integer type being
+    /// used, which goes from `min` to `max`.
+    NumberOutOfRange {
+        input: String,
+        min: String,
+        max: String,
+    },
+    /// `input` isn't one of the supported operators.
+    UnknownOperator { input: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Empty => write!(f, "nothing was entered"),
+            InputError::InvalidNumber { input, radix } => {
+                write!(f, "{:?} is not a valid base {} number", input, radix)
+            }
+            InputError::NumberOutOfRange { input, min, max } => {
+                write!(
+                    f,
+                    "{:?} is out of range, numbers go from {} to {}",
+                    input, min, max
+                )
+            }
+            InputError::UnknownOperator { input } => {
+                write!(f, "{:?} is not a supported operation", input)
+            }
+        }
+    }
+}
+
+/// Parses a number written in decimal, hexadecimal (`0x`), or binary (`0b`).
+///
+/// Decimal numbers may be negative if `T` is signed. Hexadecimal and binary
+/// numbers give the bit pattern of the number instead, so `0xFF` is -1 as an
+/// `i8`.
+fn parse_number<T: Integer>(input: &str) -> Result<T, InputError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(InputError::Empty);
+    }
+
+    // Check for a base prefix, falling back to decimal
+    let (digits, radix) = if let Some(hex) = input.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(binary) = input.strip_prefix("0b") {
+        (binary, 2)
+    } else {
+        (input, 10)
+    };
+
+    let parsed = match radix {
+        10 => T::from_decimal(digits),
+        _ => T::from_bits(digits, radix),
+    };
+    parsed.map_err(|err| match err.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => InputError::NumberOutOfRange {
+            input: input.to_owned(),
+            min: T::MIN.to_string(),
+            max: T::MAX.to_string(),
+        },
+        // Everything else, including a prefix with no digits after it, is a
+        // malformed number
+        _ => InputError::InvalidNumber {
+            input: input.to_owned(),
+            radix,
+        },
+    })
+}
+
+/// Prints `prompt` and reads lines from `input` until one can be parsed by
+/// `parse`, explaining what was wrong with each invalid line. Returns `None`
+/// if `input` runs out first.
+fn prompt_until_valid<T>(
+    input: &mut impl BufRead,
+    prompt: &str,
+    parse: impl Fn(&str) -> Result<T, InputError>,
+) -> Option<T> {
+    println!("{}", prompt);
+
+    loop {
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            // Reading 0 bytes means the input was closed (e.g. by Ctrl-D)
+            Ok(0) => return None,
+            Ok(_) => {}
+            // The bad line has already been read past, so just ask again
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                println!("Invalid input: it isn't valid UTF-8. Please try again:");
+                continue;
+            }
+            Err(err) => {
+                println!("Failed to read input: {}", err);
+                return None;
+            }
+        }
+
+        match parse(&line) {
+            Ok(value) => return Some(value),
+            Err(err) => println!("Invalid input: {}. Please try again:", err),
+        }
+    }
+}
+
+/// Runs one calculation, reading the operands and operator from stdin. The
+/// second number is only asked for when the operator needs one. Invalid
+/// numbers and operators are asked for again rather than ending the program.
+pub fn calculator(config: Config) {
+    with_integer_type!(config, T => calculate::<T>(config.overflow))
+}
+
+fn calculate<T: Integer>(overflow: Overflow) {
+    let mut stdin = io::stdin().lock();
+    let Some(lhs) = prompt_until_valid(
+        &mut stdin,
+        "Please enter the first number:",
+        parse_number::<T>,
+    ) else {
+        return;
+    };
+    let Some(operator) = prompt_until_valid(
+        &mut stdin,
+        "Please enter the desired operation:",
+        Operators::parse,
+    ) else {
+        return;
+    };
+
+    if operator.is_unary() {
+        match operator.apply(lhs, T::ZERO, overflow) {
+            Ok(result) => println!("The result of {}{} is {}", operator.symbol(), lhs, result),
+            Err(err) => println!("Can't calculate {}{}: {}", operator.symbol(), lhs, err),
+        }
+        return;
+    }
+
+    let Some(rhs) = prompt_until_valid(
+        &mut stdin,
+        "Please enter the second number:",
+        parse_number::<T>,
+    ) else {
+        return;
+    };
+    match operator.apply(lhs, rhs, overflow) {
+        Ok(result) => println!(
+            "The result of {} {} {} is {}",
+            lhs,
+            operator.symbol(),
+            rhs,
+            result
+        ),
+        Err(err) => println!(
+            "Can't calculate {} {} {}: {}",
+            lhs,
+            operator.symbol(),
+            rhs,
+            err
+        ),
+    }
+}
+
+/// Repeatedly reads an expression such as `(0xFF & 0b1010) << 2 | 7` from
+/// stdin and prints its value, until stdin closes or the user enters `quit`.
+pub fn repl(config: Config) {
+    with_integer_type!(config, T => evaluate_lines::<T>(config.overflow))
+}
+
+fn evaluate_lines<T: Integer>(overflow: Overflow) {
+    println!("Enter an expression to evaluate, or \"quit\" to exit.");
+
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut input = String::new();
+        let bytes_read = match io::stdin().read_line(&mut input) {
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                println!("Error: the expression isn't valid UTF-8");
+                continue;
+            }
+            Err(err) => {
+                println!("Failed to read input: {}", err);
+                break;
+            }
+        };
+        let input = input.trim();
+        // Reading 0 bytes means stdin was closed (e.g. by Ctrl-D)
+        if bytes_read == 0 || input.eq_ignore_ascii_case("quit") {
+            break;
+        }
+        if input.is_empty() {
+            continue;
+        }
+
+        match evaluate::<T>(input, overflow) {
+            Ok(result) => println!("{} (0x{:X}, 0b{:b})", result, result, result),
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use crate::calculator::integer::ArithmeticError;
+    use crate::calculator::{parse_number, prompt_until_valid, InputError, Operators, Overflow};
+
+    #[test_case("0", 0 ; "decimal zero")]
+    #[test_case("12", 12 ; "decimal")]
+    #[test_case("4294967295", u32::MAX ; "decimal max")]
+    #[test_case("0x0", 0 ; "hex zero")]
+    #[test_case("0xF8", 248 ; "hex uppercase")]
+    #[test_case("0x3a", 58 ; "hex lowercase")]
+    #[test_case("0xFFFFFFFF", u32::MAX ; "hex max")]
+    #[test_case("0b0", 0 ; "binary zero")]
+    #[test_case("0b11011", 27 ; "binary")]
+    #[test_case("  0b10\n", 2 ; "surrounding whitespace")]
+    fn test_parse_number(input: &str, expected: u32) {
+        assert_eq!(parse_number::<u32>(input), Ok(expected));
+    }
+
+    #[test_case("0b2", "0b2", 2 ; "binary digit out of range")]
+    #[test_case("0xG", "0xG", 16 ; "hex digit out of range")]
+    #[test_case("abc", "abc", 10 ; "not a number")]
+    #[test_case("-1", "-1", 10 ; "negative")]
+    #[test_case("1.5", "1.5", 10 ; "decimal point")]
+    #[test_case("0x", "0x", 16 ; "hex prefix only")]
+    #[test_case("0b", "0b", 2 ; "binary prefix only")]
+    #[test_case(" 12 34\n", "12 34", 10 ; "inner whitespace")]
+    fn test_parse_number_invalid(input: &str, trimmed: &str, radix: u32) {
+        assert_eq!(
+            parse_number::<u32>(input),
+            Err(InputError::InvalidNumber {
+                input: String::from(trimmed),
+                radix
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_number_out_of_range_or_empty() {
+        assert_eq!(
+            parse_number::<u32>("4294967296"),
+            Err(out_of_range("4294967296", "0", "4294967295"))
+        );
+        assert_eq!(
+            parse_number::<u32>("0x1FFFFFFFF"),
+            Err(out_of_range("0x1FFFFFFFF", "0", "4294967295"))
+        );
+        assert_eq!(
+            parse_number::<i8>("-129"),
+            Err(out_of_range("-129", "-128", "127"))
+        );
+        assert_eq!(
+            parse_number::<i8>("128"),
+            Err(out_of_range("128", "-128", "127"))
+        );
+        assert_eq!(parse_number::<u32>(""), Err(InputError::Empty));
+        assert_eq!(parse_number::<u32>("  \n"), Err(InputError::Empty));
+    }
+
+    #[test]
+    fn test_parse_number_other_widths() {
+        assert_eq!(parse_number::<u8>("255"), Ok(u8::MAX));
+        assert_eq!(parse_number::<i8>("-128"), Ok(i8::MIN));
+        assert_eq!(parse_number::<i16>("-300"), Ok(-300));
+        // Hex and binary give a bit pattern, rather than a magnitude
+        assert_eq!(parse_number::<i8>("0xFF"), Ok(-1));
+        assert_eq!(parse_number::<i8>("0b10000000"), Ok(i8::MIN));
+        assert_eq!(
+            parse_number::<u128>(&format!("0x{}", "F".repeat(32))),
+            Ok(u128::MAX)
+        );
+        assert_eq!(
+            parse_number::<i8>("0x100"),
+            Err(out_of_range("0x100", "-128", "127"))
+        );
+        // Unsigned types don't accept negative numbers at all
+        assert_eq!(
+            parse_number::<u64>("-1"),
+            Err(InputError::InvalidNumber {
+                input: String::from("-1"),
+                radix: 10
+            })
+        );
+    }
+
+    fn out_of_range(input: &str, min: &str, max: &str) -> InputError {
+        InputError::NumberOutOfRange {
+            input: String::from(input),
+            min: String::from(min),
+            max: String::from(max),
+        }
+    }
+
+    #[test_case("&", Operators::And ; "and symbol")]
+    #[test_case("AND", Operators::And ; "and uppercase")]
+    #[test_case("and", Operators::And ; "and lowercase")]
+    #[test_case("|", Operators::Or ; "or symbol")]
+    #[test_case("Or", Operators::Or ; "or mixed case")]
+    #[test_case("^", Operators::Xor ; "xor symbol")]
+    #[test_case("xor", Operators::Xor ; "xor word")]
+    #[test_case("!", Operators::Not ; "not symbol")]
+    #[test_case("~", Operators::Not ; "not tilde")]
+    #[test_case("NOT", Operators::Not ; "not word")]
+    #[test_case("nand", Operators::Nand ; "nand")]
+    #[test_case("NOR", Operators::Nor ; "nor")]
+    #[test_case("<<", Operators::ShiftLeft ; "shift left symbol")]
+    #[test_case("shl", Operators::ShiftLeft ; "shift left word")]
+    #[test_case(">>", Operators::ShiftRight ; "shift right symbol")]
+    #[test_case("SHR", Operators::ShiftRight ; "shift right word")]
+    #[test_case("+", Operators::Add ; "add symbol")]
+    #[test_case("plus", Operators::Add ; "add word")]
+    #[test_case("-", Operators::Subtract ; "subtract symbol")]
+    #[test_case("minus", Operators::Subtract ; "subtract word")]
+    #[test_case("*", Operators::Multiply ; "multiply symbol")]
+    #[test_case("times", Operators::Multiply ; "multiply word")]
+    #[test_case("/", Operators::Divide ; "divide symbol")]
+    #[test_case(" div\n", Operators::Divide ; "divide word with whitespace")]
+    fn test_parse_operator(input: &str, expected: Operators) {
+        assert_eq!(Operators::parse(input), Ok(expected));
+    }
+
+    #[test_case("&&", "&&" ; "doubled symbol")]
+    #[test_case("modulo", "modulo" ; "unsupported")]
+    #[test_case(" MODULO\n", "modulo" ; "reported lowercase and trimmed")]
+    fn test_parse_operator_invalid(input: &str, reported: &str) {
+        assert_eq!(
+            Operators::parse(input),
+            Err(InputError::UnknownOperator {
+                input: String::from(reported)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_operator_empty() {
+        assert_eq!(Operators::parse(""), Err(InputError::Empty));
+    }
+
+    #[test]
+    fn test_prompt_until_valid() {
+        // Invalid lines are skipped until a valid one comes along
+        let mut input = "abc\n0b2\n\n4294967296\n0x2A\n7\n".as_bytes();
+        assert_eq!(
+            prompt_until_valid(&mut input, "number:", parse_number::<u32>),
+            Some(42)
+        );
+        // The rest of the input is left for the next prompt
+        assert_eq!(
+            prompt_until_valid(&mut input, "number:", parse_number::<u32>),
+            Some(7)
+        );
+        assert_eq!(
+            prompt_until_valid(&mut input, "number:", parse_number::<u32>),
+            None
+        );
+
+        let mut input = "%\nxor\n".as_bytes();
+        assert_eq!(
+            prompt_until_valid(&mut input, "operation:", Operators::parse),
+            Some(Operators::Xor)
+        );
+
+        // A line that isn't UTF-8 is asked for again instead of crashing
+        let mut input = &b"\xff\xfe\n7\n"[..];
+        assert_eq!(
+            prompt_until_valid(&mut input, "number:", parse_number::<u32>),
+            Some(7)
+        );
+
+        // Running out of input without a valid line gives up
+        let mut input = "nope\n0b2".as_bytes();
+        assert_eq!(
+            prompt_until_valid(&mut input, "number:", parse_number::<u32>),
+            None
+        );
+    }
+
+    #[test_case(Operators::And, 0b10, 0b11011, 2 ; "and")]
+    #[test_case(Operators::Or, 0xF8, 0x3A, 250 ; "or")]
+    #[test_case(Operators::Xor, 12, 32, 44 ; "xor")]
+    #[test_case(Operators::Nand, 0b1100, 0b1010, !0b1000 ; "nand")]
+    #[test_case(Operators::Nor, 0b1100, 0b1010, !0b1110 ; "nor")]
+    #[test_case(Operators::ShiftLeft, 1, 4, 16 ; "shift left")]
+    #[test_case(Operators::ShiftLeft, 1, 32, 0 ; "shift left past width")]
+    #[test_case(Operators::ShiftRight, 0xF0, 4, 0xF ; "shift right")]
+    #[test_case(Operators::ShiftRight, u32::MAX, 40, 0 ; "shift right past width")]
+    #[test_case(Operators::Add, 2, 3, 5 ; "add")]
+    #[test_case(Operators::Add, u32::MAX, 1, 0 ; "add wraps")]
+    #[test_case(Operators::Subtract, 10, 4, 6 ; "subtract")]
+    #[test_case(Operators::Subtract, 0, 1, u32::MAX ; "subtract wraps")]
+    #[test_case(Operators::Multiply, 6, 7, 42 ; "multiply")]
+    #[test_case(Operators::Divide, 42, 5, 8 ; "divide rounds down")]
+    fn test_apply_binary(operator: Operators, lhs: u32, rhs: u32, expected: u32) {
+        assert!(!operator.is_unary());
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Wrapping), Ok(expected));
+    }
+
+    #[test]
+    fn test_apply_unary() {
+        assert!(Operators::Not.is_unary());
+        assert_eq!(
+            Operators::Not.apply(0_u32, 0, Overflow::Wrapping),
+            Ok(u32::MAX)
+        );
+        // The second operand is ignored
+        assert_eq!(
+            Operators::Not.apply(0xFFFF_0000_u32, 123, Overflow::Wrapping),
+            Ok(0x0000_FFFF)
+        );
+        assert_eq!(Operators::Not.apply(5_i8, 0, Overflow::Checked), Ok(-6));
+    }
+
+    #[test]
+    fn test_divide_by_zero() {
+        for overflow in [Overflow::Wrapping, Overflow::Checked, Overflow::Saturating] {
+            assert_eq!(
+                Operators::Divide.apply(1_u32, 0, overflow),
+                Err(ArithmeticError::DivideByZero)
+            );
+        }
+    }
+
+    #[test_case(Operators::Add, 250, 10, Ok(4), Err(ArithmeticError::Overflow), Ok(u8::MAX) ; "add")]
+    #[test_case(Operators::Subtract, 3, 5, Ok(254), Err(ArithmeticError::Overflow), Ok(0) ; "subtract")]
+    #[test_case(Operators::Multiply, 16, 17, Ok(16), Err(ArithmeticError::Overflow), Ok(u8::MAX) ; "multiply")]
+    #[test_case(Operators::ShiftLeft, 0b1100_0000, 1, Ok(0b1000_0000), Err(ArithmeticError::Overflow), Ok(u8::MAX) ; "shift left")]
+    #[test_case(Operators::ShiftLeft, 1, 8, Ok(0), Err(ArithmeticError::Overflow), Ok(u8::MAX) ; "shift left past width")]
+    #[test_case(Operators::Add, 200, 55, Ok(255), Ok(255), Ok(255) ; "add without overflow")]
+    fn test_apply_overflow_u8(
+        operator: Operators,
+        lhs: u8,
+        rhs: u8,
+        wrapping: Result<u8, ArithmeticError>,
+        checked: Result<u8, ArithmeticError>,
+        saturating: Result<u8, ArithmeticError>,
+    ) {
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Wrapping), wrapping);
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Checked), checked);
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Saturating), saturating);
+    }
+
+    #[test_case(Operators::Add, 100, 100, Ok(-56), Err(ArithmeticError::Overflow), Ok(i8::MAX) ; "add")]
+    #[test_case(Operators::Subtract, -100, 100, Ok(56), Err(ArithmeticError::Overflow), Ok(i8::MIN) ; "subtract")]
+    #[test_case(Operators::Multiply, -64, 3, Ok(64), Err(ArithmeticError::Overflow), Ok(i8::MIN) ; "multiply")]
+    #[test_case(Operators::Divide, i8::MIN, -1, Ok(i8::MIN), Err(ArithmeticError::Overflow), Ok(i8::MAX) ; "divide min by minus one")]
+    #[test_case(Operators::Divide, -7, 2, Ok(-3), Ok(-3), Ok(-3) ; "divide rounds toward zero")]
+    #[test_case(Operators::ShiftLeft, 0b0100_0000, 1, Ok(i8::MIN), Err(ArithmeticError::Overflow), Ok(i8::MAX) ; "shift left into sign bit")]
+    #[test_case(Operators::ShiftLeft, -1, 7, Ok(i8::MIN), Ok(i8::MIN), Ok(i8::MIN) ; "shift left negative")]
+    #[test_case(Operators::ShiftRight, -128, 3, Ok(-16), Ok(-16), Ok(-16) ; "shift right keeps sign")]
+    #[test_case(Operators::ShiftRight, -128, 100, Ok(-1), Ok(-1), Ok(-1) ; "shift right past width")]
+    #[test_case(Operators::ShiftLeft, 1, -1, Err(ArithmeticError::NegativeShift), Err(ArithmeticError::NegativeShift), Err(ArithmeticError::NegativeShift) ; "negative shift")]
+    fn test_apply_overflow_i8(
+        operator: Operators,
+        lhs: i8,
+        rhs: i8,
+        wrapping: Result<i8, ArithmeticError>,
+        checked: Result<i8, ArithmeticError>,
+        saturating: Result<i8, ArithmeticError>,
+    ) {
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Wrapping), wrapping);
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Checked), checked);
+        assert_eq!(operator.apply(lhs, rhs, Overflow::Saturating), saturating);
+    }
+
+    #[test]
+    fn test_apply_wide() {
+        assert_eq!(
+            Operators::Add.apply(u64::from(u32::MAX), 1, Overflow::Checked),
+            Ok(1 << 32)
+        );
+        assert_eq!(
+            Operators::ShiftLeft.apply(1_u128, 127, Overflow::Checked),
+            Ok(1 << 127)
+        );
+        assert_eq!(
+            Operators::Multiply.apply(i128::MAX, 2, Overflow::Saturating),
+            Ok(i128::MAX)
+        );
+        assert_eq!(
+            Operators::Subtract.apply(0_i16, 1, Overflow::Checked),
+            Ok(-1)
+        );
+    }
+}
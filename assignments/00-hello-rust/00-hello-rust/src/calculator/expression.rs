@@ -0,0 +1,481 @@
+//! Parses and evaluates whole expressions such as `(0xFF & 0b1010) << 2 | 7`.
+//!
+//! Parsing uses Pratt parsing (top-down operator precedence): every binary
+//! operator has a precedence, and an operator on the right only gets to
+//! take the current operand if it binds tighter than the one on the left.
+//! The precedence matches C, from loosest to tightest:
+//!
+//! | Operators      | Precedence    |
+//! | -------------- | ------------- |
+//! | `\|`, `nor`    | 1             |
+//! | `^`            | 2             |
+//! | `&`, `nand`    | 3             |
+//! | `<<`, `>>`     | 4             |
+//! | `+`, `-`       | 5             |
+//! | `*`, `/`       | 6             |
+//! | `!`, `-` (prefix) | 7          |
+//!
+//! All binary operators are left associative, so `8 - 2 - 1` is `5`. A prefix
+//! `-` right before a decimal number makes a negative number when the integer
+//! type is signed, and otherwise subtracts from zero.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::str::CharIndices;
+
+use super::integer::{ArithmeticError, Integer, Overflow};
+use super::{parse_number, InputError, Operators};
+
+/// Why an expression couldn't be evaluated. Indices are byte offsets into the
+/// expression.
+#[derive(Debug, PartialEq)]
+pub enum ExpressionError {
+    /// Something that looked like a number but isn't valid in its base, or
+    /// doesn't fit in the integer type.
+    InvalidNumber { index: usize, error: InputError },
+    /// A word or symbol that isn't an operator.
+    UnknownOperator { index: usize, text: String },
+    /// A token that can't appear where it did, like a second operator in a
+    /// row or an operand right after another operand.
+    UnexpectedToken { index: usize, text: String },
+    /// The expression ended while an operand was still expected.
+    UnexpectedEnd,
+    /// A `(` without a matching `)`.
+    UnclosedParenthesis { index: usize },
+    /// An operation without a result, like division by zero.
+    Arithmetic(ArithmeticError),
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::InvalidNumber { index, error } => {
+                write!(f, "{error} at position {index}")
+            }
+            ExpressionError::UnknownOperator { index, text } => {
+                write!(f, "unknown operator {text:?} at position {index}")
+            }
+            ExpressionError::UnexpectedToken { index, text } => {
+                write!(f, "unexpected {text:?} at position {index}")
+            }
+            ExpressionError::UnexpectedEnd => write!(f, "expression ended unexpectedly"),
+            ExpressionError::UnclosedParenthesis { index } => {
+                write!(f, "parenthesis at position {index} is never closed")
+            }
+            ExpressionError::Arithmetic(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<ArithmeticError> for ExpressionError {
+    fn from(err: ArithmeticError) -> Self {
+        ExpressionError::Arithmetic(err)
+    }
+}
+
+/// Numbers are kept as text until the parser knows what they mean, since the
+/// lexer can't tell whether a `-` in front of one is part of it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TokenKind {
+    Number,
+    Operator(Operators),
+    OpenParen,
+    CloseParen,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    index: usize,
+    text: &'a str,
+}
+
+/// Splits an expression into numbers, operators, and parentheses.
+struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            chars: input.char_indices().peekable(),
+        }
+    }
+
+    /// Consumes characters while `pred` holds, returning the byte offset just
+    /// past the last one.
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) -> usize {
+        while self.chars.next_if(|&(_, c)| pred(c)).is_some() {}
+        self.chars.peek().map_or(self.input.len(), |&(idx, _)| idx)
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, ExpressionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.eat_while(char::is_whitespace);
+        let (start, first) = self.chars.next()?;
+
+        let (kind, end) = if first.is_ascii_digit() {
+            // Take letters too, so "0x1F" and typos like "12ab" are one token
+            (
+                TokenKind::Number,
+                self.eat_while(|c| c.is_ascii_alphanumeric()),
+            )
+        } else {
+            let end = match first {
+                '(' | ')' => start + 1,
+                c if c.is_alphabetic() => self.eat_while(char::is_alphabetic),
+                // Shifts are the only operators made of two symbols
+                '<' | '>' => {
+                    self.chars.next_if(|&(_, c)| c == first);
+                    self.eat_while(|_| false)
+                }
+                _ => start + first.len_utf8(),
+            };
+            let text = &self.input[start..end];
+            let kind = match text {
+                "(" => TokenKind::OpenParen,
+                ")" => TokenKind::CloseParen,
+                _ => match Operators::parse(text) {
+                    Ok(operator) => TokenKind::Operator(operator),
+                    Err(_) => {
+                        let text = text.to_owned();
+                        return Some(Err(ExpressionError::UnknownOperator { index: start, text }));
+                    }
+                },
+            };
+            (kind, end)
+        };
+
+        Some(Ok(Token {
+            kind,
+            index: start,
+            text: &self.input[start..end],
+        }))
+    }
+}
+
+/// How tightly a binary operator holds on to its operands, as (left, right)
+/// binding powers. The right one is higher to make operators left
+/// associative. Returns `None` for operators that can't be used in between
+/// two operands.
+fn infix_binding_power(operator: Operators) -> Option<(u8, u8)> {
+    let power = match operator {
+        Operators::Or | Operators::Nor => 1,
+        Operators::Xor => 2,
+        Operators::And | Operators::Nand => 3,
+        Operators::ShiftLeft | Operators::ShiftRight => 4,
+        Operators::Add | Operators::Subtract => 5,
+        Operators::Multiply | Operators::Divide => 6,
+        Operators::Not => return None,
+    };
+    Some((power * 2 - 1, power * 2))
+}
+
+/// Binds tighter than every binary operator, so `!1 + 1` is `(!1) + 1`.
+const PREFIX_BINDING_POWER: u8 = 15;
+
+struct Parser<'a, T> {
+    tokens: Peekable<Lexer<'a>>,
+    overflow: Overflow,
+    integer: PhantomData<T>,
+}
+
+impl<'a, T: Integer> Parser<'a, T> {
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, ExpressionError> {
+        self.tokens.next().transpose()
+    }
+
+    fn peek_token(&mut self) -> Result<Option<Token<'a>>, ExpressionError> {
+        match self.tokens.peek() {
+            Some(Ok(token)) => Ok(Some(*token)),
+            // Consume the error so it can be returned by value
+            Some(Err(_)) => self.next_token(),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses and evaluates operators until reaching one that binds no
+    /// tighter than `min_power`, which is left for the caller.
+    fn expression(&mut self, min_power: u8) -> Result<T, ExpressionError> {
+        let mut lhs = self.operand()?;
+
+        while let Some(token) = self.peek_token()? {
+            let operator = match token.kind {
+                TokenKind::Operator(operator) => operator,
+                TokenKind::CloseParen => break,
+                TokenKind::Number | TokenKind::OpenParen => return Err(unexpected(token)),
+            };
+            let (left_power, right_power) =
+                infix_binding_power(operator).ok_or_else(|| unexpected(token))?;
+            if left_power < min_power {
+                break;
+            }
+
+            self.next_token()?;
+            let rhs = self.expression(right_power)?;
+            lhs = operator.apply(lhs, rhs, self.overflow)?;
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parses a number, a prefix operator and its operand, or a parenthesized
+    /// expression.
+    fn operand(&mut self) -> Result<T, ExpressionError> {
+        let token = self.next_token()?.ok_or(ExpressionError::UnexpectedEnd)?;
+        match token.kind {
+            TokenKind::Number => number(token.text, token.index),
+            TokenKind::Operator(Operators::Subtract) => {
+                // Reading "-128" as one number lets it be the smallest i8,
+                // even though 128 on its own doesn't fit
+                if let Some(next) = self.peek_token()? {
+                    let negative = format!("-{}", next.text);
+                    let adjacent = next.index == token.index + 1;
+                    if let (TokenKind::Number, true, Ok(num)) =
+                        (next.kind, adjacent, parse_number(&negative))
+                    {
+                        self.next_token()?;
+                        return Ok(num);
+                    }
+                }
+                let operand = self.expression(PREFIX_BINDING_POWER)?;
+                Ok(Operators::Subtract.apply(T::ZERO, operand, self.overflow)?)
+            }
+            TokenKind::Operator(operator) if operator.is_unary() => {
+                let operand = self.expression(PREFIX_BINDING_POWER)?;
+                Ok(operator.apply(operand, T::ZERO, self.overflow)?)
+            }
+            TokenKind::OpenParen => {
+                let inner = self.expression(0)?;
+                match self.next_token()? {
+                    Some(Token {
+                        kind: TokenKind::CloseParen,
+                        ..
+                    }) => Ok(inner),
+                    Some(token) => Err(unexpected(token)),
+                    None => Err(ExpressionError::UnclosedParenthesis { index: token.index }),
+                }
+            }
+            TokenKind::Operator(_) | TokenKind::CloseParen => Err(unexpected(token)),
+        }
+    }
+}
+
+fn number<T: Integer>(text: &str, index: usize) -> Result<T, ExpressionError> {
+    parse_number(text).map_err(|error| ExpressionError::InvalidNumber { index, error })
+}
+
+fn unexpected(token: Token) -> ExpressionError {
+    ExpressionError::UnexpectedToken {
+        index: token.index,
+        text: token.text.to_owned(),
+    }
+}
+
+/// Evaluates an expression of numbers (in any base the calculator accepts),
+/// operators, and parentheses, as `T`s. `overflow` decides what happens when
+/// an operation's result doesn't fit in `T`.
+pub fn evaluate<T: Integer>(expression: &str, overflow: Overflow) -> Result<T, ExpressionError> {
+    let mut parser = Parser {
+        tokens: Lexer::new(expression).peekable(),
+        overflow,
+        integer: PhantomData,
+    };
+    let result = parser.expression(0)?;
+
+    // A leftover token can only be a `)` that was never opened, since the
+    // loop in `expression` rejects everything else
+    match parser.next_token()? {
+        Some(token) => Err(unexpected(token)),
+        None => Ok(result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{evaluate, ArithmeticError, ExpressionError, InputError, Overflow};
+
+    #[test_case("42", 42 ; "single number")]
+    #[test_case("0x2A", 42 ; "single hex number")]
+    #[test_case("0b101010", 42 ; "single binary number")]
+    #[test_case("12 ^ 32", 44 ; "xor")]
+    #[test_case("0b10 and 0b11011", 2 ; "word operator")]
+    #[test_case("0xF8|0x3A", 250 ; "no whitespace")]
+    #[test_case("(0xFF & 0b1010) << 2 | 7", 47 ; "readme example")]
+    #[test_case("1 + 2 * 3", 7 ; "multiply before add")]
+    #[test_case("(1 + 2) * 3", 9 ; "parentheses")]
+    #[test_case("1 << 2 + 1", 8 ; "add before shift")]
+    #[test_case("6 & 3 | 8", 10 ; "and before or")]
+    #[test_case("1 | 6 ^ 3", 5 ; "xor before or")]
+    #[test_case("12 & 10 ^ 6", 14 ; "and before xor")]
+    #[test_case("8 - 2 - 1", 5 ; "left associative subtract")]
+    #[test_case("64 / 4 / 2", 8 ; "left associative divide")]
+    #[test_case("256 >> 2 >> 1", 32 ; "left associative shift")]
+    #[test_case("!0", u32::MAX ; "not")]
+    #[test_case("!!5", 5 ; "double not")]
+    #[test_case("~0xFFFF0000 + 1", 0x1_0000 ; "not binds tightest")]
+    #[test_case("NOT (0xFFFF0000 + 1)", 0xFFFE ; "not of parentheses")]
+    #[test_case("12 nand 10", !8 ; "nand")]
+    #[test_case("12 NOR 10", !14 ; "nor")]
+    #[test_case("((((7))))", 7 ; "nested parentheses")]
+    #[test_case("0 - 1", u32::MAX ; "wrapping subtract")]
+    fn test_evaluate(expression: &str, expected: u32) {
+        assert_eq!(evaluate_u32(expression), Ok(expected));
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert_eq!(evaluate_u32(""), Err(ExpressionError::UnexpectedEnd));
+        assert_eq!(evaluate_u32("1 +"), Err(ExpressionError::UnexpectedEnd));
+        assert_eq!(
+            evaluate_u32("1 + 0b12"),
+            Err(ExpressionError::InvalidNumber {
+                index: 4,
+                error: InputError::InvalidNumber {
+                    input: String::from("0b12"),
+                    radix: 2,
+                },
+            })
+        );
+        assert_eq!(
+            evaluate_u32("1 % 2"),
+            Err(ExpressionError::UnknownOperator {
+                index: 2,
+                text: String::from("%"),
+            })
+        );
+        assert_eq!(
+            evaluate_u32("1 <= 2"),
+            Err(ExpressionError::UnknownOperator {
+                index: 2,
+                text: String::from("<"),
+            })
+        );
+        assert_eq!(
+            evaluate_u32("1 2"),
+            Err(ExpressionError::UnexpectedToken {
+                index: 2,
+                text: String::from("2"),
+            })
+        );
+        assert_eq!(
+            evaluate_u32("1 & | 2"),
+            Err(ExpressionError::UnexpectedToken {
+                index: 4,
+                text: String::from("|"),
+            })
+        );
+        assert_eq!(
+            evaluate_u32("1 ! 2"),
+            Err(ExpressionError::UnexpectedToken {
+                index: 2,
+                text: String::from("!"),
+            })
+        );
+        assert_eq!(
+            evaluate_u32("(1 + 2"),
+            Err(ExpressionError::UnclosedParenthesis { index: 0 })
+        );
+        assert_eq!(
+            evaluate_u32("1 + 2)"),
+            Err(ExpressionError::UnexpectedToken {
+                index: 5,
+                text: String::from(")"),
+            })
+        );
+        assert_eq!(evaluate_u32("()"), Err(unexpected_close(1)));
+        assert_eq!(
+            evaluate_u32("1 / (2 - 2)"),
+            Err(ExpressionError::Arithmetic(ArithmeticError::DivideByZero))
+        );
+        assert_eq!(
+            evaluate_u32("4294967296 + 1"),
+            Err(ExpressionError::InvalidNumber {
+                index: 0,
+                error: InputError::NumberOutOfRange {
+                    input: String::from("4294967296"),
+                    min: String::from("0"),
+                    max: String::from("4294967295"),
+                },
+            })
+        );
+    }
+
+    #[test_case("-5", -5 ; "negative number")]
+    #[test_case("-128", i8::MIN ; "smallest number")]
+    #[test_case("- 5", -5 ; "negate")]
+    #[test_case("-(2 + 3)", -5 ; "negate parentheses")]
+    #[test_case("3 - -2", 5 ; "subtract negative")]
+    #[test_case("-2 * 3", -6 ; "negative times")]
+    #[test_case("- -4", 4 ; "double negation")]
+    #[test_case("0xFF", -1 ; "hex bit pattern")]
+    #[test_case("-64 >> 2", -16 ; "arithmetic shift right")]
+    #[test_case("!-1", 0 ; "not negative")]
+    #[test_case("-7 / 2", -3 ; "divide rounds toward zero")]
+    fn test_evaluate_signed(expression: &str, expected: i8) {
+        assert_eq!(evaluate(expression, Overflow::Checked), Ok(expected));
+    }
+
+    #[test]
+    fn test_evaluate_overflow() {
+        assert_eq!(evaluate::<u8>("200 + 100", Overflow::Wrapping), Ok(44));
+        assert_eq!(
+            evaluate::<u8>("200 + 100", Overflow::Saturating),
+            Ok(u8::MAX)
+        );
+        assert_eq!(
+            evaluate::<u8>("200 + 100", Overflow::Checked),
+            Err(ExpressionError::Arithmetic(ArithmeticError::Overflow))
+        );
+        // Saturating happens at each step, not just at the end
+        assert_eq!(
+            evaluate::<u8>("200 + 100 - 100", Overflow::Saturating),
+            Ok(155)
+        );
+
+        // Negating in an unsigned type is subtracting from zero
+        assert_eq!(evaluate::<u16>("-1", Overflow::Wrapping), Ok(u16::MAX));
+        assert_eq!(evaluate::<u16>("-1", Overflow::Saturating), Ok(0));
+        assert_eq!(
+            evaluate::<u16>("-1", Overflow::Checked),
+            Err(ExpressionError::Arithmetic(ArithmeticError::Overflow))
+        );
+        assert_eq!(
+            evaluate::<i8>("-(-128)", Overflow::Checked),
+            Err(ExpressionError::Arithmetic(ArithmeticError::Overflow))
+        );
+        assert_eq!(evaluate::<i8>("-(-128)", Overflow::Wrapping), Ok(i8::MIN));
+
+        assert_eq!(
+            evaluate::<i64>("1 << -1", Overflow::Wrapping),
+            Err(ExpressionError::Arithmetic(ArithmeticError::NegativeShift))
+        );
+        assert_eq!(
+            evaluate::<u128>("1 << 127 >> 127", Overflow::Checked),
+            Ok(1)
+        );
+        assert!(matches!(
+            evaluate::<i8>("128", Overflow::Wrapping),
+            Err(ExpressionError::InvalidNumber { index: 0, .. })
+        ));
+    }
+
+    fn evaluate_u32(expression: &str) -> Result<u32, ExpressionError> {
+        evaluate(expression, Overflow::Wrapping)
+    }
+
+    fn unexpected_close(index: usize) -> ExpressionError {
+        ExpressionError::UnexpectedToken {
+            index,
+            text: String::from(")"),
+        }
+    }
+}
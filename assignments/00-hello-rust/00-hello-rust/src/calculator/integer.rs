@@ -0,0 +1,387 @@
+//! The integer types the calculator can work in, and how it handles results
+//! that don't fit in them.
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use clap::ValueEnum;
+
+/// How many bits each number has.
+#[derive(Debug, PartialEq, Clone, Copy, ValueEnum)]
+pub enum Width {
+    #[value(name = "8")]
+    W8,
+    #[value(name = "16")]
+    W16,
+    #[value(name = "32")]
+    W32,
+    #[value(name = "64")]
+    W64,
+    #[value(name = "128")]
+    W128,
+}
+
+/// What happens when the result of an operation doesn't fit in the chosen
+/// width.
+#[derive(Debug, PartialEq, Clone, Copy, ValueEnum)]
+pub enum Overflow {
+    /// Keep only the low bits of the result, like two's complement hardware.
+    Wrapping,
+    /// Report an error instead of giving a result.
+    Checked,
+    /// Clamp the result to the largest or smallest representable number.
+    Saturating,
+}
+
+impl Overflow {
+    /// Picks the result for this overflow behavior, given what each behavior
+    /// would produce. `checked` is `None` when the operation overflowed.
+    pub fn resolve<T>(
+        self,
+        checked: Option<T>,
+        wrapping: impl FnOnce() -> T,
+        saturating: impl FnOnce() -> T,
+    ) -> Result<T, ArithmeticError> {
+        match self {
+            Overflow::Wrapping => Ok(wrapping()),
+            Overflow::Checked => checked.ok_or(ArithmeticError::Overflow),
+            Overflow::Saturating => Ok(saturating()),
+        }
+    }
+}
+
+/// The integer type and overflow behavior the calculator works with. The
+/// default is unsigned 32-bit numbers that wrap around on overflow.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Config {
+    pub width: Width,
+    pub signed: bool,
+    pub overflow: Overflow,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: Width::W32,
+            signed: false,
+            overflow: Overflow::Wrapping,
+        }
+    }
+}
+
+/// Why an operation couldn't produce a result.
+#[derive(Debug, PartialEq)]
+pub enum ArithmeticError {
+    DivideByZero,
+    /// The result didn't fit, with `Overflow::Checked` selected.
+    Overflow,
+    /// A shift by a negative number of bits.
+    NegativeShift,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArithmeticError::DivideByZero => write!(f, "division by zero"),
+            ArithmeticError::Overflow => write!(f, "the result doesn't fit"),
+            ArithmeticError::NegativeShift => write!(f, "can't shift by a negative amount"),
+        }
+    }
+}
+
+/// The operations the calculator needs from each of the primitive integer
+/// types, so it can be written once for all of them.
+pub trait Integer:
+    Copy
+    + PartialEq
+    + fmt::Display
+    + fmt::UpperHex
+    + fmt::Binary
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+{
+    const BITS: u32;
+    const ZERO: Self;
+    const MIN: Self;
+    const MAX: Self;
+
+    /// Parses a decimal number, which may be negative for signed types.
+    fn from_decimal(digits: &str) -> Result<Self, ParseIntError>;
+    /// Parses hex or binary digits as a bit pattern, so `FF` is -1 as an
+    /// `i8` rather than out of range.
+    fn from_bits(digits: &str, radix: u32) -> Result<Self, ParseIntError>;
+
+    fn is_negative(self) -> bool;
+    /// The number of bits a shift by `self` moves, or `None` if `self` is
+    /// negative. Amounts too large for a u32 are clamped, since they shift
+    /// every bit out anyway.
+    fn shift_amount(self) -> Option<u32>;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn saturating_mul(self, rhs: Self) -> Self;
+    /// Like the others, except that dividing by zero must be ruled out first.
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn wrapping_div(self, rhs: Self) -> Self;
+    fn saturating_div(self, rhs: Self) -> Self;
+    /// Shifts left by `amount`, which must be less than `BITS`.
+    fn shl(self, amount: u32) -> Self;
+    /// Shifts right by `amount`, which must be less than `BITS`. Signed
+    /// types copy the sign bit into the bits shifted in.
+    fn shr(self, amount: u32) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($type:ty, $bits_type:ty) => {
+        impl Integer for $type {
+            const BITS: u32 = <$type>::BITS;
+            const ZERO: Self = 0;
+            const MIN: Self = <$type>::MIN;
+            const MAX: Self = <$type>::MAX;
+
+            fn from_decimal(digits: &str) -> Result<Self, ParseIntError> {
+                digits.parse()
+            }
+
+            fn from_bits(digits: &str, radix: u32) -> Result<Self, ParseIntError> {
+                <$bits_type>::from_str_radix(digits, radix).map(|bits| bits as $type)
+            }
+
+            fn is_negative(self) -> bool {
+                self < Self::ZERO
+            }
+
+            fn shift_amount(self) -> Option<u32> {
+                if self.is_negative() {
+                    None
+                } else {
+                    Some(u32::try_from(self).unwrap_or(u32::MAX))
+                }
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.checked_add(rhs)
+            }
+            fn wrapping_add(self, rhs: Self) -> Self {
+                self.wrapping_add(rhs)
+            }
+            fn saturating_add(self, rhs: Self) -> Self {
+                self.saturating_add(rhs)
+            }
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                self.checked_sub(rhs)
+            }
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                self.wrapping_sub(rhs)
+            }
+            fn saturating_sub(self, rhs: Self) -> Self {
+                self.saturating_sub(rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                self.checked_mul(rhs)
+            }
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                self.wrapping_mul(rhs)
+            }
+            fn saturating_mul(self, rhs: Self) -> Self {
+                self.saturating_mul(rhs)
+            }
+            fn checked_div(self, rhs: Self) -> Option<Self> {
+                self.checked_div(rhs)
+            }
+            fn wrapping_div(self, rhs: Self) -> Self {
+                self.wrapping_div(rhs)
+            }
+            fn saturating_div(self, rhs: Self) -> Self {
+                self.saturating_div(rhs)
+            }
+            fn shl(self, amount: u32) -> Self {
+                self << amount
+            }
+            fn shr(self, amount: u32) -> Self {
+                self >> amount
+            }
+        }
+    };
+}
+
+impl_integer!(u8, u8);
+impl_integer!(u16, u16);
+impl_integer!(u32, u32);
+impl_integer!(u64, u64);
+impl_integer!(u128, u128);
+impl_integer!(i8, u8);
+impl_integer!(i16, u16);
+impl_integer!(i32, u32);
+impl_integer!(i64, u64);
+impl_integer!(i128, u128);
+
+/// Shifts `lhs` left by `amount` bits. Bits shifted past the top are lost,
+/// which counts as an overflow.
+pub fn shift_left<T: Integer>(
+    lhs: T,
+    amount: u32,
+    overflow: Overflow,
+) -> Result<T, ArithmeticError> {
+    let (result, lossless) = if amount < T::BITS {
+        let result = lhs.shl(amount);
+        // Shifting back only restores `lhs` if no bits (including the sign
+        // bit) were lost
+        (result, result.shr(amount) == lhs)
+    } else {
+        (T::ZERO, lhs == T::ZERO)
+    };
+
+    overflow.resolve(
+        lossless.then_some(result),
+        || result,
+        || match lhs.is_negative() {
+            true => T::MIN,
+            false => T::MAX,
+        },
+    )
+}
+
+/// Shifts `lhs` right by `amount` bits. This can't overflow, so shifting by
+/// the full width or more just shifts every bit out.
+pub fn shift_right<T: Integer>(lhs: T, amount: u32) -> T {
+    if amount < T::BITS {
+        lhs.shr(amount)
+    } else if lhs.is_negative() {
+        !T::ZERO
+    } else {
+        T::ZERO
+    }
+}
+
+/// Runs `$body` with `$type` as an alias for the integer type that `$config`
+/// selects.
+macro_rules! with_integer_type {
+    ($config:expr, $type:ident => $body:expr) => {{
+        use $crate::calculator::integer::Width;
+        match ($config.width, $config.signed) {
+            (Width::W8, false) => {
+                type $type = u8;
+                $body
+            }
+            (Width::W16, false) => {
+                type $type = u16;
+                $body
+            }
+            (Width::W32, false) => {
+                type $type = u32;
+                $body
+            }
+            (Width::W64, false) => {
+                type $type = u64;
+                $body
+            }
+            (Width::W128, false) => {
+                type $type = u128;
+                $body
+            }
+            (Width::W8, true) => {
+                type $type = i8;
+                $body
+            }
+            (Width::W16, true) => {
+                type $type = i16;
+                $body
+            }
+            (Width::W32, true) => {
+                type $type = i32;
+                $body
+            }
+            (Width::W64, true) => {
+                type $type = i64;
+                $body
+            }
+            (Width::W128, true) => {
+                type $type = i128;
+                $body
+            }
+        }
+    }};
+}
+
+pub(crate) use with_integer_type;
+
+#[cfg(test)]
+mod tests {
+    use super::{shift_left, shift_right, ArithmeticError, Integer, Overflow};
+
+    #[test]
+    fn test_from_bits() {
+        assert_eq!(i8::from_bits("FF", 16), Ok(-1));
+        assert_eq!(i8::from_bits("10000000", 2), Ok(i8::MIN));
+        assert_eq!(u8::from_bits("FF", 16), Ok(255));
+        assert!(i8::from_bits("1FF", 16).is_err());
+        assert_eq!(i128::from_bits(&"F".repeat(32), 16), Ok(-1));
+    }
+
+    #[test]
+    fn test_shift_amount() {
+        assert_eq!(5_u8.shift_amount(), Some(5));
+        assert_eq!((-1_i32).shift_amount(), None);
+        assert_eq!(u128::MAX.shift_amount(), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_shift_left() {
+        assert_eq!(shift_left(1_u8, 7, Overflow::Checked), Ok(128));
+        assert_eq!(shift_left(-1_i8, 7, Overflow::Checked), Ok(i8::MIN));
+
+        // Losing bits off the top
+        assert_eq!(shift_left(0xF0_u8, 1, Overflow::Wrapping), Ok(0xE0));
+        assert_eq!(
+            shift_left(0xF0_u8, 1, Overflow::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(shift_left(0xF0_u8, 1, Overflow::Saturating), Ok(u8::MAX));
+
+        // Changing the sign of a signed number
+        assert_eq!(shift_left(64_i8, 1, Overflow::Wrapping), Ok(i8::MIN));
+        assert_eq!(
+            shift_left(64_i8, 1, Overflow::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(shift_left(64_i8, 1, Overflow::Saturating), Ok(i8::MAX));
+        assert_eq!(shift_left(-65_i8, 1, Overflow::Saturating), Ok(i8::MIN));
+
+        // Shifting by the full width or more
+        assert_eq!(shift_left(1_u32, 32, Overflow::Wrapping), Ok(0));
+        assert_eq!(
+            shift_left(1_u32, 100, Overflow::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(shift_left(0_u32, 100, Overflow::Checked), Ok(0));
+    }
+
+    #[test]
+    fn test_shift_right() {
+        assert_eq!(shift_right(0xF0_u8, 4), 0x0F);
+        assert_eq!(shift_right(-16_i8, 2), -4);
+        assert_eq!(shift_right(u64::MAX, 64), 0);
+        assert_eq!(shift_right(-5_i64, 200), -1);
+    }
+
+    #[test]
+    fn test_overflow_resolve() {
+        assert_eq!(Overflow::Wrapping.resolve(None, || 1, || 2), Ok(1));
+        assert_eq!(Overflow::Saturating.resolve(None, || 1, || 2), Ok(2));
+        assert_eq!(Overflow::Checked.resolve(Some(3), || 1, || 2), Ok(3));
+        assert_eq!(
+            Overflow::Checked.resolve(None::<u8>, || 1, || 2),
+            Err(ArithmeticError::Overflow)
+        );
+    }
+}
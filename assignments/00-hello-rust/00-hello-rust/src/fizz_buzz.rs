@@ -1 +1,244 @@
-pub fn print_fizz_buzz(max_num: u32) {}
+//! Fizz Buzz, with the classic rules or any others, e.g. 7=Bazz.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Say `word` instead of the number when it's a multiple of `divisor`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub divisor: u32,
+    pub word: String,
+}
+
+impl Rule {
+    pub fn new(divisor: u32, word: &str) -> Self {
+        Rule {
+            divisor,
+            word: String::from(word),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    MissingEquals,
+    InvalidDivisor(String),
+    ZeroDivisor,
+    EmptyWord,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::MissingEquals => write!(f, "expected a rule like 3=Fizz"),
+            RuleError::InvalidDivisor(divisor) => write!(f, "{:?} isn't a number", divisor),
+            RuleError::ZeroDivisor => write!(f, "nothing is a multiple of 0"),
+            RuleError::EmptyWord => write!(f, "the word can't be empty"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Parses rules written like `3=Fizz`.
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (divisor, word) = rule.split_once('=').ok_or(RuleError::MissingEquals)?;
+        let divisor: u32 = divisor
+            .trim()
+            .parse()
+            .map_err(|_| RuleError::InvalidDivisor(String::from(divisor)))?;
+        if divisor == 0 {
+            return Err(RuleError::ZeroDivisor);
+        }
+        if word.is_empty() {
+            return Err(RuleError::EmptyWord);
+        }
+        Ok(Rule::new(divisor, word))
+    }
+}
+
+/// The rules to play by. When several rules match a number their words are
+/// joined in order, so 15 is FizzBuzz with the classic rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Rules { rules }
+    }
+
+    /// 3=Fizz and 5=Buzz.
+    pub fn classic() -> Self {
+        Rules::new(vec![Rule::new(3, "Fizz"), Rule::new(5, "Buzz")])
+    }
+
+    /// What to say for `num`: the words of every rule it matches, or the
+    /// number itself if it doesn't match any.
+    pub fn say(&self, num: u32) -> String {
+        let words: String = self
+            .rules
+            .iter()
+            .filter(|rule| num.is_multiple_of(rule.divisor))
+            .map(|rule| rule.word.as_str())
+            .collect();
+        if words.is_empty() {
+            num.to_string()
+        } else {
+            words
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// One answer per line
+    Text,
+    /// One JSON object per line, e.g. {"number":15,"output":"FizzBuzz"}
+    Json,
+}
+
+/// Which numbers to play, and how to print the answers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub from: u32,
+    pub to: u32,
+    pub step: usize,
+    pub rules: Rules,
+    pub format: Format,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    number: u32,
+    output: &'a str,
+}
+
+/// Writes the answer for every `step`th number from `from` to `to`
+/// (inclusive) to `output`.
+pub fn write_fizz_buzz<W: Write>(output: &mut W, config: &Config) -> io::Result<()> {
+    for num in (config.from..=config.to).step_by(config.step) {
+        let said = config.rules.say(num);
+        match config.format {
+            Format::Text => writeln!(output, "{}", said)?,
+            Format::Json => {
+                serde_json::to_writer(
+                    &mut *output,
+                    &Line {
+                        number: num,
+                        output: &said,
+                    },
+                )?;
+                writeln!(output)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn fizz_buzz(config: &Config) -> io::Result<()> {
+    write_fizz_buzz(&mut io::stdout().lock(), config)
+}
+
+pub fn print_fizz_buzz(max_num: u32) {
+    let config = Config {
+        from: 1,
+        to: max_num,
+        step: 1,
+        rules: Rules::classic(),
+        format: Format::Text,
+    };
+    fizz_buzz(&config).expect("couldn't write to stdout");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_fizz_buzz, Config, Format, Rule, RuleError, Rules};
+
+    fn run(config: &Config) -> String {
+        let mut output = Vec::new();
+        write_fizz_buzz(&mut output, config).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn classic(from: u32, to: u32) -> Config {
+        Config {
+            from,
+            to,
+            step: 1,
+            rules: Rules::classic(),
+            format: Format::Text,
+        }
+    }
+
+    #[test]
+    fn test_classic() {
+        let lines: Vec<_> = run(&classic(1, 15)).lines().map(String::from).collect();
+        assert_eq!(
+            lines,
+            [
+                "1", "2", "Fizz", "4", "Buzz", "Fizz", "7", "8", "Fizz", "Buzz", "11", "Fizz",
+                "13", "14", "FizzBuzz"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_range_and_step() {
+        let config = Config {
+            step: 5,
+            ..classic(0, 20)
+        };
+        assert_eq!(run(&config), "FizzBuzz\nBuzz\nBuzz\nFizzBuzz\nBuzz\n");
+        assert_eq!(run(&classic(10, 9)), "");
+        assert_eq!(run(&classic(u32::MAX, u32::MAX)), "FizzBuzz\n");
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = Rules::new(vec![Rule::new(2, "Bizz"), Rule::new(7, "Bazz")]);
+        assert_eq!(rules.say(1), "1");
+        assert_eq!(rules.say(4), "Bizz");
+        assert_eq!(rules.say(7), "Bazz");
+        assert_eq!(rules.say(14), "BizzBazz");
+        // No rules, just counting
+        assert_eq!(Rules::new(vec![]).say(15), "15");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let config = Config {
+            format: Format::Json,
+            ..classic(14, 15)
+        };
+        assert_eq!(
+            run(&config),
+            "{\"number\":14,\"output\":\"14\"}\n{\"number\":15,\"output\":\"FizzBuzz\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!("3=Fizz".parse(), Ok(Rule::new(3, "Fizz")));
+        assert_eq!("11=Fizz Buzz".parse(), Ok(Rule::new(11, "Fizz Buzz")));
+        assert_eq!("3".parse::<Rule>(), Err(RuleError::MissingEquals));
+        assert_eq!(
+            "three=Fizz".parse::<Rule>(),
+            Err(RuleError::InvalidDivisor(String::from("three")))
+        );
+        assert_eq!(
+            "-3=Fizz".parse::<Rule>(),
+            Err(RuleError::InvalidDivisor(String::from("-3")))
+        );
+        assert_eq!("0=Fizz".parse::<Rule>(), Err(RuleError::ZeroDivisor));
+        assert_eq!("3=".parse::<Rule>(), Err(RuleError::EmptyWord));
+    }
+}
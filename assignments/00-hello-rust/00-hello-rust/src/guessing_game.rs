@@ -1,39 +1,511 @@
 #![warn(missing_docs)]
 
+//! A game where the player guesses a secret number, being told after each
+//! guess whether it was too high or too low. Games played at one of the
+//! difficulty presets are recorded in a high-score table.
+
+mod high_scores;
+
+use clap::ValueEnum;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::io;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::num::IntErrorKind;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
 
-fn get_input() -> i32 {
-    println!("Please input your guess");
+use high_scores::{HighScores, Score};
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
+/// A preset range and number of guesses, with its own high scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// 1 to 20, with 6 guesses
+    Easy,
+    /// 1 to 100, with 7 guesses
+    Medium,
+    /// 1 to 1000, with 10 guesses
+    Hard,
+}
 
-    match input.trim().parse() {
-        Ok(num) => num,
-        Err(_) => panic!("Invalid entry."),
+impl Difficulty {
+    /// The settings for a game at this difficulty.
+    pub fn config(self) -> GameConfig {
+        // Each budget is enough to always win by halving the range, with a
+        // spare guess on easy
+        let (range, max_attempts) = match self {
+            Difficulty::Easy => (1..=20, 6),
+            Difficulty::Medium => (1..=100, 7),
+            Difficulty::Hard => (1..=1000, 10),
+        };
+        GameConfig {
+            range,
+            max_attempts: Some(max_attempts),
+            reveal_on_quit: false,
+            difficulty: Some(self),
+        }
     }
 }
 
-fn main() {
-    println!("Guess the number!");
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
 
-    let secret_number = rand::thread_rng().gen_range(1..=100);
+/// The settings for a game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameConfig {
+    /// The range the secret number is picked from. Guesses outside of it
+    /// are rejected.
+    pub range: RangeInclusive<i32>,
+    /// How many guesses the player gets, or `None` for as many as they need.
+    pub max_attempts: Option<u32>,
+    /// Whether to tell the player the secret number when they quit.
+    pub reveal_on_quit: bool,
+    /// The difficulty these settings came from. Only games played at a
+    /// difficulty are put in the high-score table.
+    pub difficulty: Option<Difficulty>,
+}
 
-    loop {
-        let guess = get_input();
-        print!("You guessed: {}. ", guess);
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            range: 1..=100,
+            max_attempts: None,
+            reveal_on_quit: false,
+            difficulty: None,
+        }
+    }
+}
 
-        match secret_number.cmp(&guess) {
-            Ordering::Equal => {
-                println!("That is correct!");
-                break;
+/// How a game went.
+#[derive(Debug, PartialEq)]
+pub struct Round {
+    /// Whether the player guessed the secret number.
+    pub won: bool,
+    /// How many guesses the player made.
+    pub guesses: u32,
+    /// How long the game took.
+    pub elapsed: Duration,
+}
+
+/// What the player entered.
+#[derive(Debug, PartialEq)]
+enum Guess {
+    Number(i32),
+    Quit,
+}
+
+/// Why a line entered by the player isn't a guess.
+#[derive(Debug, PartialEq)]
+enum GuessError {
+    Empty,
+    NotANumber { input: String },
+    OutOfRange { input: String, min: i32, max: i32 },
+}
+
+impl fmt::Display for GuessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuessError::Empty => write!(f, "nothing was entered"),
+            GuessError::NotANumber { input } => write!(f, "{:?} is not a number", input),
+            GuessError::OutOfRange { input, min, max } => {
+                write!(f, "{} is not between {} and {}", input, min, max)
             }
-            Ordering::Greater => println!("You're guess is too low."),
-            Ordering::Less => println!("You're guess is too high."),
         }
     }
 }
+
+/// Parses a guess, which must be a number in `range`, or `quit` to give up.
+fn parse_guess(input: &str, range: &RangeInclusive<i32>) -> Result<Guess, GuessError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(GuessError::Empty);
+    }
+    if input.eq_ignore_ascii_case("quit") || input.eq_ignore_ascii_case("q") {
+        return Ok(Guess::Quit);
+    }
+
+    let out_of_range = || GuessError::OutOfRange {
+        input: input.to_owned(),
+        min: *range.start(),
+        max: *range.end(),
+    };
+    match input.parse() {
+        Ok(num) if range.contains(&num) => Ok(Guess::Number(num)),
+        Ok(_) => Err(out_of_range()),
+        Err(err) => match err.kind() {
+            // Numbers too large for an i32 are definitely outside the range
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => Err(out_of_range()),
+            _ => Err(GuessError::NotANumber {
+                input: input.to_owned(),
+            }),
+        },
+    }
+}
+
+/// A single game, reading guesses from `input` and writing to `output`.
+///
+/// The secret number comes from `rng`, so tests can pass a predictable
+/// generator and script the whole game.
+pub struct Game<R, I, O> {
+    config: GameConfig,
+    rng: R,
+    input: I,
+    output: O,
+}
+
+impl<R: Rng, I: BufRead, O: Write> Game<R, I, O> {
+    /// Creates a game with the given settings, that hasn't started yet.
+    pub fn new(config: GameConfig, rng: R, input: I, output: O) -> Self {
+        Game {
+            config,
+            rng,
+            input,
+            output,
+        }
+    }
+
+    /// Plays the game until the player wins, quits, or runs out of guesses.
+    pub fn play(&mut self) -> io::Result<Round> {
+        let config = &self.config;
+        match config.difficulty {
+            Some(difficulty) => writeln!(
+                self.output,
+                "Guess the number between {} and {} ({} difficulty)! Enter \"quit\" to give up.",
+                config.range.start(),
+                config.range.end(),
+                difficulty
+            )?,
+            None => writeln!(
+                self.output,
+                "Guess the number between {} and {}! Enter \"quit\" to give up.",
+                config.range.start(),
+                config.range.end()
+            )?,
+        }
+        if let Some(max_attempts) = config.max_attempts {
+            writeln!(self.output, "You have {} guesses.", max_attempts)?;
+        }
+
+        let secret_number = self.rng.gen_range(self.config.range.clone());
+        let start = Instant::now();
+        let mut attempts = 0;
+
+        let won = loop {
+            if self.config.max_attempts.is_some_and(|max| attempts >= max) {
+                writeln!(
+                    self.output,
+                    "You're out of guesses! The number was {}.",
+                    secret_number
+                )?;
+                break false;
+            }
+
+            let Guess::Number(guess) = self.get_input()? else {
+                if self.config.reveal_on_quit {
+                    writeln!(self.output, "The number was {}.", secret_number)?;
+                }
+                break false;
+            };
+            attempts += 1;
+            write!(self.output, "You guessed: {}. ", guess)?;
+
+            match secret_number.cmp(&guess) {
+                Ordering::Equal => {
+                    writeln!(self.output, "That is correct!")?;
+                    break true;
+                }
+                Ordering::Greater => writeln!(self.output, "You're guess is too low.")?,
+                Ordering::Less => writeln!(self.output, "You're guess is too high.")?,
+            }
+            match self.config.max_attempts.map(|max| max - attempts) {
+                Some(0) | None => {}
+                Some(left) => writeln!(self.output, "{} guesses left.", left)?,
+            }
+        };
+
+        let round = Round {
+            won,
+            guesses: attempts,
+            elapsed: start.elapsed(),
+        };
+        writeln!(
+            self.output,
+            "You made {} guesses in {:.1} seconds.",
+            round.guesses,
+            round.elapsed.as_secs_f64()
+        )?;
+        Ok(round)
+    }
+
+    /// Asks for a guess until the player enters a valid one. Running out of
+    /// input counts as quitting.
+    fn get_input(&mut self) -> io::Result<Guess> {
+        writeln!(self.output, "Please input your guess")?;
+
+        loop {
+            let mut line = String::new();
+            // Reading 0 bytes means the input was closed (e.g. by Ctrl-D)
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(Guess::Quit);
+            }
+
+            match parse_guess(&line, &self.config.range) {
+                Ok(guess) => return Ok(guess),
+                Err(err) => writeln!(self.output, "Invalid guess: {}. Please try again:", err)?,
+            }
+        }
+    }
+}
+
+/// Plays one game on stdin and stdout. If the game was won at a difficulty
+/// preset, it's added to the high scores, which are then shown.
+pub fn play(config: &GameConfig) -> io::Result<()> {
+    let mut game = Game::new(
+        config.clone(),
+        rand::thread_rng(),
+        io::stdin().lock(),
+        io::stdout(),
+    );
+    let round = game.play()?;
+
+    if let (true, Some(difficulty)) = (round.won, config.difficulty) {
+        let score = Score {
+            difficulty,
+            guesses: round.guesses,
+            elapsed: round.elapsed,
+        };
+        if let Err(err) = record_score(score) {
+            println!("Couldn't save your score: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Adds `score` to the saved high scores, then shows the scores for its
+/// difficulty.
+fn record_score(score: Score) -> io::Result<()> {
+    let path = HighScores::default_path().ok_or_else(no_data_dir)?;
+    let mut scores = HighScores::load(&path)?;
+    let difficulty = score.difficulty;
+
+    if let Some(rank) = scores.insert(score) {
+        scores.save(&path)?;
+        println!("New high score! You placed #{}.", rank);
+    }
+    write_high_scores(&mut io::stdout(), &scores, difficulty)
+}
+
+/// Prints the saved high scores for every difficulty.
+pub fn show_high_scores() -> io::Result<()> {
+    let path = HighScores::default_path().ok_or_else(no_data_dir)?;
+    let scores = HighScores::load(&path)?;
+    for difficulty in Difficulty::value_variants() {
+        write_high_scores(&mut io::stdout(), &scores, *difficulty)?;
+    }
+    Ok(())
+}
+
+fn write_high_scores(
+    output: &mut impl Write,
+    scores: &HighScores,
+    difficulty: Difficulty,
+) -> io::Result<()> {
+    writeln!(output, "{} high scores:", difficulty)?;
+    let mut scores = scores.for_difficulty(difficulty).peekable();
+    if scores.peek().is_none() {
+        writeln!(output, "  None yet")?;
+    }
+    for (rank, score) in scores.enumerate() {
+        writeln!(
+            output,
+            "  {:>2}. {} guesses in {:.1} seconds",
+            rank + 1,
+            score.guesses,
+            score.elapsed.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
+
+fn no_data_dir() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "there's no data directory to keep high scores in",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::mock::StepRng;
+    use test_case::test_case;
+
+    use crate::guessing_game::{
+        parse_guess, Difficulty, Game, GameConfig, Guess, GuessError, Round,
+    };
+
+    /// Plays a game with `input` as everything the player types, returning
+    /// how it went and everything that was printed. The secret number is
+    /// always the start of the range.
+    fn play_scripted(config: GameConfig, input: &str) -> (Round, String) {
+        let mut output = Vec::new();
+        let round = Game::new(config, StepRng::new(0, 0), input.as_bytes(), &mut output)
+            .play()
+            .unwrap();
+        (round, String::from_utf8(output).unwrap())
+    }
+
+    #[test_case("50", 50 ; "number")]
+    #[test_case("  1\n", 1 ; "surrounding whitespace")]
+    #[test_case("100", 100 ; "end of range")]
+    #[test_case("-5", -5 ; "negative")]
+    fn test_parse_guess(input: &str, expected: i32) {
+        assert_eq!(
+            parse_guess(input, &(-10..=100)),
+            Ok(Guess::Number(expected))
+        );
+    }
+
+    #[test_case("quit" ; "quit")]
+    #[test_case("QUIT\n" ; "uppercase")]
+    #[test_case("q" ; "short")]
+    fn test_parse_guess_quit(input: &str) {
+        assert_eq!(parse_guess(input, &(1..=100)), Ok(Guess::Quit));
+    }
+
+    #[test]
+    fn test_parse_guess_invalid() {
+        assert_eq!(parse_guess(" \n", &(1..=100)), Err(GuessError::Empty));
+        assert_eq!(
+            parse_guess("fifty", &(1..=100)),
+            Err(GuessError::NotANumber {
+                input: String::from("fifty")
+            })
+        );
+        assert_eq!(
+            parse_guess("4.5", &(1..=100)),
+            Err(GuessError::NotANumber {
+                input: String::from("4.5")
+            })
+        );
+        assert_eq!(
+            parse_guess("0", &(1..=100)),
+            Err(GuessError::OutOfRange {
+                input: String::from("0"),
+                min: 1,
+                max: 100
+            })
+        );
+        assert_eq!(
+            parse_guess("99999999999", &(1..=100)),
+            Err(GuessError::OutOfRange {
+                input: String::from("99999999999"),
+                min: 1,
+                max: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_game_won() {
+        let config = GameConfig {
+            range: 10..=20,
+            ..GameConfig::default()
+        };
+        let (round, output) = play_scripted(config, "abc\n\n25\n15\n12\n10\n");
+        assert!(round.won);
+        assert_eq!(round.guesses, 3);
+
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(
+            lines[..lines.len() - 1],
+            [
+                "Guess the number between 10 and 20! Enter \"quit\" to give up.",
+                "Please input your guess",
+                "Invalid guess: \"abc\" is not a number. Please try again:",
+                "Invalid guess: nothing was entered. Please try again:",
+                "Invalid guess: 25 is not between 10 and 20. Please try again:",
+                "You guessed: 15. You're guess is too high.",
+                "Please input your guess",
+                "You guessed: 12. You're guess is too high.",
+                "Please input your guess",
+                "You guessed: 10. That is correct!",
+            ]
+        );
+        assert!(lines[lines.len() - 1].starts_with("You made 3 guesses in "));
+    }
+
+    #[test]
+    fn test_game_out_of_guesses() {
+        let config = GameConfig {
+            range: 1..=100,
+            max_attempts: Some(2),
+            ..GameConfig::default()
+        };
+        // Input after the last allowed guess is never read
+        let (round, output) = play_scripted(config, "50\n25\n1\n");
+        assert!(!round.won);
+        assert_eq!(round.guesses, 2);
+        assert!(output.contains("You have 2 guesses."));
+        assert!(output.contains("1 guesses left."));
+        assert!(output.contains("You're out of guesses! The number was 1."));
+    }
+
+    #[test]
+    fn test_game_quit() {
+        let config = GameConfig {
+            range: -5..=5,
+            reveal_on_quit: true,
+            ..GameConfig::default()
+        };
+        let (round, output) = play_scripted(config.clone(), "0\nquit\n");
+        assert!(!round.won);
+        assert_eq!(round.guesses, 1);
+        assert!(output.contains("You guessed: 0. You're guess is too high."));
+        assert!(output.contains("The number was -5."));
+
+        // Running out of input is the same as quitting
+        let (round, output) = play_scripted(config.clone(), "");
+        assert!(!round.won);
+        assert!(output.contains("The number was -5."));
+
+        let config = GameConfig {
+            reveal_on_quit: false,
+            ..config
+        };
+        let (_, output) = play_scripted(config, "q\n");
+        assert!(!output.contains("The number was"));
+    }
+
+    #[test]
+    fn test_game_difficulty() {
+        let (round, output) = play_scripted(Difficulty::Hard.config(), "500\n1\n");
+        assert!(round.won);
+        assert!(output.starts_with(
+            "Guess the number between 1 and 1000 (Hard difficulty)! Enter \"quit\" to give up.\n\
+             You have 10 guesses.\n"
+        ));
+        assert!(output.contains("9 guesses left."));
+    }
+
+    #[test_case(Difficulty::Easy ; "easy")]
+    #[test_case(Difficulty::Medium ; "medium")]
+    #[test_case(Difficulty::Hard ; "hard")]
+    fn test_difficulty_is_winnable(difficulty: Difficulty) {
+        let config = difficulty.config();
+        assert_eq!(config.difficulty, Some(difficulty));
+
+        // Halving the range each guess finds any number in 2^n - 1 numbers
+        let size = config.range.end() - config.range.start() + 1;
+        let attempts = config.max_attempts.unwrap();
+        assert!(2_i32.pow(attempts) > size);
+    }
+}
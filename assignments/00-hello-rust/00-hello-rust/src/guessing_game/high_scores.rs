@@ -0,0 +1,188 @@
+//! The best games played at each difficulty, saved as JSON so they last
+//! between runs.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::Difficulty;
+
+/// How many scores are kept for each difficulty.
+pub const MAX_SCORES_PER_DIFFICULTY: usize = 10;
+
+/// One won game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// The difficulty the game was played at.
+    pub difficulty: Difficulty,
+    /// How many guesses it took, including the correct one.
+    pub guesses: u32,
+    /// How long it took, from the first prompt to the correct guess.
+    pub elapsed: Duration,
+}
+
+impl Score {
+    /// Whether this score ranks above `other`. Fewer guesses is better, and
+    /// ties are broken by time.
+    fn beats(&self, other: &Score) -> bool {
+        (self.guesses, self.elapsed) < (other.guesses, other.elapsed)
+    }
+}
+
+/// The high-score table, best scores first.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    scores: Vec<Score>,
+}
+
+impl HighScores {
+    /// Where the table is saved, inside the user's data directory, or `None`
+    /// if the platform doesn't have one.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("hello_rust").join("high_scores.json"))
+    }
+
+    /// Reads the table saved at `path`. A missing file is an empty table, so
+    /// the first game doesn't need any setup.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the table to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Adds `score` to the table if it's good enough, returning its rank
+    /// (starting from 1) among scores of the same difficulty.
+    pub fn insert(&mut self, score: Score) -> Option<usize> {
+        let rank = self
+            .for_difficulty(score.difficulty)
+            .take_while(|other| !score.beats(other))
+            .count()
+            + 1;
+        if rank > MAX_SCORES_PER_DIFFICULTY {
+            return None;
+        }
+
+        // Insert before the first worse score, keeping the whole list sorted
+        let index = self
+            .scores
+            .iter()
+            .position(|other| other.difficulty == score.difficulty && score.beats(other))
+            .unwrap_or(self.scores.len());
+        let difficulty = score.difficulty;
+        self.scores.insert(index, score);
+
+        // Drop whatever got pushed off the bottom of the table
+        let mut kept = 0;
+        self.scores.retain(|other| {
+            if other.difficulty != difficulty {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_SCORES_PER_DIFFICULTY
+        });
+        Some(rank)
+    }
+
+    /// The scores for `difficulty`, best first.
+    pub fn for_difficulty(&self, difficulty: Difficulty) -> impl Iterator<Item = &Score> {
+        self.scores
+            .iter()
+            .filter(move |score| score.difficulty == difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use super::{HighScores, Score, MAX_SCORES_PER_DIFFICULTY};
+    use crate::guessing_game::Difficulty;
+
+    fn score(difficulty: Difficulty, guesses: u32, seconds: u64) -> Score {
+        Score {
+            difficulty,
+            guesses,
+            elapsed: Duration::from_secs(seconds),
+        }
+    }
+
+    #[test]
+    fn test_insert_ranks() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.insert(score(Difficulty::Easy, 5, 10)), Some(1));
+        assert_eq!(scores.insert(score(Difficulty::Easy, 3, 20)), Some(1));
+        // Ties on guesses are broken by time
+        assert_eq!(scores.insert(score(Difficulty::Easy, 5, 5)), Some(2));
+        // Other difficulties have their own ranking
+        assert_eq!(scores.insert(score(Difficulty::Hard, 9, 60)), Some(1));
+
+        let easy: Vec<_> = scores.for_difficulty(Difficulty::Easy).cloned().collect();
+        assert_eq!(
+            easy,
+            vec![
+                score(Difficulty::Easy, 3, 20),
+                score(Difficulty::Easy, 5, 5),
+                score(Difficulty::Easy, 5, 10),
+            ]
+        );
+        assert_eq!(scores.for_difficulty(Difficulty::Medium).count(), 0);
+    }
+
+    #[test]
+    fn test_insert_full_table() {
+        let mut scores = HighScores::default();
+        for guesses in 1..=MAX_SCORES_PER_DIFFICULTY as u32 {
+            scores.insert(score(Difficulty::Medium, guesses, 1));
+        }
+        scores.insert(score(Difficulty::Easy, 20, 100));
+
+        // Too slow to make the table
+        assert_eq!(scores.insert(score(Difficulty::Medium, 10, 2)), None);
+        // Good enough, pushing the worst score out
+        assert_eq!(scores.insert(score(Difficulty::Medium, 2, 0)), Some(2));
+
+        let medium: Vec<_> = scores
+            .for_difficulty(Difficulty::Medium)
+            .map(|score| score.guesses)
+            .collect();
+        assert_eq!(medium, vec![1, 2, 2, 3, 4, 5, 6, 7, 8, 9]);
+        // A full table at one difficulty doesn't affect the others
+        assert_eq!(scores.for_difficulty(Difficulty::Easy).count(), 1);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("hello_rust_scores_{}", std::process::id()));
+        let path = dir.join("nested").join("high_scores.json");
+
+        // Nothing saved yet
+        assert_eq!(HighScores::load(&path).unwrap(), HighScores::default());
+
+        let mut scores = HighScores::default();
+        scores.insert(score(Difficulty::Hard, 8, 42));
+        scores.insert(score(Difficulty::Easy, 2, 3));
+        scores.save(&path).unwrap();
+        assert_eq!(HighScores::load(&path).unwrap(), scores);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(HighScores::load(&path).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -6,6 +6,163 @@ mod guessing_game;
 mod traffic_light;
 mod university;
 
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+
+use calculator::{Config, Overflow, Width};
+use fizz_buzz::{Rule, Rules};
+use guessing_game::{Difficulty, GameConfig};
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the bitwise and arithmetic calculator
+    Calculator {
+        /// Evaluate whole expressions like `(0xFF & 0b1010) << 2 | 7`, one per
+        /// line, instead of prompting for a single operation
+        #[clap(long)]
+        repl: bool,
+        /// How many bits each number has
+        #[clap(long, value_enum, default_value = "32")]
+        bits: Width,
+        /// Use signed (two's complement) numbers instead of unsigned ones
+        #[clap(long)]
+        signed: bool,
+        /// What happens when a result doesn't fit in the number of bits
+        #[clap(long, value_enum, default_value = "wrapping")]
+        overflow: Overflow,
+    },
+    /// Count, saying a word instead of the number for some multiples
+    FizzBuzz {
+        /// The first number to count from
+        #[clap(long, default_value_t = 1)]
+        from: u32,
+        /// The last number to count to, inclusive
+        #[clap(long, default_value_t = 100)]
+        to: u32,
+        /// Only count every this many numbers
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        step: u32,
+        /// Say WORD for multiples of N, written N=WORD, instead of the
+        /// classic 3=Fizz and 5=Buzz. Can be given more than once
+        #[clap(long = "rule", value_name = "N=WORD")]
+        rules: Vec<Rule>,
+        /// How to print each answer
+        #[clap(long, value_enum, default_value = "text")]
+        format: fizz_buzz::Format,
+    },
+    /// Play a game of guessing a secret number
+    GuessingGame {
+        /// Play with a preset range and number of guesses, and keep a high
+        /// score
+        #[clap(long, value_enum, conflicts_with_all = ["min", "max", "attempts"])]
+        difficulty: Option<Difficulty>,
+        /// Show the high scores instead of playing
+        #[clap(long)]
+        scores: bool,
+        /// The smallest the secret number can be
+        #[clap(long, default_value_t = 1, allow_negative_numbers = true)]
+        min: i32,
+        /// The largest the secret number can be
+        #[clap(long, default_value_t = 100, allow_negative_numbers = true)]
+        max: i32,
+        /// How many guesses you get, instead of as many as you need
+        #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+        attempts: Option<u32>,
+        /// Show the secret number if you quit
+        #[clap(long)]
+        reveal: bool,
+    },
+}
+
 fn main() {
-    println!("Hello world!");
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Calculator {
+            repl,
+            bits,
+            signed,
+            overflow,
+        }) => {
+            let config = Config {
+                width: bits,
+                signed,
+                overflow,
+            };
+            if repl {
+                calculator::repl(config);
+            } else {
+                calculator::calculator(config);
+            }
+        }
+        Some(Command::FizzBuzz { from, to, .. }) if from > to => Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("--from ({}) can't be larger than --to ({})", from, to),
+            )
+            .exit(),
+        Some(Command::FizzBuzz {
+            from,
+            to,
+            step,
+            rules,
+            format,
+        }) => {
+            let config = fizz_buzz::Config {
+                from,
+                to,
+                step: step as usize,
+                rules: if rules.is_empty() {
+                    Rules::classic()
+                } else {
+                    Rules::new(rules)
+                },
+                format,
+            };
+            if let Err(err) = fizz_buzz::fizz_buzz(&config) {
+                eprintln!("Couldn't print the answers: {}", err);
+            }
+        }
+        Some(Command::GuessingGame { scores: true, .. }) => {
+            if let Err(err) = guessing_game::show_high_scores() {
+                eprintln!("Couldn't read the high scores: {}", err);
+            }
+        }
+        Some(Command::GuessingGame {
+            difficulty,
+            min,
+            max,
+            attempts,
+            reveal,
+            ..
+        }) => {
+            let config = match difficulty {
+                Some(difficulty) => GameConfig {
+                    reveal_on_quit: reveal,
+                    ..difficulty.config()
+                },
+                None if min > max => Args::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("--min ({}) can't be larger than --max ({})", min, max),
+                    )
+                    .exit(),
+                None => GameConfig {
+                    range: min..=max,
+                    max_attempts: attempts,
+                    reveal_on_quit: reveal,
+                    difficulty: None,
+                },
+            };
+            if let Err(err) = guessing_game::play(&config) {
+                eprintln!("The game stopped unexpectedly: {}", err);
+            }
+        }
+        None => println!("Hello world!"),
+    }
 }
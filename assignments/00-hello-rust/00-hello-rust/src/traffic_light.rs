@@ -1,23 +1,215 @@
+//! A traffic light controller with a crosswalk and vehicle detectors.
+//! `get_next_state` decides what the light and the pedestrian signal show at
+//! a given time, and the `simulation` module runs a light over simulated
+//! time.
+
+mod simulation;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
-enum TrafficLightColor {
+pub enum TrafficLightColor {
     Red,
     Yellow,
     Green,
 }
 
+/// What the crosswalk signal shows to pedestrians.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum PedestrianSignal {
+    Walk,
+    /// Anyone already crossing should finish, but nobody should start.
+    FlashingDontWalk,
+    DontWalk,
+}
+
+/// What the light shows to drivers and pedestrians at the same moment.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Signals {
+    pub vehicle: TrafficLightColor,
+    pub pedestrian: PedestrianSignal,
+}
+
 #[derive(Debug, Copy, Clone)]
-struct TrafficLightState {
-    current_color: TrafficLightColor,
-    last_transition_time_ms: u32,
+pub struct TrafficLightState {
+    pub current_color: TrafficLightColor,
+    pub last_transition_time_ms: u32,
+    /// How much longer than usual the current green has been made to last
+    /// by cars arriving, see `extend_green`. Zero for other colors.
+    pub green_extension_ms: u32,
 }
 
-fn get_next_color(state: TrafficLightState) -> TrafficLightColor {}
+/// What the light's sensors currently report.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Sensors {
+    /// A pedestrian has pressed the button and not crossed yet.
+    pub pedestrian_walk_request: bool,
+    /// Cars are waiting at the red on the cross street.
+    pub cross_traffic_waiting: bool,
+}
+
+/// How long the light holds each color, and the pedestrian signal each of
+/// its phases, in milliseconds.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Timing {
+    pub red_ms: u32,
+    pub yellow_ms: u32,
+    pub green_ms: u32,
+    /// How long green lasts instead of `green_ms` when a pedestrian is
+    /// waiting to cross.
+    pub green_with_pedestrian_ms: u32,
+    /// How long pedestrians are shown Walk, from the start of the red.
+    pub walk_ms: u32,
+    /// How long pedestrians are shown Flashing Don't Walk after Walk. Walk
+    /// and Flashing Don't Walk together must fit in `red_ms`, so nobody is
+    /// crossing when the light turns green.
+    pub flashing_dont_walk_ms: u32,
+    /// How long green is kept on after a car is detected, so it can make it
+    /// through the intersection.
+    pub green_extension_ms: u32,
+    /// The longest green can last, however many cars keep arriving.
+    pub max_green_ms: u32,
+    /// How long green lasts when cars are waiting on the cross street, so
+    /// they don't have to wait for a full green.
+    pub min_green_ms: u32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            red_ms: 25000,
+            yellow_ms: 5000,
+            green_ms: 30000,
+            green_with_pedestrian_ms: 20000,
+            walk_ms: 15000,
+            flashing_dont_walk_ms: 7000,
+            green_extension_ms: 3000,
+            max_green_ms: 45000,
+            min_green_ms: 10000,
+        }
+    }
+}
+
+impl Timing {
+    /// How long the color in `state` has to be held before the light can
+    /// change.
+    fn duration_ms(&self, state: &TrafficLightState, sensors: Sensors) -> u32 {
+        let base_green_ms = self.base_green_ms(sensors);
+        match state.current_color {
+            TrafficLightColor::Red => self.red_ms,
+            TrafficLightColor::Yellow => self.yellow_ms,
+            // Waiting cross traffic cuts green short, whatever is coming
+            TrafficLightColor::Green if sensors.cross_traffic_waiting => {
+                base_green_ms.min(self.min_green_ms)
+            }
+            TrafficLightColor::Green => base_green_ms
+                .saturating_add(state.green_extension_ms)
+                .min(self.max_green_ms.max(base_green_ms)),
+        }
+    }
+
+    /// How long green lasts before any extensions.
+    fn base_green_ms(&self, sensors: Sensors) -> u32 {
+        if sensors.pedestrian_walk_request {
+            self.green_with_pedestrian_ms
+        } else {
+            self.green_ms
+        }
+    }
+
+    /// The pedestrian signal `red_elapsed_ms` after the light turned red.
+    /// Both phases are cut short if they don't fit in the red.
+    fn pedestrian_signal(&self, red_elapsed_ms: u32) -> PedestrianSignal {
+        let walk_end_ms = self.walk_ms.min(self.red_ms);
+        let flashing_end_ms = walk_end_ms
+            .saturating_add(self.flashing_dont_walk_ms)
+            .min(self.red_ms);
+        if red_elapsed_ms < walk_end_ms {
+            PedestrianSignal::Walk
+        } else if red_elapsed_ms < flashing_end_ms {
+            PedestrianSignal::FlashingDontWalk
+        } else {
+            PedestrianSignal::DontWalk
+        }
+    }
+}
+
+fn get_next_color(state: TrafficLightState) -> TrafficLightColor {
+    match state.current_color {
+        TrafficLightColor::Red => TrafficLightColor::Green,
+        TrafficLightColor::Yellow => TrafficLightColor::Red,
+        TrafficLightColor::Green => TrafficLightColor::Yellow,
+    }
+}
 
 fn get_next_state(
     state: TrafficLightState,
     current_time_ms: u32,
     pedestrian_walk_request: bool,
-) -> TrafficLightColor {
+) -> Signals {
+    let sensors = Sensors {
+        pedestrian_walk_request,
+        ..Sensors::default()
+    };
+    get_next_state_with(state, current_time_ms, sensors, &Timing::default())
+}
+
+/// What the light should show at `current_time_ms`. The color changes to
+/// the next one once the current one has been held for as long as `timing`
+/// requires.
+///
+/// A pedestrian walk request shortens green, and while the light is red it
+/// gives them Walk, then Flashing Don't Walk. Pedestrians are shown Don't
+/// Walk at every other time. Cars waiting on the cross street shorten green
+/// further, to `timing.min_green_ms`.
+fn get_next_state_with(
+    state: TrafficLightState,
+    current_time_ms: u32,
+    sensors: Sensors,
+    timing: &Timing,
+) -> Signals {
+    let held_ms = current_time_ms.saturating_sub(state.last_transition_time_ms);
+    let changing = held_ms >= timing.duration_ms(&state, sensors);
+    let vehicle = if changing {
+        get_next_color(state)
+    } else {
+        state.current_color
+    };
+
+    let pedestrian = match (vehicle, sensors.pedestrian_walk_request) {
+        // A red that starts now has been red for no time at all
+        (TrafficLightColor::Red, true) if changing => timing.pedestrian_signal(0),
+        (TrafficLightColor::Red, true) => timing.pedestrian_signal(held_ms),
+        _ => PedestrianSignal::DontWalk,
+    };
+    Signals {
+        vehicle,
+        pedestrian,
+    }
+}
+
+/// Records a car detected at `current_time_ms` approaching the green, which
+/// keeps the light green for at least `timing.green_extension_ms` longer, up
+/// to `timing.max_green_ms` in total. Cars don't extend any other color, or
+/// a green that waiting cross traffic is cutting short.
+fn extend_green(
+    state: TrafficLightState,
+    current_time_ms: u32,
+    sensors: Sensors,
+    timing: &Timing,
+) -> TrafficLightState {
+    if state.current_color != TrafficLightColor::Green || sensors.cross_traffic_waiting {
+        return state;
+    }
+
+    let held_ms = current_time_ms.saturating_sub(state.last_transition_time_ms);
+    // The green has to last until the car is through, which might already
+    // be covered by the green so far
+    let needed_ms = held_ms
+        .saturating_add(timing.green_extension_ms)
+        .saturating_sub(timing.base_green_ms(sensors));
+    TrafficLightState {
+        green_extension_ms: state.green_extension_ms.max(needed_ms),
+        ..state
+    }
 }
 
 // Do not modify below here
@@ -26,7 +218,8 @@ mod tests {
     use test_case::test_case;
 
     use crate::traffic_light::{
-        get_next_color, get_next_state, TrafficLightColor, TrafficLightState,
+        extend_green, get_next_color, get_next_state, get_next_state_with, PedestrianSignal,
+        Sensors, Timing, TrafficLightColor, TrafficLightState,
     };
 
     #[test_case(TrafficLightColor::Green, TrafficLightColor::Yellow ; "green -> yellow")]
@@ -36,6 +229,7 @@ mod tests {
         let state = TrafficLightState {
             current_color: start_color,
             last_transition_time_ms: 0,
+            green_extension_ms: 0,
         };
 
         assert_eq!(get_next_color(state), next_color);
@@ -46,23 +240,27 @@ mod tests {
         let mut state = TrafficLightState {
             current_color: TrafficLightColor::Green,
             last_transition_time_ms: 0,
+            green_extension_ms: 0,
         };
 
-        assert_eq!(get_next_state(state, 0, false), TrafficLightColor::Green);
         assert_eq!(
-            get_next_state(state, 19000, false),
+            get_next_state(state, 0, false).vehicle,
             TrafficLightColor::Green
         );
         assert_eq!(
-            get_next_state(state, 21000, false),
+            get_next_state(state, 19000, false).vehicle,
             TrafficLightColor::Green
         );
         assert_eq!(
-            get_next_state(state, 29000, false),
+            get_next_state(state, 21000, false).vehicle,
             TrafficLightColor::Green
         );
         assert_eq!(
-            get_next_state(state, 31000, false),
+            get_next_state(state, 29000, false).vehicle,
+            TrafficLightColor::Green
+        );
+        assert_eq!(
+            get_next_state(state, 31000, false).vehicle,
             TrafficLightColor::Yellow
         );
 
@@ -70,22 +268,31 @@ mod tests {
         state.last_transition_time_ms = 30000;
 
         assert_eq!(
-            get_next_state(state, 30000, false),
+            get_next_state(state, 30000, false).vehicle,
             TrafficLightColor::Yellow
         );
         assert_eq!(
-            get_next_state(state, 34000, false),
+            get_next_state(state, 34000, false).vehicle,
             TrafficLightColor::Yellow
         );
-        assert_eq!(get_next_state(state, 36000, false), TrafficLightColor::Red);
+        assert_eq!(
+            get_next_state(state, 36000, false).vehicle,
+            TrafficLightColor::Red
+        );
 
         state.current_color = TrafficLightColor::Red;
         state.last_transition_time_ms = 35000;
 
-        assert_eq!(get_next_state(state, 35000, false), TrafficLightColor::Red);
-        assert_eq!(get_next_state(state, 59000, false), TrafficLightColor::Red);
         assert_eq!(
-            get_next_state(state, 61000, false),
+            get_next_state(state, 35000, false).vehicle,
+            TrafficLightColor::Red
+        );
+        assert_eq!(
+            get_next_state(state, 59000, false).vehicle,
+            TrafficLightColor::Red
+        );
+        assert_eq!(
+            get_next_state(state, 61000, false).vehicle,
             TrafficLightColor::Green
         );
     }
@@ -95,12 +302,19 @@ mod tests {
         let mut state = TrafficLightState {
             current_color: TrafficLightColor::Green,
             last_transition_time_ms: 0,
+            green_extension_ms: 0,
         };
 
-        assert_eq!(get_next_state(state, 0, true), TrafficLightColor::Green);
-        assert_eq!(get_next_state(state, 19000, true), TrafficLightColor::Green);
         assert_eq!(
-            get_next_state(state, 21000, true),
+            get_next_state(state, 0, true).vehicle,
+            TrafficLightColor::Green
+        );
+        assert_eq!(
+            get_next_state(state, 19000, true).vehicle,
+            TrafficLightColor::Green
+        );
+        assert_eq!(
+            get_next_state(state, 21000, true).vehicle,
             TrafficLightColor::Yellow
         );
 
@@ -108,20 +322,166 @@ mod tests {
         state.last_transition_time_ms = 20000;
 
         assert_eq!(
-            get_next_state(state, 20000, true),
+            get_next_state(state, 20000, true).vehicle,
             TrafficLightColor::Yellow
         );
         assert_eq!(
-            get_next_state(state, 24000, true),
+            get_next_state(state, 24000, true).vehicle,
             TrafficLightColor::Yellow
         );
-        assert_eq!(get_next_state(state, 26000, true), TrafficLightColor::Red);
+        assert_eq!(
+            get_next_state(state, 26000, true).vehicle,
+            TrafficLightColor::Red
+        );
 
         state.current_color = TrafficLightColor::Red;
         state.last_transition_time_ms = 25000;
 
-        assert_eq!(get_next_state(state, 25000, true), TrafficLightColor::Red);
-        assert_eq!(get_next_state(state, 49000, true), TrafficLightColor::Red);
-        assert_eq!(get_next_state(state, 51000, true), TrafficLightColor::Green);
+        assert_eq!(
+            get_next_state(state, 25000, true).vehicle,
+            TrafficLightColor::Red
+        );
+        assert_eq!(
+            get_next_state(state, 49000, true).vehicle,
+            TrafficLightColor::Red
+        );
+        assert_eq!(
+            get_next_state(state, 51000, true).vehicle,
+            TrafficLightColor::Green
+        );
+    }
+
+    #[test]
+    fn test_get_next_state_pedestrian_signal() {
+        let mut state = TrafficLightState {
+            current_color: TrafficLightColor::Green,
+            last_transition_time_ms: 0,
+            green_extension_ms: 0,
+        };
+
+        // Nobody is allowed to walk unless the light is red
+        assert_eq!(
+            get_next_state(state, 0, true).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+        assert_eq!(
+            get_next_state(state, 21000, true).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+
+        state.current_color = TrafficLightColor::Yellow;
+        state.last_transition_time_ms = 20000;
+
+        assert_eq!(
+            get_next_state(state, 24000, true).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+        // Walk starts as soon as the light turns red
+        assert_eq!(
+            get_next_state(state, 25000, true).pedestrian,
+            PedestrianSignal::Walk
+        );
+
+        state.current_color = TrafficLightColor::Red;
+        state.last_transition_time_ms = 25000;
+
+        assert_eq!(
+            get_next_state(state, 39000, true).pedestrian,
+            PedestrianSignal::Walk
+        );
+        assert_eq!(
+            get_next_state(state, 41000, true).pedestrian,
+            PedestrianSignal::FlashingDontWalk
+        );
+        assert_eq!(
+            get_next_state(state, 46000, true).pedestrian,
+            PedestrianSignal::FlashingDontWalk
+        );
+        // Everyone has finished crossing before the light turns green
+        assert_eq!(
+            get_next_state(state, 48000, true).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+        assert_eq!(
+            get_next_state(state, 51000, true).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+
+        // Without a request, the crosswalk stays closed
+        assert_eq!(
+            get_next_state(state, 30000, false).pedestrian,
+            PedestrianSignal::DontWalk
+        );
+    }
+
+    #[test]
+    fn test_extend_green() {
+        let timing = Timing::default();
+        let sensors = Sensors::default();
+        let mut state = TrafficLightState {
+            current_color: TrafficLightColor::Green,
+            last_transition_time_ms: 0,
+            green_extension_ms: 0,
+        };
+
+        // A car well before the end of the green doesn't need any more time
+        state = extend_green(state, 10000, sensors, &timing);
+        assert_eq!(state.green_extension_ms, 0);
+
+        // A car near the end keeps the light green until it's through
+        state = extend_green(state, 29000, sensors, &timing);
+        assert_eq!(state.green_extension_ms, 2000);
+        let color = |state, time| get_next_state_with(state, time, sensors, &timing).vehicle;
+        assert_eq!(color(state, 31000), TrafficLightColor::Green);
+        assert_eq!(color(state, 32000), TrafficLightColor::Yellow);
+
+        // Cars that keep arriving keep extending it, up to the maximum
+        for time in (31000..60000).step_by(2000) {
+            state = extend_green(state, time, sensors, &timing);
+        }
+        assert_eq!(color(state, 44000), TrafficLightColor::Green);
+        assert_eq!(color(state, 45000), TrafficLightColor::Yellow);
+
+        // Only green can be extended
+        let red = TrafficLightState {
+            current_color: TrafficLightColor::Red,
+            last_transition_time_ms: 0,
+            green_extension_ms: 0,
+        };
+        assert_eq!(
+            extend_green(red, 24000, sensors, &timing).green_extension_ms,
+            0
+        );
+    }
+
+    #[test]
+    fn test_cross_traffic_shortens_green() {
+        let timing = Timing::default();
+        let sensors = Sensors {
+            cross_traffic_waiting: true,
+            ..Sensors::default()
+        };
+        let mut state = TrafficLightState {
+            current_color: TrafficLightColor::Green,
+            last_transition_time_ms: 0,
+            green_extension_ms: 0,
+        };
+
+        let color = |state, time| get_next_state_with(state, time, sensors, &timing).vehicle;
+        assert_eq!(color(state, 9000), TrafficLightColor::Green);
+        assert_eq!(color(state, 10000), TrafficLightColor::Yellow);
+
+        // Extensions are ignored while cross traffic is waiting
+        state.green_extension_ms = 10000;
+        assert_eq!(color(state, 10000), TrafficLightColor::Yellow);
+        assert_eq!(
+            extend_green(state, 9000, sensors, &timing).green_extension_ms,
+            10000
+        );
+
+        // Red and yellow aren't affected
+        state.current_color = TrafficLightColor::Red;
+        state.green_extension_ms = 0;
+        assert_eq!(color(state, 24000), TrafficLightColor::Red);
     }
 }
@@ -0,0 +1,391 @@
+//! Runs a traffic light over simulated time, keeping a log of every color
+//! change.
+
+use super::{
+    extend_green, get_next_state_with, Sensors, Signals, Timing, TrafficLightColor,
+    TrafficLightState,
+};
+
+/// The light changing from one color to another.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct TransitionEvent {
+    pub time_ms: u32,
+    pub from: TrafficLightColor,
+    pub to: TrafficLightColor,
+}
+
+/// A traffic light running on a simulated clock, which starts at 0 and only
+/// moves forward when told to.
+#[derive(Debug)]
+pub struct TrafficLightSim {
+    timing: Timing,
+    state: TrafficLightState,
+    time_ms: u32,
+    /// When the pedestrian waiting to cross pressed the button, if anyone
+    /// is waiting.
+    walk_requested_ms: Option<u32>,
+    cross_traffic_waiting: bool,
+    events: Vec<TransitionEvent>,
+}
+
+impl TrafficLightSim {
+    /// Creates a light that has just turned `color`.
+    ///
+    /// Panics if any duration in `timing` is zero, since the light would
+    /// never stop changing.
+    pub fn new(timing: Timing, color: TrafficLightColor) -> Self {
+        assert!(
+            timing.red_ms > 0
+                && timing.yellow_ms > 0
+                && timing.green_ms > 0
+                && timing.green_with_pedestrian_ms > 0
+                && timing.min_green_ms > 0,
+            "every color has to last for some time"
+        );
+        TrafficLightSim {
+            timing,
+            state: TrafficLightState {
+                current_color: color,
+                last_transition_time_ms: 0,
+                green_extension_ms: 0,
+            },
+            time_ms: 0,
+            walk_requested_ms: None,
+            cross_traffic_waiting: false,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn time_ms(&self) -> u32 {
+        self.time_ms
+    }
+
+    pub fn color(&self) -> TrafficLightColor {
+        self.state.current_color
+    }
+
+    /// What drivers and pedestrians are being shown right now.
+    pub fn signals(&self) -> Signals {
+        get_next_state_with(self.state, self.time_ms, self.sensors(), &self.timing)
+    }
+
+    fn sensors(&self) -> Sensors {
+        Sensors {
+            pedestrian_walk_request: self.walk_requested_ms.is_some(),
+            cross_traffic_waiting: self.cross_traffic_waiting,
+        }
+    }
+
+    /// Every color change so far, oldest first.
+    pub fn events(&self) -> &[TransitionEvent] {
+        &self.events
+    }
+
+    /// Has a pedestrian press the crosswalk button now. They get to walk
+    /// during the next red, and the green before it is shortened. If the
+    /// light is already red and still in its walk phase, they can walk
+    /// straight away.
+    pub fn request_walk(&mut self) {
+        self.walk_requested_ms.get_or_insert(self.time_ms);
+    }
+
+    /// Has a car arrive now on the street with the green, which may keep the
+    /// light green for longer.
+    pub fn detect_vehicle(&mut self) {
+        self.state = extend_green(self.state, self.time_ms, self.sensors(), &self.timing);
+    }
+
+    /// Sets whether cars are waiting on the cross street, which cuts green
+    /// short while they are.
+    pub fn set_cross_traffic_waiting(&mut self, waiting: bool) {
+        self.cross_traffic_waiting = waiting;
+    }
+
+    /// Moves the clock forward by `duration_ms`, changing colors at the exact
+    /// times they're due.
+    pub fn advance_ms(&mut self, duration_ms: u32) {
+        let end_ms = self.time_ms.saturating_add(duration_ms);
+
+        loop {
+            let sensors = self.sensors();
+            let hold_ms = self.timing.duration_ms(&self.state, sensors);
+            // A walk request or waiting cross traffic can make the current
+            // color overdue, in which case it changes right away
+            let due_ms = self
+                .state
+                .last_transition_time_ms
+                .saturating_add(hold_ms)
+                .max(self.time_ms);
+            if due_ms > end_ms {
+                break;
+            }
+
+            let from = self.state.current_color;
+            let to = get_next_state_with(self.state, due_ms, sensors, &self.timing).vehicle;
+            self.events.push(TransitionEvent {
+                time_ms: due_ms,
+                from,
+                to,
+            });
+            if from == TrafficLightColor::Red && self.walked_during_red() {
+                self.walk_requested_ms = None;
+            }
+            self.state = TrafficLightState {
+                current_color: to,
+                last_transition_time_ms: due_ms,
+                green_extension_ms: 0,
+            };
+            self.time_ms = due_ms;
+        }
+
+        self.time_ms = end_ms;
+    }
+
+    /// Whether the pedestrian waiting was shown Walk during the current red,
+    /// rather than pressing the button too late.
+    fn walked_during_red(&self) -> bool {
+        let walk_end_ms = self
+            .state
+            .last_transition_time_ms
+            .saturating_add(self.timing.walk_ms);
+        self.walk_requested_ms
+            .is_some_and(|requested_ms| requested_ms < walk_end_ms)
+    }
+
+    /// Moves the clock forward by `seconds`.
+    pub fn run_for_secs(&mut self, seconds: u32) {
+        self.advance_ms(seconds.saturating_mul(1000));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrafficLightSim, TransitionEvent};
+    use crate::traffic_light::{PedestrianSignal, Timing, TrafficLightColor};
+
+    use TrafficLightColor::{Green, Red, Yellow};
+
+    fn event(time_ms: u32, from: TrafficLightColor, to: TrafficLightColor) -> TransitionEvent {
+        TransitionEvent { time_ms, from, to }
+    }
+
+    #[test]
+    fn test_default_cycle() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.run_for_secs(60);
+
+        assert_eq!(
+            sim.events(),
+            [
+                event(30000, Green, Yellow),
+                event(35000, Yellow, Red),
+                event(60000, Red, Green),
+            ]
+        );
+        assert_eq!(sim.color(), Green);
+        assert_eq!(sim.time_ms(), 60000);
+    }
+
+    #[test]
+    fn test_advance_in_steps() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Red);
+        sim.advance_ms(24999);
+        assert_eq!(sim.color(), Red);
+        assert!(sim.events().is_empty());
+
+        sim.advance_ms(1);
+        assert_eq!(sim.color(), Green);
+        assert_eq!(sim.events(), [event(25000, Red, Green)]);
+    }
+
+    #[test]
+    fn test_long_run() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        // Ten full 60 second cycles
+        sim.run_for_secs(600);
+        assert_eq!(sim.events().len(), 30);
+        assert_eq!(sim.events().last(), Some(&event(600000, Red, Green)));
+    }
+
+    #[test]
+    fn test_custom_timing() {
+        let timing = Timing {
+            red_ms: 1000,
+            yellow_ms: 500,
+            green_ms: 2000,
+            green_with_pedestrian_ms: 1500,
+            walk_ms: 500,
+            flashing_dont_walk_ms: 300,
+            ..Timing::default()
+        };
+        let mut sim = TrafficLightSim::new(timing, Yellow);
+        sim.advance_ms(4000);
+        assert_eq!(
+            sim.events(),
+            [
+                event(500, Yellow, Red),
+                event(1500, Red, Green),
+                event(3500, Green, Yellow),
+                event(4000, Yellow, Red),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pedestrian_shortens_green() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.run_for_secs(5);
+        sim.request_walk();
+        sim.run_for_secs(55);
+
+        assert_eq!(
+            sim.events(),
+            [
+                event(20000, Green, Yellow),
+                event(25000, Yellow, Red),
+                event(50000, Red, Green),
+            ]
+        );
+
+        // The pedestrian crossed during the red, so the next green is normal
+        sim.run_for_secs(21);
+        assert_eq!(sim.events().last(), Some(&event(80000, Green, Yellow)));
+    }
+
+    #[test]
+    fn test_late_pedestrian_changes_green_immediately() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.run_for_secs(25);
+        sim.request_walk();
+        sim.advance_ms(0);
+        assert_eq!(sim.events(), [event(25000, Green, Yellow)]);
+    }
+
+    #[test]
+    fn test_pedestrian_signal() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.request_walk();
+        assert_eq!(sim.signals().vehicle, Green);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::DontWalk);
+
+        // Green ends at 20s and yellow at 25s
+        sim.run_for_secs(24);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::DontWalk);
+        sim.run_for_secs(1);
+        assert_eq!(sim.color(), Red);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::Walk);
+        sim.run_for_secs(15);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::FlashingDontWalk);
+        sim.run_for_secs(7);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::DontWalk);
+        assert_eq!(sim.color(), Red);
+
+        // Once they've crossed, the signal stays at Don't Walk
+        sim.run_for_secs(30);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::DontWalk);
+    }
+
+    #[test]
+    fn test_walk_only_during_red() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        for _ in 0..600 {
+            if sim.time_ms().is_multiple_of(7000) {
+                sim.request_walk();
+            }
+            let signals = sim.signals();
+            if signals.pedestrian != PedestrianSignal::DontWalk {
+                assert_eq!(signals.vehicle, Red, "at {}ms", sim.time_ms());
+            }
+            sim.advance_ms(500);
+        }
+    }
+
+    #[test]
+    fn test_pedestrian_too_late_for_walk() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Red);
+        // Walk ends 15s into the red
+        sim.run_for_secs(16);
+        sim.request_walk();
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::FlashingDontWalk);
+
+        // They wait for the next red, which comes sooner
+        sim.run_for_secs(9);
+        assert_eq!(sim.events(), [event(25000, Red, Green)]);
+        sim.run_for_secs(20);
+        assert_eq!(sim.events().last(), Some(&event(45000, Green, Yellow)));
+        sim.run_for_secs(5);
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::Walk);
+    }
+
+    #[test]
+    fn test_pedestrian_during_red() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Red);
+        sim.run_for_secs(10);
+        sim.request_walk();
+        // Walk is still showing, so they cross straight away and the next
+        // green isn't shortened
+        assert_eq!(sim.signals().pedestrian, PedestrianSignal::Walk);
+        sim.run_for_secs(47);
+        assert_eq!(
+            sim.events(),
+            [event(25000, Red, Green), event(55000, Green, Yellow)]
+        );
+    }
+
+    #[test]
+    fn test_vehicles_extend_green() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        // A car every 2 seconds from 28s keeps the light green
+        sim.run_for_secs(28);
+        for _ in 0..5 {
+            sim.detect_vehicle();
+            sim.run_for_secs(2);
+        }
+        assert_eq!(sim.color(), Green);
+
+        // The last car, at 36s, gets 3 seconds to make it through
+        sim.run_for_secs(10);
+        assert_eq!(sim.events()[0], event(39000, Green, Yellow));
+
+        // The extension doesn't carry over to the next green
+        sim.run_for_secs(60);
+        assert_eq!(sim.events()[3], event(99000, Green, Yellow));
+    }
+
+    #[test]
+    fn test_vehicles_extend_green_up_to_max() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        for _ in 0..100 {
+            sim.detect_vehicle();
+            sim.advance_ms(1000);
+        }
+        assert_eq!(sim.events()[0], event(45000, Green, Yellow));
+    }
+
+    #[test]
+    fn test_cross_traffic_cuts_green_short() {
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.run_for_secs(5);
+        sim.set_cross_traffic_waiting(true);
+        sim.run_for_secs(5);
+        assert_eq!(sim.events(), [event(10000, Green, Yellow)]);
+
+        // Arriving after the minimum green has passed ends it right away
+        let mut sim = TrafficLightSim::new(Timing::default(), Green);
+        sim.run_for_secs(20);
+        sim.detect_vehicle();
+        sim.set_cross_traffic_waiting(true);
+        sim.advance_ms(0);
+        assert_eq!(sim.events(), [event(20000, Green, Yellow)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "every color has to last for some time")]
+    fn test_zero_duration() {
+        let timing = Timing {
+            yellow_ms: 0,
+            ..Timing::default()
+        };
+        TrafficLightSim::new(timing, Green);
+    }
+}
@@ -1,29 +1,857 @@
 // Now try and implement a doubly linked version. Give an explanation
 // for why this doesn't work.
+//
+// With `Option<Box<Node>>` links, every node would need to be owned by both
+// its predecessor (through `next`) and its successor (through `prev`), and
+// the tail would additionally be owned by the list itself. A `Box` has exactly
+// one owner, so that design can't be expressed. Instead, forward links are
+// shared-ownership `Rc<RefCell<Node>>`s and backward links are non-owning
+// `Weak` references, which avoids a reference cycle that would leak memory.
 
-struct Node {
-    val: i32,
-    next: Link,
-    prev: Link,
+use std::cell::{RefCell, RefMut};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+struct Node<T> {
+    val: T,
+    next: Link<T>,
+    prev: WeakLink<T>,
+}
+
+type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+type WeakLink<T> = Option<Weak<RefCell<Node<T>>>>;
+
+pub struct LinkedStack<T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+}
+
+impl<T> Node<T> {
+    fn new(val: T) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Node {
+            val,
+            next: None,
+            prev: None,
+        }))
+    }
+
+    /// Consumes a node that has already been unlinked from the list and
+    /// returns its value.
+    fn into_val(node: Rc<RefCell<Self>>) -> T {
+        match Rc::try_unwrap(node) {
+            Ok(cell) => cell.into_inner().val,
+            Err(_) => unreachable!("an unlinked node should have no other owners"),
+        }
+    }
+}
+
+impl<T> LinkedStack<T> {
+    pub fn new() -> Self {
+        LinkedStack {
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes onto the head of the list, giving stack semantics together
+    /// with `pop`.
+    pub fn push(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    /// Pops from the head of the list.
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let new_head = Node::new(val);
+        match self.head.take() {
+            Some(old_head) => {
+                old_head.borrow_mut().prev = Some(Rc::downgrade(&new_head));
+                new_head.borrow_mut().next = Some(old_head);
+            }
+            None => self.tail = Some(Rc::clone(&new_head)),
+        }
+        self.head = Some(new_head);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        let new_tail = Node::new(val);
+        match self.tail.take() {
+            Some(old_tail) => {
+                new_tail.borrow_mut().prev = Some(Rc::downgrade(&old_tail));
+                old_tail.borrow_mut().next = Some(Rc::clone(&new_tail));
+            }
+            None => self.head = Some(Rc::clone(&new_tail)),
+        }
+        self.tail = Some(new_tail);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.take().map(|old_head| {
+            match old_head.borrow_mut().next.take() {
+                Some(new_head) => {
+                    new_head.borrow_mut().prev = None;
+                    self.head = Some(new_head);
+                }
+                None => self.tail = None,
+            }
+            self.len -= 1;
+            Node::into_val(old_head)
+        })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.take().map(|old_tail| {
+            let prev = old_tail
+                .borrow_mut()
+                .prev
+                .take()
+                .and_then(|weak| weak.upgrade());
+            match prev {
+                Some(new_tail) => {
+                    new_tail.borrow_mut().next = None;
+                    self.tail = Some(new_tail);
+                }
+                None => self.head = None,
+            }
+            self.len -= 1;
+            Node::into_val(old_tail)
+        })
+    }
+
+    /// Moves every element of `other` onto the tail of this list in O(1),
+    /// leaving `other` empty.
+    pub fn append(&mut self, other: &mut LinkedStack<T>) {
+        let Some(other_head) = other.head.take() else {
+            return;
+        };
+        match self.tail.take() {
+            Some(tail) => {
+                other_head.borrow_mut().prev = Some(Rc::downgrade(&tail));
+                tail.borrow_mut().next = Some(other_head);
+            }
+            None => self.head = Some(other_head),
+        }
+        self.tail = other.tail.take();
+        self.len += std::mem::take(&mut other.len);
+    }
+
+    /// Splits the list in two at index `at`, returning everything from `at`
+    /// onwards and keeping `[0, at)` in this list. Walks from whichever end is
+    /// closer to `at`.
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> LinkedStack<T> {
+        assert!(at <= self.len, "cannot split off at a nonexistent index");
+        if at == 0 {
+            return std::mem::take(self);
+        }
+
+        let len = self.len;
+        let mut cursor = self.cursor_front_mut();
+        if at - 1 < len / 2 {
+            for _ in 0..at - 1 {
+                cursor.move_next();
+            }
+        } else {
+            // Step back onto the ghost position, then walk back from the tail
+            cursor.move_prev();
+            for _ in at - 1..len {
+                cursor.move_prev();
+            }
+        }
+        cursor.split_after()
+    }
+
+    /// Returns a cursor positioned at the head of the list (or at the "ghost"
+    /// position if the list is empty).
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        let current = self.head.clone();
+        let index = current.as_ref().map(|_| 0);
+        CursorMut {
+            list: self,
+            current,
+            index,
+        }
+    }
+
+    /// Calls `f` on each value from head to tail, stopping at the first error.
+    fn try_for_each<E>(&self, mut f: impl FnMut(&T) -> Result<(), E>) -> Result<(), E> {
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let node = node.borrow();
+            f(&node.val)?;
+            current = node.next.clone();
+        }
+        Ok(())
+    }
+}
+
+/// A cursor that can walk a `LinkedStack` in either direction and edit it in
+/// place.
+///
+/// Like `std::collections::linked_list::CursorMut`, the cursor is either
+/// pointing at an element or at a "ghost" position that sits between the tail
+/// and the head. Moving forward from the tail (or backward from the head)
+/// lands on the ghost, and moving from the ghost wraps around to the other
+/// end.
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedStack<T>,
+    current: Link<T>,
+    index: Option<usize>,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// The index of the current element, or `None` at the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// A mutable borrow of the current element, or `None` at the ghost
+    /// position.
+    pub fn current(&mut self) -> Option<RefMut<'_, T>> {
+        self.current
+            .as_ref()
+            .map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.val))
+    }
+
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            Some(node) => {
+                self.current = node.borrow().next.clone();
+                self.index = match self.current {
+                    Some(_) => self.index.map(|index| index + 1),
+                    None => None,
+                };
+            }
+            None => {
+                self.current = self.list.head.clone();
+                self.index = self.current.as_ref().map(|_| 0);
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            Some(node) => {
+                self.current = node.borrow().prev.as_ref().and_then(Weak::upgrade);
+                self.index = match self.current {
+                    Some(_) => self.index.map(|index| index - 1),
+                    None => None,
+                };
+            }
+            None => {
+                self.current = self.list.tail.clone();
+                self.index = self.current.as_ref().map(|_| self.list.len - 1);
+            }
+        }
+    }
+
+    /// Inserts a value directly after the current element. At the ghost
+    /// position, the value becomes the new head. The cursor doesn't move.
+    pub fn insert_after(&mut self, val: T) {
+        let Some(current) = self.current.clone() else {
+            self.list.push_front(val);
+            return;
+        };
+
+        let new_node = Node::new(val);
+        new_node.borrow_mut().prev = Some(Rc::downgrade(&current));
+        match current.borrow_mut().next.take() {
+            Some(next) => {
+                next.borrow_mut().prev = Some(Rc::downgrade(&new_node));
+                new_node.borrow_mut().next = Some(next);
+            }
+            None => self.list.tail = Some(Rc::clone(&new_node)),
+        }
+        current.borrow_mut().next = Some(new_node);
+        self.list.len += 1;
+    }
+
+    /// Removes the current element and returns it, moving the cursor to the
+    /// following element (or to the ghost position if the tail was removed).
+    /// Returns `None` without modifying the list at the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current.take()?;
+        let next = node.borrow_mut().next.take();
+        let prev = node
+            .borrow_mut()
+            .prev
+            .take()
+            .and_then(|weak| weak.upgrade());
+
+        match &prev {
+            Some(prev) => prev.borrow_mut().next = next.clone(),
+            None => self.list.head = next.clone(),
+        }
+        match &next {
+            Some(next) => next.borrow_mut().prev = prev.as_ref().map(Rc::downgrade),
+            None => self.list.tail = prev,
+        }
+
+        self.list.len -= 1;
+        if next.is_none() {
+            self.index = None;
+        }
+        self.current = next;
+        Some(Node::into_val(node))
+    }
+
+    /// Splits the list after the current element, returning everything that
+    /// followed it as a new list. At the ghost position, the whole list is
+    /// moved into the returned list and the original is left empty.
+    pub fn split_after(&mut self) -> LinkedStack<T> {
+        let (Some(current), Some(index)) = (&self.current, self.index) else {
+            return std::mem::take(self.list);
+        };
+
+        let Some(new_head) = current.borrow_mut().next.take() else {
+            return LinkedStack::new();
+        };
+        new_head.borrow_mut().prev = None;
+
+        let split_len = self.list.len - index - 1;
+        self.list.len = index + 1;
+        LinkedStack {
+            head: Some(new_head),
+            tail: self.list.tail.replace(Rc::clone(current)),
+            len: split_len,
+        }
+    }
+}
+
+/// The cursor holds a strong reference to the current node, so it must be
+/// dropped before the list can be used again. Implementing `Drop` makes the
+/// borrow checker enforce that instead of letting the borrow end early.
+impl<T> Drop for CursorMut<'_, T> {
+    fn drop(&mut self) {}
+}
+
+impl<T: fmt::Debug> fmt::Debug for LinkedStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        self.try_for_each(|val| {
+            list.entry(val);
+            Ok(())
+        })?;
+        list.finish()
+    }
 }
 
-type Link = Option<Box<Node>>;
+/// Renders the list from head to tail, e.g. `3 -> 2 -> 1` after pushing 1, 2,
+/// and 3. An empty list renders as an empty string.
+impl<T: fmt::Display> fmt::Display for LinkedStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        self.try_for_each(|val| {
+            if !first {
+                write!(f, " -> ")?;
+            }
+            first = false;
+            write!(f, "{val}")
+        })
+    }
+}
 
-pub struct LinkedStack {
-    head: Link,
-    tail: Link,
+impl<T> Default for LinkedStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl LinkedStack {
-    fn new() -> Self {
-        todo!();
+/// Pushes each item onto the head in iteration order, exactly as repeated
+/// calls to `push` would, so the last item yielded ends up on top.
+impl<T> Extend<T> for LinkedStack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.push(val);
+        }
+    }
+}
+
+/// Builds a stack with the same ordering semantics as `Extend`: the last item
+/// yielded is on top and is the first one popped.
+impl<T> FromIterator<T> for LinkedStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = LinkedStack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+/// Treats the end of the `Vec` as the top of the stack, so popping from the
+/// resulting list yields values in the same order as `Vec::pop`.
+impl<T> From<Vec<T>> for LinkedStack<T> {
+    fn from(vec: Vec<T>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<T> Drop for LinkedStack<T> {
+    fn drop(&mut self) {
+        // Unlink iteratively so long lists don't overflow the stack with
+        // recursive `Rc` drops.
+        while self.pop_front().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_stack() {
+        let mut stack: LinkedStack<i32> = LinkedStack::new();
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.pop_back(), None);
+    }
+
+    #[test]
+    fn test_linked_stack() {
+        let mut stack = LinkedStack::new();
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+
+        stack.push(4);
+
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_push_back_pop_back() {
+        let mut stack = LinkedStack::new();
+        stack.push_back(1);
+        stack.push_back(2);
+        stack.push_back(3);
+
+        assert_eq!(stack.pop_back(), Some(3));
+        assert_eq!(stack.pop_back(), Some(2));
+
+        stack.push_back(4);
+
+        assert_eq!(stack.pop_back(), Some(4));
+        assert_eq!(stack.pop_back(), Some(1));
+        assert_eq!(stack.pop_back(), None);
+    }
+
+    #[test]
+    fn test_both_ends() {
+        let mut stack = LinkedStack::new();
+        stack.push_front(2);
+        stack.push_back(3);
+        stack.push_front(1);
+        stack.push_back(4);
+
+        assert_eq!(stack.pop_front(), Some(1));
+        assert_eq!(stack.pop_back(), Some(4));
+        assert_eq!(stack.pop_back(), Some(3));
+        assert_eq!(stack.pop_back(), Some(2));
+        assert_eq!(stack.pop_front(), None);
+        assert_eq!(stack.pop_back(), None);
+
+        // The list should be reusable after being emptied from either end
+        stack.push_back(5);
+        assert_eq!(stack.pop_front(), Some(5));
+        stack.push_front(6);
+        assert_eq!(stack.pop_back(), Some(6));
+    }
+
+    #[test]
+    fn test_len_and_is_empty() {
+        let mut stack = LinkedStack::new();
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
+
+        stack.push_front(1);
+        stack.push_back(2);
+        stack.push(3);
+        assert_eq!(stack.len(), 3);
+        assert!(!stack.is_empty());
+
+        stack.pop_back();
+        assert_eq!(stack.len(), 2);
+        stack.pop_front();
+        stack.pop();
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
+
+        // Popping an empty list shouldn't underflow the length
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.pop_back(), None);
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut stack = LinkedStack::new();
+        assert_eq!(stack.to_string(), "");
+
+        stack.push(1);
+        assert_eq!(stack.to_string(), "1");
+
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.to_string(), "3 -> 2 -> 1");
+
+        stack.push_back(0);
+        assert_eq!(stack.to_string(), "3 -> 2 -> 1 -> 0");
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut stack = LinkedStack::new();
+        assert_eq!(format!("{:?}", stack), "[]");
+
+        stack.push("b");
+        stack.push("a");
+        assert_eq!(format!("{:?}", stack), r#"["a", "b"]"#);
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let mut stack: LinkedStack<_> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.to_string(), "3 -> 2 -> 1");
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop_back(), Some(1));
+
+        let empty: LinkedStack<i32> = std::iter::empty().collect();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut stack = LinkedStack::new();
+        stack.push(1);
+        stack.extend(vec![2, 3]);
+        stack.extend(4..=5);
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.to_string(), "5 -> 4 -> 3 -> 2 -> 1");
+
+        stack.extend(Vec::new());
+        assert_eq!(stack.len(), 5);
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let mut vec = vec!['a', 'b', 'c'];
+        let mut stack = LinkedStack::from(vec.clone());
+        assert_eq!(stack.len(), vec.len());
+
+        // Popping should match `Vec::pop` order
+        while let Some(expected) = vec.pop() {
+            assert_eq!(stack.pop(), Some(expected));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    /// Inserts `val` before the first element that is not smaller than it,
+    /// keeping an ascending list sorted.
+    fn insert_sorted(stack: &mut LinkedStack<i32>, val: i32) {
+        let mut cursor = stack.cursor_front_mut();
+        cursor.move_prev();
+        loop {
+            cursor.move_next();
+            if cursor.current().is_none_or(|current| *current >= val) {
+                cursor.move_prev();
+                break;
+            }
+        }
+        cursor.insert_after(val);
+    }
+
+    #[test]
+    fn test_cursor_walk() {
+        let mut stack = LinkedStack::from(vec![3, 2, 1]);
+        let mut cursor = stack.cursor_front_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current().map(|val| *val), Some(1));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current().map(|val| *val), Some(3));
+
+        // Walking off the tail lands on the ghost, then wraps to the head
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        assert!(cursor.current().is_none());
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+
+        // And the same going backwards
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current().map(|val| *val), Some(3));
+
+        if let Some(mut val) = cursor.current() {
+            *val = 30;
+        }
+        drop(cursor);
+        assert_eq!(stack.to_string(), "1 -> 2 -> 30");
+    }
+
+    #[test]
+    fn test_cursor_empty() {
+        let mut stack: LinkedStack<i32> = LinkedStack::new();
+        let mut cursor = stack.cursor_front_mut();
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.remove_current(), None);
+        assert!(cursor.split_after().is_empty());
+
+        cursor.insert_after(1);
+        assert_eq!(cursor.index(), None);
+        drop(cursor);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop_back(), Some(1));
+    }
+
+    #[test]
+    fn test_cursor_insert_after() {
+        let mut stack = LinkedStack::from(vec![3, 1]);
+        let mut cursor = stack.cursor_front_mut();
+
+        // In the middle
+        cursor.insert_after(2);
+        // At the tail
+        cursor.move_next();
+        cursor.move_next();
+        cursor.insert_after(4);
+        // At the ghost, which inserts at the head
+        cursor.move_next();
+        assert_eq!(cursor.current().map(|val| *val), Some(4));
+        cursor.move_next();
+        cursor.insert_after(0);
+        drop(cursor);
+
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.to_string(), "0 -> 1 -> 2 -> 3 -> 4");
+        assert_eq!(stack.pop_back(), Some(4));
+        assert_eq!(stack.pop_front(), Some(0));
+    }
+
+    #[test]
+    fn test_cursor_remove_current() {
+        let mut stack = LinkedStack::from(vec![4, 3, 2, 1]);
+        let mut cursor = stack.cursor_front_mut();
+
+        // Head
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current().map(|val| *val), Some(2));
+
+        // Middle
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(3));
+        assert_eq!(cursor.current().map(|val| *val), Some(4));
+
+        // Tail, which leaves the cursor on the ghost
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.remove_current(), None);
+        drop(cursor);
+
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop_back(), Some(2));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_split_after() {
+        let mut stack = LinkedStack::from(vec![5, 4, 3, 2, 1]);
+        let mut cursor = stack.cursor_front_mut();
+        cursor.move_next();
+        let mut back = cursor.split_after();
+        drop(cursor);
+        assert_eq!(stack.to_string(), "1 -> 2");
+        assert_eq!(stack.len(), 2);
+        assert_eq!(back.to_string(), "3 -> 4 -> 5");
+        assert_eq!(back.len(), 3);
+
+        // Both halves should have consistent ends
+        assert_eq!(stack.pop_back(), Some(2));
+        assert_eq!(back.pop_front(), Some(3));
+        assert_eq!(back.pop_back(), Some(5));
+
+        // Splitting after the tail yields an empty list
+        let mut cursor = back.cursor_front_mut();
+        assert!(cursor.split_after().is_empty());
+        drop(cursor);
+        assert_eq!(back.len(), 1);
+
+        // Splitting at the ghost moves everything
+        let mut cursor = back.cursor_front_mut();
+        cursor.move_prev();
+        let everything = cursor.split_after();
+        drop(cursor);
+        assert!(back.is_empty());
+        assert_eq!(everything.to_string(), "4");
+    }
+
+    #[test]
+    fn test_cursor_ordered_insert() {
+        let mut stack = LinkedStack::new();
+        for val in [5, 1, 4, 2, 3, 0, 6] {
+            insert_sorted(&mut stack, val);
+        }
+        assert_eq!(stack.to_string(), "0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 6");
+        assert_eq!(stack.len(), 7);
+        assert_eq!(stack.pop_back(), Some(6));
+    }
+
+    #[test]
+    fn test_append() {
+        let mut first = LinkedStack::from(vec![3, 2, 1]);
+        let mut second = LinkedStack::from(vec![6, 5, 4]);
+        first.append(&mut second);
+
+        assert_eq!(first.to_string(), "1 -> 2 -> 3 -> 4 -> 5 -> 6");
+        assert_eq!(first.len(), 6);
+        assert!(second.is_empty());
+        assert_eq!(second.pop(), None);
+        assert_eq!(second.pop_back(), None);
+
+        // The spliced ends should be linked in both directions
+        assert_eq!(first.pop_back(), Some(6));
+        assert_eq!(first.pop_back(), Some(5));
+        assert_eq!(first.pop_back(), Some(4));
+        assert_eq!(first.pop_back(), Some(3));
+
+        // The drained list stays usable
+        second.push(7);
+        first.append(&mut second);
+        assert_eq!(first.to_string(), "1 -> 2 -> 7");
+    }
+
+    #[test]
+    fn test_append_empty() {
+        let mut first = LinkedStack::new();
+        let mut second = LinkedStack::from(vec![2, 1]);
+
+        // Appending onto an empty list moves everything over
+        first.append(&mut second);
+        assert_eq!(first.to_string(), "1 -> 2");
+        assert!(second.is_empty());
+
+        // Appending an empty list is a no-op
+        first.append(&mut second);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first.pop_back(), Some(2));
+        assert_eq!(first.pop_front(), Some(1));
     }
 
-    fn push(&mut self, val: i32) {
-        todo!()
+    #[test]
+    fn test_append_moves_ownership() {
+        let tracker = Rc::new(());
+        let mut first = LinkedStack::new();
+        {
+            let mut second = LinkedStack::new();
+            for _ in 0..5 {
+                second.push(Rc::clone(&tracker));
+            }
+            first.append(&mut second);
+        }
+        // Dropping the drained list must not drop the moved values
+        assert_eq!(Rc::strong_count(&tracker), 6);
+        drop(first);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn test_split_off() {
+        for at in 0..=6 {
+            let mut front: LinkedStack<_> = (0..6).rev().collect();
+            let back = front.split_off(at);
+
+            assert_eq!(front.len(), at);
+            assert_eq!(back.len(), 6 - at);
+
+            let front_vals: Vec<_> = std::iter::from_fn(|| front.pop_front()).collect();
+            assert_eq!(front_vals, (0..at).collect::<Vec<_>>());
+
+            // Walk the split half from the back to check its `prev` links too
+            let mut back = back;
+            let back_vals: Vec<_> = std::iter::from_fn(|| back.pop_back()).collect();
+            assert_eq!(back_vals, (at..6).rev().collect::<Vec<_>>());
+        }
     }
 
-    fn pop(&mut self) -> Option<i32> {
-        todo!();
+    #[test]
+    fn test_split_off_then_append() {
+        let mut stack: LinkedStack<_> = (1..=5).rev().collect();
+        let mut back = stack.split_off(2);
+        assert_eq!(stack.to_string(), "1 -> 2");
+        assert_eq!(back.to_string(), "3 -> 4 -> 5");
+
+        stack.append(&mut back);
+        assert_eq!(stack.to_string(), "1 -> 2 -> 3 -> 4 -> 5");
+        assert_eq!(stack.len(), 5);
+        assert!(back.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_split_off_out_of_bounds() {
+        let mut stack = LinkedStack::from(vec![1, 2]);
+        stack.split_off(3);
+    }
+
+    #[test]
+    fn test_drop_releases_values() {
+        let tracker = Rc::new(());
+        {
+            let mut stack = LinkedStack::new();
+            for _ in 0..10 {
+                stack.push_back(Rc::clone(&tracker));
+            }
+            stack.pop_back();
+            assert_eq!(Rc::strong_count(&tracker), 10);
+        }
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn test_big_stack() {
+        let mut stack = LinkedStack::new();
+        for i in 0..1_000_000 {
+            stack.push_back(i);
+        }
+
+        for i in 0..1_000_000 {
+            assert_eq!(stack.pop_front(), Some(i));
+        }
+
+        assert_eq!(stack.pop_back(), None);
+
+        // Dropping a long list shouldn't overflow the stack
+        let mut stack = LinkedStack::new();
+        for i in 0..1_000_000 {
+            stack.push(i);
+        }
     }
 }
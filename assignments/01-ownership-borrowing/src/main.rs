@@ -4,6 +4,7 @@ mod basics;
 mod doubly_linked_list;
 mod mutables;
 mod singly_linked_list;
+mod unsafe_deque;
 
 // SOLUTIONS
 //
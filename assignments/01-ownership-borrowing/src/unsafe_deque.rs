@@ -0,0 +1,338 @@
+// A doubly linked deque built on raw pointers, as a contrast to the
+// `Rc<RefCell<Node>>` version in `doubly_linked_list.rs`.
+//
+// Each node is allocated with `Box` and then leaked into a `NonNull` pointer,
+// so the deque itself is the single logical owner of every node. That lets
+// nodes point at each other in both directions without reference counting or
+// runtime borrow checks, at the cost of having to uphold the aliasing rules by
+// hand. All of the `unsafe` is kept behind a safe public API.
+//
+// The tests are written to run under Miri, which checks for use-after-free,
+// leaks, and aliasing violations:
+//
+//     cargo +nightly miri test unsafe_deque
+
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+struct Node<T> {
+    val: T,
+    next: Link<T>,
+    prev: Link<T>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+pub struct UnsafeDeque<T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    // Tells the drop checker that the deque owns values of type `T`
+    _owns: PhantomData<T>,
+}
+
+impl<T> UnsafeDeque<T> {
+    pub fn new() -> Self {
+        UnsafeDeque {
+            head: None,
+            tail: None,
+            len: 0,
+            _owns: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let new_head = Self::allocate(val);
+        // SAFETY: `new_head` was just allocated and `old_head` is a live node
+        // owned by this deque. No other references to either exist.
+        unsafe {
+            match self.head {
+                Some(old_head) => {
+                    (*old_head.as_ptr()).prev = Some(new_head);
+                    (*new_head.as_ptr()).next = Some(old_head);
+                }
+                None => self.tail = Some(new_head),
+            }
+        }
+        self.head = Some(new_head);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        let new_tail = Self::allocate(val);
+        // SAFETY: see `push_front`
+        unsafe {
+            match self.tail {
+                Some(old_tail) => {
+                    (*old_tail.as_ptr()).next = Some(new_tail);
+                    (*new_tail.as_ptr()).prev = Some(old_tail);
+                }
+                None => self.head = Some(new_tail),
+            }
+        }
+        self.tail = Some(new_tail);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.map(|old_head| {
+            // SAFETY: `old_head` came from `allocate` and is still linked into
+            // this deque, so reclaiming its box is the only outstanding use.
+            // After unlinking, nothing points at it anymore.
+            let old_head = unsafe { Box::from_raw(old_head.as_ptr()) };
+            self.head = old_head.next;
+            match self.head {
+                // SAFETY: `new_head` is a live node owned by this deque
+                Some(new_head) => unsafe { (*new_head.as_ptr()).prev = None },
+                None => self.tail = None,
+            }
+            self.len -= 1;
+            old_head.val
+        })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.map(|old_tail| {
+            // SAFETY: see `pop_front`
+            let old_tail = unsafe { Box::from_raw(old_tail.as_ptr()) };
+            self.tail = old_tail.prev;
+            match self.tail {
+                // SAFETY: `new_tail` is a live node owned by this deque
+                Some(new_tail) => unsafe { (*new_tail.as_ptr()).next = None },
+                None => self.head = None,
+            }
+            self.len -= 1;
+            old_tail.val
+        })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        // SAFETY: the node is live for as long as `self` is borrowed, since
+        // removing it requires `&mut self`
+        self.head.map(|node| unsafe { &(*node.as_ptr()).val })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: see `front`
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).val })
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: `&mut self` guarantees this is the only reference into the
+        // deque for the returned lifetime
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).val })
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: see `front_mut`
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).val })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head,
+            remaining: self.len,
+            _borrow: PhantomData,
+        }
+    }
+
+    fn allocate(val: T) -> NonNull<Node<T>> {
+        let node = Box::new(Node {
+            val,
+            next: None,
+            prev: None,
+        });
+        NonNull::from(Box::leak(node))
+    }
+}
+
+impl<T> Default for UnsafeDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for UnsafeDeque<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+/// A front-to-back iterator over shared references to the deque's values.
+pub struct Iter<'a, T> {
+    next: Link<T>,
+    remaining: usize,
+    _borrow: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            // SAFETY: the iterator borrows the deque, so every node it can
+            // reach stays alive and unmodified for `'a`
+            let node = unsafe { &*node.as_ptr() };
+            self.next = node.next;
+            self.remaining -= 1;
+            &node.val
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_new_deque() {
+        let mut deque: UnsafeDeque<i32> = UnsafeDeque::new();
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.front(), None);
+        assert_eq!(deque.back(), None);
+    }
+
+    #[test]
+    fn test_front() {
+        let mut deque = UnsafeDeque::new();
+        deque.push_front(1);
+        deque.push_front(2);
+        deque.push_front(3);
+
+        assert_eq!(deque.pop_front(), Some(3));
+        assert_eq!(deque.pop_front(), Some(2));
+
+        deque.push_front(4);
+
+        assert_eq!(deque.pop_front(), Some(4));
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_front(), None);
+    }
+
+    #[test]
+    fn test_back() {
+        let mut deque = UnsafeDeque::new();
+        deque.push_back(1);
+        deque.push_back(2);
+        deque.push_back(3);
+
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_back(), Some(2));
+
+        deque.push_back(4);
+
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_back(), Some(1));
+        assert_eq!(deque.pop_back(), None);
+    }
+
+    #[test]
+    fn test_both_ends() {
+        let mut deque = UnsafeDeque::new();
+        deque.push_front(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        deque.push_back(4);
+        assert_eq!(deque.len(), 4);
+
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_front(), Some(2));
+        assert_eq!(deque.pop_front(), Some(3));
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_back(), None);
+
+        // The deque should be reusable after being emptied
+        deque.push_back(5);
+        assert_eq!(deque.pop_front(), Some(5));
+        deque.push_front(6);
+        assert_eq!(deque.pop_back(), Some(6));
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut deque = UnsafeDeque::new();
+        deque.push_back(1);
+        deque.push_back(2);
+        assert_eq!(deque.front(), Some(&1));
+        assert_eq!(deque.back(), Some(&2));
+
+        if let Some(val) = deque.front_mut() {
+            *val = 10;
+        }
+        if let Some(val) = deque.back_mut() {
+            *val *= 10;
+        }
+        assert_eq!(deque.pop_front(), Some(10));
+        assert_eq!(deque.pop_front(), Some(20));
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut deque = UnsafeDeque::new();
+        assert_eq!(deque.iter().next(), None);
+
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        let mut iter = deque.iter();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![&2, &3]);
+    }
+
+    #[test]
+    fn test_drop_releases_values() {
+        let tracker = Rc::new(());
+        {
+            let mut deque = UnsafeDeque::new();
+            for _ in 0..10 {
+                deque.push_back(Rc::clone(&tracker));
+                deque.push_front(Rc::clone(&tracker));
+            }
+            deque.pop_back();
+            deque.pop_front();
+            assert_eq!(Rc::strong_count(&tracker), 19);
+        }
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn test_big_deque() {
+        // Miri is slow, so keep the size modest when running under it
+        let size = if cfg!(miri) { 1_000 } else { 1_000_000 };
+        let mut deque = UnsafeDeque::new();
+        for i in 0..size {
+            deque.push_back(i);
+        }
+
+        for i in 0..size / 2 {
+            assert_eq!(deque.pop_front(), Some(i));
+        }
+        for i in (size / 2..size).rev() {
+            assert_eq!(deque.pop_back(), Some(i));
+        }
+        assert!(deque.is_empty());
+
+        // Dropping a long deque shouldn't overflow the stack
+        for i in 0..size {
+            deque.push_front(i);
+        }
+    }
+}
@@ -1,23 +1,301 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-fn longest_equal_sequence_prescriptive(sequence) -> i32 {
-    todo!()
+/// Returns the length of the longest run of equal adjacent values in
+/// `sequence`, or 0 if it's empty.
+fn longest_equal_sequence_prescriptive<T: PartialEq>(sequence: &[T]) -> i32 {
+    longest_equal_run_prescriptive(sequence).map_or(0, |(range, _)| range.len() as i32)
 }
 
-fn longest_equal_sequence_functional(sequence) -> i32 {
-    todo!()
+/// Same as `longest_equal_sequence_prescriptive`, built on the functional
+/// implementation.
+fn longest_equal_sequence_functional<T: PartialEq>(sequence: &[T]) -> i32 {
+    longest_equal_run_functional(sequence).map_or(0, |(range, _)| range.len() as i32)
 }
 
+/// Finds the longest run of equal adjacent values in `sequence`, returning its
+/// index range along with the run itself. When several runs share the maximum
+/// length, the first one wins. Returns `None` for an empty sequence.
+fn longest_equal_run<T: PartialEq>(sequence: &[T]) -> Option<(Range<usize>, &[T])> {
+    longest_equal_run_functional(sequence)
+}
+
+fn longest_equal_run_prescriptive<T: PartialEq>(sequence: &[T]) -> Option<(Range<usize>, &[T])> {
+    if sequence.is_empty() {
+        return None;
+    }
+
+    let mut best = 0..1;
+    let mut run_start = 0;
+    for idx in 1..sequence.len() {
+        if sequence[idx] != sequence[idx - 1] {
+            run_start = idx;
+        }
+        if idx + 1 - run_start > best.len() {
+            best = run_start..idx + 1;
+        }
+    }
+
+    Some((best.clone(), &sequence[best]))
+}
+
+fn longest_equal_run_functional<T: PartialEq>(sequence: &[T]) -> Option<(Range<usize>, &[T])> {
+    // `chunk_by` splits the slice into the runs themselves. Their lengths add
+    // up to where each run starts. `reduce` only replaces the best run with a
+    // strictly longer one, so the first of several equally long runs wins.
+    sequence
+        .chunk_by(|a, b| a == b)
+        .scan(0, |start, run| {
+            let range = *start..*start + run.len();
+            *start = range.end;
+            Some((range, run))
+        })
+        .reduce(|best, candidate| {
+            if candidate.0.len() > best.0.len() {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Why a string failed bracket validation. Every index is the byte offset of
+/// the offending character in the validated string.
+#[derive(Debug, PartialEq)]
+pub enum BracketError {
+    /// A closing bracket that doesn't close the most recently opened bracket.
+    /// `expected` is the closing bracket that was needed there, or `None` if
+    /// no bracket was open.
+    Mismatched {
+        index: usize,
+        found: char,
+        expected: Option<char>,
+    },
+    /// An opening bracket that is never closed.
+    Unclosed { index: usize, bracket: char },
+    /// A character that isn't a bracket, when those are being rejected.
+    UnexpectedCharacter { index: usize, found: char },
+}
+
+/// The bracket pairs `validate_brackets` checks, mapping each opening bracket
+/// to its closing bracket.
+fn default_bracket_pairs() -> HashMap<char, char> {
+    HashMap::from([('(', ')'), ('[', ']'), ('{', '}')])
+}
+
+/// Returns whether every bracket in `paranthesis` is closed in the right
+/// order. Any other characters are ignored.
 fn is_valid_paranthesis(paranthesis: &str) -> bool {
-    todo!()
+    validate_brackets(paranthesis).is_ok()
 }
 
-fn longest_common_substring<(first_str: &str, second_str: &str) -> &str {
-    todo!()
+/// Checks that every `()`, `[]`, and `{}` bracket in `text` is closed in the
+/// right order, ignoring any other characters, and describes the first
+/// problem found otherwise.
+fn validate_brackets(text: &str) -> Result<(), BracketError> {
+    validate_brackets_with(text, &default_bracket_pairs(), false)
 }
 
-fn longest_common_substring_multiple(strings: &[&str]) -> &str {
-    todo!()
+/// Like `validate_brackets`, but with the caller's own `pairs` of opening to
+/// closing brackets. When `reject_others` is set, any character that isn't
+/// one of those brackets is an error rather than being skipped.
+///
+/// A character can be both an opening and a closing bracket, such as `|` in
+/// `('|', '|')`. It closes the open bracket if that's what's expected there,
+/// and opens a new one otherwise.
+fn validate_brackets_with(
+    text: &str,
+    pairs: &HashMap<char, char>,
+    reject_others: bool,
+) -> Result<(), BracketError> {
+    let closing: HashSet<char> = pairs.values().copied().collect();
+    // The index and expected closing bracket of every bracket still open
+    let mut open: Vec<(usize, char, char)> = Vec::new();
+
+    for (index, found) in text.char_indices() {
+        let expected = open.last().map(|&(_, _, close)| close);
+        if expected == Some(found) {
+            open.pop();
+        } else if let Some(&close) = pairs.get(&found) {
+            open.push((index, found, close));
+        } else if closing.contains(&found) {
+            return Err(BracketError::Mismatched {
+                index,
+                found,
+                expected,
+            });
+        } else if reject_others {
+            return Err(BracketError::UnexpectedCharacter { index, found });
+        }
+    }
+
+    match open.pop() {
+        // Report the innermost unclosed bracket, since that's the one the
+        // end of the string should have closed first
+        Some((index, bracket, _)) => Err(BracketError::Unclosed { index, bracket }),
+        None => Ok(()),
+    }
+}
+
+/// Finds the longest substring shared by `first_str` and `second_str`, returned
+/// as a slice of `first_str`. When several common substrings share the maximum
+/// length, the one that ends earliest in `first_str` is returned.
+///
+/// Uses the classic dynamic-programming table of common suffix lengths, which
+/// runs in O(n·m) time. Only the previous row of the table is kept around, so
+/// memory use is O(m). Comparisons are done on chars rather than bytes so the
+/// returned slice always falls on char boundaries.
+fn longest_common_substring<'a>(first_str: &'a str, second_str: &str) -> &'a str {
+    let first_chars: Vec<(usize, char)> = first_str.char_indices().collect();
+    let second_chars: Vec<char> = second_str.chars().collect();
+
+    // suffix_lens[j + 1] is the length of the common suffix of the current
+    // prefix of `first_str` and the first j + 1 chars of `second_str`
+    let mut suffix_lens = vec![0; second_chars.len() + 1];
+    let mut best_len = 0;
+    let mut best_end = 0;
+
+    for (i, &(_, first_char)) in first_chars.iter().enumerate() {
+        // Walk backwards so suffix_lens[j] still holds the previous row's value
+        for j in (0..second_chars.len()).rev() {
+            if first_char == second_chars[j] {
+                suffix_lens[j + 1] = suffix_lens[j] + 1;
+                if suffix_lens[j + 1] > best_len {
+                    best_len = suffix_lens[j + 1];
+                    best_end = i + 1;
+                }
+            } else {
+                suffix_lens[j + 1] = 0;
+            }
+        }
+    }
+
+    let byte_offset = |char_idx: usize| {
+        first_chars
+            .get(char_idx)
+            .map_or(first_str.len(), |&(offset, _)| offset)
+    };
+    &first_str[byte_offset(best_end - best_len)..byte_offset(best_end)]
+}
+
+/// Finds the longest substring that appears in every string of `strings`,
+/// returned as a slice of the first string. When several common substrings
+/// share the maximum length, the one that starts earliest in the first string
+/// is returned. An empty slice of strings yields `""`.
+///
+/// If a substring of length L is common to all strings, so is every shorter
+/// substring of it, which means the answer's length can be binary searched.
+/// Each probe compares rolling hashes of every length-L window (O(total length)
+/// per probe), and any hash match is confirmed with a real string comparison
+/// so collisions can't produce a wrong answer.
+fn longest_common_substring_multiple<'a>(strings: &[&'a str]) -> &'a str {
+    let Some((&first_str, rest)) = strings.split_first() else {
+        return "";
+    };
+
+    let first = RollingHash::new(first_str);
+    let rest_hashes: Vec<RollingHash> =
+        rest.iter().map(|string| RollingHash::new(string)).collect();
+    let max_len = rest_hashes
+        .iter()
+        .map(RollingHash::len)
+        .fold(first.len(), usize::min);
+
+    // Returns the char index in `first_str` of the earliest length-`len`
+    // window that appears in every other string
+    let find_common_window = |len: usize| -> Option<usize> {
+        let mut common: Option<HashSet<u64>> = None;
+        for hashes in &rest_hashes {
+            let window_hashes: HashSet<u64> = hashes.windows(len).map(|(_, hash)| hash).collect();
+            common = Some(match common {
+                Some(common) => &common & &window_hashes,
+                None => window_hashes,
+            });
+        }
+
+        first.windows(len).map(|(start, _)| start).find(|&start| {
+            let window = first.slice(start, len);
+            let hash = first.hash(start, len);
+            common.as_ref().is_none_or(|common| common.contains(&hash))
+                && rest.iter().all(|string| string.contains(window))
+        })
+    };
+
+    let (mut low, mut high) = (0, max_len);
+    let mut best_start = 0;
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        match find_common_window(mid) {
+            Some(start) => {
+                low = mid;
+                best_start = start;
+            }
+            None => high = mid - 1,
+        }
+    }
+
+    first.slice(best_start, low)
+}
+
+/// Polynomial prefix hashes over the chars of a string, allowing the hash of
+/// any window to be computed in O(1).
+struct RollingHash<'a> {
+    string: &'a str,
+    char_offsets: Vec<usize>,
+    prefix_hashes: Vec<u64>,
+    powers: Vec<u64>,
+}
+
+impl<'a> RollingHash<'a> {
+    // A Mersenne prime modulus keeps collisions unlikely without needing a
+    // second hash
+    const MODULUS: u64 = (1 << 61) - 1;
+    const BASE: u64 = 1_000_003;
+
+    fn new(string: &'a str) -> Self {
+        let mut char_offsets = Vec::new();
+        let mut prefix_hashes = vec![0];
+        let mut powers = vec![1];
+        for (offset, chr) in string.char_indices() {
+            char_offsets.push(offset);
+            let prev_hash = prefix_hashes[prefix_hashes.len() - 1];
+            prefix_hashes.push((Self::mul_mod(prev_hash, Self::BASE) + chr as u64) % Self::MODULUS);
+            let prev_power = powers[powers.len() - 1];
+            powers.push(Self::mul_mod(prev_power, Self::BASE));
+        }
+        char_offsets.push(string.len());
+
+        RollingHash {
+            string,
+            char_offsets,
+            prefix_hashes,
+            powers,
+        }
+    }
+
+    /// The length of the string in chars.
+    fn len(&self) -> usize {
+        self.char_offsets.len() - 1
+    }
+
+    fn hash(&self, start: usize, len: usize) -> u64 {
+        let whole = self.prefix_hashes[start + len];
+        let prefix = Self::mul_mod(self.prefix_hashes[start], self.powers[len]);
+        (whole + Self::MODULUS - prefix) % Self::MODULUS
+    }
+
+    /// Every length-`len` window as `(start char index, hash)` pairs.
+    fn windows(&self, len: usize) -> impl Iterator<Item = (usize, u64)> + '_ {
+        (0..(self.len() + 1).saturating_sub(len)).map(move |start| (start, self.hash(start, len)))
+    }
+
+    fn slice(&self, start: usize, len: usize) -> &'a str {
+        &self.string[self.char_offsets[start]..self.char_offsets[start + len]]
+    }
+
+    fn mul_mod(a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % Self::MODULUS as u128) as u64
+    }
 }
 
 #[cfg(test)]
@@ -89,6 +367,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_longest_equal_run() {
+        let sequence = vec![1, 1, 2, 2, 2, 3, 1, 1, 1, 1, 1];
+        assert_eq!(longest_equal_run(&sequence), Some((6..11, &sequence[6..])));
+
+        // Ties go to the first run
+        let sequence = vec!['a', 'b', 'e', 'g', 'g', 'z', 'z'];
+        assert_eq!(longest_equal_run(&sequence), Some((3..5, &['g', 'g'][..])));
+        assert_eq!(longest_equal_run(&[7]), Some((0..1, &[7][..])));
+
+        let empty: [u8; 0] = [];
+        assert_eq!(longest_equal_run(&empty), None);
+
+        // NaN is never equal to itself, so each one is its own run
+        let floats = [f64::NAN, f64::NAN, 1.0, 1.0];
+        assert_eq!(
+            longest_equal_run(&floats).map(|(range, _)| range),
+            Some(2..4)
+        );
+    }
+
+    #[test]
+    fn test_longest_equal_run_implementations_agree() {
+        let sequences: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![1],
+            vec![1, 2, 3],
+            vec![3, 3, 3, 1, 1, 1],
+            vec![1, 2, 2, 3, 3, 4, 4, 4, 5],
+            (0..1000).map(|x| (x / 7 % 5) as u8).collect(),
+        ];
+        for sequence in &sequences {
+            assert_eq!(
+                longest_equal_run_prescriptive(sequence),
+                longest_equal_run_functional(sequence)
+            );
+        }
+    }
+
     #[test]
     fn test_is_valid_paranthesis() {
         assert_eq!(is_valid_paranthesis(&String::from("{}")), true);
@@ -109,6 +426,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_valid_paranthesis_ignores_other_characters() {
+        assert!(is_valid_paranthesis("fn main() { let v = vec![1, 2]; }"));
+        assert!(is_valid_paranthesis("no brackets at all"));
+        assert!(is_valid_paranthesis(""));
+        assert!(!is_valid_paranthesis("if (x > 0 { y[0] }"));
+    }
+
+    #[test]
+    fn test_validate_brackets_errors() {
+        assert_eq!(validate_brackets("a(b)c"), Ok(()));
+        assert_eq!(
+            validate_brackets("([)]"),
+            Err(BracketError::Mismatched {
+                index: 2,
+                found: ')',
+                expected: Some(']'),
+            })
+        );
+        assert_eq!(
+            validate_brackets("x}"),
+            Err(BracketError::Mismatched {
+                index: 1,
+                found: '}',
+                expected: None,
+            })
+        );
+        // The innermost unclosed bracket is reported
+        assert_eq!(
+            validate_brackets("{ (( )"),
+            Err(BracketError::Unclosed {
+                index: 2,
+                bracket: '(',
+            })
+        );
+        // Indices are byte offsets, so they can be used to slice the input
+        let text = "é(]";
+        let Err(BracketError::Mismatched { index, .. }) = validate_brackets(text) else {
+            panic!("expected a mismatch");
+        };
+        assert_eq!(&text[index..], "]");
+    }
+
+    #[test]
+    fn test_validate_brackets_with() {
+        let angles = HashMap::from([('<', '>'), ('(', ')')]);
+        assert_eq!(
+            validate_brackets_with("Vec<(u8, u8)>", &angles, false),
+            Ok(())
+        );
+        // Square brackets aren't brackets in this mapping, so they're skipped
+        assert_eq!(validate_brackets_with("<[>", &angles, false), Ok(()));
+        assert_eq!(
+            validate_brackets_with("<(>)", &angles, false),
+            Err(BracketError::Mismatched {
+                index: 2,
+                found: '>',
+                expected: Some(')'),
+            })
+        );
+
+        assert_eq!(validate_brackets_with("<()>", &angles, true), Ok(()));
+        assert_eq!(
+            validate_brackets_with("<(x)>", &angles, true),
+            Err(BracketError::UnexpectedCharacter {
+                index: 2,
+                found: 'x',
+            })
+        );
+
+        // Symmetric pairs open and close with the same character
+        let pipes = HashMap::from([('|', '|'), ('(', ')')]);
+        assert_eq!(validate_brackets_with("|(|x|)|", &pipes, false), Ok(()));
+        assert_eq!(
+            validate_brackets_with("(|)", &pipes, false),
+            Err(BracketError::Mismatched {
+                index: 2,
+                found: ')',
+                expected: Some('|'),
+            })
+        );
+        assert_eq!(
+            validate_brackets_with("|(|", &pipes, false),
+            Err(BracketError::Unclosed {
+                index: 2,
+                bracket: '|',
+            })
+        );
+    }
+
     #[test]
     fn test_common_substring() {
         assert_eq!(longest_common_substring(&"abcdefg", &"bcdef"), "bcdef");
@@ -131,6 +538,50 @@ mod tests {
         assert_eq!(longest_common_substring("a", "a"), "a");
     }
 
+    #[test]
+    fn test_common_substring_unicode() {
+        assert_eq!(longest_common_substring("héllo wörld", "wörldly"), "wörld");
+        assert_eq!(longest_common_substring("🍎🍌🍇", "🍌🍇🍉"), "🍌🍇");
+        assert_eq!(longest_common_substring("日本語", "本"), "本");
+    }
+
+    /// Deterministic pseudo-random lowercase text, so large inputs don't need
+    /// fixture files.
+    fn pseudo_random_text(len: usize, mut seed: u64) -> String {
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (b'a' + (seed >> 33) as u8 % 4) as char
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_common_substring_large_inputs() {
+        let needle = "THISISTHECOMMONSUBSTRING";
+        let first = format!(
+            "{}{needle}{}",
+            pseudo_random_text(3000, 1),
+            pseudo_random_text(3000, 2)
+        );
+        let second = format!(
+            "{}{needle}{}",
+            pseudo_random_text(2000, 3),
+            pseudo_random_text(4000, 4)
+        );
+
+        let start = std::time::Instant::now();
+        let common = longest_common_substring(&first, &second);
+        let elapsed = start.elapsed();
+
+        assert!(common.contains(needle));
+        assert!(second.contains(common));
+        assert!(
+            elapsed < std::time::Duration::from_secs(5),
+            "took {elapsed:?} on ~6KB inputs"
+        );
+    }
+
     #[test]
     fn test_common_substring_multiple() {
         assert_eq!(
@@ -189,4 +640,71 @@ mod tests {
             "commonsubstring",
         );
     }
+
+    #[test]
+    fn test_common_substring_multiple_must_be_in_every_string() {
+        // "abc" is the longest substring shared by the first two strings, but
+        // it's absent from the third
+        assert_eq!(
+            longest_common_substring_multiple(&["abcx", "abcy", "zzz"]),
+            ""
+        );
+        assert_eq!(
+            longest_common_substring_multiple(&["abcdx", "abcdy", "bcz"]),
+            "bc"
+        );
+        assert_eq!(longest_common_substring_multiple(&[]), "");
+        assert_eq!(longest_common_substring_multiple(&["only"]), "only");
+    }
+
+    #[test]
+    fn test_common_substring_multiple_tie_breaking() {
+        // "ab" and "xy" are both common, "ab" starts first in the first string
+        assert_eq!(
+            longest_common_substring_multiple(&["abxy", "xyab", "ab-xy"]),
+            "ab"
+        );
+        assert_eq!(
+            longest_common_substring_multiple(&["xyab", "abxy", "ab-xy"]),
+            "xy"
+        );
+    }
+
+    #[test]
+    fn test_common_substring_multiple_unicode() {
+        assert_eq!(
+            longest_common_substring_multiple(&["🍎🍌🍇", "🍌🍇🍉", "🍉🍌🍇"]),
+            "🍌🍇"
+        );
+        assert_eq!(
+            longest_common_substring_multiple(&["crème brûlée", "brûlée", "la brûle"]),
+            "brûl"
+        );
+    }
+
+    #[test]
+    fn test_common_substring_multiple_large_inputs() {
+        let needle = "THISISTHECOMMONSUBSTRING";
+        let strings: Vec<String> = (0..5)
+            .map(|seed| {
+                format!(
+                    "{}{needle}{}",
+                    pseudo_random_text(1000 * (seed as usize + 1), seed),
+                    pseudo_random_text(3000, seed + 10)
+                )
+            })
+            .collect();
+        let strings: Vec<&str> = strings.iter().map(String::as_str).collect();
+
+        let start = std::time::Instant::now();
+        let common = longest_common_substring_multiple(&strings);
+        let elapsed = start.elapsed();
+
+        assert!(common.contains(needle));
+        assert!(strings.iter().all(|string| string.contains(common)));
+        assert!(
+            elapsed < std::time::Duration::from_secs(5),
+            "took {elapsed:?} on five ~5KB inputs"
+        );
+    }
 }
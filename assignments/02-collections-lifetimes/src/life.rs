@@ -1,19 +1,205 @@
-fn split_string(string: &str, delimeter: &str) -> Vec<&str> {
-    todo!()
+use std::collections::{HashMap, HashSet};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+fn split_string<'a>(string: &'a str, delimeter: &str) -> Vec<&'a str> {
+    split(string, &[delimeter]).collect()
+}
+
+/// Lazily splits `string` on any of `delimeters`, yielding slices of `string`.
+///
+/// At each position the delimeters are tried in order of length (longest
+/// first), so overlapping delimeters like `", "` and `","` behave as expected.
+/// Empty delimeters never match. Empty segments are skipped unless
+/// `keep_empty` is called on the returned iterator.
+fn split<'a, 'd>(string: &'a str, delimeters: &'d [&'d str]) -> Split<'a, 'd> {
+    let mut delimeters: Vec<&'d str> = delimeters
+        .iter()
+        .copied()
+        .filter(|delimeter| !delimeter.is_empty())
+        .collect();
+    delimeters.sort_by_key(|delimeter| std::cmp::Reverse(delimeter.len()));
+
+    Split {
+        remaining: Some(string),
+        delimeters,
+        keep_empty: false,
+    }
+}
+
+struct Split<'a, 'd> {
+    // `None` once the final segment has been yielded
+    remaining: Option<&'a str>,
+    delimeters: Vec<&'d str>,
+    keep_empty: bool,
+}
+
+impl<'a, 'd> Split<'a, 'd> {
+    /// Also yield the empty segments between adjacent delimeters and at
+    /// either end of the string, matching `str::split`.
+    fn keep_empty(mut self) -> Self {
+        self.keep_empty = true;
+        self
+    }
+
+    /// Returns the segment before the next delimeter and advances past it.
+    fn next_segment(&mut self) -> Option<&'a str> {
+        let remaining = self.remaining?;
+        for (idx, _) in remaining.char_indices() {
+            let rest = &remaining[idx..];
+            if let Some(delimeter) = self.delimeters.iter().find(|d| rest.starts_with(**d)) {
+                self.remaining = Some(&rest[delimeter.len()..]);
+                return Some(&remaining[..idx]);
+            }
+        }
+        self.remaining = None;
+        Some(remaining)
+    }
 }
 
+impl<'a> Iterator for Split<'a, '_> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let segment = self.next_segment()?;
+            if self.keep_empty || !segment.is_empty() {
+                return Some(segment);
+            }
+        }
+    }
+}
+
+/// The words that only appear in one of two strings, in order of first
+/// appearance, each paired with the number of times it occurs in its string.
 #[derive(PartialEq, Debug)]
-struct Differences {
-    only_in_first: Vec<&str>,
-    only_in_second: Vec<&str>,
+struct Differences<'a> {
+    only_in_first: Vec<(&'a str, usize)>,
+    only_in_second: Vec<(&'a str, usize)>,
+}
+
+/// Finds the words that appear in one string but not the other, comparing
+/// whole words case-sensitively.
+fn find_differences<'a>(first_string: &'a str, second_string: &'a str) -> Differences<'a> {
+    find_differences_with(first_string, second_string, false)
+}
+
+/// Finds the words that appear in one string but not the other.
+///
+/// Words are split on whitespace and punctuation (apostrophes inside a word,
+/// like in "don't", are kept) and compared exactly, so "apple" is still a
+/// difference even if the other string contains "pineapple". With
+/// `ignore_case`, "Apple" and "apple" are the same word, and the difference is
+/// reported using the spelling that appeared first.
+fn find_differences_with<'a>(
+    first_string: &'a str,
+    second_string: &'a str,
+    ignore_case: bool,
+) -> Differences<'a> {
+    let first_words = count_words(first_string, ignore_case);
+    let second_words = count_words(second_string, ignore_case);
+
+    let only_in = |words: &[WordCount<'a>], other: &[WordCount<'a>]| {
+        let other_keys: HashSet<&str> = other.iter().map(|word| word.key.as_str()).collect();
+        words
+            .iter()
+            .filter(|word| !other_keys.contains(word.key.as_str()))
+            .map(|word| (word.spelling, word.count))
+            .collect()
+    };
+
+    Differences {
+        only_in_first: only_in(&first_words, &second_words),
+        only_in_second: only_in(&second_words, &first_words),
+    }
+}
+
+struct WordCount<'a> {
+    // The normalized form words are compared by
+    key: String,
+    // The first spelling of the word in the original string
+    spelling: &'a str,
+    count: usize,
 }
 
-fn find_differences(first_string: &str, second_string: &str) -> Differences {
-    todo!()
+/// Counts each distinct word in `string`, in order of first appearance.
+fn count_words(string: &str, ignore_case: bool) -> Vec<WordCount<'_>> {
+    let mut words: Vec<WordCount> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    let tokens = string
+        .split(|chr: char| !chr.is_alphanumeric() && chr != '\'')
+        .map(|token| token.trim_matches('\''))
+        .filter(|token| !token.is_empty());
+
+    for token in tokens {
+        let key = match ignore_case {
+            true => token.to_lowercase(),
+            false => token.to_owned(),
+        };
+        match positions.get(&key) {
+            Some(&position) => words[position].count += 1,
+            None => {
+                positions.insert(key.clone(), words.len());
+                words.push(WordCount {
+                    key,
+                    spelling: token,
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    words
 }
 
+/// The vowels `merge_names` switches names on.
+const DEFAULT_VOWELS: [char; 5] = ['a', 'e', 'i', 'o', 'u'];
+
 fn merge_names(first_name: &str, second_name: &str) -> String {
-    todo!()
+    merge_names_with(first_name, second_name, &DEFAULT_VOWELS)
+}
+
+/// Interweaves two names, switching to the other name every time a vowel is
+/// reached (see the README for the full algorithm).
+///
+/// Names are walked by grapheme cluster, so accents and other combining marks
+/// stay attached to their letter. A cluster counts as a vowel if its base
+/// letter, ignoring case and accents, is in `vowels` (so "É" and "e\u{301}"
+/// are both vowels when `vowels` contains 'e').
+fn merge_names_with(first_name: &str, second_name: &str, vowels: &[char]) -> String {
+    let vowels: HashSet<char> = vowels
+        .iter()
+        .flat_map(|vowel| vowel.to_lowercase())
+        .collect();
+    let is_vowel = |grapheme: &str| {
+        grapheme
+            .nfd()
+            .next()
+            .is_some_and(|base| base.to_lowercase().all(|chr| vowels.contains(&chr)))
+    };
+
+    let mut names = [
+        first_name.graphemes(true).peekable(),
+        second_name.graphemes(true).peekable(),
+    ];
+    let mut merged = String::with_capacity(first_name.len() + second_name.len());
+    let mut turn = 0;
+
+    while names.iter_mut().any(|name| name.peek().is_some()) {
+        let name = &mut names[turn];
+        // The first grapheme is always taken, even if it's a vowel, then
+        // everything up until the next vowel
+        if let Some(grapheme) = name.next() {
+            merged.push_str(grapheme);
+            while let Some(grapheme) = name.next_if(|grapheme| !is_vowel(grapheme)) {
+                merged.push_str(grapheme);
+            }
+        }
+        turn = 1 - turn;
+    }
+
+    merged
 }
 
 #[cfg(test)]
@@ -62,6 +248,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_split_multiple_delimeters() {
+        assert_eq!(
+            split(
+                "Ayush;put|a,lot~of`random;delimeters|in|this,sentence",
+                &[";", "|", ",", "~", "`"]
+            )
+            .collect::<Vec<_>>(),
+            vec![
+                "Ayush",
+                "put",
+                "a",
+                "lot",
+                "of",
+                "random",
+                "delimeters",
+                "in",
+                "this",
+                "sentence"
+            ]
+        );
+
+        // The longer delimeter wins when several match at the same spot
+        assert_eq!(
+            split("a, b,c", &[",", ", "]).collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+
+        // Empty delimeters are ignored rather than matching everywhere
+        assert_eq!(split("a-b", &["", "-"]).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(split("abc", &[""]).collect::<Vec<_>>(), vec!["abc"]);
+        assert_eq!(split("abc", &[]).collect::<Vec<_>>(), vec!["abc"]);
+    }
+
+    #[test]
+    fn test_split_empty_segments() {
+        let segments: Vec<_> = split(",a,,b;", &[",", ";"]).collect();
+        assert_eq!(segments, vec!["a", "b"]);
+
+        let segments: Vec<_> = split(",a,,b;", &[",", ";"]).keep_empty().collect();
+        assert_eq!(segments, vec!["", "a", "", "b", ""]);
+
+        // Matches `str::split` when keeping empty segments
+        for (string, delimeter) in [("", ","), ("a", ","), (",,", ","), ("🍎x🍎", "🍎")] {
+            assert_eq!(
+                split(string, &[delimeter]).keep_empty().collect::<Vec<_>>(),
+                string.split(delimeter).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_is_lazy() {
+        let mut segments = split("one two three", &[" "]);
+        assert_eq!(segments.next(), Some("one"));
+        assert_eq!(segments.next(), Some("two"));
+        assert_eq!(segments.next(), Some("three"));
+        assert_eq!(segments.next(), None);
+        assert_eq!(segments.next(), None);
+
+        // Only as much of the input as needed is scanned
+        let text = "word ".repeat(100_000);
+        assert_eq!(split(&text, &[" "]).next(), Some("word"));
+    }
+
     #[test]
     fn test_find_differences() {
         assert_eq!(
@@ -74,8 +325,8 @@ mod tests {
         assert_eq!(
             find_differences(&"pineapple pen", &"apple"),
             Differences {
-                only_in_first: vec!["pineapple", "pen"],
-                only_in_second: Vec::new()
+                only_in_first: vec![("pineapple", 1), ("pen", 1)],
+                only_in_second: vec![("apple", 1)]
             }
         );
         assert_eq!(
@@ -84,8 +335,8 @@ mod tests {
                 &"Seashells seashells at the seashore"
             ),
             Differences {
-                only_in_first: vec!["Sally", "sold"],
-                only_in_second: vec!["Seashells"]
+                only_in_first: vec![("Sally", 1), ("sold", 1)],
+                only_in_second: vec![("Seashells", 1)]
             }
         );
         assert_eq!(
@@ -94,8 +345,8 @@ mod tests {
                 "If a wood chuck could chuck wood"
             ),
             Differences {
-                only_in_first: vec!["How", "much"],
-                only_in_second: vec!["If"]
+                only_in_first: vec![("How", 1), ("much", 1)],
+                only_in_second: vec![("If", 1)]
             }
         );
         assert_eq!(
@@ -104,8 +355,58 @@ mod tests {
                 &"If a groundhog could hog ground"
             ),
             Differences {
-                only_in_first: vec!["How", "much", "would"],
-                only_in_second: vec!["If", "could"]
+                only_in_first: vec![("How", 1), ("much", 1), ("would", 1)],
+                only_in_second: vec![("If", 1), ("could", 1)]
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_differences_punctuation_and_counts() {
+        assert_eq!(
+            find_differences(
+                "Don't stop, don't stop! Keep going.",
+                "Keep going; don't look back..."
+            ),
+            Differences {
+                only_in_first: vec![("Don't", 1), ("stop", 2)],
+                only_in_second: vec![("look", 1), ("back", 1)]
+            }
+        );
+        assert_eq!(
+            find_differences("apple apple banana", "banana"),
+            Differences {
+                only_in_first: vec![("apple", 2)],
+                only_in_second: Vec::new()
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_differences_ignore_case() {
+        assert_eq!(
+            find_differences_with(
+                "Sally sold seashells at the seashore",
+                "Seashells seashells at the SEASHORE",
+                true
+            ),
+            Differences {
+                only_in_first: vec![("Sally", 1), ("sold", 1)],
+                only_in_second: Vec::new()
+            }
+        );
+        assert_eq!(
+            find_differences_with("Apple apple APPLE pear", "Pear", true),
+            Differences {
+                only_in_first: vec![("Apple", 3)],
+                only_in_second: Vec::new()
+            }
+        );
+        assert_eq!(
+            find_differences_with("Ärger über", "ärger", true),
+            Differences {
+                only_in_first: vec![("über", 1)],
+                only_in_second: Vec::new()
             }
         );
     }
@@ -125,4 +426,33 @@ mod tests {
         assert_eq!(merge_names(&"", &"second"), "second");
         assert_eq!(merge_names(&"first", &""), "first");
     }
+
+    #[test]
+    fn test_merge_names_unicode() {
+        // Accented vowels are still vowels
+        assert_eq!(merge_names("renée", "andré"), "randrenéée");
+        assert_eq!(merge_names("ÉMILE", "zoë"), "ÉMzILoEë");
+
+        // A decomposed accent stays attached to its letter
+        assert_eq!(
+            merge_names("rene\u{301}e", "andre\u{301}"),
+            "randrene\u{301}e\u{301}e"
+        );
+
+        // Non-letters are just consonants
+        assert_eq!(merge_names("a🍎b", "🍌c"), "a🍎b🍌c");
+    }
+
+    #[test]
+    fn test_merge_names_custom_vowels() {
+        let with_y = ['a', 'e', 'i', 'o', 'u', 'y'];
+        assert_eq!(merge_names_with("gym", "rhythm", &with_y), "grhymythm");
+        assert_eq!(merge_names_with("alex", "jake", &with_y), "aljexake");
+
+        // Vowels are matched case-insensitively
+        assert_eq!(merge_names_with("bob", "tom", &['O']), "btobom");
+
+        // With no vowels, the first name is taken whole
+        assert_eq!(merge_names_with("alex", "jake", &[]), "alexjake");
+    }
 }
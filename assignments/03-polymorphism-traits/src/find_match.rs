@@ -0,0 +1,489 @@
+//! Finding the needle in each line, as plain text or as a regex.
+
+use std::collections::VecDeque;
+use std::io;
+use std::iter;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+/// What to search each line for.
+pub trait Needle {
+    /// Where the needle is in `line`, as the byte range of each match, in
+    /// order and without overlaps. Empty if the line doesn't match.
+    fn find_matches(&self, line: &str) -> Vec<Range<usize>>;
+}
+
+/// How much of a line a match has to take up.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Extent {
+    /// Any part of the line.
+    #[default]
+    Anywhere,
+    /// Whole words, so there can't be a letter, digit, or `_` right before
+    /// or after the match, for `-w`.
+    Words,
+    /// The whole line, for `-x`.
+    Line,
+}
+
+/// Text that has to appear exactly as it is, like `a+b`.
+pub struct LiteralNeedle {
+    text: String,
+    ignore_case: bool,
+    extent: Extent,
+}
+
+impl LiteralNeedle {
+    pub fn new(text: &str, ignore_case: bool, extent: Extent) -> Self {
+        LiteralNeedle {
+            text: String::from(text),
+            ignore_case,
+            extent,
+        }
+    }
+
+    /// How many bytes at the start of `text` are the needle, or `None` if
+    /// `text` doesn't start with it.
+    fn prefix(&self, text: &str) -> Option<usize> {
+        if self.ignore_case {
+            caseless_prefix(text, &self.text)
+        } else {
+            text.starts_with(&self.text).then_some(self.text.len())
+        }
+    }
+}
+
+impl Needle for LiteralNeedle {
+    fn find_matches(&self, line: &str) -> Vec<Range<usize>> {
+        if self.extent == Extent::Line {
+            return self
+                .prefix(line)
+                .filter(|len| *len == line.len())
+                .map(|len| 0..len)
+                .into_iter()
+                .collect();
+        }
+        if self.extent == Extent::Anywhere {
+            // An empty needle matches every line, like grep
+            if self.text.is_empty() {
+                return iter::once(0..0).collect();
+            }
+            if !self.ignore_case {
+                return line
+                    .match_indices(&self.text)
+                    .map(|(start, text)| start..start + text.len())
+                    .collect();
+            }
+        }
+        // Every place the needle could start is tried, since a match that
+        // isn't a whole word can overlap one that is
+        let starts = line
+            .char_indices()
+            .map(|(start, _)| start)
+            .chain(iter::once(line.len()));
+        let mut matches = Vec::new();
+        let mut next = 0;
+        for start in starts {
+            if start < next {
+                continue;
+            }
+            let Some(len) = self.prefix(&line[start..]) else {
+                continue;
+            };
+            let found = start..start + len;
+            if self.extent == Extent::Words && !is_whole_words(line, &found) {
+                continue;
+            }
+            next = found.end.max(start + 1);
+            matches.push(found);
+        }
+        matches
+    }
+}
+
+/// How many bytes at the start of `text` are `needle`, ignoring case, or
+/// `None` if `text` doesn't start with it.
+fn caseless_prefix(text: &str, needle: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for expected in needle.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(index, _)| index))
+}
+
+/// Whether `range` is whole words in `line`, without a word character right
+/// before or after it.
+fn is_whole_words(line: &str, range: &Range<usize>) -> bool {
+    let before = line[..range.start].chars().next_back();
+    let after = line[range.end..].chars().next();
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+/// Letters, digits, and `_`, the same as `\w` in a regex.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A regular expression, like `fo+|ba[rz]`.
+pub struct RegexNeedle {
+    regex: Regex,
+}
+
+impl RegexNeedle {
+    /// Compiles `pattern`, failing if it isn't a valid regex.
+    pub fn new(pattern: &str, ignore_case: bool, extent: Extent) -> Result<Self, regex::Error> {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()
+        };
+        let regex = build(pattern)?;
+        let regex = match extent {
+            Extent::Anywhere => regex,
+            // `pattern` is compiled on its own first, so errors show it as
+            // it was given, and so it can't close the group around it
+            Extent::Words => build(&format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern))?,
+            Extent::Line => build(&format!(r"\A(?:{})\z", pattern))?,
+        };
+        Ok(RegexNeedle { regex })
+    }
+}
+
+impl Needle for RegexNeedle {
+    fn find_matches(&self, line: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(line)
+            .map(|found| found.range())
+            .collect()
+    }
+}
+
+/// A line picked out by [`filter_lines`], along with where the needle is in
+/// it. Lines picked for not matching have no matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedLine {
+    pub text: String,
+    pub matches: Vec<Range<usize>>,
+}
+
+impl MatchedLine {
+    /// Each part of the line that matched as a line of its own, for `-o`.
+    /// Empty matches are left out, since there's nothing to print.
+    pub fn parts(&self) -> impl Iterator<Item = MatchedLine> + '_ {
+        self.matches
+            .iter()
+            .filter(|found| !found.is_empty())
+            .map(|found| MatchedLine {
+                text: String::from(&self.text[found.clone()]),
+                matches: iter::once(0..found.len()).collect(),
+            })
+    }
+}
+
+/// The lines that contain `needle`, or with `invert_match`, the lines that
+/// don't, each with its line number, starting from 1. Errors reading the
+/// lines are passed along.
+pub fn filter_lines<'a>(
+    lines: impl Iterator<Item = io::Result<String>> + 'a,
+    needle: &'a dyn Needle,
+    invert_match: bool,
+) -> impl Iterator<Item = io::Result<(usize, MatchedLine)>> + 'a {
+    lines.zip(1..).filter_map(move |(line, number)| {
+        let text = match line {
+            Ok(text) => text,
+            Err(err) => return Some(Err(err)),
+        };
+        let matches = needle.find_matches(&text);
+        (matches.is_empty() == invert_match).then_some(Ok((number, MatchedLine { text, matches })))
+    })
+}
+
+/// How many lines to print around each line found, for `-A`, `-B`, and `-C`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Context {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Context {
+    pub fn is_empty(&self) -> bool {
+        self.before == 0 && self.after == 0
+    }
+}
+
+/// A line picked out by [`context_lines`], numbered like in [`filter_lines`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextLine {
+    /// A line that was looked for.
+    Found(usize, MatchedLine),
+    /// A line near one that was found.
+    Context(usize, MatchedLine),
+    /// Where lines were skipped between one group of lines and the next.
+    Break,
+}
+
+/// Like [`filter_lines`], but along with the lines `context` says to print
+/// around each line found. Lines are only printed once, so groups of lines
+/// that overlap or touch are joined together, with a [`ContextLine::Break`]
+/// between the ones that don't.
+pub fn context_lines<'a>(
+    lines: impl Iterator<Item = io::Result<String>> + 'a,
+    needle: &'a dyn Needle,
+    invert_match: bool,
+    context: Context,
+) -> impl Iterator<Item = io::Result<ContextLine>> + 'a {
+    // The latest lines that weren't printed, in case one after them is found
+    let mut before = VecDeque::with_capacity(context.before + 1);
+    let mut after_left = 0;
+    let mut last_printed = None;
+    lines.zip(1..).flat_map(move |(line, number)| {
+        let text = match line {
+            Ok(text) => text,
+            Err(err) => return vec![Err(err)],
+        };
+        let matches = needle.find_matches(&text);
+        let found = matches.is_empty() == invert_match;
+        let line = MatchedLine { text, matches };
+        if !found {
+            if after_left > 0 {
+                after_left -= 1;
+                last_printed = Some(number);
+                return vec![Ok(ContextLine::Context(number, line))];
+            }
+            before.push_back((number, line));
+            if before.len() > context.before {
+                before.pop_front();
+            }
+            return Vec::new();
+        }
+
+        let mut printed = Vec::new();
+        let first = before.front().map_or(number, |(first, _)| *first);
+        if last_printed.is_some_and(|last| last + 1 < first) {
+            printed.push(Ok(ContextLine::Break));
+        }
+        printed.extend(
+            before
+                .drain(..)
+                .map(|(number, line)| Ok(ContextLine::Context(number, line))),
+        );
+        printed.push(Ok(ContextLine::Found(number, line)));
+        after_left = context.after;
+        last_printed = Some(number);
+        printed
+    })
+}
+
+#[cfg(test)]
+// One match is written `[2..5]`, which is what the lint is wary of
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use std::io;
+    use std::ops::Range;
+
+    use super::{
+        context_lines, filter_lines, Context, ContextLine, Extent, LiteralNeedle, MatchedLine,
+        Needle, RegexNeedle,
+    };
+
+    #[test]
+    fn test_literal() {
+        let needle = LiteralNeedle::new("ab", false, Extent::Anywhere);
+        assert_eq!(needle.find_matches("xabyabab"), [1..3, 4..6, 6..8]);
+        assert!(needle.find_matches("aBx").is_empty());
+        assert!(needle.find_matches("").is_empty());
+        // Regex syntax means nothing in a literal
+        assert_eq!(
+            LiteralNeedle::new("a+b", false, Extent::Anywhere).find_matches("aab a+b"),
+            [4..7]
+        );
+        assert_eq!(
+            LiteralNeedle::new("", false, Extent::Anywhere).find_matches("anything"),
+            [0..0]
+        );
+    }
+
+    #[test]
+    fn test_literal_ignore_case() {
+        let needle = LiteralNeedle::new("ab", true, Extent::Anywhere);
+        assert_eq!(needle.find_matches("AB aB ab x"), [0..2, 3..5, 6..8]);
+        assert!(needle.find_matches("a b").is_empty());
+        let needle = LiteralNeedle::new("straße", true, Extent::Anywhere);
+        assert_eq!(needle.find_matches("die STRAßE"), [4..11]);
+        assert_eq!(
+            LiteralNeedle::new("aa", true, Extent::Anywhere).find_matches("AAA"),
+            [0..2]
+        );
+    }
+
+    #[test]
+    fn test_regex() {
+        let needle = RegexNeedle::new("fo+|ba[rz]", false, Extent::Anywhere).unwrap();
+        assert_eq!(needle.find_matches("foo bar fbaz"), [0..3, 4..7, 9..12]);
+        assert!(needle.find_matches("FOO").is_empty());
+        let needle = RegexNeedle::new("fo+", true, Extent::Anywhere).unwrap();
+        assert_eq!(needle.find_matches("FoO"), [0..3]);
+        assert!(RegexNeedle::new("(unclosed", false, Extent::Anywhere).is_err());
+    }
+
+    #[test]
+    fn test_literal_words() {
+        let needle = LiteralNeedle::new("ab", false, Extent::Words);
+        assert_eq!(needle.find_matches("ab abc cab (ab) ab_"), [0..2, 12..14]);
+        assert!(needle.find_matches("abab").is_empty());
+        // A match that isn't a word doesn't hide one that overlaps it
+        let needle = LiteralNeedle::new("a-a", false, Extent::Words);
+        assert_eq!(needle.find_matches("ba-a-a"), [3..6]);
+        let needle = LiteralNeedle::new("AB", true, Extent::Words);
+        assert_eq!(needle.find_matches("éab aB"), [5..7]);
+        let needle = LiteralNeedle::new("-", false, Extent::Words);
+        assert_eq!(needle.find_matches("a-b - c"), [4..5]);
+    }
+
+    #[test]
+    fn test_regex_words() {
+        let needle = RegexNeedle::new("fo+", false, Extent::Words).unwrap();
+        assert_eq!(needle.find_matches("foo food afoo fooo"), [0..3, 14..18]);
+        let needle = RegexNeedle::new("a|b", true, Extent::Words).unwrap();
+        assert_eq!(needle.find_matches("A ab b"), [0..1, 5..6]);
+        // The pattern can't escape the group it's put in
+        assert!(RegexNeedle::new("a)(b", false, Extent::Words).is_err());
+    }
+
+    #[test]
+    fn test_literal_line() {
+        let needle = LiteralNeedle::new("ab", false, Extent::Line);
+        assert_eq!(needle.find_matches("ab"), [0..2]);
+        assert!(needle.find_matches("ab ab").is_empty());
+        assert!(needle.find_matches("aB").is_empty());
+        let needle = LiteralNeedle::new("straße", true, Extent::Line);
+        assert_eq!(needle.find_matches("STRAßE"), [0..7]);
+        assert!(needle.find_matches("STRAßEN").is_empty());
+        let needle = LiteralNeedle::new("", false, Extent::Line);
+        assert_eq!(needle.find_matches(""), [0..0]);
+        assert!(needle.find_matches("a").is_empty());
+    }
+
+    #[test]
+    fn test_regex_line() {
+        let needle = RegexNeedle::new("fo+", false, Extent::Line).unwrap();
+        assert_eq!(needle.find_matches("fooo"), [0..4]);
+        assert!(needle.find_matches("foo bar").is_empty());
+        // Every alternative has to take up the whole line, not just the first
+        let needle = RegexNeedle::new("a|ab", false, Extent::Line).unwrap();
+        assert_eq!(needle.find_matches("ab"), [0..2]);
+    }
+
+    /// A needle that matches wherever there's an `x`, to test filtering
+    /// separately from matching.
+    struct FakeNeedle;
+
+    impl Needle for FakeNeedle {
+        fn find_matches(&self, line: &str) -> Vec<Range<usize>> {
+            line.match_indices('x')
+                .map(|(start, _)| start..start + 1)
+                .collect()
+        }
+    }
+
+    fn filter(lines: &[&str], invert_match: bool) -> Vec<(usize, MatchedLine)> {
+        let lines = lines.iter().map(|line| Ok(String::from(*line)));
+        filter_lines(lines, &FakeNeedle, invert_match)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn matched(text: &str, matches: Vec<Range<usize>>) -> MatchedLine {
+        MatchedLine {
+            text: String::from(text),
+            matches,
+        }
+    }
+
+    #[test]
+    fn test_filter_lines() {
+        assert_eq!(
+            filter(&["axb", "none", "xx"], false),
+            [
+                (1, matched("axb", vec![1..2])),
+                (3, matched("xx", vec![0..1, 1..2]))
+            ]
+        );
+        assert!(filter(&[], false).is_empty());
+    }
+
+    #[test]
+    fn test_parts() {
+        let line = matched("a fox, a fo", vec![0..0, 2..5, 9..11]);
+        assert_eq!(
+            line.parts().collect::<Vec<_>>(),
+            [matched("fox", vec![0..3]), matched("fo", vec![0..2])]
+        );
+        assert_eq!(matched("none", vec![]).parts().count(), 0);
+    }
+
+    #[test]
+    fn test_invert_match() {
+        assert_eq!(
+            filter(&["axb", "none", "xx", ""], true),
+            [(2, matched("none", vec![])), (4, matched("", vec![]))]
+        );
+    }
+
+    #[test]
+    fn test_filter_lines_passes_errors_along() {
+        let lines = vec![Ok(String::from("x")), Err(io::Error::other("oops"))];
+        let filtered: Vec<_> = filter_lines(lines.into_iter(), &FakeNeedle, false).collect();
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered[1].is_err());
+    }
+
+    /// The numbers of the lines [`context_lines`] picks out of `lines`,
+    /// negative for context and 0 for a break.
+    fn context(lines: &[&str], before: usize, after: usize) -> Vec<isize> {
+        let lines = lines.iter().map(|line| Ok(String::from(*line)));
+        context_lines(lines, &FakeNeedle, false, Context { before, after })
+            .map(|line| match line.unwrap() {
+                ContextLine::Found(number, _) => number as isize,
+                ContextLine::Context(number, _) => -(number as isize),
+                ContextLine::Break => 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let lines = ["a", "x", "b", "c", "d", "e", "x", "f"];
+        assert_eq!(context(&lines, 0, 0), [2, 0, 7]);
+        assert_eq!(context(&lines, 1, 0), [-1, 2, 0, -6, 7]);
+        assert_eq!(context(&lines, 0, 1), [2, -3, 0, 7, -8]);
+        assert_eq!(context(&lines, 1, 2), [-1, 2, -3, -4, 0, -6, 7, -8]);
+        // Groups that touch or overlap are joined
+        assert_eq!(context(&lines, 2, 2), [-1, 2, -3, -4, -5, -6, 7, -8]);
+        assert_eq!(context(&lines, 0, 4), [2, -3, -4, -5, -6, 7, -8]);
+        assert_eq!(context(&["x", "x", "a"], 3, 3), [1, 2, -3]);
+    }
+
+    #[test]
+    fn test_inverted_context_lines_keep_matches() {
+        let lines = ["x", "y"].map(|line| Ok(String::from(line)));
+        let context = Context {
+            before: 1,
+            after: 0,
+        };
+        let picked: Vec<_> = context_lines(lines.into_iter(), &FakeNeedle, true, context)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            picked,
+            [
+                ContextLine::Context(1, matched("x", vec![0..1])),
+                ContextLine::Found(2, matched("y", vec![]))
+            ]
+        );
+    }
+}
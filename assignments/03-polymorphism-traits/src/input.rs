@@ -0,0 +1,267 @@
+//! Where the lines to search come from: files, directories of them, or
+//! stdin.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::iter;
+use std::path::PathBuf;
+
+/// Something that can be searched line by line.
+pub trait Input {
+    /// What the input is called in output and warnings, like its path.
+    fn name(&self) -> String;
+
+    /// Opens the input for reading.
+    fn open(&self) -> io::Result<Box<dyn BufRead>>;
+}
+
+/// A file on disk.
+pub struct FileInput {
+    path: PathBuf,
+}
+
+impl FileInput {
+    pub fn new(path: PathBuf) -> Self {
+        FileInput { path }
+    }
+}
+
+impl Input for FileInput {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn open(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+/// The standard input, searched when no files are given.
+pub struct StdinInput;
+
+impl Input for StdinInput {
+    fn name(&self) -> String {
+        String::from("(standard input)")
+    }
+
+    fn open(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(io::stdin().lock()))
+    }
+}
+
+/// Every file to search under a path, for `--recursive`. A path that isn't a
+/// directory is a file to search itself, whatever it is. Directories are
+/// searched in order of name, entering each subdirectory as it comes up and
+/// only searching regular files inside it.
+///
+/// Symlinks are followed, but a directory that has already been walked
+/// through is skipped, so a link back up to a parent doesn't loop forever.
+pub struct Walk {
+    /// Paths still to visit, with the next one last.
+    pending: Vec<PathBuf>,
+    /// Canonical paths of the directories walked so far.
+    visited: HashSet<PathBuf>,
+    /// Whether the next path is the one the walk started from.
+    at_root: bool,
+}
+
+/// A directory that couldn't be listed, or a path that couldn't be looked
+/// at, while walking.
+#[derive(Debug)]
+pub struct WalkError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Walk {
+    pub fn new(root: PathBuf) -> Self {
+        Walk {
+            pending: vec![root],
+            visited: HashSet::new(),
+            at_root: true,
+        }
+    }
+
+    /// Queues up what's in the directory at `path`, unless it's been walked
+    /// already.
+    fn enter(&mut self, path: PathBuf) -> io::Result<()> {
+        if !self.visited.insert(fs::canonicalize(&path)?) {
+            return Ok(());
+        }
+        let mut entries = fs::read_dir(&path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort_unstable_by(|a, b| b.cmp(a));
+        self.pending.extend(entries);
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(path) = self.pending.pop() {
+            let at_root = std::mem::replace(&mut self.at_root, false);
+            let result = fs::metadata(&path).and_then(|metadata| {
+                if metadata.is_dir() {
+                    self.enter(path.clone()).map(|_| false)
+                } else {
+                    Ok(at_root || metadata.is_file())
+                }
+            });
+            match result {
+                Ok(true) => return Some(Ok(path)),
+                Ok(false) => {}
+                Err(error) => return Some(Err(WalkError { path, error })),
+            }
+        }
+        None
+    }
+}
+
+/// Every line in `reader`, without its line ending. Bytes that aren't valid
+/// UTF-8 are replaced rather than failing, so binary files can still be
+/// searched. Nothing more is read after an error.
+pub fn lines(mut reader: impl BufRead) -> impl Iterator<Item = io::Result<String>> {
+    let mut buffer = Vec::new();
+    let mut failed = false;
+    iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buffer.ends_with(b"\n") {
+                    buffer.pop();
+                    if buffer.ends_with(b"\r") {
+                        buffer.pop();
+                    }
+                }
+                Some(Ok(String::from_utf8_lossy(&buffer).into_owned()))
+            }
+            Err(err) => {
+                failed = true;
+                Some(Err(err))
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, BufRead, Read};
+
+    use std::path::{Path, PathBuf};
+
+    use super::{lines, FileInput, Input, Walk};
+
+    fn read_lines(text: &[u8]) -> Vec<String> {
+        lines(text).map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(
+            read_lines(b"one\ntwo\r\n\nthree"),
+            ["one", "two", "", "three"]
+        );
+        assert_eq!(read_lines(b"one\n"), ["one"]);
+        assert!(read_lines(b"").is_empty());
+        assert_eq!(read_lines(b"bad \xff byte\n"), ["bad \u{fffd} byte"]);
+    }
+
+    /// A reader that fails partway through.
+    struct Broken<'a>(&'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::other("disk on fire")),
+                len => Ok(len),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lines_stop_at_error() {
+        let reader = io::BufReader::new(Broken(b"one\ntwo"));
+        let lines: Vec<_> = lines(reader).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].as_ref().unwrap(), "one");
+        assert!(lines[1].is_err());
+    }
+
+    #[test]
+    fn test_file_input() {
+        let path = std::env::temp_dir().join(format!("greprs-input-{}.txt", std::process::id()));
+        fs::write(&path, "first\nsecond\n").unwrap();
+        let input = FileInput::new(path.clone());
+        assert_eq!(input.name(), path.display().to_string());
+        let read: Vec<String> = input.open().unwrap().lines().map(Result::unwrap).collect();
+        assert_eq!(read, ["first", "second"]);
+        fs::remove_file(&path).unwrap();
+
+        assert!(input.open().is_err());
+    }
+
+    /// A fresh, empty directory to walk.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("greprs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    fn walk(root: &Path) -> Vec<PathBuf> {
+        Walk::new(root.to_path_buf())
+            .map(|path| path.unwrap().strip_prefix(root).unwrap().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        let dir = temp_dir("walk");
+        fs::create_dir_all(dir.join("b/c")).unwrap();
+        fs::create_dir(dir.join("empty")).unwrap();
+        for file in ["a.txt", "b/x.txt", "b/c/y.txt", "d.txt"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        assert_eq!(
+            walk(&dir),
+            ["a.txt", "b/c/y.txt", "b/x.txt", "d.txt"].map(PathBuf::from)
+        );
+        // A file is walked as itself
+        assert_eq!(walk(&dir.join("a.txt")), [PathBuf::new()]);
+
+        let missing = Walk::new(dir.join("missing")).next().unwrap();
+        assert_eq!(missing.unwrap_err().path, dir.join("missing"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_skips_symlink_loops() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir("walk-loop");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.txt"), "").unwrap();
+        symlink(&dir, dir.join("sub/parent")).unwrap();
+        symlink(dir.join("sub/a.txt"), dir.join("link.txt")).unwrap();
+        // Files behind links are still searched, but each directory only once
+        assert_eq!(walk(&dir), ["link.txt", "sub/a.txt"].map(PathBuf::from));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
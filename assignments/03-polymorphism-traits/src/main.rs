@@ -1,7 +1,19 @@
+mod find_match;
+mod input;
+mod output;
+
 use anyhow::Result;
 use clap::Parser;
 use colored::Color;
+use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
+
+use find_match::{
+    context_lines, filter_lines, Context, ContextLine, Extent, LiteralNeedle, Needle, RegexNeedle,
+};
+use input::{FileInput, Input, StdinInput, Walk, WalkError};
+use output::{ColoredOutput, Output, PlainOutput, Prefix};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -11,19 +23,551 @@ struct Args {
     #[clap(short = 'v', long)]
     invert_match: bool,
 
+    /// Search for the needle as a regex, like `fo+|ba[rz]`. Otherwise, it's
+    /// found only exactly as it is
     #[clap(short, long)]
     regex: bool,
 
+    /// Search for the needle exactly as it is, even if it looks like a
+    /// regex. This is what happens without `--regex` too
+    #[clap(short = 'F', long, conflicts_with = "regex")]
+    fixed_strings: bool,
+
+    /// Only find the needle as whole words, without a letter, digit, or `_`
+    /// right before or after it
     #[clap(short, long)]
+    word_regexp: bool,
+
+    /// Only find lines that are the needle, or that the regex matches, from
+    /// start to end. This wins over `-w`
+    #[clap(short = 'x', long)]
+    line_regexp: bool,
+
+    /// Print each match in this color. There's no short form, as `-c` is
+    /// taken by `--count`
+    #[clap(long)]
     color: Option<Color>,
 
+    /// Print only how many lines were found, instead of the lines
+    #[clap(short = 'c', long)]
+    count: bool,
+
+    /// Print only the names of the files with a line found, instead of the
+    /// lines
+    #[clap(short = 'l', long)]
+    files_with_matches: bool,
+
+    /// Print only the names of the files without any lines found
+    #[clap(short = 'L', long, conflicts_with = "files_with_matches")]
+    files_without_match: bool,
+
+    /// Print only the parts of each line that match, each on a line of its
+    /// own, instead of the whole line
+    #[clap(short = 'o', long)]
+    only_matching: bool,
+
+    /// Start each line found with its line number, after any file name
+    #[clap(short = 'n', long)]
+    line_number: bool,
+
+    /// Also print this many lines after each line found
+    #[clap(short = 'A', long, value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Also print this many lines before each line found
+    #[clap(short = 'B', long, value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Also print this many lines before and after each line found, unless
+    /// `-A` or `-B` say otherwise
+    #[clap(short = 'C', long, value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Search every file in each directory given, and in the directories
+    /// inside them, or in the current directory if none are given. Only the
+    /// short form `-R` works, as `-r` is taken by `--regex`
+    #[clap(short = 'R', long)]
+    recursive: bool,
+
     needle: String,
 
-    file: Option<PathBuf>,
+    /// The files to search, or stdin if there aren't any. With more than
+    /// one, or a directory to search recursively, each line found starts
+    /// with the name of its file
+    files: Vec<PathBuf>,
+}
+
+impl Args {
+    /// What to search for. It's a regex only when asked for, so a needle
+    /// like `a+b` is found as it is by default, and an invalid regex is an
+    /// error instead of being searched for as text.
+    fn needle(&self) -> Result<Box<dyn Needle>, regex::Error> {
+        if self.regex {
+            Ok(Box::new(RegexNeedle::new(
+                &self.needle,
+                self.ignore_case,
+                self.extent(),
+            )?))
+        } else {
+            Ok(Box::new(LiteralNeedle::new(
+                &self.needle,
+                self.ignore_case,
+                self.extent(),
+            )))
+        }
+    }
+
+    /// How much of each line the needle has to take up.
+    fn extent(&self) -> Extent {
+        if self.line_regexp {
+            Extent::Line
+        } else if self.word_regexp {
+            Extent::Words
+        } else {
+            Extent::Anywhere
+        }
+    }
+
+    /// Whether only file names are printed, so searching a file can stop at
+    /// the first line found.
+    fn lists_files(&self) -> bool {
+        self.files_with_matches || self.files_without_match
+    }
+
+    /// How many lines to print around each line found, which is none when
+    /// the lines aren't printed at all.
+    fn context(&self) -> Context {
+        if self.count || self.lists_files() || self.only_matching {
+            return Context::default();
+        }
+        Context {
+            before: self.before_context.or(self.context).unwrap_or(0),
+            after: self.after_context.or(self.context).unwrap_or(0),
+        }
+    }
 }
 
-fn main() -> Result<()> {
+/// Exit status when no lines were found, or with `-L`, no files were listed,
+/// like grep.
+const NO_MATCH: u8 = 1;
+/// Exit status when the needle is invalid or an input couldn't be read.
+const ERROR: u8 = 2;
+
+/// What searching has turned up so far, for the exit status.
+#[derive(Debug, Default, PartialEq)]
+struct Status {
+    found: bool,
+    failed: bool,
+}
+
+impl Status {
+    fn exit_code(&self) -> ExitCode {
+        if self.failed {
+            ExitCode::from(ERROR)
+        } else if self.found {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::from(NO_MATCH)
+        }
+    }
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
-    println!("{:?}", args);
+    match run(&args) {
+        Ok(status) => status.exit_code(),
+        // Stop quietly when the output is closed early, like `greprs a | head`
+        Err(err)
+            if err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("greprs: {:#}", err);
+            ExitCode::from(ERROR)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<Status> {
+    let needle = args.needle()?;
+    // Searching the current directory leaves `./` off of the names, like grep
+    let implicit_root = args.recursive && args.files.is_empty();
+    let roots = if implicit_root {
+        vec![PathBuf::from(".")]
+    } else {
+        args.files.clone()
+    };
+    let show_names = roots.len() > 1 || args.recursive && roots.iter().any(|root| root.is_dir());
+    let files: Box<dyn Iterator<Item = Result<PathBuf, WalkError>>> = if args.recursive {
+        Box::new(roots.into_iter().flat_map(Walk::new))
+    } else {
+        Box::new(roots.into_iter().map(Ok))
+    };
+
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let mut output: Box<dyn Output + '_> = match args.color {
+        Some(color) => {
+            // Asking for a color means printing it, even into a pipe
+            colored::control::set_override(true);
+            Box::new(ColoredOutput::new(&mut stdout, color))
+        }
+        None => Box::new(PlainOutput::new(&mut stdout)),
+    };
+    let mut status = Status::default();
+    if args.files.is_empty() && !args.recursive {
+        search(
+            &StdinInput,
+            None,
+            needle.as_ref(),
+            args,
+            output.as_mut(),
+            &mut status,
+        )?;
+    }
+    for file in files {
+        let path = match file {
+            Ok(path) if implicit_root => path.strip_prefix(".").map(PathBuf::from).unwrap_or(path),
+            Ok(path) => path,
+            Err(err) => {
+                warn(&err.path.display().to_string(), &err.error, &mut status);
+                continue;
+            }
+        };
+        let input = FileInput::new(path);
+        let name = show_names.then(|| input.name());
+        search(
+            &input,
+            name.as_deref(),
+            needle.as_ref(),
+            args,
+            output.as_mut(),
+            &mut status,
+        )?;
+    }
+    drop(output);
+    stdout.flush()?;
+    Ok(status)
+}
+
+/// Prints each line of `input` that's found, and any context around it,
+/// starting with `name:` if there's a name and its number if asked for. Or,
+/// prints just how many there are for `--count`, or `input`'s name for `-l`
+/// and `-L`. A problem reading the input is printed as a warning and stops
+/// the search of just that input, while failing to print stops everything.
+fn search(
+    input: &dyn Input,
+    name: Option<&str>,
+    needle: &dyn Needle,
+    args: &Args,
+    output: &mut dyn Output,
+    status: &mut Status,
+) -> io::Result<()> {
+    let reader = match input.open() {
+        Ok(reader) => reader,
+        Err(err) => {
+            warn(&input.name(), &err, status);
+            return Ok(());
+        }
+    };
+    let context = args.context();
+    let lines = input::lines(reader);
+    let picked: Box<dyn Iterator<Item = io::Result<ContextLine>>> = if context.is_empty() {
+        Box::new(
+            filter_lines(lines, needle, args.invert_match)
+                .map(|line| line.map(|(number, line)| ContextLine::Found(number, line))),
+        )
+    } else {
+        Box::new(context_lines(lines, needle, args.invert_match, context))
+    };
+    // Groups of lines from different files are kept apart too, and anything
+    // found in an earlier file was printed in a group
+    let mut needs_break = !context.is_empty() && status.found;
+    let mut count = 0;
+    for line in picked {
+        let (number, line, is_context) = match line {
+            Ok(ContextLine::Found(number, line)) => {
+                count += 1;
+                if args.lists_files() {
+                    break;
+                }
+                if args.count {
+                    continue;
+                }
+                (number, line, false)
+            }
+            Ok(ContextLine::Context(number, line)) => (number, line, true),
+            Ok(ContextLine::Break) => {
+                output.print_break()?;
+                continue;
+            }
+            Err(err) => {
+                warn(&input.name(), &err, status);
+                break;
+            }
+        };
+        if needs_break {
+            output.print_break()?;
+            needs_break = false;
+        }
+        let prefix = Prefix {
+            file: name,
+            line_number: args.line_number.then_some(number),
+            context: is_context,
+        };
+        if args.only_matching {
+            for part in line.parts() {
+                output.print_line(prefix, &part)?;
+            }
+        } else {
+            output.print_line(prefix, &line)?;
+        }
+    }
+    if args.lists_files() {
+        if args.files_with_matches == (count > 0) {
+            output.print_file(&input.name())?;
+            status.found = true;
+        }
+    } else {
+        if args.count {
+            output.print_count(name, count)?;
+        }
+        status.found |= count > 0;
+    }
     Ok(())
 }
+
+fn warn(name: &str, err: &io::Error, status: &mut Status) {
+    eprintln!("greprs: {}: {}", name, err);
+    status.failed = true;
+}
+
+#[cfg(test)]
+// One match is written `[4..7]`, which is what the lint is wary of
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use std::io::{self, BufRead, Read};
+
+    use clap::Parser;
+
+    use super::{search, Args, Status};
+    use crate::find_match::LiteralNeedle;
+    use crate::input::Input;
+    use crate::output::PlainOutput;
+
+    /// An input that's already in memory, or `None` for one that can't be
+    /// opened.
+    struct FakeInput(Option<&'static str>);
+
+    impl Input for FakeInput {
+        fn name(&self) -> String {
+            String::from("fake")
+        }
+
+        fn open(&self) -> io::Result<Box<dyn BufRead>> {
+            match self.0 {
+                Some(text) => Ok(Box::new(text.as_bytes())),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "not found")),
+            }
+        }
+    }
+
+    /// An input with a line to find, followed by a failure to read any
+    /// further.
+    struct FailingInput;
+
+    impl Input for FailingInput {
+        fn name(&self) -> String {
+            String::from("failing")
+        }
+
+        fn open(&self) -> io::Result<Box<dyn BufRead>> {
+            struct Failing;
+
+            impl Read for Failing {
+                fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                    Err(io::Error::other("disk on fire"))
+                }
+            }
+
+            Ok(Box::new(io::BufReader::new(b"fox\n".chain(Failing))))
+        }
+    }
+
+    fn search_all(inputs: &[(FakeInput, Option<&str>)], args: &[&str]) -> (String, Status) {
+        let args = Args::parse_from([&["greprs"], args].concat());
+        let needle = LiteralNeedle::new(&args.needle, args.ignore_case, args.extent());
+        let mut printed = Vec::new();
+        let mut output = PlainOutput::new(&mut printed);
+        let mut status = Status::default();
+        for (input, name) in inputs {
+            search(input, *name, &needle, &args, &mut output, &mut status).unwrap();
+        }
+        (String::from_utf8(printed).unwrap(), status)
+    }
+
+    #[test]
+    fn test_search() {
+        let (printed, status) =
+            search_all(&[(FakeInput(Some("a fox\nno\nfoxes")), None)], &["fox"]);
+        assert_eq!(printed, "a fox\nfoxes\n");
+        assert_eq!(
+            status,
+            Status {
+                found: true,
+                failed: false
+            }
+        );
+
+        let (printed, status) = search_all(&[(FakeInput(Some("no")), None)], &["fox"]);
+        assert_eq!(printed, "");
+        assert_eq!(status, Status::default());
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let inputs = [(FakeInput(Some("fox\nno\nfox")), Some("a.txt"))];
+        let (printed, _) = search_all(&inputs, &["-n", "fox"]);
+        assert_eq!(printed, "a.txt:1:fox\na.txt:3:fox\n");
+    }
+
+    #[test]
+    fn test_count() {
+        let inputs = [
+            (FakeInput(Some("fox\nno\nfox")), Some("a.txt")),
+            (FakeInput(Some("no")), Some("b.txt")),
+        ];
+        let (printed, status) = search_all(&inputs, &["-c", "fox"]);
+        assert_eq!(printed, "a.txt:2\nb.txt:0\n");
+        assert!(status.found);
+
+        let (printed, status) = search_all(&inputs[1..], &["-c", "fox"]);
+        assert_eq!(printed, "b.txt:0\n");
+        assert!(!status.found);
+
+        let (printed, _) = search_all(&[(FakeInput(Some("fox\nno\nfox")), None)], &["-cv", "fox"]);
+        assert_eq!(printed, "1\n");
+    }
+
+    #[test]
+    fn test_needle() {
+        let needle = |args: &[&str]| {
+            Args::try_parse_from([&["greprs"], args].concat())
+                .map(|args| args.needle().map(|needle| needle.find_matches("aab a+b")))
+        };
+        assert_eq!(needle(&["a+b"]).unwrap().unwrap(), [4..7]);
+        assert_eq!(needle(&["-F", "a+b"]).unwrap().unwrap(), [4..7]);
+        assert_eq!(needle(&["-r", "a+b"]).unwrap().unwrap(), [0..3]);
+        assert!(needle(&["-r", "(a"]).unwrap().is_err());
+        assert!(needle(&["(a"]).unwrap().unwrap().is_empty());
+        assert!(needle(&["-r", "-F", "a+b"]).is_err());
+    }
+
+    #[test]
+    fn test_only_matching() {
+        let inputs = [(FakeInput(Some("a fox, a FOX\nno\nfoxes")), Some("a.txt"))];
+        let (printed, status) = search_all(&inputs, &["-o", "-n", "-i", "fox"]);
+        assert_eq!(printed, "a.txt:1:fox\na.txt:1:FOX\na.txt:3:fox\n");
+        assert!(status.found);
+        // Lines found for not matching have nothing to print
+        let (printed, status) = search_all(&inputs, &["-ov", "fox"]);
+        assert_eq!(printed, "");
+        assert!(status.found);
+        let (printed, _) = search_all(&inputs, &["-o", "-C1", "no"]);
+        assert_eq!(printed, "a.txt:no\n");
+    }
+
+    #[test]
+    fn test_line_regexp() {
+        let inputs = [(FakeInput(Some("fox\na fox\nFOX")), None)];
+        let (printed, _) = search_all(&inputs, &["-x", "fox"]);
+        assert_eq!(printed, "fox\n");
+        let (printed, _) = search_all(&inputs, &["-xi", "fox"]);
+        assert_eq!(printed, "fox\nFOX\n");
+        let (printed, _) = search_all(&inputs, &["-xw", "fox"]);
+        assert_eq!(printed, "fox\n");
+    }
+
+    #[test]
+    fn test_files_with_matches() {
+        let inputs = [
+            (FakeInput(Some("fox\nno\nfox")), None),
+            (FakeInput(Some("no")), None),
+        ];
+        let (printed, status) = search_all(&inputs, &["-l", "fox"]);
+        assert_eq!(printed, "fake\n");
+        assert!(status.found);
+        let (printed, status) = search_all(&inputs[1..], &["-l", "fox"]);
+        assert_eq!(printed, "");
+        assert!(!status.found);
+
+        let (printed, status) = search_all(&inputs, &["-L", "fox"]);
+        assert_eq!(printed, "fake\n");
+        assert!(status.found);
+        let (printed, status) = search_all(&inputs[..1], &["-L", "fox"]);
+        assert_eq!(printed, "");
+        assert!(!status.found);
+    }
+
+    #[test]
+    fn test_files_with_matches_stops_at_first_match() {
+        let args = Args::parse_from(["greprs", "-l", "fox"]);
+        let needle = LiteralNeedle::new(&args.needle, args.ignore_case, args.extent());
+        let mut printed = Vec::new();
+        let mut status = Status::default();
+        let mut output = PlainOutput::new(&mut printed);
+        search(
+            &FailingInput,
+            None,
+            &needle,
+            &args,
+            &mut output,
+            &mut status,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(printed).unwrap(), "failing\n");
+        // Nothing past the first line was read
+        assert!(!status.failed);
+    }
+
+    #[test]
+    fn test_context() {
+        let text = "a\nfox\nb\nc\nd\nfox\ne";
+        let (printed, _) = search_all(&[(FakeInput(Some(text)), None)], &["-n", "-C1", "fox"]);
+        assert_eq!(printed, "1-a\n2:fox\n3-b\n--\n5-d\n6:fox\n7-e\n");
+        let (printed, _) = search_all(&[(FakeInput(Some(text)), None)], &["-C1", "-A0", "fox"]);
+        assert_eq!(printed, "a\nfox\n--\nd\nfox\n");
+
+        let inputs = [
+            (FakeInput(Some("fox\na")), Some("a.txt")),
+            (FakeInput(Some("no")), Some("b.txt")),
+            (FakeInput(Some("fox")), Some("c.txt")),
+        ];
+        let (printed, _) = search_all(&inputs, &["-A", "1", "fox"]);
+        assert_eq!(printed, "a.txt:fox\na.txt-a\n--\nc.txt:fox\n");
+        // Context is only for lines that are printed
+        let (printed, _) = search_all(&inputs, &["-c", "-C", "1", "fox"]);
+        assert_eq!(printed, "a.txt:1\nb.txt:0\nc.txt:1\n");
+    }
+
+    #[test]
+    fn test_multiple_inputs() {
+        let inputs = [
+            (FakeInput(Some("fox\nno")), Some("a.txt")),
+            (FakeInput(None), Some("missing.txt")),
+            (FakeInput(Some("no\nFOX")), Some("b.txt")),
+        ];
+        let (printed, status) = search_all(&inputs, &["-i", "fox"]);
+        assert_eq!(printed, "a.txt:fox\nb.txt:FOX\n");
+        // Every readable input is still searched
+        assert_eq!(
+            status,
+            Status {
+                found: true,
+                failed: true
+            }
+        );
+    }
+}
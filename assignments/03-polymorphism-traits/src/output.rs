@@ -0,0 +1,261 @@
+//! Printing the lines that were found, plainly or with each match in color.
+
+use std::io::{self, Write};
+
+use colored::{Color, Colorize};
+
+use crate::find_match::MatchedLine;
+
+/// What gets printed before a line, to say where it came from.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Prefix<'a> {
+    /// The file's name, printed as `file:`.
+    pub file: Option<&'a str>,
+    /// The line's number in its file, printed as `42:` after the name.
+    pub line_number: Option<usize>,
+    /// Whether the line is only there as context, so it's printed as
+    /// `file-42-` instead.
+    pub context: bool,
+}
+
+/// Where found lines get printed, and how.
+pub trait Output {
+    /// Prints `line`, starting with whatever's in its `prefix`.
+    fn print_line(&mut self, prefix: Prefix, line: &MatchedLine) -> io::Result<()>;
+
+    /// Prints how many lines were found, starting with `file:` if a file
+    /// name is given.
+    fn print_count(&mut self, file: Option<&str>, count: usize) -> io::Result<()>;
+
+    /// Prints just the name of a file, for listing which files matched.
+    fn print_file(&mut self, file: &str) -> io::Result<()>;
+
+    /// Prints `--` to show that lines were skipped, between groups of lines
+    /// printed with context.
+    fn print_break(&mut self) -> io::Result<()>;
+}
+
+/// Prints each line as it is.
+pub struct PlainOutput<W> {
+    output: W,
+}
+
+impl<W: Write> PlainOutput<W> {
+    pub fn new(output: W) -> Self {
+        PlainOutput { output }
+    }
+}
+
+impl<W: Write> Output for PlainOutput<W> {
+    fn print_line(&mut self, prefix: Prefix, line: &MatchedLine) -> io::Result<()> {
+        print_prefix(&mut self.output, prefix)?;
+        writeln!(self.output, "{}", line.text)
+    }
+
+    fn print_count(&mut self, file: Option<&str>, count: usize) -> io::Result<()> {
+        print_count(&mut self.output, file, count)
+    }
+
+    fn print_file(&mut self, file: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", file)
+    }
+
+    fn print_break(&mut self) -> io::Result<()> {
+        writeln!(self.output, "--")
+    }
+}
+
+/// Prints each line with the parts that match in a color.
+pub struct ColoredOutput<W> {
+    output: W,
+    color: Color,
+}
+
+impl<W: Write> ColoredOutput<W> {
+    pub fn new(output: W, color: Color) -> Self {
+        ColoredOutput { output, color }
+    }
+}
+
+impl<W: Write> Output for ColoredOutput<W> {
+    fn print_line(&mut self, prefix: Prefix, line: &MatchedLine) -> io::Result<()> {
+        print_prefix(&mut self.output, prefix)?;
+        let mut printed = 0;
+        for found in &line.matches {
+            write!(self.output, "{}", &line.text[printed..found.start])?;
+            if !found.is_empty() {
+                write!(
+                    self.output,
+                    "{}",
+                    line.text[found.clone()].color(self.color)
+                )?;
+            }
+            printed = found.end;
+        }
+        writeln!(self.output, "{}", &line.text[printed..])
+    }
+
+    fn print_count(&mut self, file: Option<&str>, count: usize) -> io::Result<()> {
+        print_count(&mut self.output, file, count)
+    }
+
+    fn print_file(&mut self, file: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", file)
+    }
+
+    fn print_break(&mut self) -> io::Result<()> {
+        writeln!(self.output, "--")
+    }
+}
+
+fn print_prefix(output: &mut impl Write, prefix: Prefix) -> io::Result<()> {
+    let separator = if prefix.context { '-' } else { ':' };
+    if let Some(file) = prefix.file {
+        write!(output, "{}{}", file, separator)?;
+    }
+    if let Some(number) = prefix.line_number {
+        write!(output, "{}{}", number, separator)?;
+    }
+    Ok(())
+}
+
+fn print_count(output: &mut impl Write, file: Option<&str>, count: usize) -> io::Result<()> {
+    let prefix = Prefix {
+        file,
+        ..Prefix::default()
+    };
+    print_prefix(output, prefix)?;
+    writeln!(output, "{}", count)
+}
+
+#[cfg(test)]
+// One match is written `[2..5]`, which is what the lint is wary of
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use std::ops::Range;
+
+    use colored::Color;
+
+    use super::{ColoredOutput, Output, PlainOutput, Prefix};
+    use crate::find_match::MatchedLine;
+
+    fn matched(text: &str, matches: Vec<Range<usize>>) -> MatchedLine {
+        MatchedLine {
+            text: String::from(text),
+            matches,
+        }
+    }
+
+    fn print(output: &mut dyn Output, file: Option<&str>, lines: &[MatchedLine]) {
+        for line in lines {
+            let prefix = Prefix {
+                file,
+                ..Prefix::default()
+            };
+            output.print_line(prefix, line).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let mut printed = Vec::new();
+        let lines = [matched("a fox", vec![2..5]), matched("no match", vec![])];
+        print(&mut PlainOutput::new(&mut printed), None, &lines);
+        print(
+            &mut PlainOutput::new(&mut printed),
+            Some("a.txt"),
+            &lines[..1],
+        );
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "a fox\nno match\na.txt:a fox\n"
+        );
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let mut printed = Vec::new();
+        let mut output = PlainOutput::new(&mut printed);
+        let line = matched("a fox", vec![2..5]);
+        for file in [None, Some("a.txt")] {
+            let prefix = Prefix {
+                file,
+                line_number: Some(42),
+                ..Prefix::default()
+            };
+            output.print_line(prefix, &line).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "42:a fox\na.txt:42:a fox\n"
+        );
+    }
+
+    #[test]
+    fn test_context() {
+        let mut printed = Vec::new();
+        let mut output = PlainOutput::new(&mut printed);
+        let line = matched("a fox", vec![2..5]);
+        let prefix = Prefix {
+            file: Some("a.txt"),
+            line_number: Some(41),
+            context: true,
+        };
+        output.print_line(prefix, &line).unwrap();
+        output.print_break().unwrap();
+        let prefix = Prefix {
+            context: true,
+            ..Prefix::default()
+        };
+        output.print_line(prefix, &line).unwrap();
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "a.txt-41-a fox\n--\na fox\n"
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let mut printed = Vec::new();
+        PlainOutput::new(&mut printed).print_count(None, 3).unwrap();
+        ColoredOutput::new(&mut printed, Color::Red)
+            .print_count(Some("a.txt"), 0)
+            .unwrap();
+        assert_eq!(String::from_utf8(printed).unwrap(), "3\na.txt:0\n");
+    }
+
+    #[test]
+    fn test_print_file() {
+        let mut printed = Vec::new();
+        PlainOutput::new(&mut printed).print_file("a.txt").unwrap();
+        ColoredOutput::new(&mut printed, Color::Red)
+            .print_file("b.txt")
+            .unwrap();
+        assert_eq!(String::from_utf8(printed).unwrap(), "a.txt\nb.txt\n");
+    }
+
+    #[test]
+    fn test_colored_output() {
+        colored::control::set_override(true);
+        let mut printed = Vec::new();
+        let lines = [
+            matched("a fox, a fox", vec![2..5, 9..12]),
+            matched("no match", vec![]),
+            matched("empty", vec![0..0]),
+        ];
+        print(
+            &mut ColoredOutput::new(&mut printed, Color::Red),
+            None,
+            &lines,
+        );
+        print(
+            &mut ColoredOutput::new(&mut printed, Color::Blue),
+            Some("a.txt"),
+            &[matched("fox", vec![0..3])],
+        );
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "a \x1b[31mfox\x1b[0m, a \x1b[31mfox\x1b[0m\nno match\nempty\na.txt:\x1b[34mfox\x1b[0m\n"
+        );
+    }
+}
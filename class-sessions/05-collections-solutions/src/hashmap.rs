@@ -1,89 +1,194 @@
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
 
+/// Returns the most common word in the file at `file_path`, ignoring case and
+/// punctuation. Ties go to the alphabetically first word.
 #[allow(dead_code)]
 fn get_most_common_words(file_path: &str) -> String {
-    let file_contents = fs::read_to_string(file_path)
-        .expect("File should exist")
-        .replace(&['?', '.', '!', '-'], "");
-    ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    // let mut word_frequencies = HashMap::new();
+    let file = File::open(file_path).expect("File should exist");
+    get_top_words(file, 1, &[])
+        .expect("File should be readable")
+        .into_iter()
+        .map(|(word, _)| word)
+        .next()
+        .expect("Most common word should exist")
+}
 
-    // for word in file_contents.split_whitespace() {
-    //     word_frequencies
-    //         .entry(word)
-    //         .and_modify(|counter| *counter += 1)
-    //         .or_insert(1);
-    // }
+/// Returns the `n` most common words read from `reader`, along with how many
+/// times each appears, ignoring case, punctuation, and any of the `stopwords`.
+///
+/// The words are sorted from most to least common, with ties broken
+/// alphabetically so the output is deterministic. Fewer than `n` pairs are
+/// returned if there aren't that many distinct words.
+#[allow(dead_code)]
+fn get_top_words(
+    reader: impl Read,
+    n: usize,
+    stopwords: &[&str],
+) -> io::Result<Vec<(String, usize)>> {
+    let stopwords: HashSet<String> = stopwords.iter().map(|word| word.to_lowercase()).collect();
+
+    let word_frequencies = count_words(reader, &stopwords)?;
 
-    // let mut max_word: &str = &String::new();
-    // let mut max_word_frequency = 0;
+    let mut top_words: Vec<(String, usize)> = word_frequencies.into_iter().collect();
+    top_words.sort_unstable_by(|(word_a, count_a), (word_b, count_b)| {
+        count_b.cmp(count_a).then_with(|| word_a.cmp(word_b))
+    });
+    top_words.truncate(n);
+
+    Ok(top_words)
+}
 
-    // for (word, freq) in word_frequencies.clone().into_iter() {
-    //     if freq > max_word_frequency {
-    //         max_word = word;
-    //         max_word_frequency = freq;
+/// Counts how many times each word appears in `reader`, ignoring case,
+/// punctuation, and any of the (lowercase) `stopwords`.
+fn count_words(
+    reader: impl Read,
+    stopwords: &HashSet<String>,
+) -> io::Result<HashMap<String, usize>> {
+    ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
+    // let mut word_frequencies = HashMap::new();
+
+    // for line in BufReader::new(reader).lines() {
+    //     let line = line?.replace(['?', '.', '!', '-'], "");
+    //     for word in line.split_whitespace() {
+    //         let word = word.to_lowercase();
+    //         if !stopwords.contains(&word) {
+    //             *word_frequencies.entry(word).or_insert(0) += 1;
+    //         }
     //     }
     // }
-    // println!("{:?}", word_frequencies.clone());
 
-    // max_word.to_owned()
+    // Ok(word_frequencies)
 
     ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    file_contents
-        .split_whitespace()
-        .fold(HashMap::new(), |mut acc, word| {
-            let _ = *acc
-                .entry(word.to_lowercase())
-                .and_modify(|counter| *counter += 1)
-                .or_insert(1);
-            acc
+    BufReader::new(reader)
+        .lines()
+        .try_fold(HashMap::new(), |mut acc, line| -> io::Result<_> {
+            line?
+                .replace(['?', '.', '!', '-'], "")
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .filter(|word| !stopwords.contains(word))
+                .for_each(|word| *acc.entry(word).or_insert(0) += 1);
+            Ok(acc)
         })
-        .into_iter()
-        .max_by_key(|(_, count)| *count)
-        .map(|(word, _)| word)
-        .expect("Most common word should exist")
-        .to_owned()
 }
 
+/// Counts the words in every file in `paths`, like `count_words`, spreading
+/// the files across `threads` threads and merging their counts. At least one
+/// thread is always used, and never more than there are files.
 #[allow(dead_code)]
-fn get_unique_characters(input: String) -> Vec<char> {
+fn get_word_counts_parallel(paths: &[PathBuf], threads: usize) -> io::Result<HashMap<String, u64>> {
+    let threads = threads.clamp(1, paths.len().max(1));
+    let files_per_thread = paths.len().div_ceil(threads).max(1);
+    let no_stopwords = HashSet::new();
+
+    // Scoped threads can borrow `paths` and `no_stopwords` directly, since
+    // they're guaranteed to finish before this function returns
+    thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(files_per_thread)
+            .map(|chunk| {
+                let no_stopwords = &no_stopwords;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .try_fold(HashMap::new(), |acc, path| -> io::Result<_> {
+                            let counts = count_words(File::open(path)?, no_stopwords)?;
+                            let counts =
+                                counts.into_iter().map(|(word, count)| (word, count as u64));
+                            Ok(merge_word_counts(acc, counts))
+                        })
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .try_fold(HashMap::new(), |acc, handle| -> io::Result<_> {
+                let counts = handle
+                    .join()
+                    .expect("Word counting thread shouldn't panic")?;
+                Ok(merge_word_counts(acc, counts))
+            })
+    })
+}
+
+fn merge_word_counts(
+    mut total: HashMap<String, u64>,
+    counts: impl IntoIterator<Item = (String, u64)>,
+) -> HashMap<String, u64> {
+    for (word, count) in counts {
+        *total.entry(word).or_insert(0) += count;
+    }
+    total
+}
+
+/// Returns the characters that appear exactly once in `input`, in the order
+/// they first appear. Characters are grapheme clusters, so an accented letter
+/// or an emoji sequence counts as a single character.
+#[allow(dead_code)]
+fn get_unique_characters(input: &str) -> Vec<&str> {
+    get_unique_characters_with(input, false)
+}
+
+/// Like `get_unique_characters`, but when `ignore_case` is set, characters
+/// that only differ in case count as the same character.
+#[allow(dead_code)]
+fn get_unique_characters_with(input: &str, ignore_case: bool) -> Vec<&str> {
+    let key = |grapheme: &str| {
+        if ignore_case {
+            grapheme.to_lowercase()
+        } else {
+            grapheme.to_owned()
+        }
+    };
+
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
     // let mut letter_frequencies = HashMap::new();
-    // for char in input.chars() {
-    //     letter_frequencies
-    //         .entry(char)
-    //         .and_modify(|counter| *counter += 1)
-    //         .or_insert(1);
+    // for grapheme in input.graphemes(true) {
+    //     *letter_frequencies.entry(key(grapheme)).or_insert(0) += 1;
     // }
 
     // let mut single_chars = Vec::new();
-    // for (char, freq) in letter_frequencies.into_iter() {
-    //     if freq == 1 {
-    //         single_chars.push(char);
+    // for grapheme in input.graphemes(true) {
+    //     if letter_frequencies[&key(grapheme)] == 1 {
+    //         single_chars.push(grapheme);
     //     }
     // }
     // single_chars
 
     ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    input
-        .chars()
-        .fold(HashMap::new(), |mut acc, char| {
-            let _ = *acc
-                .entry(char)
-                .and_modify(|counter| *counter += 1)
-                .or_insert(1);
+    // Count first, then walk the input again so the output keeps the order of
+    // the input rather than the arbitrary order of the HashMap
+    let letter_frequencies = input
+        .graphemes(true)
+        .fold(HashMap::new(), |mut acc, grapheme| {
+            *acc.entry(key(grapheme)).or_insert(0) += 1;
             acc
-        })
-        .into_iter()
-        .filter_map(|(char, freq)| if freq == 1 { Some(char) } else { None })
+        });
+
+    input
+        .graphemes(true)
+        .filter(|grapheme| letter_frequencies[&key(grapheme)] == 1)
         .collect()
 }
 
 // Do not modify below here
 #[cfg(test)]
 mod tests {
-    use crate::hashmap::{get_most_common_words, get_unique_characters};
+    use crate::hashmap::{
+        get_most_common_words, get_top_words, get_unique_characters, get_unique_characters_with,
+        get_word_counts_parallel,
+    };
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Instant;
+    use std::{env, fs, process};
 
     #[test]
     fn test_get_most_common_words() {
@@ -102,30 +207,172 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_top_words() {
+        let text = "The cat and the dog. The DOG and the bird!\nA cat-nap?";
+        assert_eq!(
+            get_top_words(text.as_bytes(), 3, &[]).unwrap(),
+            vec![
+                (String::from("the"), 4),
+                (String::from("and"), 2),
+                (String::from("dog"), 2),
+            ]
+        );
+
+        // Asking for more words than exist returns all of them
+        assert_eq!(get_top_words(text.as_bytes(), 100, &[]).unwrap().len(), 7);
+        assert_eq!(get_top_words(text.as_bytes(), 0, &[]).unwrap(), Vec::new());
+        assert_eq!(get_top_words("".as_bytes(), 5, &[]).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_get_top_words_stopwords() {
+        let text = "The cat and the dog. The DOG and the bird!";
+        assert_eq!(
+            get_top_words(text.as_bytes(), 2, &["the", "AND"]).unwrap(),
+            vec![(String::from("dog"), 2), (String::from("bird"), 1)]
+        );
+    }
+
+    #[test]
+    fn test_get_top_words_ties() {
+        // Every word appears twice, so the order is purely alphabetical
+        let text = "pear apple fig\nfig pear apple";
+        assert_eq!(
+            get_top_words(text.as_bytes(), 3, &[]).unwrap(),
+            vec![
+                (String::from("apple"), 2),
+                (String::from("fig"), 2),
+                (String::from("pear"), 2),
+            ]
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                get_top_words(text.as_bytes(), 1, &[]).unwrap(),
+                vec![(String::from("apple"), 2)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_top_words_file() {
+        let file = std::fs::File::open("poems/poem.txt").unwrap();
+        let top_words = get_top_words(file, 1, &[]).unwrap();
+        assert_eq!(top_words[0].0, "to");
+    }
+
+    fn poem_paths() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir("poems")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_get_word_counts_parallel() {
+        let paths = poem_paths();
+        let single_threaded = get_word_counts_parallel(&paths, 1).unwrap();
+        assert!(single_threaded["the"] > 0);
+
+        // The thread count shouldn't affect the result, even when it's 0 or
+        // more than the number of files
+        for threads in [0, 2, 3, 16] {
+            assert_eq!(
+                get_word_counts_parallel(&paths, threads).unwrap(),
+                single_threaded
+            );
+        }
+
+        // Counting the same files twice should double every count
+        let doubled: Vec<PathBuf> = paths.iter().chain(&paths).cloned().collect();
+        let doubled_counts = get_word_counts_parallel(&doubled, 4).unwrap();
+        assert!(single_threaded
+            .iter()
+            .all(|(word, count)| doubled_counts[word] == count * 2));
+
+        assert_eq!(get_word_counts_parallel(&[], 4).unwrap(), HashMap::new());
+        assert!(get_word_counts_parallel(&[PathBuf::from("poems/missing.txt")], 1).is_err());
+    }
+
+    /// Compares single and multi-threaded counting over many copies of the
+    /// poems. Run it with
+    /// `cargo test --release -- --ignored --nocapture bench_get_word_counts_parallel`.
+    #[test]
+    #[ignore]
+    fn bench_get_word_counts_parallel() {
+        const COPIES: usize = 500;
+        let dir = env::temp_dir().join(format!("word_counts_bench_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut paths = Vec::new();
+        for copy in 0..COPIES {
+            for poem in poem_paths() {
+                let path = dir.join(format!(
+                    "{copy}_{}",
+                    poem.file_name().unwrap().to_str().unwrap()
+                ));
+                // Repeat each poem so there's a meaningful amount of work per file
+                fs::write(&path, fs::read_to_string(&poem).unwrap().repeat(20)).unwrap();
+                paths.push(path);
+            }
+        }
+
+        let mut results = Vec::new();
+        for threads in [1, 2, 4, 8] {
+            let start = Instant::now();
+            let counts = get_word_counts_parallel(&paths, threads).unwrap();
+            println!("{threads} thread(s): {:?}", start.elapsed());
+            results.push(counts);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(results.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
     #[test]
     fn test_get_unique_characters() {
-        assert!(check_unordered_vec_are_eq(
-            &mut get_unique_characters(String::from("strawberry")),
-            &mut vec!['s', 't', 'a', 'w', 'b', 'e', 'y']
-        ));
-        assert!(check_unordered_vec_are_eq(
-            &mut get_unique_characters(String::from("black")),
-            &mut vec!['b', 'l', 'a', 'c', 'k']
-        ));
-        assert!(check_unordered_vec_are_eq(
-            &mut get_unique_characters(String::from("white")),
-            &mut vec!['w', 'h', 'i', 't', 'e']
-        ));
-        assert!(check_unordered_vec_are_eq(
-            &mut get_unique_characters(String::from("none")),
-            &mut vec!['o', 'e']
-        ));
+        assert_eq!(
+            get_unique_characters("strawberry"),
+            vec!["s", "t", "a", "w", "b", "e", "y"]
+        );
+        assert_eq!(
+            get_unique_characters("black"),
+            vec!["b", "l", "a", "c", "k"]
+        );
+        assert_eq!(
+            get_unique_characters("white"),
+            vec!["w", "h", "i", "t", "e"]
+        );
+        assert_eq!(get_unique_characters("none"), vec!["o", "e"]);
+        assert_eq!(get_unique_characters("aabb"), Vec::<&str>::new());
+        assert_eq!(get_unique_characters(""), Vec::<&str>::new());
     }
 
-    fn check_unordered_vec_are_eq(vec1: &mut Vec<char>, vec2: &mut Vec<char>) -> bool {
-        vec1.sort();
-        vec2.sort();
+    #[test]
+    fn test_get_unique_characters_unicode() {
+        // "e\u{301}" is an "e" followed by a combining accent, which is one
+        // character to a reader even though it's two chars
+        assert_eq!(
+            get_unique_characters("cafe\u{301} cafe"),
+            vec!["e\u{301}", " ", "e"]
+        );
+        assert_eq!(get_unique_characters("👍🏽👍👍🏽"), vec!["👍"]);
+        assert_eq!(get_unique_characters("日本語の本"), vec!["日", "語", "の"]);
+    }
 
-        vec1 == vec2
+    #[test]
+    fn test_get_unique_characters_ignore_case() {
+        assert_eq!(get_unique_characters("Banana"), vec!["B"]);
+        assert_eq!(get_unique_characters_with("Banana", true), vec!["B"]);
+        assert_eq!(get_unique_characters_with("Noon", false), vec!["N", "n"]);
+        assert_eq!(get_unique_characters_with("Noon", true), Vec::<&str>::new());
+        // The original spelling is returned, not the lowercased one
+        assert_eq!(
+            get_unique_characters_with("Strawberry", true),
+            vec!["S", "t", "a", "w", "b", "e", "y"]
+        );
+        assert_eq!(get_unique_characters_with("ÉCOLE école", true), vec![" "]);
     }
 }
@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
 #[allow(dead_code)]
 fn merge_strings(arr: Vec<String>) -> String {
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
@@ -15,33 +19,99 @@ fn merge_strings(arr: Vec<String>) -> String {
     })
 }
 
+/// Returns how many times `letter` appears in `word`.
 #[allow(dead_code)]
 fn count_letter(word: String, letter: char) -> u8 {
-    ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    // let mut count = 0;
-    // for chr in word.chars() {
-    //     if chr == letter {
-    //         count += 1;
-    //     }
-    // }
+    count_occurrences(&word, letter.encode_utf8(&mut [0; 4]))
+        .try_into()
+        .expect("Letter count should fit in a u8")
+}
 
-    // count
+/// How `count_occurrences_with` decides what counts as a match.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CountMode {
+    /// Matches can't share any characters, so "aa" appears once in "aaa".
+    NonOverlapping,
+    /// Every position the needle starts at counts, so "aa" appears twice in
+    /// "aaa".
+    Overlapping,
+    /// Like `NonOverlapping`, but matches must also start and end on grapheme
+    /// cluster boundaries, so "e" isn't found in "e\u{301}" ("é" written with
+    /// a combining accent).
+    Graphemes,
+}
 
-    ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    word.chars().fold(0, |count, chr| match chr == letter {
-        true => count + 1,
-        false => count,
-    })
+/// Returns the number of non-overlapping times `needle` appears in
+/// `haystack`. An empty `needle` never matches.
+#[allow(dead_code)]
+fn count_occurrences(haystack: &str, needle: &str) -> usize {
+    count_occurrences_with(haystack, needle, CountMode::NonOverlapping)
 }
 
+/// Like `count_occurrences`, but with a choice of how matches are counted.
+#[allow(dead_code)]
+fn count_occurrences_with(haystack: &str, needle: &str, mode: CountMode) -> usize {
+    if needle.is_empty() {
+        return 0;
+    }
+
+    match mode {
+        CountMode::NonOverlapping => haystack.matches(needle).count(),
+        CountMode::Overlapping => haystack
+            .char_indices()
+            .filter(|&(idx, _)| haystack[idx..].starts_with(needle))
+            .count(),
+        CountMode::Graphemes => {
+            let haystack: Vec<&str> = haystack.graphemes(true).collect();
+            let needle: Vec<&str> = needle.graphemes(true).collect();
+
+            let mut count = 0;
+            let mut idx = 0;
+            while idx + needle.len() <= haystack.len() {
+                if haystack[idx..idx + needle.len()] == needle[..] {
+                    count += 1;
+                    idx += needle.len();
+                } else {
+                    idx += 1;
+                }
+            }
+            count
+        }
+    }
+}
+
+/// Uppercases the characters of `input` at the indices in `idx_to_capitalize`.
+/// See `selectively_capitalize_ranges` for what an index refers to.
 #[allow(dead_code)]
 fn selectively_capitalize(input: String, idx_to_capitalize: Vec<usize>) -> String {
+    selectively_capitalize_ranges(
+        &input,
+        idx_to_capitalize.into_iter().map(|idx| idx..idx + 1),
+    )
+}
+
+/// Uppercases the characters of `input` whose indices fall in any of the
+/// `ranges`, so `0..3` capitalizes the first three characters.
+///
+/// A character is a grapheme cluster rather than a `char`, so an accented
+/// letter written with a combining mark counts once. Indices always refer to
+/// the characters of the original `input`, even when uppercasing a character
+/// produces more than one (e.g. 'ß' becomes "SS"). Indices past the end of
+/// `input` are ignored.
+#[allow(dead_code)]
+fn selectively_capitalize_ranges(
+    input: &str,
+    ranges: impl IntoIterator<Item = Range<usize>>,
+) -> String {
+    let ranges: Vec<Range<usize>> = ranges.into_iter().collect();
+
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    // let mut ret = String::new();
-    // for (idx, val) in input.chars().enumerate() {
-    //     match idx_to_capitalize.contains(&idx) {
-    //         true => ret.push_str(&val.to_uppercase().to_string()),
-    //         false => ret.push(val),
+    // let mut ret = String::with_capacity(input.len());
+    // for (idx, grapheme) in input.graphemes(true).enumerate() {
+    //     match ranges.iter().any(|range| range.contains(&idx)) {
+    //         true => ret.push_str(&grapheme.to_uppercase()),
+    //         false => ret.push_str(grapheme),
     //     }
     // }
 
@@ -49,19 +119,24 @@ fn selectively_capitalize(input: String, idx_to_capitalize: Vec<usize>) -> Strin
 
     ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
     input
-        .chars()
+        .graphemes(true)
         .enumerate()
-        .map(|(idx, val)| match idx_to_capitalize.contains(&idx) {
-            true => val.to_uppercase().to_string(),
-            false => val.to_string(),
-        })
+        .map(
+            |(idx, grapheme)| match ranges.iter().any(|range| range.contains(&idx)) {
+                true => Cow::Owned(grapheme.to_uppercase()),
+                false => Cow::Borrowed(grapheme),
+            },
+        )
         .collect()
 }
 
 // Do not modify below here
 #[cfg(test)]
 mod tests {
-    use crate::strings::{count_letter, merge_strings, selectively_capitalize};
+    use crate::strings::{
+        count_letter, count_occurrences, count_occurrences_with, merge_strings,
+        selectively_capitalize, selectively_capitalize_ranges, CountMode,
+    };
 
     #[test]
     fn test_merge_strings() {
@@ -100,6 +175,82 @@ mod tests {
         assert_eq!(count_letter(String::from("blackberry"), 'z'), 0);
     }
 
+    #[test]
+    fn test_count_letter_unicode() {
+        assert_eq!(count_letter(String::from("naïve ïcon"), 'ï'), 2);
+        assert_eq!(count_letter(String::from("日本の日"), '日'), 2);
+    }
+
+    #[test]
+    fn test_count_occurrences() {
+        assert_eq!(count_occurrences("banana", "an"), 2);
+        assert_eq!(count_occurrences("banana", "ana"), 1);
+        assert_eq!(count_occurrences("banana", "banana"), 1);
+        assert_eq!(count_occurrences("banana", "bananas"), 0);
+        assert_eq!(count_occurrences("banana", ""), 0);
+        assert_eq!(count_occurrences("", "a"), 0);
+    }
+
+    #[test]
+    fn test_count_occurrences_overlapping() {
+        assert_eq!(
+            count_occurrences_with("aaaa", "aa", CountMode::NonOverlapping),
+            2
+        );
+        assert_eq!(
+            count_occurrences_with("aaaa", "aa", CountMode::Overlapping),
+            3
+        );
+        assert_eq!(
+            count_occurrences_with("banana", "ana", CountMode::Overlapping),
+            2
+        );
+        assert_eq!(
+            count_occurrences_with("ééé", "éé", CountMode::Overlapping),
+            2
+        );
+        assert_eq!(count_occurrences_with("abc", "", CountMode::Overlapping), 0);
+    }
+
+    #[test]
+    fn test_count_occurrences_graphemes() {
+        // "e\u{301}" is an "e" followed by a combining accent
+        let text = "cafe\u{301} and cafe";
+        assert_eq!(
+            count_occurrences_with(text, "e", CountMode::NonOverlapping),
+            2
+        );
+        assert_eq!(count_occurrences_with(text, "e", CountMode::Graphemes), 1);
+        assert_eq!(
+            count_occurrences_with(text, "e\u{301}", CountMode::Graphemes),
+            1
+        );
+        assert_eq!(
+            count_occurrences_with(text, "cafe", CountMode::Graphemes),
+            1
+        );
+
+        // A thumbs up with a skin tone modifier is one grapheme that starts
+        // with a plain thumbs up
+        let thumbs = "👍🏽👍👍🏽";
+        assert_eq!(
+            count_occurrences_with(thumbs, "👍", CountMode::NonOverlapping),
+            3
+        );
+        assert_eq!(
+            count_occurrences_with(thumbs, "👍", CountMode::Graphemes),
+            1
+        );
+        assert_eq!(
+            count_occurrences_with(thumbs, "👍🏽", CountMode::Graphemes),
+            2
+        );
+        assert_eq!(
+            count_occurrences_with("aaaa", "aa", CountMode::Graphemes),
+            2
+        );
+    }
+
     #[test]
     fn test_selectively_capitalize() {
         assert_eq!(
@@ -122,4 +273,43 @@ mod tests {
             String::from("silent goodbye")
         );
     }
+
+    #[test]
+    fn test_selectively_capitalize_ranges() {
+        assert_eq!(
+            selectively_capitalize_ranges("hello world", [0..1, 6..11]),
+            "Hello WORLD"
+        );
+        assert_eq!(selectively_capitalize_ranges("hello", Some(1..4)), "hELLo");
+        // Overlapping, empty, and out of bounds ranges
+        assert_eq!(
+            selectively_capitalize_ranges("hello", [0..2, 1..3, 4..4, 4..100]),
+            "HELlO"
+        );
+        assert_eq!(selectively_capitalize_ranges("", Some(0..10)), "");
+    }
+
+    #[test]
+    fn test_selectively_capitalize_unicode() {
+        // 'ß' uppercases to "SS", which shouldn't shift the characters after it
+        assert_eq!(
+            selectively_capitalize(String::from("straße"), vec![4, 5]),
+            String::from("straSSE")
+        );
+        assert_eq!(selectively_capitalize_ranges("ßaß", Some(0..3)), "SSASS");
+
+        // "e\u{301}" is one character made of two chars
+        assert_eq!(
+            selectively_capitalize(String::from("cafe\u{301}s"), vec![3, 4]),
+            String::from("cafE\u{301}S")
+        );
+        assert_eq!(
+            selectively_capitalize_ranges("日本 ok", Some(0..4)),
+            String::from("日本 Ok")
+        );
+        assert_eq!(
+            selectively_capitalize_ranges("i👍🏽i", Some(0..3)),
+            String::from("I👍🏽I")
+        );
+    }
 }
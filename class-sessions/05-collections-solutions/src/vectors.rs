@@ -1,7 +1,31 @@
+use num_traits::PrimInt;
 use std::cmp::Ordering;
 
+/// Lazily yields the Fibonacci numbers, starting from 1, 1, so callers can
+/// `take`, `filter`, or `zip` them without building a Vec first. The sequence
+/// ends after the largest term that fits in a u128 (the 186th).
 #[allow(dead_code)]
-fn get_fibonacci(fibonacci_size: usize) -> Vec<u32> {
+fn fibonacci() -> impl Iterator<Item = u128> {
+    std::iter::successors(Some((0_u128, 1_u128)), |&(prev, current)| {
+        prev.checked_add(current).map(|next| (current, next))
+    })
+    .map(|(_, current)| current)
+}
+
+/// Returns the first `fibonacci_size` Fibonacci numbers, starting from 1, 1.
+///
+/// Panics if a term doesn't fit in a u128, which first happens at the 187th
+/// term. Use `get_fibonacci_checked` or `get_fibonacci_saturating` to handle
+/// that case instead.
+#[allow(dead_code)]
+fn get_fibonacci(fibonacci_size: usize) -> Vec<u128> {
+    get_fibonacci_checked(fibonacci_size).expect("Fibonacci terms should fit in a u128")
+}
+
+/// Like `get_fibonacci`, but returns `None` if any of the requested terms
+/// would overflow a u128.
+#[allow(dead_code)]
+fn get_fibonacci_checked(fibonacci_size: usize) -> Option<Vec<u128>> {
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
     // let mut fibonacci = Vec::with_capacity(fibonacci_size);
 
@@ -9,51 +33,112 @@ fn get_fibonacci(fibonacci_size: usize) -> Vec<u32> {
     // fibonacci.push(1);
 
     // for idx in 2..fibonacci_size {
-    //     fibonacci.push(fibonacci[idx - 1] + fibonacci[idx - 2]);
+    //     fibonacci.push(fibonacci[idx - 1].checked_add(fibonacci[idx - 2])?);
     // }
 
     ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    (0..fibonacci_size)
-        .scan((0, 1), |state, _| {
-            let (a, b) = *state;
-            *state = (b, a + b);
-            Some(b)
-        })
+    // `fibonacci` stops early rather than overflowing, so coming up short
+    // means some of the requested terms don't fit
+    let terms: Vec<u128> = fibonacci().take(fibonacci_size).collect();
+    (terms.len() == fibonacci_size).then_some(terms)
+}
+
+/// Like `get_fibonacci`, but terms that would overflow a u128 are clamped to
+/// `u128::MAX`.
+#[allow(dead_code)]
+fn get_fibonacci_saturating(fibonacci_size: usize) -> Vec<u128> {
+    fibonacci()
+        .chain(std::iter::repeat(u128::MAX))
+        .take(fibonacci_size)
         .collect()
 }
 
+/// Returns the index of `search_val` in the sorted slice `arr`, or `None` if it
+/// isn't present. If `search_val` appears more than once, any one of the
+/// matching indices may be returned.
 #[allow(dead_code)]
-fn binary_search(arr: Vec<u8>, search_val: u8) -> usize {
+fn binary_search<T: Ord>(arr: &[T], search_val: &T) -> Option<usize> {
+    // Search the half-open range [low, high) of the original slice, so no
+    // sub-slices need to be copied and an empty range means a miss
     let mut low = 0;
     let mut high = arr.len();
 
-    loop {
-        let mid = (high + low) / 2;
-        match arr[mid].cmp(&search_val) {
-            Ordering::Equal => return mid,
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match arr[mid].cmp(search_val) {
+            Ordering::Equal => return Some(mid),
             Ordering::Greater => high = mid,
-            Ordering::Less => low = mid,
+            Ordering::Less => low = mid + 1,
         }
-        println!("{high}, {low}")
     }
+
+    None
 }
 
+/// Which numbers `filter_numbers` should keep.
 #[allow(dead_code)]
-fn filter_even_numbers(arr: Vec<u8>) -> Vec<u8> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    #[allow(dead_code)]
+    fn of<T: PrimInt>(num: T) -> Parity {
+        // Checking the lowest bit rather than `num % 2 == 0` also works for
+        // negative numbers, where the remainder of an odd number is -1
+        if num & T::one() == T::zero() {
+            Parity::Even
+        } else {
+            Parity::Odd
+        }
+    }
+}
+
+/// Returns the numbers in `arr` with the given parity, in their original
+/// order.
+#[allow(dead_code)]
+fn filter_numbers<T: PrimInt>(arr: Vec<T>, keep: Parity) -> Vec<T> {
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    // new_arr = Vec::new();
+    // let mut new_arr = Vec::new();
     // for num in arr {
-    //     if num % 2 == 0 {
+    //     if Parity::of(num) == keep {
     //         new_arr.push(num);
     //     }
     // }
 
     ////////////////////////// FUNCTIONAL SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\
-    arr.into_iter().filter(|x| x % 2 == 1).collect()
+    arr.into_iter()
+        .filter(|&num| Parity::of(num) == keep)
+        .collect()
+}
+
+/// Returns only the even numbers in `arr`.
+#[allow(dead_code)]
+fn keep_even_numbers<T: PrimInt>(arr: Vec<T>) -> Vec<T> {
+    filter_numbers(arr, Parity::Even)
 }
 
+/// Returns only the odd numbers in `arr`.
 #[allow(dead_code)]
-fn get_longest_increasing_subsequence_len(arr: Vec<u8>) -> u8 {
+fn keep_odd_numbers<T: PrimInt>(arr: Vec<T>) -> Vec<T> {
+    filter_numbers(arr, Parity::Odd)
+}
+
+/// Despite the name, this filters the even numbers *out*, returning the odd
+/// ones.
+#[allow(dead_code)]
+#[deprecated(note = "this returns the odd numbers; use `keep_odd_numbers` or `filter_numbers`")]
+fn filter_even_numbers(arr: Vec<u8>) -> Vec<u8> {
+    keep_odd_numbers(arr)
+}
+
+/// Returns the length of the longest run of strictly increasing adjacent
+/// values in `arr`. Unlike a subsequence, the values in a run must be
+/// contiguous; see `get_longest_increasing_subsequence` for that.
+#[allow(dead_code)]
+fn get_longest_increasing_run_len(arr: Vec<u8>) -> u8 {
     ///////////////////////// IMPERATIVE SOLUTION \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
     // let mut curr_streak = 1;
     // let mut max_streak = 1;
@@ -88,13 +173,53 @@ fn get_longest_increasing_subsequence_len(arr: Vec<u8>) -> u8 {
     max_streak
 }
 
+/// Returns the length of the longest strictly increasing subsequence of `arr`,
+/// along with one such subsequence. The values don't need to be adjacent, so
+/// `[3, 1, 2, 5, 4]` has `[1, 2, 5]` (or `[1, 2, 4]`) as its longest one.
+///
+/// Uses patience sorting, which takes O(n log n) time.
+#[allow(dead_code)]
+fn get_longest_increasing_subsequence<T: Ord>(arr: &[T]) -> (usize, Vec<&T>) {
+    // `tails[len - 1]` is the index of the smallest value that ends an
+    // increasing subsequence of length `len` seen so far. Those values are
+    // sorted, so the pile each new value goes on can be binary searched.
+    let mut tails: Vec<usize> = Vec::new();
+    // The index of the value before each value in the subsequence it ends
+    let mut predecessors: Vec<Option<usize>> = Vec::with_capacity(arr.len());
+
+    for (idx, val) in arr.iter().enumerate() {
+        let pile = tails.partition_point(|&tail| arr[tail] < *val);
+        predecessors.push(pile.checked_sub(1).map(|prev_pile| tails[prev_pile]));
+        if pile == tails.len() {
+            tails.push(idx);
+        } else {
+            tails[pile] = idx;
+        }
+    }
+
+    let mut subsequence: Vec<&T> =
+        std::iter::successors(tails.last().copied(), |&idx| predecessors[idx])
+            .map(|idx| &arr[idx])
+            .collect();
+    subsequence.reverse();
+
+    (tails.len(), subsequence)
+}
+
 // Do not modify below here
 #[cfg(test)]
 mod tests {
+    #[allow(deprecated)]
+    use crate::vectors::filter_even_numbers;
     use crate::vectors::{
-        binary_search, filter_even_numbers, get_fibonacci, get_longest_increasing_subsequence_len,
+        binary_search, fibonacci, filter_numbers, get_fibonacci, get_fibonacci_checked,
+        get_fibonacci_saturating, get_longest_increasing_run_len,
+        get_longest_increasing_subsequence, keep_even_numbers, keep_odd_numbers, Parity,
     };
 
+    /// The 186th Fibonacci number, the largest that fits in a u128
+    const LARGEST_U128_FIBONACCI: u128 = 332_825_110_087_067_562_321_196_029_789_634_457_848;
+
     #[test]
     fn test_get_fibonacci() {
         assert_eq!(get_fibonacci(2), vec![1, 1]);
@@ -108,24 +233,110 @@ mod tests {
         assert_eq!(get_fibonacci(10), vec![1, 1, 2, 3, 5, 8, 13, 21, 34, 55]);
     }
 
+    #[test]
+    fn test_get_fibonacci_large() {
+        assert_eq!(get_fibonacci(0), Vec::new());
+        assert_eq!(get_fibonacci(1), vec![1]);
+
+        // These used to overflow a u32
+        let fibonacci = get_fibonacci(100);
+        assert_eq!(fibonacci[46], 2_971_215_073);
+        assert_eq!(fibonacci[47], 4_807_526_976);
+        assert_eq!(fibonacci[99], 354_224_848_179_261_915_075);
+
+        let fibonacci = get_fibonacci(186);
+        assert_eq!(fibonacci.last(), Some(&LARGEST_U128_FIBONACCI));
+        assert!(fibonacci.windows(3).all(|w| w[0] + w[1] == w[2]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_fibonacci_overflow_panics() {
+        get_fibonacci(187);
+    }
+
+    #[test]
+    fn test_get_fibonacci_checked() {
+        assert_eq!(get_fibonacci_checked(5), Some(vec![1, 1, 2, 3, 5]));
+        assert_eq!(
+            get_fibonacci_checked(186).and_then(|fibonacci| fibonacci.last().copied()),
+            Some(LARGEST_U128_FIBONACCI)
+        );
+        assert_eq!(get_fibonacci_checked(187), None);
+        assert_eq!(get_fibonacci_checked(10_000), None);
+    }
+
+    #[test]
+    fn test_get_fibonacci_saturating() {
+        assert_eq!(get_fibonacci_saturating(5), vec![1, 1, 2, 3, 5]);
+
+        let fibonacci = get_fibonacci_saturating(190);
+        assert_eq!(fibonacci.len(), 190);
+        assert_eq!(fibonacci[185], LARGEST_U128_FIBONACCI);
+        assert!(fibonacci[186..].iter().all(|&term| term == u128::MAX));
+    }
+
+    #[test]
+    fn test_fibonacci() {
+        assert_eq!(fibonacci().take(10).collect::<Vec<_>>(), get_fibonacci(10));
+        assert_eq!(fibonacci().count(), 186);
+        assert_eq!(fibonacci().last(), Some(LARGEST_U128_FIBONACCI));
+
+        // Every third Fibonacci number is even
+        let evens: Vec<u128> = fibonacci().filter(|term| term % 2 == 0).take(4).collect();
+        assert_eq!(evens, vec![2, 8, 34, 144]);
+        assert!(fibonacci()
+            .zip(fibonacci().skip(1))
+            .zip(fibonacci().skip(2))
+            .all(|((a, b), c)| a + b == c));
+        assert_eq!(fibonacci().position(|term| term > 1_000_000), Some(30));
+    }
+
     #[test]
     fn test_binary_search() {
         let arr = vec![0, 10, 20, 50, 80, 100, 121, 144, 169, 250, 255];
 
-        assert_eq!(binary_search(arr.clone(), 0), 0);
-        assert_eq!(binary_search(arr.clone(), 10), 1);
-        assert_eq!(binary_search(arr.clone(), 20), 2);
-        assert_eq!(binary_search(arr.clone(), 50), 3);
-        assert_eq!(binary_search(arr.clone(), 80), 4);
-        assert_eq!(binary_search(arr.clone(), 100), 5);
-        assert_eq!(binary_search(arr.clone(), 121), 6);
-        assert_eq!(binary_search(arr.clone(), 144), 7);
-        assert_eq!(binary_search(arr.clone(), 169), 8);
-        assert_eq!(binary_search(arr.clone(), 250), 9);
-        assert_eq!(binary_search(arr.clone(), 255), 10);
+        assert_eq!(binary_search(&arr, &0), Some(0));
+        assert_eq!(binary_search(&arr, &10), Some(1));
+        assert_eq!(binary_search(&arr, &20), Some(2));
+        assert_eq!(binary_search(&arr, &50), Some(3));
+        assert_eq!(binary_search(&arr, &80), Some(4));
+        assert_eq!(binary_search(&arr, &100), Some(5));
+        assert_eq!(binary_search(&arr, &121), Some(6));
+        assert_eq!(binary_search(&arr, &144), Some(7));
+        assert_eq!(binary_search(&arr, &169), Some(8));
+        assert_eq!(binary_search(&arr, &250), Some(9));
+        assert_eq!(binary_search(&arr, &255), Some(10));
+    }
+
+    #[test]
+    fn test_binary_search_misses() {
+        let arr: Vec<u8> = vec![0, 10, 20, 50, 80, 100, 121, 144, 169, 250];
+
+        // Below, between, and above the stored values
+        assert_eq!(binary_search(&arr, &5), None);
+        assert_eq!(binary_search(&arr, &51), None);
+        assert_eq!(binary_search(&arr, &255), None);
+
+        let empty: [u8; 0] = [];
+        assert_eq!(binary_search(&empty, &0), None);
+        assert_eq!(binary_search(&[7], &3), None);
+        assert_eq!(binary_search(&[7], &7), Some(0));
+    }
+
+    #[test]
+    fn test_binary_search_generic() {
+        let words = ["apple", "banana", "cherry", "date"];
+        assert_eq!(binary_search(&words, &"cherry"), Some(2));
+        assert_eq!(binary_search(&words, &"blueberry"), None);
+
+        let big: Vec<u64> = (0..1_000_000).map(|x| x * 2).collect();
+        assert_eq!(binary_search(&big, &123_456), Some(61_728));
+        assert_eq!(binary_search(&big, &123_457), None);
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_filter_even_numbers() {
         let arr = vec![0, 2, 4, 6, 8, 10];
         assert_eq!(filter_even_numbers(arr), Vec::new());
@@ -138,17 +349,86 @@ mod tests {
     }
 
     #[test]
-    fn test_get_longest_increasing_subsequence_len() {
+    fn test_filter_numbers() {
+        let arr: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(keep_even_numbers(arr.clone()), vec![0, 2, 4, 6, 8]);
+        assert_eq!(keep_odd_numbers(arr.clone()), vec![1, 3, 5, 7, 9]);
+        assert_eq!(
+            filter_numbers(arr.clone(), Parity::Even),
+            vec![0, 2, 4, 6, 8]
+        );
+        assert_eq!(filter_numbers(arr, Parity::Odd), vec![1, 3, 5, 7, 9]);
+
+        assert_eq!(keep_even_numbers(Vec::<u32>::new()), Vec::new());
+        assert_eq!(keep_odd_numbers(vec![2_u64, 4, 6]), Vec::new());
+    }
+
+    #[test]
+    fn test_filter_numbers_signed() {
+        let arr: Vec<i32> = vec![-5, -4, -3, -2, -1, 0, 1, 2, i32::MIN, i32::MAX];
+        assert_eq!(keep_even_numbers(arr.clone()), vec![-4, -2, 0, 2, i32::MIN]);
+        assert_eq!(keep_odd_numbers(arr), vec![-5, -3, -1, 1, i32::MAX]);
+
+        assert_eq!(
+            keep_odd_numbers(vec![u128::MAX, u128::MAX - 1]),
+            vec![u128::MAX]
+        );
+    }
+
+    #[test]
+    fn test_get_longest_increasing_run_len() {
         let arr = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
-        assert_eq!(get_longest_increasing_subsequence_len(arr), 10);
+        assert_eq!(get_longest_increasing_run_len(arr), 10);
 
         let arr = vec![1, 2, 3, 2, 1, 2, 3, 4, 5];
-        assert_eq!(get_longest_increasing_subsequence_len(arr), 5);
+        assert_eq!(get_longest_increasing_run_len(arr), 5);
 
         let arr = vec![1, 0, 1, 0, 1, 0, 1, 0];
-        assert_eq!(get_longest_increasing_subsequence_len(arr), 2);
+        assert_eq!(get_longest_increasing_run_len(arr), 2);
 
         let arr = vec![0; 10];
-        assert_eq!(get_longest_increasing_subsequence_len(arr), 1);
+        assert_eq!(get_longest_increasing_run_len(arr), 1);
+    }
+
+    #[test]
+    fn test_get_longest_increasing_subsequence() {
+        let arr = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let (len, subsequence) = get_longest_increasing_subsequence(&arr);
+        assert_eq!(len, 10);
+        assert_eq!(subsequence, arr.iter().collect::<Vec<_>>());
+
+        // The longest run is only 2 long, but skipping values gives 4
+        let arr = [1, 9, 2, 8, 3, 7, 4, 0];
+        assert_eq!(
+            get_longest_increasing_subsequence(&arr),
+            (4, vec![&1, &2, &3, &4])
+        );
+
+        let arr = [10, 9, 2, 5, 3, 7, 101, 18];
+        let (len, subsequence) = get_longest_increasing_subsequence(&arr);
+        assert_eq!(len, 4);
+        assert_eq!(subsequence, vec![&2, &3, &7, &18]);
+
+        // Equal values don't count as increasing
+        assert_eq!(get_longest_increasing_subsequence(&[5; 10]), (1, vec![&5]));
+        assert_eq!(get_longest_increasing_subsequence::<u8>(&[]), (0, vec![]));
+    }
+
+    #[test]
+    fn test_get_longest_increasing_subsequence_generic() {
+        let words = ["pear", "apple", "fig", "banana", "kiwi", "cherry", "plum"];
+        let (len, subsequence) = get_longest_increasing_subsequence(&words);
+        assert_eq!(len, 4);
+        assert!(subsequence.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Strictly decreasing input of a size that would be too slow for an
+        // O(n^2) approach in a debug build
+        let arr: Vec<u32> = (0..200_000).rev().collect();
+        assert_eq!(get_longest_increasing_subsequence(&arr).0, 1);
+
+        let arr: Vec<u32> = (0..200_000).map(|x| (x * 7_919) % 200_003).collect();
+        let (len, subsequence) = get_longest_increasing_subsequence(&arr);
+        assert_eq!(len, subsequence.len());
+        assert!(subsequence.windows(2).all(|pair| pair[0] < pair[1]));
     }
 }
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// Anything that can go wrong while parsing or running a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterError {
+    /// The filter string can't be parsed, or calls a function that doesn't
+    /// exist.
+    InvalidFilter(String),
+    /// Indexing a value that can't be indexed that way, like `.foo` on an
+    /// array.
+    CannotIndex {
+        value_type: &'static str,
+        index: String,
+    },
+    /// `.[]` on something that isn't an array or object.
+    CannotIterate(&'static str),
+    /// A builtin was given a value it doesn't work on. The value is
+    /// described as in [`describe`].
+    InvalidInput {
+        function: &'static str,
+        value: String,
+    },
+    /// Two values that can't be combined with an operator, like adding a
+    /// number and a string, each described as in [`describe`]. The verb is
+    /// what the operator does, like "added".
+    CannotCombine {
+        lhs: String,
+        rhs: String,
+        verb: &'static str,
+    },
+    /// Dividing, or taking the remainder, by zero.
+    DivisionByZero(String, String),
+    /// Setting a negative index that's before the start of an array.
+    OutOfBounds,
+    /// A regex builtin was given a regex that can't be compiled.
+    InvalidRegex { regex: String, reason: String },
+    /// `fromjson` was given a string that isn't valid JSON.
+    InvalidJson { text: String, reason: String },
+    /// A filter that needs a path, like the argument of `del`, was given
+    /// one that doesn't pick out part of its input. Holds the described
+    /// value it produced instead.
+    InvalidPath(String),
+    /// `input` was used after every document was read.
+    NoMoreInputs,
+    /// `input` or `inputs` couldn't read the next document, like when it
+    /// isn't valid JSON.
+    ReadInput(String),
+    /// Raised by the filter itself with `error(value)`.
+    Custom(Value),
+}
+
+impl FilterError {
+    pub fn invalid_input(function: &'static str, value: &Value) -> Self {
+        FilterError::InvalidInput {
+            function,
+            value: describe(value),
+        }
+    }
+
+    pub fn cannot_combine(verb: &'static str, lhs: &Value, rhs: &Value) -> Self {
+        FilterError::CannotCombine {
+            lhs: describe(lhs),
+            rhs: describe(rhs),
+            verb,
+        }
+    }
+
+    /// What `try ... catch` gives the handler: the value passed to `error`,
+    /// or the message of any other error.
+    pub fn value(&self) -> Value {
+        match self {
+            FilterError::Custom(value) => value.clone(),
+            _ => Value::String(self.to_string()),
+        }
+    }
+}
+
+/// The JSON type of `value`, as jq names it.
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// How values are shown in error messages, like `string ("abc")`. Long
+/// values are cut short.
+pub fn describe(value: &Value) -> String {
+    const MAX_LEN: usize = 30;
+    let mut json = value.to_string();
+    if let Some((index, _)) = json.char_indices().nth(MAX_LEN) {
+        json.truncate(index);
+        json.push_str("...");
+    }
+    format!("{} ({})", type_name(value), json)
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterError::InvalidFilter(filter) => write!(f, "invalid filter: {}", filter),
+            FilterError::CannotIndex { value_type, index } => {
+                write!(f, "Cannot index {} with {}", value_type, index)
+            }
+            FilterError::CannotIterate(value_type) => {
+                write!(f, "Cannot iterate over {}", value_type)
+            }
+            FilterError::InvalidInput { function, value } => {
+                write!(f, "{} is not valid input for {}", value, function)
+            }
+            FilterError::CannotCombine { lhs, rhs, verb } => {
+                write!(f, "{} and {} cannot be {}", lhs, rhs, verb)
+            }
+            FilterError::OutOfBounds => write!(f, "Out of bounds negative array index"),
+            FilterError::InvalidRegex { regex, reason } => {
+                write!(f, "{:?} is not a valid regex: {}", regex, reason)
+            }
+            FilterError::DivisionByZero(lhs, rhs) => write!(
+                f,
+                "{} and {} cannot be divided because the divisor is zero",
+                lhs, rhs
+            ),
+            FilterError::InvalidJson { text, reason } => {
+                write!(f, "{} (while parsing '{}')", reason, text)
+            }
+            FilterError::InvalidPath(value) => {
+                write!(f, "Invalid path expression with result {}", value)
+            }
+            FilterError::NoMoreInputs => write!(f, "No more inputs"),
+            FilterError::ReadInput(message) => write!(f, "{}", message),
+            FilterError::Custom(Value::String(message)) => write!(f, "{}", message),
+            FilterError::Custom(value) => write!(f, "{} (not a string)", value),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{describe, FilterError};
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(&json!("abc")), "string (\"abc\")");
+        assert_eq!(describe(&json!(null)), "null (null)");
+        assert_eq!(
+            describe(&json!(["aaaaaaaaaa", "bbbbbbbbbb", "cccccccccc"])),
+            "array ([\"aaaaaaaaaa\",\"bbbbbbbbbb\",\"cc...)"
+        );
+    }
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(
+            FilterError::cannot_combine("added", &json!(1), &json!("a")).to_string(),
+            "number (1) and string (\"a\") cannot be added"
+        );
+        assert_eq!(
+            FilterError::invalid_input("length", &json!(true)).to_string(),
+            "boolean (true) is not valid input for length"
+        );
+        assert_eq!(FilterError::Custom(json!("oops")).to_string(), "oops");
+        assert_eq!(
+            FilterError::Custom(json!({"a": 1})).to_string(),
+            "{\"a\":1} (not a string)"
+        );
+    }
+
+    #[test]
+    fn test_value() {
+        assert_eq!(FilterError::Custom(json!([1])).value(), json!([1]));
+        assert_eq!(
+            FilterError::CannotIterate("number").value(),
+            json!("Cannot iterate over number")
+        );
+    }
+}
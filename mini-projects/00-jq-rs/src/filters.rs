@@ -0,0 +1,1303 @@
+//! Parsing filter programs like `.fizzes | .[1]` and running them against
+//! JSON values.
+
+mod lexer;
+mod operators;
+mod parser;
+mod scope;
+
+use std::borrow::Cow;
+use std::iter;
+use std::rc::Rc;
+
+use serde_json::{json, Number, Value};
+
+use crate::error::{describe, type_name, FilterError};
+use crate::functions;
+use crate::input::SharedInputs;
+use operators::Operator;
+pub(crate) use operators::{add, number};
+pub use parser::{parse, Filter};
+use scope::{bind, instantiate};
+
+/// The values a filter produces, computed one at a time as they're needed,
+/// so `first(.[])` or `limit(3; inputs)` only does as much work as it
+/// takes. Nothing should be taken after the first error.
+///
+/// Values are shared rather than copied between the filters that need
+/// them, so `.a, .b` doesn't copy the whole document for each side. Only
+/// the parts of it that are output, like the value of `.a`, get copied.
+///
+/// They don't borrow the filter that made them. Whatever is still to run
+/// keeps hold of its part of the filter, so a function body that's made
+/// while running lives exactly as long as its outputs do.
+pub type Outputs = Box<dyn Iterator<Item = Result<Rc<Value>, FilterError>>>;
+
+/// Where a value is inside a document, like `["fizzes", 1]`. Each step is a
+/// key, an index, or a slice given as `{"start": ..., "end": ...}`.
+pub type Path = Vec<Value>;
+
+/// Runs `filter` against `input`, returning every value it produces. Most
+/// filters produce exactly one value, but `.[]` produces one per element,
+/// `a, b` produces the outputs of both, and `a?` can produce none. `input`
+/// and `inputs` in the filter read from `inputs`.
+pub fn evaluate(filter: &Filter, input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    match filter {
+        Filter::Identity => Box::new(iter::once(Ok(input.clone()))),
+        Filter::Literal(value) => single(Ok(value.clone())),
+        Filter::Recurse => recurse(input.clone()),
+        Filter::Index(target, index) => {
+            let index = index.clone();
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(target, &input, &inputs), move |target| {
+                each(evaluate(&index, &input, &inputs), move |index| {
+                    single(index_value(&target, &index))
+                })
+            })
+        }
+        Filter::Slice(target, start, end) => {
+            let (start, end) = (start.clone(), end.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(target, &input, &inputs), move |target| {
+                let end = end.clone();
+                let input = input.clone();
+                let inputs = inputs.clone();
+                each(
+                    evaluate_bound(start.as_deref(), &input, &inputs),
+                    move |start| {
+                        let target = target.clone();
+                        each(
+                            evaluate_bound(end.as_deref(), &input, &inputs),
+                            move |end| single(slice(&target, &start, &end)),
+                        )
+                    },
+                )
+            })
+        }
+        Filter::Iterate(target) => each(evaluate(target, input, inputs), iterate),
+        Filter::Update(target, update) => {
+            let (target, update) = (target.clone(), update.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            Box::new(iter::once_with(move || {
+                update_paths(&target, &update, &input, &inputs).map(Rc::new)
+            }))
+        }
+        Filter::Assign(operator, target, value) => {
+            let paths = match paths(target, input, inputs) {
+                Ok(paths) => paths,
+                Err(err) => return single(Err(err)),
+            };
+            let operator = *operator;
+            let input = input.clone();
+            each(evaluate(value, &input, inputs), move |value| {
+                single(assign(&input, &paths, operator, &value))
+            })
+        }
+        Filter::Pipe(first, second) => {
+            let second = second.clone();
+            let inputs = inputs.clone();
+            each(evaluate(first, input, &inputs), move |value| {
+                evaluate(&second, &value, &inputs)
+            })
+        }
+        Filter::Comma(first, second) => {
+            let first = evaluate(first, input, inputs);
+            let second = second.clone();
+            let input = input.clone();
+            let inputs = inputs.clone();
+            Box::new(
+                first.chain(iter::once_with(move || evaluate(&second, &input, &inputs)).flatten()),
+            )
+        }
+        Filter::Optional(filter) => Box::new(
+            evaluate(filter, input, inputs)
+                .map_while(Result::ok)
+                .map(Ok),
+        ),
+        Filter::Try(body, handler) => {
+            let mut body = evaluate(body, input, inputs);
+            let handler = handler.clone();
+            let inputs = inputs.clone();
+            let mut caught: Option<Outputs> = None;
+            Box::new(iter::from_fn(move || loop {
+                if let Some(outputs) = &mut caught {
+                    return outputs.next();
+                }
+                match body.next()? {
+                    Ok(value) => return Some(Ok(value)),
+                    Err(err) => {
+                        caught = Some(evaluate(&handler, &Rc::new(err.value()), &inputs));
+                    }
+                }
+            }))
+        }
+        Filter::And(lhs, rhs) => {
+            let rhs = rhs.clone();
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(lhs, &input, &inputs), move |lhs| {
+                if !is_truthy(&lhs) {
+                    return single(Ok(Value::Bool(false)));
+                }
+                Box::new(
+                    evaluate(&rhs, &input, &inputs)
+                        .map(|rhs| Ok(Rc::new(Value::Bool(is_truthy(&*rhs?))))),
+                )
+            })
+        }
+        Filter::Or(lhs, rhs) => {
+            let rhs = rhs.clone();
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(lhs, &input, &inputs), move |lhs| {
+                if is_truthy(&lhs) {
+                    return single(Ok(Value::Bool(true)));
+                }
+                Box::new(
+                    evaluate(&rhs, &input, &inputs)
+                        .map(|rhs| Ok(Rc::new(Value::Bool(is_truthy(&*rhs?))))),
+                )
+            })
+        }
+        Filter::Array(filter) => single(collect(evaluate(filter, input, inputs)).map(Value::Array)),
+        Filter::If(condition, then, otherwise) => {
+            let (then, otherwise) = (then.clone(), otherwise.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(condition, &input, &inputs), move |condition| {
+                let branch = if is_truthy(&condition) {
+                    &then
+                } else {
+                    &otherwise
+                };
+                evaluate(branch, &input, &inputs)
+            })
+        }
+        Filter::Binary(operator, lhs, rhs) => {
+            let (operator, lhs) = (*operator, lhs.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            // Like jq, the outputs of the left side vary fastest
+            each(evaluate(rhs, &input, &inputs), move |rhs| {
+                Box::new(
+                    evaluate(&lhs, &input, &inputs)
+                        .map(move |lhs| operator.apply(&*lhs?, &rhs).map(Rc::new)),
+                )
+            })
+        }
+        Filter::Call(name, args) => functions::call(name, args, input, inputs),
+        Filter::Function(definition, args) => {
+            let bodies = match instantiate(definition, args, input, inputs) {
+                Ok(bodies) => bodies,
+                Err(err) => return single(Err(err)),
+            };
+            // Each body is only run once the outputs before it are used up,
+            // so a recursive function can produce endless outputs
+            let input = input.clone();
+            let inputs = inputs.clone();
+            Box::new(
+                bodies
+                    .into_iter()
+                    .flat_map(move |body| evaluate(&body, &input, &inputs)),
+            )
+        }
+        Filter::Variable(name) => single(Err(FilterError::InvalidFilter(format!(
+            "${} is not defined",
+            name
+        )))),
+        Filter::Reduce(source, name, init, update) => {
+            let (source, name, update) = (source.clone(), name.clone(), update.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(init, &input, &inputs), move |state| {
+                Box::new(iter::once(reduce(
+                    &source, &name, &update, &input, state, &inputs,
+                )))
+            })
+        }
+        Filter::Foreach(source, name, init, update, extract) => {
+            let (source, name) = (source.clone(), name.clone());
+            let (update, extract) = (update.clone(), extract.clone());
+            let input = input.clone();
+            let inputs = inputs.clone();
+            each(evaluate(init, &input, &inputs), move |mut state| {
+                let (name, update, extract) = (name.clone(), update.clone(), extract.clone());
+                let inputs = inputs.clone();
+                each(evaluate(&source, &input, &inputs), move |value| {
+                    let update = bind(&update, &name, &value);
+                    let extract = bind(&extract, &name, &value);
+                    let mut outputs = Vec::new();
+                    for result in evaluate(&update, &state, &inputs) {
+                        match result {
+                            Ok(result) => {
+                                outputs.extend(evaluate(&extract, &result, &inputs));
+                                state = result;
+                            }
+                            Err(err) => {
+                                outputs.push(Err(err));
+                                break;
+                            }
+                        }
+                    }
+                    Box::new(outputs.into_iter())
+                })
+            })
+        }
+    }
+}
+
+/// Runs `filter` on a copy of `input` like [`evaluate`], without any more
+/// input, but collects all of its outputs, or stops at the first error.
+#[cfg(test)]
+pub(crate) fn evaluate_all(filter: &Filter, input: &Value) -> Result<Vec<Value>, FilterError> {
+    collect(evaluate(
+        filter,
+        &Rc::new(input.clone()),
+        &SharedInputs::default(),
+    ))
+}
+
+impl Filter {
+    /// Runs the filter on a copy of `input`, producing its outputs one at a
+    /// time. Nothing is produced after the first error. There's no more
+    /// input, so `input` fails and `inputs` outputs nothing.
+    pub fn run(&self, input: &Value) -> impl Iterator<Item = Result<Value, FilterError>> + '_ {
+        self.run_with_inputs(input, &SharedInputs::default())
+    }
+
+    /// Like [`Filter::run`], but `input` and `inputs` in the filter take
+    /// values from `inputs`.
+    pub fn run_with_inputs(
+        &self,
+        input: &Value,
+        inputs: &SharedInputs,
+    ) -> impl Iterator<Item = Result<Value, FilterError>> + '_ {
+        self.run_owned(input.clone(), inputs)
+    }
+
+    /// Like [`Filter::run_with_inputs`], but takes `input` rather than a
+    /// copy of it, so parts of it the filter outputs as they are, like the
+    /// elements from `.[]`, are moved out instead of copied.
+    pub fn run_owned(
+        &self,
+        input: Value,
+        inputs: &SharedInputs,
+    ) -> impl Iterator<Item = Result<Value, FilterError>> {
+        evaluate(self, &Rc::new(input), inputs)
+            .map(|output| output.map(Rc::unwrap_or_clone))
+            .scan(false, |failed, output| {
+                (!*failed).then(|| {
+                    *failed = output.is_err();
+                    output
+                })
+            })
+    }
+}
+
+/// Every one of `outputs`, or the first error. Values that are still shared
+/// are copied.
+pub(crate) fn collect(outputs: Outputs) -> Result<Vec<Value>, FilterError> {
+    outputs
+        .map(|output| output.map(Rc::unwrap_or_clone))
+        .collect()
+}
+
+/// Outputs of just `output`, or just its error.
+pub(crate) fn single(output: Result<Value, FilterError>) -> Outputs {
+    Box::new(iter::once(output.map(Rc::new)))
+}
+
+/// Outputs of `output` if there is one, or its error.
+pub(crate) fn optional(output: Result<Option<Value>, FilterError>) -> Outputs {
+    Box::new(
+        output
+            .transpose()
+            .into_iter()
+            .map(|output| output.map(Rc::new)),
+    )
+}
+
+/// The outputs of `function` on each of `outputs`, passing errors along.
+pub(crate) fn each(
+    outputs: Outputs,
+    mut function: impl FnMut(Rc<Value>) -> Outputs + 'static,
+) -> Outputs {
+    Box::new(outputs.flat_map(move |output| match output {
+        Ok(value) => function(value),
+        Err(err) => single(Err(err)),
+    }))
+}
+
+/// `target |= update`: `input` with the value at each path of `target`
+/// replaced by the first output of `update` on it, or deleted if there
+/// isn't one.
+fn update_paths(
+    target: &Filter,
+    update: &Filter,
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let paths = paths(target, input, inputs)?;
+    let mut output = Value::clone(input);
+    let mut deleted = Vec::new();
+    for path in paths {
+        let value = Rc::new(get_path(&output, &path));
+        match evaluate(update, &value, inputs).next().transpose()? {
+            Some(value) => functions::set_path(&mut output, &path, Rc::unwrap_or_clone(value))?,
+            None => deleted.push(path),
+        }
+    }
+    Ok(functions::delete_paths(&output, deleted))
+}
+
+/// `target = value` or `target op= value` for one output of `value`, given
+/// the paths of `target`.
+fn assign(
+    input: &Value,
+    paths: &[Path],
+    operator: Option<Operator>,
+    value: &Value,
+) -> Result<Value, FilterError> {
+    let mut output = input.clone();
+    for path in paths {
+        let value = match operator {
+            Some(operator) => operator.apply(&get_path(&output, path), value)?,
+            None => value.clone(),
+        };
+        functions::set_path(&mut output, path, value)?;
+    }
+    Ok(output)
+}
+
+/// `reduce source as $name (state; update)` for one initial state.
+fn reduce(
+    source: &Filter,
+    name: &str,
+    update: &Filter,
+    input: &Rc<Value>,
+    mut state: Rc<Value>,
+    inputs: &SharedInputs,
+) -> Result<Rc<Value>, FilterError> {
+    for value in evaluate(source, input, inputs) {
+        let update = bind(update, name, &*value?);
+        // An update with no outputs leaves `null`, like jq
+        let mut next = Rc::new(Value::Null);
+        for output in evaluate(&update, &state, inputs) {
+            next = output?;
+        }
+        state = next;
+    }
+    Ok(state)
+}
+
+/// Everything but `null` and `false` counts as true in conditions.
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// A missing slice bound is `null`, meaning the start or end.
+fn evaluate_bound(bound: Option<&Filter>, input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    match bound {
+        Some(bound) => evaluate(bound, input, inputs),
+        None => single(Ok(Value::Null)),
+    }
+}
+
+/// The paths of every value `filter` would produce from `input`, for
+/// builtins like `del` that change parts of a document. Only filters that
+/// pick out parts of their input have paths; `1`, `length`, or `.a + 1`
+/// don't.
+pub fn paths(
+    filter: &Filter,
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+) -> Result<Vec<Path>, FilterError> {
+    match filter {
+        Filter::Identity => Ok(vec![Vec::new()]),
+        Filter::Recurse => Ok(all_paths(input)),
+        Filter::Index(target, index) => {
+            let indexes = collect(evaluate(index, input, inputs))?;
+            let mut paths = Vec::new();
+            for path in self::paths(target, input, inputs)? {
+                let target = lookup(input, &path).unwrap_or_default();
+                for index in &indexes {
+                    index_value(&target, index)?;
+                    paths.push(with_step(&path, index.clone()));
+                }
+            }
+            Ok(paths)
+        }
+        Filter::Slice(target, start, end) => {
+            let starts = collect(evaluate_bound(start.as_deref(), input, inputs))?;
+            let ends = collect(evaluate_bound(end.as_deref(), input, inputs))?;
+            let mut paths = Vec::new();
+            for path in self::paths(target, input, inputs)? {
+                let target = lookup(input, &path).unwrap_or_default();
+                for start in &starts {
+                    for end in &ends {
+                        slice(&target, start, end)?;
+                        paths.push(with_step(&path, json!({"start": start, "end": end})));
+                    }
+                }
+            }
+            Ok(paths)
+        }
+        Filter::Iterate(target) => {
+            let mut paths = Vec::new();
+            for path in self::paths(target, input, inputs)? {
+                let value = lookup(input, &path).unwrap_or_default();
+                let Some(steps) = steps(&value) else {
+                    return Err(FilterError::CannotIterate(type_name(&value)));
+                };
+                paths.extend(steps.into_iter().map(|step| with_step(&path, step)));
+            }
+            Ok(paths)
+        }
+        Filter::Pipe(first, second) => {
+            let mut paths = Vec::new();
+            for path in self::paths(first, input, inputs)? {
+                let value = Rc::new(get_path(input, &path));
+                for rest in self::paths(second, &value, inputs)? {
+                    paths.push([path.clone(), rest].concat());
+                }
+            }
+            Ok(paths)
+        }
+        Filter::Comma(first, second) => {
+            let mut paths = self::paths(first, input, inputs)?;
+            paths.extend(self::paths(second, input, inputs)?);
+            Ok(paths)
+        }
+        Filter::Optional(filter) => Ok(self::paths(filter, input, inputs).unwrap_or_default()),
+        Filter::If(condition, then, otherwise) => {
+            let mut paths = Vec::new();
+            for condition in evaluate(condition, input, inputs) {
+                let branch = if is_truthy(&*condition?) {
+                    then
+                } else {
+                    otherwise
+                };
+                paths.extend(self::paths(branch, input, inputs)?);
+            }
+            Ok(paths)
+        }
+        Filter::Function(definition, args) => {
+            let mut paths = Vec::new();
+            for body in instantiate(definition, args, input, inputs)? {
+                paths.extend(self::paths(&body, input, inputs)?);
+            }
+            Ok(paths)
+        }
+        Filter::Call(name, args) => match functions::call_paths(name, args, input, inputs) {
+            Some(paths) => paths,
+            None => invalid_path(filter, input, inputs),
+        },
+        Filter::Literal(_)
+        | Filter::Binary(..)
+        | Filter::And(..)
+        | Filter::Or(..)
+        | Filter::Array(_)
+        | Filter::Try(..)
+        | Filter::Update(..)
+        | Filter::Assign(..)
+        | Filter::Variable(_)
+        | Filter::Reduce(..)
+        | Filter::Foreach(..) => invalid_path(filter, input, inputs),
+    }
+}
+
+/// The error for using `filter`, which makes new values instead of picking
+/// them out of `input`, as a path expression.
+fn invalid_path(
+    filter: &Filter,
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+) -> Result<Vec<Path>, FilterError> {
+    let output = evaluate(filter, input, inputs).next().transpose()?;
+    Err(FilterError::InvalidPath(describe(
+        output.as_deref().unwrap_or(&Value::Null),
+    )))
+}
+
+/// `..`: `value` and then everything inside it, depth first. Each value
+/// inside is copied only when it's reached.
+fn recurse(value: Rc<Value>) -> Outputs {
+    let steps = steps(&value).unwrap_or_default();
+    let output = iter::once(Ok(value.clone()));
+    Box::new(
+        output.chain(steps.into_iter().flat_map(move |step| {
+            recurse(Rc::new(index_value(&value, &step).unwrap_or_default()))
+        })),
+    )
+}
+
+/// The index of each element of an array or the key of each value of an
+/// object, or `None` for anything else.
+fn steps(value: &Value) -> Option<Vec<Value>> {
+    match value {
+        Value::Array(values) => Some((0..values.len()).map(Value::from).collect()),
+        Value::Object(map) => Some(map.keys().cloned().map(Value::String).collect()),
+        _ => None,
+    }
+}
+
+/// The paths of `value` and everything inside it, in the same order as `..`.
+pub(crate) fn all_paths(value: &Value) -> Vec<Path> {
+    let mut paths = Vec::new();
+    recurse_paths(value, Vec::new(), &mut paths);
+    paths
+}
+
+/// The paths of everything [`recurse`] outputs, in the same order.
+fn recurse_paths(value: &Value, path: Path, paths: &mut Vec<Path>) {
+    match value {
+        Value::Array(values) => {
+            paths.push(path.clone());
+            for (index, value) in values.iter().enumerate() {
+                recurse_paths(value, with_step(&path, Value::from(index)), paths);
+            }
+        }
+        Value::Object(map) => {
+            paths.push(path.clone());
+            for (key, value) in map {
+                recurse_paths(value, with_step(&path, Value::String(key.clone())), paths);
+            }
+        }
+        _ => paths.push(path),
+    }
+}
+
+fn with_step(path: &[Value], step: Value) -> Path {
+    let mut path = path.to_vec();
+    path.push(step);
+    path
+}
+
+/// The value at `path` inside `value`, or `null` if there isn't one.
+pub fn get_path(value: &Value, path: &[Value]) -> Value {
+    try_get_path(value, path).unwrap_or(Value::Null)
+}
+
+/// Like [`get_path`], but fails if a step of the path can't be used on the
+/// value it's used on, like a key on an array.
+pub fn try_get_path(value: &Value, path: &[Value]) -> Result<Value, FilterError> {
+    lookup(value, path).map(Cow::into_owned)
+}
+
+/// Like [`try_get_path`], but without copying the value unless a step is a
+/// slice.
+fn lookup<'v>(value: &'v Value, path: &[Value]) -> Result<Cow<'v, Value>, FilterError> {
+    let mut value = Cow::Borrowed(value);
+    for step in path {
+        value = match (step, value) {
+            (Value::Object(bounds), value) => Cow::Owned(slice(
+                &value,
+                bounds.get("start").unwrap_or(&Value::Null),
+                bounds.get("end").unwrap_or(&Value::Null),
+            )?),
+            (_, Cow::Borrowed(value)) => match index_ref(value, step)? {
+                Some(value) => Cow::Borrowed(value),
+                None => Cow::Owned(Value::Null),
+            },
+            (_, Cow::Owned(value)) => Cow::Owned(index_value(&value, step)?),
+        };
+    }
+    Ok(value)
+}
+
+/// `.[index]`: the value at a key of an object or an index of an array, or
+/// `null` if it's missing. Negative indexes count from the end.
+fn index_value(target: &Value, index: &Value) -> Result<Value, FilterError> {
+    Ok(index_ref(target, index)?.cloned().unwrap_or(Value::Null))
+}
+
+/// Like [`index_value`], but borrows the value, or gives `None` if it's
+/// missing.
+fn index_ref<'v>(target: &'v Value, index: &Value) -> Result<Option<&'v Value>, FilterError> {
+    match (target, index) {
+        (Value::Object(map), Value::String(key)) => Ok(map.get(key)),
+        (Value::Array(values), Value::Number(index)) => {
+            Ok(resolve_index(as_index(index), values.len()).and_then(|index| values.get(index)))
+        }
+        (Value::Null, Value::String(_) | Value::Number(_)) => Ok(None),
+        _ => Err(FilterError::CannotIndex {
+            value_type: type_name(target),
+            index: index.to_string(),
+        }),
+    }
+}
+
+/// Numbers used as indexes are rounded down, like jq.
+pub(crate) fn as_index(number: &Number) -> i64 {
+    number
+        .as_i64()
+        .unwrap_or_else(|| number.as_f64().unwrap_or_default().floor() as i64)
+}
+
+/// `.[]`: every element of an array, or every value of an object.
+fn iterate(input: Rc<Value>) -> Outputs {
+    let input = match Rc::try_unwrap(input) {
+        // Nothing else needs the input, so its values can be moved out
+        Ok(Value::Array(values)) => return Box::new(values.into_iter().map(|v| Ok(Rc::new(v)))),
+        Ok(Value::Object(map)) => {
+            return Box::new(map.into_iter().map(|(_, value)| Ok(Rc::new(value))))
+        }
+        Ok(input) => Rc::new(input),
+        Err(input) => input,
+    };
+    let Some(steps) = steps(&input) else {
+        return single(Err(FilterError::CannotIterate(type_name(&input))));
+    };
+    Box::new(
+        steps
+            .into_iter()
+            .map(move |step| index_value(&input, &step).map(Rc::new)),
+    )
+}
+
+/// Turns a possibly negative index into one counting from the start, or
+/// `None` if it's before the start.
+pub(crate) fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)
+    } else {
+        Some(index as usize)
+    }
+}
+
+/// Clamps slice bounds to `0..=len`, with negative bounds counting from the
+/// end and `null` meaning the start or end.
+pub(crate) fn slice_range(
+    start: &Value,
+    end: &Value,
+    len: usize,
+) -> Result<(usize, usize), FilterError> {
+    let clamp = |bound: &Value, default: usize| match bound {
+        Value::Null => Ok(default),
+        Value::Number(bound) => Ok(resolve_index(as_index(bound), len).unwrap_or(0).min(len)),
+        _ => Err(FilterError::InvalidInput {
+            function: "slice",
+            value: describe(bound),
+        }),
+    };
+    let start = clamp(start, 0)?;
+    let end = clamp(end, len)?.max(start);
+    Ok((start, end))
+}
+
+/// `.[start:end]` on an array or a string.
+fn slice(input: &Value, start: &Value, end: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::Array(values) => {
+            let (start, end) = slice_range(start, end, values.len())?;
+            Ok(Value::Array(values[start..end].to_vec()))
+        }
+        Value::String(string) => {
+            let chars: Vec<char> = string.chars().collect();
+            let (start, end) = slice_range(start, end, chars.len())?;
+            Ok(Value::String(chars[start..end].iter().collect()))
+        }
+        Value::Null => Ok(Value::Null),
+        _ => Err(FilterError::CannotIndex {
+            value_type: type_name(input),
+            index: String::from("object"),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use serde_json::{json, Value};
+
+    use super::{evaluate, evaluate_all, get_path, parse, paths};
+    use crate::error::FilterError;
+    use crate::input::SharedInputs;
+
+    fn all_types() -> Value {
+        json!({
+            "fizz": "buzz",
+            "baz": null,
+            "fuzz": true,
+            "bizz": 22.0,
+            "biz": 42,
+            "fizzes": ["buzz", null, true, 22.0, 42.0]
+        })
+    }
+
+    fn filter_input(filter: &str, input: &Value) -> Result<Vec<Value>, FilterError> {
+        evaluate_all(&parse(filter)?, input)
+    }
+
+    fn run(filter: &str, input: &Value) -> Vec<Value> {
+        filter_input(filter, input).unwrap()
+    }
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(run(".", &all_types()), [all_types()]);
+        assert_eq!(run(" . ", &json!(1)), [json!(1)]);
+    }
+
+    #[test]
+    fn test_object_identifier() {
+        assert_eq!(run(".fizz", &all_types()), [json!("buzz")]);
+        assert_eq!(run(".\"fizz\"", &all_types()), [json!("buzz")]);
+        assert_eq!(run(".[\"fizz\"]", &all_types()), [json!("buzz")]);
+        assert_eq!(run(".missing", &all_types()), [Value::Null]);
+        assert_eq!(run(".anything", &Value::Null), [Value::Null]);
+        assert_eq!(
+            filter_input(".fizz", &json!([1])),
+            Err(FilterError::CannotIndex {
+                value_type: "array",
+                index: String::from("\"fizz\"")
+            })
+        );
+        assert!(matches!(
+            filter_input(".fi zz", &all_types()),
+            Err(FilterError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_array_index() {
+        let array = json!(["one", "two", "three"]);
+        assert_eq!(run(".[0]", &array), [json!("one")]);
+        assert_eq!(run(".[ 2 ]", &array), [json!("three")]);
+        assert_eq!(run(".[-1]", &array), [json!("three")]);
+        assert_eq!(run(".[3]", &array), [Value::Null]);
+        assert_eq!(run(".[-4]", &array), [Value::Null]);
+        assert_eq!(run(".[0]", &Value::Null), [Value::Null]);
+        assert!(matches!(
+            filter_input(".[0]", &json!({"a": 1})),
+            Err(FilterError::CannotIndex { .. })
+        ));
+        assert!(matches!(
+            filter_input(".[one]", &array),
+            Err(FilterError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_array_slice() {
+        let array = json!(["one", "two", "three"]);
+        assert_eq!(run(".[0:2]", &array), [json!(["one", "two"])]);
+        assert_eq!(run(".[1:]", &array), [json!(["two", "three"])]);
+        assert_eq!(run(".[:1]", &array), [json!(["one"])]);
+        assert_eq!(run(".[-2:]", &array), [json!(["two", "three"])]);
+        assert_eq!(run(".[2:1]", &array), [json!([])]);
+        assert_eq!(run(".[0:100]", &array), [array]);
+        assert_eq!(run(".[1:3]", &json!("héllo")), [json!("él")]);
+        assert!(matches!(
+            filter_input(".[1:2]", &json!(5)),
+            Err(FilterError::CannotIndex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pipe() {
+        assert_eq!(run(".fizzes | .[1]", &all_types()), [Value::Null]);
+        assert_eq!(run(".fizzes|.[0]|.", &all_types()), [json!("buzz")]);
+        // Pipes inside strings aren't pipes
+        assert_eq!(run(".\"a|b\"", &json!({"a|b": 1})), [json!(1)]);
+    }
+
+    #[test]
+    fn test_iterator() {
+        let football = json!([{"name": "Leo"}, {"name": "Max"}]);
+        assert_eq!(run(".[] | .name", &football), [json!("Leo"), json!("Max")]);
+        assert_eq!(run(".[]", &json!({"a": 1, "b": 2})), [json!(1), json!(2)]);
+        assert_eq!(run(".[]", &json!([])), Vec::<Value>::new());
+        assert_eq!(
+            filter_input(".[]", &json!(3)),
+            Err(FilterError::CannotIterate("number"))
+        );
+        assert!(matches!(
+            filter_input(".name", &football),
+            Err(FilterError::CannotIndex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chained_access() {
+        let input = json!({
+            "user": {"address": {"city": "Needham"}},
+            "items": [{"name": "pen", "tags": ["blue", "cheap"]}, {"name": "ink"}]
+        });
+        assert_eq!(run(".user.address.city", &input), [json!("Needham")]);
+        assert_eq!(run(".user.\"address\".city", &input), [json!("Needham")]);
+        assert_eq!(run(".items[0].name", &input), [json!("pen")]);
+        assert_eq!(run(".items[-1].name", &input), [json!("ink")]);
+        assert_eq!(run(".items[0].tags[1]", &input), [json!("cheap")]);
+        assert_eq!(run(".items[].name", &input), [json!("pen"), json!("ink")]);
+        assert_eq!(run(".items[0].tags[:1]", &input), [json!(["blue"])]);
+        assert_eq!(run(".items[1].tags[0]", &input), [Value::Null]);
+        assert_eq!(run(".user.missing.deeper", &input), [Value::Null]);
+        assert_eq!(
+            filter_input(".items.name", &input),
+            Err(FilterError::CannotIndex {
+                value_type: "array",
+                index: String::from("\"name\"")
+            })
+        );
+    }
+
+    #[test]
+    fn test_recurse() {
+        let input = json!({"a": [1, {"id": 2}], "b": "c"});
+        assert_eq!(
+            run("..", &input),
+            [
+                input.clone(),
+                json!([1, {"id": 2}]),
+                json!(1),
+                json!({"id": 2}),
+                json!(2),
+                json!("c")
+            ]
+        );
+        assert_eq!(run("..", &json!(3)), [json!(3)]);
+        assert_eq!(run(".a | ..", &json!({"a": []})), [json!([])]);
+        assert!(filter_input(".. | .id", &input).is_err());
+        assert_eq!(run(".. | .id?", &input), [Value::Null, json!(2)]);
+    }
+
+    #[test]
+    fn test_optional() {
+        let input = json!({"a": [1, 2], "b": 3});
+        assert_eq!(run(".a.b?", &input), Vec::<Value>::new());
+        assert_eq!(run(".b[0]?", &input), Vec::<Value>::new());
+        assert_eq!(run(".b[]?", &input), Vec::<Value>::new());
+        assert_eq!(run(".a[0]?", &input), [json!(1)]);
+        assert_eq!(run(".a[]?", &input), [json!(1), json!(2)]);
+        assert_eq!(run(".missing?", &input), [Value::Null]);
+        assert_eq!(run(".[] | .[0]?", &input), [json!(1)]);
+        assert_eq!(run(".b?, .a.b?, 4", &input), [json!(3), json!(4)]);
+        // Only the part before the `?` is allowed to fail
+        assert!(filter_input(".a?.b", &input).is_err());
+    }
+
+    #[test]
+    fn test_try() {
+        let input = json!({"a": [1, 2], "b": 3});
+        assert_eq!(run("try .b[0]", &input), Vec::<Value>::new());
+        assert_eq!(
+            run("try .b[0] catch .", &input),
+            [json!("Cannot index number with 0")]
+        );
+        assert_eq!(run("try .a[0] catch 0", &input), [json!(1)]);
+        assert_eq!(
+            run(".a[] | try error(\"bad\") catch . + \"!\"", &input),
+            [json!("bad!"), json!("bad!")]
+        );
+        assert_eq!(run("try error(.a) catch .[1]", &input), [json!(2)]);
+        assert_eq!(run("try (.b | error) catch . + 1", &input), [json!(4)]);
+        assert_eq!(
+            filter_input("error(\"bad\")", &input),
+            Err(FilterError::Custom(json!("bad")))
+        );
+        // Errors in the handler aren't caught
+        assert!(filter_input("try error(1) catch error(2)", &input).is_err());
+        // The outputs before an error are kept, and nothing after it runs
+        assert_eq!(
+            run("[try (1, error(\"x\"), 3) catch .]", &input),
+            [json!([1, "x"])]
+        );
+        assert_eq!(run("[(1, error(\"x\"), 3)?]", &input), [json!([1])]);
+    }
+
+    #[test]
+    fn test_lazy_outputs() {
+        let input = json!([1, 2, 3]);
+        let filter = parse(".[], error(\"boom\")").unwrap();
+        let mut outputs = evaluate(&filter, &Rc::new(input.clone()), &SharedInputs::default());
+        assert_eq!(outputs.next(), Some(Ok(Rc::new(json!(1)))));
+        assert_eq!(outputs.next(), Some(Ok(Rc::new(json!(2)))));
+        assert_eq!(outputs.next(), Some(Ok(Rc::new(json!(3)))));
+        assert_eq!(
+            outputs.next(),
+            Some(Err(FilterError::Custom(json!("boom"))))
+        );
+        // Outputs that aren't needed are never computed
+        assert_eq!(run("first(.[], error(\"boom\"))", &input), [json!(1)]);
+        assert_eq!(
+            run("[limit(3; .[] * 10, error(\"boom\"))]", &input),
+            [json!([10, 20, 30])]
+        );
+        assert_eq!(
+            filter_input("[limit(4; .[] * 10, error(\"boom\"))]", &input),
+            Err(FilterError::Custom(json!("boom")))
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let order = json!({"price": 2.5, "quantity": 4, "name": "pen", "tags": ["a"]});
+        assert_eq!(run(".price * .quantity", &order), [json!(10)]);
+        assert_eq!(run(".quantity - 1 * 2", &order), [json!(2)]);
+        assert_eq!(run("(.quantity - 1) * 2", &order), [json!(6)]);
+        assert_eq!(run(".quantity % 3 + .price", &order), [json!(3.5)]);
+        assert_eq!(run(".name + \"s\"", &order), [json!("pens")]);
+        assert_eq!(run(".tags + .tags | length", &order), [json!(2)]);
+        assert_eq!(run(".missing + 1", &order), [json!(1)]);
+        assert_eq!(
+            run("(1, 2) + (10, 20)", &order),
+            [json!(11), json!(12), json!(21), json!(22)]
+        );
+        assert_eq!(
+            filter_input(".name - 1", &order),
+            Err(FilterError::cannot_combine(
+                "subtracted",
+                &json!("pen"),
+                &json!(1)
+            ))
+        );
+        assert!(filter_input(".quantity / 0", &order).is_err());
+        let no_inputs = SharedInputs::default();
+        assert!(paths(&parse(".a + 1").unwrap(), &Rc::new(order), &no_inputs).is_err());
+    }
+
+    #[test]
+    fn test_comparisons_and_booleans() {
+        let item = json!({"status": "active", "count": 5, "tags": []});
+        assert_eq!(
+            run(".status == \"active\" and .count > 3", &item),
+            [json!(true)]
+        );
+        assert_eq!(run(".count <= 3 or .tags", &item), [json!(true)]);
+        assert_eq!(run(".missing or false", &item), [json!(false)]);
+        assert_eq!(
+            run(".count != 5, .count >= 5", &item),
+            [json!(false), json!(true)]
+        );
+        assert_eq!(
+            run("(true, false) and (true, null)", &item),
+            [json!(true), json!(false), json!(false)]
+        );
+        assert_eq!(
+            run("(true, false) or (true, null)", &item),
+            [json!(true), json!(true), json!(false)]
+        );
+        // The right side isn't run when the left side decides the answer
+        assert_eq!(run("false and .count[0]", &item), [json!(false)]);
+        assert_eq!(run("1 or .count[0]", &item), [json!(true)]);
+    }
+
+    #[test]
+    fn test_array_construction() {
+        let input = json!({"a": [1, 2], "b": 3});
+        assert_eq!(run("[.a[], .b]", &input), [json!([1, 2, 3])]);
+        assert_eq!(run("[.a[] | . * 2]", &input), [json!([2, 4])]);
+        assert_eq!(run("[]", &input), [json!([])]);
+        assert_eq!(run("[.a[]?, .b[]?]", &input), [json!([1, 2])]);
+        assert_eq!(run("[.b] | length", &input), [json!(1)]);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let users = json!([
+            {"type": "admin", "perms": ["all"]},
+            {"type": "user", "perms": ["read"]},
+            {"type": "guest"}
+        ]);
+        assert_eq!(
+            run(
+                ".[] | if .type == \"admin\" then .perms else [] end",
+                &users
+            ),
+            [json!(["all"]), json!([]), json!([])]
+        );
+        assert_eq!(
+            run(
+                ".[] | if .type == \"admin\" then 2 elif .perms then 1 else 0 end",
+                &users
+            ),
+            [json!(2), json!(1), json!(0)]
+        );
+        assert_eq!(
+            run(".[2] | if .perms then 1 end", &users),
+            [json!({"type": "guest"})]
+        );
+        // Each output of the condition picks a branch
+        assert_eq!(
+            run(
+                "if (true, false) then \"yes\" else \"no\" end",
+                &Value::Null
+            ),
+            [json!("yes"), json!("no")]
+        );
+        assert_eq!(
+            paths(
+                &parse("if .a then .b else .c end").unwrap(),
+                &Rc::new(json!({"a": false})),
+                &SharedInputs::default()
+            ),
+            Ok(vec![vec![json!("c")]])
+        );
+    }
+
+    #[test]
+    fn test_assignments() {
+        let input = json!({"count": 1, "name": "a", "items": [1, 2, 3]});
+        assert_eq!(
+            run("(.items[] | select(. > 1)) |= 0 | .items", &input),
+            [json!([1, 0, 0])]
+        );
+        assert_eq!(run(".count += 1 | .count", &input), [json!(2)]);
+        assert_eq!(run(".name = \"x\" | .name", &input), [json!("x")]);
+        assert_eq!(
+            run(".items[] |= . * 2", &input),
+            [json!({"count": 1, "name": "a", "items": [2, 4, 6]})]
+        );
+        // The value is run against the input, not the value being replaced
+        assert_eq!(
+            run(".items[] = .count", &input),
+            [json!({"count": 1, "name": "a", "items": [1, 1, 1]})]
+        );
+        assert_eq!(
+            run(".items[] -= .count, 10", &input),
+            [
+                json!({"count": 1, "name": "a", "items": [0, 1, 2]}),
+                json!(10)
+            ]
+        );
+        assert_eq!(
+            run(".count = (1, 2) | .count", &input),
+            [json!(1), json!(2)]
+        );
+        assert_eq!(run(".new.deep |= 5 | .new", &input), [json!({"deep": 5})]);
+        // An update with no output deletes the value
+        assert_eq!(
+            run(".items[] |= select(. != 2) | .items", &input),
+            [json!([1, 3])]
+        );
+        assert_eq!(
+            filter_input(".name -= 1", &input),
+            Err(FilterError::cannot_combine(
+                "subtracted",
+                &json!("a"),
+                &json!(1)
+            ))
+        );
+        assert!(filter_input("1 = 2", &input).is_err());
+    }
+
+    #[test]
+    fn test_reduce() {
+        let input = json!([1, 2, 3, 4]);
+        assert_eq!(run("reduce .[] as $x (0; . + $x)", &input), [json!(10)]);
+        assert_eq!(
+            run("reduce .[] as $x ([]; [$x] + .)", &input),
+            [json!([4, 3, 2, 1])]
+        );
+        assert_eq!(
+            run("reduce .[] as $x (0, 10; . + $x)", &input),
+            [json!(10), json!(20)]
+        );
+        assert_eq!(run("reduce .[2:2][] as $x (0; . + 1)", &input), [json!(0)]);
+        // Variables can be used inside builtin arguments, and inner ones
+        // shadow outer ones
+        assert_eq!(
+            run(
+                "reduce .[] as $x (0; . + ([1, 2, 3, 4] | [.[] | select(. < $x)] | length))",
+                &input
+            ),
+            [json!(6)]
+        );
+        assert_eq!(
+            run(
+                "reduce .[] as $x (0; reduce (1, 2) as $x (.; . + $x))",
+                &input
+            ),
+            [json!(12)]
+        );
+        assert_eq!(
+            run(
+                "reduce .[] as $x (0; reduce (1, 2) as $y (.; . + $x * $y))",
+                &json!([1, 2])
+            ),
+            [json!(9)]
+        );
+        assert!(filter_input("reduce .[] as $x (0; . + $x)", &json!([1, "a"])).is_err());
+    }
+
+    #[test]
+    fn test_foreach() {
+        let input = json!([1, 2, 3]);
+        assert_eq!(
+            run("foreach .[] as $x (0; . + $x)", &input),
+            [json!(1), json!(3), json!(6)]
+        );
+        assert_eq!(
+            run("foreach .[] as $x (0; . + $x; [$x, .])", &input),
+            [json!([1, 1]), json!([2, 3]), json!([3, 6])]
+        );
+        assert_eq!(
+            run("[foreach .[] as $x (0, 10; . + $x)]", &input),
+            [json!([1, 3, 6, 11, 13, 16])]
+        );
+        // Each output of the update is extracted, and the last one is kept
+        assert_eq!(
+            run("[foreach .[] as $x (0; (. + 1, . + $x); .)]", &input),
+            [json!([1, 1, 2, 3, 4, 6])]
+        );
+        assert_eq!(
+            run("[foreach .[] as $x (0; .; select($x > 1) | $x)]", &input),
+            [json!([2, 3])]
+        );
+        assert_eq!(
+            run("[foreach .[:0][] as $x (0; . + 1)]", &input),
+            [json!([])]
+        );
+    }
+
+    #[test]
+    fn test_definitions() {
+        let input = json!({"items": [1, 2, 3]});
+        assert_eq!(
+            run("def double: . * 2; .items | map(double)", &input),
+            [json!([2, 4, 6])]
+        );
+        assert_eq!(
+            run(
+                "def fac: if . <= 1 then 1 else . * (. - 1 | fac) end; [.items[] | fac]",
+                &json!({"items": [1, 4, 5]})
+            ),
+            [json!([1, 24, 120])]
+        );
+        // Filter parameters run wherever they're called
+        assert_eq!(
+            run("def twice(f): [f, f]; twice(.items[0], 9)", &input),
+            [json!([1, 9, 1, 9])]
+        );
+        assert_eq!(
+            run("def apply(f; g): f | g; apply(.items; length)", &input),
+            [json!(3)]
+        );
+        // Value parameters are bound to each output of their argument
+        assert_eq!(
+            run("def pair($a; $b): [$a, b]; [pair(1, 2; 3)]", &input),
+            [json!([[1, 3], [2, 3]])]
+        );
+        // Functions see what was defined before them, not after
+        assert_eq!(
+            run("def f: 1; def g: f; def f: 2; [f, g]", &input),
+            [json!([2, 1])]
+        );
+        assert_eq!(run("def f(f): f + 1; f(10)", &input), [json!(11)]);
+        assert_eq!(
+            run(".items | def second: .[1]; del(second)", &input),
+            [json!([1, 3])]
+        );
+        assert_eq!(
+            run(
+                "reduce .items[] as $x (0; def plus_x: . + $x; plus_x)",
+                &input
+            ),
+            [json!(6)]
+        );
+        // Only as much of a recursive function is run as is needed
+        assert_eq!(
+            run("def r: ., (. + 1 | r); [limit(3; 0 | r)]", &input),
+            [json!([0, 1, 2])]
+        );
+        assert_eq!(
+            run("def r: ., (. + 1 | r); first(r | select(. > 5))", &json!(0)),
+            [json!(6)]
+        );
+        // The outputs keep what they still need of the filter
+        let filter = parse("def r: ., (. + 1 | r); r").unwrap();
+        let outputs = evaluate(&filter, &Rc::new(json!(0)), &SharedInputs::default());
+        drop(filter);
+        let outputs: Vec<Value> = outputs
+            .take(3)
+            .map(|output| (*output.unwrap()).clone())
+            .collect();
+        assert_eq!(outputs, [json!(0), json!(1), json!(2)]);
+        assert_eq!(
+            filter_input("def f: 1; g", &input),
+            Err(FilterError::InvalidFilter(String::from(
+                "g/0 is not defined at column 11"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_comma() {
+        assert_eq!(run(".fizz, .biz", &all_types()), [json!("buzz"), json!(42)]);
+        assert_eq!(
+            run(".fizzes | .[0, -1]", &all_types()),
+            [json!("buzz"), json!(42.0)]
+        );
+        assert_eq!(
+            run("1, \"two\", null", &Value::Null),
+            [json!(1), json!("two"), Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_parentheses_and_whitespace() {
+        assert_eq!(run("(.fizzes)|(.[1:2])", &all_types()), [json!([null])]);
+        assert_eq!(
+            run(".fizzes\n  | .[ 0 ]  # the first one", &all_types()),
+            [json!("buzz")]
+        );
+    }
+
+    #[test]
+    fn test_paths() {
+        let input = json!({"a": [{"b": 1}, {"b": 2}], "c": null});
+        let input = Rc::new(input);
+        let no_inputs = SharedInputs::default();
+        let paths_of = |filter: &str| paths(&parse(filter).unwrap(), &input, &no_inputs);
+        assert_eq!(paths_of("."), Ok(vec![vec![]]));
+        assert_eq!(paths_of(".a | .[1]"), Ok(vec![vec![json!("a"), json!(1)]]));
+        assert_eq!(
+            paths_of(".a | .[] | .b"),
+            Ok(vec![
+                vec![json!("a"), json!(0), json!("b")],
+                vec![json!("a"), json!(1), json!("b")]
+            ])
+        );
+        assert_eq!(paths_of(".a[1].b"), paths_of(".a | .[1] | .b"));
+        assert_eq!(
+            paths_of(".a | .[1:]"),
+            Ok(vec![vec![json!("a"), json!({"start": 1, "end": null})]])
+        );
+        assert_eq!(
+            paths_of(".c, .d"),
+            Ok(vec![vec![json!("c")], vec![json!("d")]])
+        );
+        assert_eq!(
+            paths_of("1"),
+            Err(FilterError::InvalidPath(String::from("number (1)")))
+        );
+        assert!(paths_of(".c | .[]").is_err());
+        assert_eq!(paths_of(".c[]?"), Ok(vec![]));
+        assert_eq!(
+            paths_of(".a[0] | .."),
+            Ok(vec![
+                vec![json!("a"), json!(0)],
+                vec![json!("a"), json!(0), json!("b")]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_get_path() {
+        let input = json!({"a": [{"b": 1}, {"b": 2}]});
+        assert_eq!(
+            get_path(&input, &[json!("a"), json!(-1), json!("b")]),
+            json!(2)
+        );
+        assert_eq!(get_path(&input, &[json!("x"), json!(0)]), Value::Null);
+        assert_eq!(
+            get_path(&input, &[json!("a"), json!({"start": 1, "end": null})]),
+            json!([{"b": 2}])
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Splitting a filter program into tokens.
+
+use serde_json::Number;
+
+use crate::error::FilterError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// `.` on its own.
+    Dot,
+    /// `..`
+    DotDot,
+    /// `.foo`, which can't have any space after the dot.
+    Field(String),
+    /// A name like `length` or `null`.
+    Ident(String),
+    /// `$name`, without the `$`.
+    Variable(String),
+    /// `@name`, without the `@`.
+    Format(String),
+    Number(Number),
+    Str(String),
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    Colon,
+    Comma,
+    Semicolon,
+    Pipe,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Question,
+    /// `=`
+    Assign,
+    /// `|=`
+    Update,
+    /// `+=`
+    PlusAssign,
+    /// `-=`
+    MinusAssign,
+    /// `*=`
+    StarAssign,
+    /// `/=`
+    SlashAssign,
+    /// `%=`
+    PercentAssign,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// A token, along with where it starts in the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    /// How many bytes into the program the token starts.
+    pub offset: usize,
+}
+
+/// Splits `program` into tokens, skipping whitespace and comments (from `#`
+/// to the end of the line).
+pub fn tokenize(program: &str) -> Result<Vec<Spanned>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut rest = program;
+    while let Some(c) = rest.chars().next() {
+        let offset = program.len() - rest.len();
+        let error = |message: &str| error_at(program, offset, message);
+        let (token, len) = match c {
+            _ if c.is_whitespace() => (None, c.len_utf8()),
+            '#' => (None, rest.find('\n').unwrap_or(rest.len())),
+            '.' if rest[1..].starts_with('.') => (Some(Token::DotDot), 2),
+            '.' => match identifier_len(&rest[1..]) {
+                0 => (Some(Token::Dot), 1),
+                len => (Some(Token::Field(String::from(&rest[1..=len]))), len + 1),
+            },
+            '[' => (Some(Token::LeftBracket), 1),
+            ']' => (Some(Token::RightBracket), 1),
+            '(' => (Some(Token::LeftParen), 1),
+            ')' => (Some(Token::RightParen), 1),
+            ':' => (Some(Token::Colon), 1),
+            ',' => (Some(Token::Comma), 1),
+            ';' => (Some(Token::Semicolon), 1),
+            '|' if rest.starts_with("|=") => (Some(Token::Update), 2),
+            '|' => (Some(Token::Pipe), 1),
+            '+' if rest.starts_with("+=") => (Some(Token::PlusAssign), 2),
+            '+' => (Some(Token::Plus), 1),
+            '-' if rest.starts_with("-=") => (Some(Token::MinusAssign), 2),
+            '-' => (Some(Token::Minus), 1),
+            '*' if rest.starts_with("*=") => (Some(Token::StarAssign), 2),
+            '*' => (Some(Token::Star), 1),
+            '/' if rest.starts_with("/=") => (Some(Token::SlashAssign), 2),
+            '/' => (Some(Token::Slash), 1),
+            '%' if rest.starts_with("%=") => (Some(Token::PercentAssign), 2),
+            '%' => (Some(Token::Percent), 1),
+            '?' => (Some(Token::Question), 1),
+            '=' if rest.starts_with("==") => (Some(Token::Equal), 2),
+            '=' => (Some(Token::Assign), 1),
+            '!' if rest.starts_with("!=") => (Some(Token::NotEqual), 2),
+            '<' if rest.starts_with("<=") => (Some(Token::LessEqual), 2),
+            '<' => (Some(Token::Less), 1),
+            '>' if rest.starts_with(">=") => (Some(Token::GreaterEqual), 2),
+            '>' => (Some(Token::Greater), 1),
+            '$' => match identifier_len(&rest[1..]) {
+                0 => return Err(error("unexpected '$'")),
+                len => (Some(Token::Variable(String::from(&rest[1..=len]))), len + 1),
+            },
+            '@' => match identifier_len(&rest[1..]) {
+                0 => return Err(error("unexpected '@'")),
+                len => (Some(Token::Format(String::from(&rest[1..=len]))), len + 1),
+            },
+            '"' => {
+                let len = string_len(rest).ok_or_else(|| error("unterminated string"))?;
+                let string = serde_json::from_str(&rest[..len])
+                    .map_err(|_| error(&format!("invalid string {}", &rest[..len])))?;
+                (Some(Token::Str(string)), len)
+            }
+            _ if c.is_ascii_digit() => {
+                let len = number_len(rest);
+                let number = serde_json::from_str(&rest[..len])
+                    .map_err(|_| error(&format!("invalid number {}", &rest[..len])))?;
+                (Some(Token::Number(number)), len)
+            }
+            _ => match identifier_len(rest) {
+                0 => return Err(error(&format!("unexpected {:?}", c))),
+                len => (Some(Token::Ident(String::from(&rest[..len]))), len),
+            },
+        };
+        tokens.extend(token.map(|token| Spanned { token, offset }));
+        rest = &rest[len..];
+    }
+    Ok(tokens)
+}
+
+/// An error for `message` about the part of `program` that starts `offset`
+/// bytes in, giving its column like `unexpected ']' at column 14`. In a
+/// program with several lines, the line is given too.
+pub fn error_at(program: &str, offset: usize, message: &str) -> FilterError {
+    let before = &program[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let column = before[line_start..].chars().count() + 1;
+    if program.contains('\n') {
+        let line = before.matches('\n').count() + 1;
+        FilterError::InvalidFilter(format!("{} at line {}, column {}", message, line, column))
+    } else {
+        FilterError::InvalidFilter(format!("{} at column {}", message, column))
+    }
+}
+
+/// The length of the identifier at the start of `text`, or 0 if there
+/// isn't one.
+fn identifier_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(text.len())
+}
+
+/// The length of the string literal at the start of `text`, including both
+/// quotes, or `None` if it's never closed.
+fn string_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The length of the number at the start of `text`, like `12`, `1.5`, or
+/// `2e10`.
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |start: usize| {
+        start
+            + bytes[start..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count()
+    };
+    let mut len = digits(0);
+    if bytes.get(len) == Some(&b'.') && bytes.get(len + 1).is_some_and(u8::is_ascii_digit) {
+        len = digits(len + 1);
+    }
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        if bytes.get(len + 1 + sign).is_some_and(u8::is_ascii_digit) {
+            len = digits(len + 1 + sign);
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Number;
+
+    use super::{tokenize, Spanned, Token};
+    use crate::error::FilterError;
+
+    fn number(n: i64) -> Token {
+        Token::Number(Number::from(n))
+    }
+
+    /// The tokens in `program`, without where they are.
+    fn tokens(program: &str) -> Result<Vec<Token>, FilterError> {
+        tokenize(program).map(|tokens| tokens.into_iter().map(|spanned| spanned.token).collect())
+    }
+
+    fn error(message: &str) -> Result<Vec<Token>, FilterError> {
+        Err(FilterError::InvalidFilter(String::from(message)))
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens(".fizzes | .[1]"),
+            Ok(vec![
+                Token::Field(String::from("fizzes")),
+                Token::Pipe,
+                Token::Dot,
+                Token::LeftBracket,
+                number(1),
+                Token::RightBracket,
+            ])
+        );
+        assert_eq!(
+            tokens("del(.[-1, 2:])"),
+            Ok(vec![
+                Token::Ident(String::from("del")),
+                Token::LeftParen,
+                Token::Dot,
+                Token::LeftBracket,
+                Token::Minus,
+                number(1),
+                Token::Comma,
+                number(2),
+                Token::Colon,
+                Token::RightBracket,
+                Token::RightParen,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_fields_and_strings() {
+        assert_eq!(
+            tokens(". foo"),
+            Ok(vec![Token::Dot, Token::Ident(String::from("foo"))])
+        );
+        assert_eq!(
+            tokens(".\"a \\\"b\\\"\""),
+            Ok(vec![Token::Dot, Token::Str(String::from("a \"b\""))])
+        );
+        assert_eq!(tokens(".\"oops"), error("unterminated string at column 2"));
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            tokens(".a+1 - 2*3/4%5"),
+            Ok(vec![
+                Token::Field(String::from("a")),
+                Token::Plus,
+                number(1),
+                Token::Minus,
+                number(2),
+                Token::Star,
+                number(3),
+                Token::Slash,
+                number(4),
+                Token::Percent,
+                number(5),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(
+            tokens("== != < <= > >=<"),
+            Ok(vec![
+                Token::Equal,
+                Token::NotEqual,
+                Token::Less,
+                Token::LessEqual,
+                Token::Greater,
+                Token::GreaterEqual,
+                Token::Less,
+            ])
+        );
+        assert_eq!(tokens(".a ! .b"), error("unexpected '!' at column 4"));
+    }
+
+    #[test]
+    fn test_assignments() {
+        assert_eq!(
+            tokens("= |= += -= *= /= %= == |="),
+            Ok(vec![
+                Token::Assign,
+                Token::Update,
+                Token::PlusAssign,
+                Token::MinusAssign,
+                Token::StarAssign,
+                Token::SlashAssign,
+                Token::PercentAssign,
+                Token::Equal,
+                Token::Update,
+            ])
+        );
+        assert_eq!(
+            tokens(".a|.b-1"),
+            Ok(vec![
+                Token::Field(String::from("a")),
+                Token::Pipe,
+                Token::Field(String::from("b")),
+                Token::Minus,
+                number(1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            tokens("$x+$long_name"),
+            Ok(vec![
+                Token::Variable(String::from("x")),
+                Token::Plus,
+                Token::Variable(String::from("long_name")),
+            ])
+        );
+        assert_eq!(tokens("$ x"), error("unexpected '$' at column 1"));
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            tokens(".[] | @base64d"),
+            Ok(vec![
+                Token::Dot,
+                Token::LeftBracket,
+                Token::RightBracket,
+                Token::Pipe,
+                Token::Format(String::from("base64d")),
+            ])
+        );
+        assert_eq!(tokens("@"), error("unexpected '@' at column 1"));
+    }
+
+    #[test]
+    fn test_dot_dot() {
+        assert_eq!(
+            tokens(".. | ..[0]"),
+            Ok(vec![
+                Token::DotDot,
+                Token::Pipe,
+                Token::DotDot,
+                Token::LeftBracket,
+                number(0),
+                Token::RightBracket,
+            ])
+        );
+        assert_eq!(tokens("..."), Ok(vec![Token::DotDot, Token::Dot]));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(
+            tokens("1.5 2e3 7."),
+            Ok(vec![
+                Token::Number(Number::from_f64(1.5).unwrap()),
+                Token::Number(Number::from_f64(2000.0).unwrap()),
+                number(7),
+                Token::Dot,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            tokens("# Every name\n.[] # each player\n| .name # done"),
+            tokens(".[] | .name")
+        );
+        assert_eq!(
+            tokens(".\"#not a comment\""),
+            Ok(vec![Token::Dot, Token::Str(String::from("#not a comment"))])
+        );
+        assert_eq!(tokens("# only a comment"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_unexpected_character() {
+        assert_eq!(tokens(".a ^ .b"), error("unexpected '^' at column 4"));
+        // Columns count characters, not bytes
+        assert_eq!(tokens("\"é\" ^"), error("unexpected '^' at column 5"));
+        assert_eq!(
+            tokens(".a |\n  .b ^"),
+            error("unexpected '^' at line 2, column 6")
+        );
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(
+            tokenize(".a |= [1]"),
+            Ok(vec![
+                Spanned {
+                    token: Token::Field(String::from("a")),
+                    offset: 0
+                },
+                Spanned {
+                    token: Token::Update,
+                    offset: 3
+                },
+                Spanned {
+                    token: Token::LeftBracket,
+                    offset: 6
+                },
+                Spanned {
+                    token: number(1),
+                    offset: 7
+                },
+                Spanned {
+                    token: Token::RightBracket,
+                    offset: 8
+                },
+            ])
+        );
+    }
+}
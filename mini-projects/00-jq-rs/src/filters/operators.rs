@@ -0,0 +1,309 @@
+//! Binary operators like `+` and `*`, which combine the outputs of two
+//! filters.
+
+use serde_json::{Map, Number, Value};
+
+use crate::error::{describe, FilterError};
+use crate::functions::{compare_values, split};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `%`
+    Modulo,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+}
+
+impl Operator {
+    /// Combines two values like jq: numbers do math, and `+` also joins
+    /// strings, arrays, and objects, with `null` leaving the other side
+    /// alone. Comparisons work on any two values, using jq's ordering.
+    pub fn apply(self, lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+        let ordering = || compare_values(lhs, rhs);
+        match self {
+            Operator::Add => add(lhs, rhs),
+            Operator::Subtract => subtract(lhs, rhs),
+            Operator::Multiply => multiply(lhs, rhs),
+            Operator::Divide => divide(lhs, rhs),
+            Operator::Modulo => modulo(lhs, rhs),
+            Operator::Equal => Ok(Value::Bool(ordering().is_eq())),
+            Operator::NotEqual => Ok(Value::Bool(ordering().is_ne())),
+            Operator::Less => Ok(Value::Bool(ordering().is_lt())),
+            Operator::LessOrEqual => Ok(Value::Bool(ordering().is_le())),
+            Operator::Greater => Ok(Value::Bool(ordering().is_gt())),
+            Operator::GreaterOrEqual => Ok(Value::Bool(ordering().is_ge())),
+        }
+    }
+}
+
+/// `lhs + rhs`, which `add` also uses to combine each value.
+pub(crate) fn add(lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+    match (lhs, rhs) {
+        (Value::Null, value) | (value, Value::Null) => Ok(value.clone()),
+        (Value::Number(a), Value::Number(b)) => {
+            Ok(arithmetic(a, b, i64::checked_add, |a, b| a + b))
+        }
+        (Value::String(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
+        (Value::Array(a), Value::Array(b)) => Ok(Value::Array([a.as_slice(), b].concat())),
+        (Value::Object(a), Value::Object(b)) => {
+            let mut merged = a.clone();
+            merged.extend(b.clone());
+            Ok(Value::Object(merged))
+        }
+        _ => Err(FilterError::cannot_combine("added", lhs, rhs)),
+    }
+}
+
+/// Numbers subtract, and arrays lose every element that's in `rhs`.
+fn subtract(lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+    match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => {
+            Ok(arithmetic(a, b, i64::checked_sub, |a, b| a - b))
+        }
+        (Value::Array(a), Value::Array(b)) => Ok(Value::Array(
+            a.iter()
+                .filter(|value| !b.iter().any(|other| compare_values(value, other).is_eq()))
+                .cloned()
+                .collect(),
+        )),
+        _ => Err(FilterError::cannot_combine("subtracted", lhs, rhs)),
+    }
+}
+
+/// Numbers multiply, a string times a number repeats it (`null` if the
+/// number isn't positive), and objects are merged recursively.
+fn multiply(lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+    match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => {
+            Ok(arithmetic(a, b, i64::checked_mul, |a, b| a * b))
+        }
+        (Value::String(string), Value::Number(count))
+        | (Value::Number(count), Value::String(string)) => {
+            let count = count.as_f64().unwrap_or_default();
+            Ok(if count >= 1.0 {
+                Value::String(string.repeat(count as usize))
+            } else {
+                Value::Null
+            })
+        }
+        (Value::Object(a), Value::Object(b)) => Ok(Value::Object(deep_merge(a, b))),
+        _ => Err(FilterError::cannot_combine("multiplied", lhs, rhs)),
+    }
+}
+
+fn deep_merge(lhs: &Map<String, Value>, rhs: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = lhs.clone();
+    for (key, value) in rhs {
+        let value = match (merged.get(key), value) {
+            (Some(Value::Object(a)), Value::Object(b)) => Value::Object(deep_merge(a, b)),
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), value);
+    }
+    merged
+}
+
+/// Numbers divide, and a string divided by a string is split on it.
+fn divide(lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+    match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => {
+            if b.as_f64() == Some(0.0) {
+                return Err(FilterError::DivisionByZero(describe(lhs), describe(rhs)));
+            }
+            let exact = |a: i64, b: i64| (a.checked_rem(b)? == 0).then(|| a / b);
+            Ok(arithmetic(a, b, exact, |a, b| a / b))
+        }
+        (Value::String(a), Value::String(b)) => Ok(split(a, b)),
+        _ => Err(FilterError::cannot_combine("divided", lhs, rhs)),
+    }
+}
+
+/// The remainder after dividing two numbers, both rounded towards zero.
+fn modulo(lhs: &Value, rhs: &Value) -> Result<Value, FilterError> {
+    let (Value::Number(a), Value::Number(b)) = (lhs, rhs) else {
+        return Err(FilterError::cannot_combine("divided", lhs, rhs));
+    };
+    let truncate = |number: &Number| {
+        number
+            .as_i64()
+            .unwrap_or_else(|| number.as_f64().unwrap_or_default() as i64)
+    };
+    match truncate(b) {
+        0 => Err(FilterError::DivisionByZero(describe(lhs), describe(rhs))),
+        b => Ok(Value::from(truncate(a).wrapping_rem(b))),
+    }
+}
+
+/// Runs `int` on integers, falling back to `float` if either side isn't
+/// one or the result doesn't fit.
+fn arithmetic(
+    lhs: &Number,
+    rhs: &Number,
+    int: impl Fn(i64, i64) -> Option<i64>,
+    float: impl Fn(f64, f64) -> f64,
+) -> Value {
+    if let (Some(a), Some(b)) = (lhs.as_i64(), rhs.as_i64()) {
+        if let Some(result) = int(a, b) {
+            return Value::from(result);
+        }
+    }
+    number(float(
+        lhs.as_f64().unwrap_or_default(),
+        rhs.as_f64().unwrap_or_default(),
+    ))
+}
+
+/// A computed number as a JSON value. Whole numbers become integers, so
+/// `1.5 * 2` is `3` rather than `3.0`. JSON has no infinity, so it's
+/// clamped to the largest number like jq, and NaN becomes `null`.
+pub(crate) fn number(float: f64) -> Value {
+    if float.is_nan() {
+        return Value::Null;
+    }
+    let float = float.clamp(f64::MIN, f64::MAX);
+    if float.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&float) {
+        Value::from(float as i64)
+    } else {
+        Value::from(float)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::Operator::{self, *};
+    use crate::error::FilterError;
+
+    fn apply(lhs: Value, operator: Operator, rhs: Value) -> Result<Value, FilterError> {
+        operator.apply(&lhs, &rhs)
+    }
+
+    #[test]
+    fn test_add() {
+        assert_eq!(apply(json!(1), Add, json!(2)), Ok(json!(3)));
+        assert_eq!(apply(json!(1), Add, json!(0.5)), Ok(json!(1.5)));
+        assert_eq!(
+            apply(json!(i64::MAX), Add, json!(1)),
+            Ok(json!(9.223372036854776e18))
+        );
+        assert_eq!(apply(json!("a"), Add, json!("b")), Ok(json!("ab")));
+        assert_eq!(apply(json!([1]), Add, json!([1, 2])), Ok(json!([1, 1, 2])));
+        assert_eq!(
+            apply(json!({"a": 1, "b": 2}), Add, json!({"b": 3, "c": 4})),
+            Ok(json!({"a": 1, "b": 3, "c": 4}))
+        );
+        assert_eq!(apply(Value::Null, Add, json!("a")), Ok(json!("a")));
+        assert_eq!(apply(json!([1]), Add, Value::Null), Ok(json!([1])));
+        assert_eq!(
+            apply(json!(1), Add, json!("a")),
+            Err(FilterError::cannot_combine("added", &json!(1), &json!("a")))
+        );
+    }
+
+    #[test]
+    fn test_subtract() {
+        assert_eq!(apply(json!(5), Subtract, json!(7)), Ok(json!(-2)));
+        assert_eq!(apply(json!(1.5), Subtract, json!(0.5)), Ok(json!(1)));
+        assert_eq!(
+            apply(json!([1, 2, 1, 3]), Subtract, json!([1, 3.0])),
+            Ok(json!([2]))
+        );
+        assert_eq!(
+            apply(json!("ab"), Subtract, json!("b")),
+            Err(FilterError::cannot_combine(
+                "subtracted",
+                &json!("ab"),
+                &json!("b")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_multiply() {
+        assert_eq!(apply(json!(4), Multiply, json!(2.5)), Ok(json!(10)));
+        assert_eq!(
+            apply(json!(0.1), Multiply, json!(3)),
+            Ok(json!(0.30000000000000004))
+        );
+        assert_eq!(apply(json!("ab"), Multiply, json!(3)), Ok(json!("ababab")));
+        assert_eq!(apply(json!(2), Multiply, json!("ab")), Ok(json!("abab")));
+        assert_eq!(apply(json!("ab"), Multiply, json!(0)), Ok(Value::Null));
+        assert_eq!(
+            apply(
+                json!({"a": {"b": 1, "c": 2}, "d": 3}),
+                Multiply,
+                json!({"a": {"b": 4}, "d": {"e": 5}})
+            ),
+            Ok(json!({"a": {"b": 4, "c": 2}, "d": {"e": 5}}))
+        );
+        assert!(apply(json!([1]), Multiply, json!(2)).is_err());
+    }
+
+    #[test]
+    fn test_divide() {
+        assert_eq!(apply(json!(10), Divide, json!(2)), Ok(json!(5)));
+        assert_eq!(apply(json!(1), Divide, json!(2)), Ok(json!(0.5)));
+        assert_eq!(apply(json!(7.5), Divide, json!(2.5)), Ok(json!(3)));
+        assert_eq!(
+            apply(json!("a, b, c"), Divide, json!(", ")),
+            Ok(json!(["a", "b", "c"]))
+        );
+        assert_eq!(
+            apply(json!(1), Divide, json!(0)).map_err(|err| err.to_string()),
+            Err(String::from(
+                "number (1) and number (0) cannot be divided because the divisor is zero"
+            ))
+        );
+        assert!(apply(json!(1), Divide, json!(0.0)).is_err());
+        assert!(apply(json!([]), Divide, json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(apply(json!(1), Equal, json!(1.0)), Ok(json!(true)));
+        assert_eq!(
+            apply(json!({"a": [1]}), Equal, json!({"a": [1]})),
+            Ok(json!(true))
+        );
+        assert_eq!(apply(json!("1"), Equal, json!(1)), Ok(json!(false)));
+        assert_eq!(apply(json!("1"), NotEqual, json!(1)), Ok(json!(true)));
+        assert_eq!(apply(json!(1), Less, json!(2)), Ok(json!(true)));
+        assert_eq!(apply(json!(2), LessOrEqual, json!(2)), Ok(json!(true)));
+        assert_eq!(apply(json!("b"), Greater, json!("a")), Ok(json!(true)));
+        assert_eq!(
+            apply(Value::Null, GreaterOrEqual, json!(false)),
+            Ok(json!(false))
+        );
+        // Anything compares with anything, in jq's order of types
+        assert_eq!(apply(json!([]), Greater, json!("z")), Ok(json!(true)));
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(apply(json!(7), Modulo, json!(3)), Ok(json!(1)));
+        assert_eq!(apply(json!(-7), Modulo, json!(3)), Ok(json!(-1)));
+        assert_eq!(apply(json!(7.9), Modulo, json!(3.2)), Ok(json!(1)));
+        assert_eq!(apply(json!(i64::MIN), Modulo, json!(-1)), Ok(json!(0)));
+        assert!(apply(json!(1), Modulo, json!(0.5)).is_err());
+        assert!(apply(json!("a"), Modulo, json!(1)).is_err());
+    }
+}
@@ -0,0 +1,1219 @@
+//! Turning tokens into a [`Filter`] with a recursive descent parser.
+//!
+//! From the loosest binding to the tightest:
+//!
+//! ```text
+//! pipe    = def | comma ("|" (def | comma))*
+//! def     = "def" NAME ("(" param (";" param)* ")")? ":" pipe ";" pipe
+//! param   = NAME | VARIABLE
+//! comma   = assign ("," assign)*
+//! assign  = or (("=" | "|=" | "+=" | "-=" | "*=" | "/=" | "%=") or)?
+//! or      = and ("or" and)*
+//! and     = compare ("and" compare)*
+//! compare = sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//! sum     = product (("+" | "-") product)*
+//! product = postfix (("*" | "/" | "%") postfix)*
+//! postfix = term (FIELD | "." STRING | "."? "[" bracket | "?")*
+//! term    = "." | ".." | "." STRING | "." "[" bracket | FIELD
+//!         | NUMBER | "-" NUMBER | STRING | NAME ("(" pipe (";" pipe)* ")")? | "(" pipe ")"
+//!         | "[" pipe? "]" | VARIABLE | FORMAT | if | try | reduce | foreach
+//! if      = "if" pipe "then" pipe ("elif" pipe "then" pipe)* ("else" pipe)? "end"
+//! try     = "try" postfix ("catch" postfix)?
+//! reduce  = "reduce" postfix "as" VARIABLE "(" pipe ";" pipe ")"
+//! foreach = "foreach" postfix "as" VARIABLE "(" pipe ";" pipe (";" pipe)? ")"
+//! bracket = "]" | pipe? ":" pipe? "]" | pipe "]"
+//! ```
+
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use super::lexer::{error_at, tokenize, Spanned, Token};
+use super::operators::Operator;
+use super::scope;
+use crate::error::FilterError;
+use crate::functions;
+
+/// A parsed filter program. The filters inside it are shared, so
+/// outputs that are still being computed can keep the parts they need.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// `.`
+    Identity,
+    /// `..`: the input, then every value nested inside it.
+    Recurse,
+    /// A constant, like `1`, `"abc"`, or `null`.
+    Literal(Value),
+    /// `.[index]` or `.foo` on each output of the first filter. The index is
+    /// run against the original input.
+    Index(Rc<Filter>, Rc<Filter>),
+    /// `.[start:end]` on each output of the first filter. A missing bound
+    /// means the start or end.
+    Slice(Rc<Filter>, Option<Rc<Filter>>, Option<Rc<Filter>>),
+    /// `.[]` on each output of the filter.
+    Iterate(Rc<Filter>),
+    /// `a |= b`: the input with each value at a path of `a` replaced by
+    /// the first output of `b` on it, or deleted if `b` has none.
+    Update(Rc<Filter>, Rc<Filter>),
+    /// `a = b`, or `a += b` and the like with an operator: the input with the
+    /// value at each path of `a` set to an output of `b`, or combined with
+    /// it. `b` runs against the input, and there's one output for each of
+    /// its outputs.
+    Assign(Option<Operator>, Rc<Filter>, Rc<Filter>),
+    /// `a | b`
+    Pipe(Rc<Filter>, Rc<Filter>),
+    /// `a, b`: every output of `a`, then every output of `b`.
+    Comma(Rc<Filter>, Rc<Filter>),
+    /// `a?` or `try a`: the outputs of `a`, or nothing at all if it fails.
+    Optional(Rc<Filter>),
+    /// `try a catch b`: the outputs of `a`, or if it fails, the outputs of
+    /// `b` run on the error.
+    Try(Rc<Filter>, Rc<Filter>),
+    /// `a + b` and the like, on every pair of outputs of `a` and `b`.
+    Binary(Operator, Rc<Filter>, Rc<Filter>),
+    /// `a and b`, which only runs `b` when `a` is truthy.
+    And(Rc<Filter>, Rc<Filter>),
+    /// `a or b`, which only runs `b` when `a` is falsy.
+    Or(Rc<Filter>, Rc<Filter>),
+    /// `[a]`: an array of every output of `a`.
+    Array(Rc<Filter>),
+    /// `if a then b else c end`, running `b` or `c` for each output of `a`.
+    /// `elif` is another `if` in the else branch, and a missing else branch
+    /// is `.`.
+    If(Rc<Filter>, Rc<Filter>, Rc<Filter>),
+    /// A builtin like `length` or `del(.a)`.
+    Call(String, Vec<Filter>),
+    /// A call to a function defined with `def`, with its arguments.
+    Function(Rc<Definition>, Vec<Filter>),
+    /// `$name`, which is replaced by its value before it runs.
+    Variable(String),
+    /// `reduce source as $name (init; update)`: for each output of `init`,
+    /// runs `update` on it once for each output of `source`, with `$name`
+    /// set to that output, and outputs the last result.
+    Reduce(Rc<Filter>, String, Rc<Filter>, Rc<Filter>),
+    /// `foreach source as $name (init; update; extract)`: like `reduce`, but
+    /// outputs `extract` run on every intermediate result. A missing
+    /// `extract` is `.`.
+    Foreach(Rc<Filter>, String, Rc<Filter>, Rc<Filter>, Rc<Filter>),
+}
+
+/// A function defined with `def`, like `def inc(f): f + 1;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub params: Vec<Param>,
+    /// Calls to the function itself and to its parameters are left as
+    /// [`Filter::Call`]s until it's called.
+    pub body: Filter,
+}
+
+impl Definition {
+    /// Whether calls to `name` with `arity` arguments inside the body
+    /// call the function itself or one of its parameters.
+    pub fn binds(&self, name: &str, arity: usize) -> bool {
+        (self.name == name && self.params.len() == arity)
+            || (arity == 0 && self.params.iter().any(|param| param.name() == name))
+    }
+}
+
+/// A parameter of a function defined with `def`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    /// `f`, which is a filter run wherever the body calls `f`.
+    Filter(String),
+    /// `$f`, which is bound to each output of its argument in turn.
+    Value(String),
+}
+
+impl Param {
+    pub fn name(&self) -> &str {
+        match self {
+            Param::Filter(name) | Param::Value(name) => name,
+        }
+    }
+}
+
+/// Parses a whole filter program.
+pub fn parse(program: &str) -> Result<Filter, FilterError> {
+    let tokens = tokenize(program)?;
+    let mut parser = Parser {
+        program,
+        tokens: &tokens,
+        position: 0,
+        variables: Vec::new(),
+        functions: Vec::new(),
+    };
+    let filter = parser.pipe()?;
+    match parser.peek() {
+        None => Ok(filter),
+        Some(_) => Err(parser.unexpected(parser.position)),
+    }
+}
+
+/// How a token is shown in error messages.
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Dot => String::from("'.'"),
+        Token::DotDot => String::from("'..'"),
+        Token::Field(name) => format!("'.{}'", name),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::Variable(name) => format!("'${}'", name),
+        Token::Format(name) => format!("'@{}'", name),
+        Token::Number(number) => format!("'{}'", number),
+        Token::Str(string) => format!("{:?}", string),
+        Token::LeftBracket => String::from("'['"),
+        Token::RightBracket => String::from("']'"),
+        Token::LeftParen => String::from("'('"),
+        Token::RightParen => String::from("')'"),
+        Token::Colon => String::from("':'"),
+        Token::Comma => String::from("','"),
+        Token::Semicolon => String::from("';'"),
+        Token::Pipe => String::from("'|'"),
+        Token::Plus => String::from("'+'"),
+        Token::Minus => String::from("'-'"),
+        Token::Star => String::from("'*'"),
+        Token::Slash => String::from("'/'"),
+        Token::Percent => String::from("'%'"),
+        Token::Question => String::from("'?'"),
+        Token::Assign => String::from("'='"),
+        Token::Update => String::from("'|='"),
+        Token::PlusAssign => String::from("'+='"),
+        Token::MinusAssign => String::from("'-='"),
+        Token::StarAssign => String::from("'*='"),
+        Token::SlashAssign => String::from("'/='"),
+        Token::PercentAssign => String::from("'%='"),
+        Token::Equal => String::from("'=='"),
+        Token::NotEqual => String::from("'!='"),
+        Token::Less => String::from("'<'"),
+        Token::LessEqual => String::from("'<='"),
+        Token::Greater => String::from("'>'"),
+        Token::GreaterEqual => String::from("'>='"),
+    }
+}
+
+struct Parser<'a> {
+    program: &'a str,
+    tokens: &'a [Spanned],
+    position: usize,
+    /// The variables bound where the parser is, innermost last.
+    variables: Vec<String>,
+    /// The names and arities of the functions defined where the parser is,
+    /// including parameters.
+    functions: Vec<(String, usize)>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.token(self.position)
+    }
+
+    fn token(&self, index: usize) -> Option<&Token> {
+        self.tokens.get(index).map(|spanned| &spanned.token)
+    }
+
+    fn next(&mut self) -> Result<&Token, FilterError> {
+        if self.position == self.tokens.len() {
+            return Err(self.unexpected(self.position));
+        }
+        self.position += 1;
+        Ok(&self.tokens[self.position - 1].token)
+    }
+
+    /// An error for `message` about the token at `index`, or about the end
+    /// of the filter if there are no more tokens.
+    fn error(&self, index: usize, message: &str) -> FilterError {
+        let offset = self
+            .tokens
+            .get(index)
+            .map_or(self.program.len(), |spanned| spanned.offset);
+        error_at(self.program, offset, message)
+    }
+
+    /// The error for a token at `index` that can't go where it is.
+    fn unexpected(&self, index: usize) -> FilterError {
+        match self.token(index) {
+            Some(token) => self.error(index, &format!("unexpected {}", describe(token))),
+            None => self.error(index, "unexpected end of filter"),
+        }
+    }
+
+    /// [`Parser::unexpected`] for the token [`Parser::next`] just took.
+    fn unexpected_previous(&self) -> FilterError {
+        self.unexpected(self.position - 1)
+    }
+
+    /// Skips the next token if it's `token`.
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Skips the next token if it's the keyword `keyword`, like `and`.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(name)) if name == keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), FilterError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            _ => Err(self.unexpected_previous()),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), FilterError> {
+        match self.next()? {
+            Token::Ident(name) if name == keyword => Ok(()),
+            _ => Err(self.unexpected_previous()),
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Filter, FilterError> {
+        if self.eat_keyword("def") {
+            return self.definition();
+        }
+        let mut filter = self.comma()?;
+        while self.eat(&Token::Pipe) {
+            let rest = if self.eat_keyword("def") {
+                self.definition()?
+            } else {
+                self.comma()?
+            };
+            filter = Filter::Pipe(Rc::new(filter), Rc::new(rest));
+        }
+        Ok(filter)
+    }
+
+    /// Whatever follows `def`, along with the rest of the pipe, which is
+    /// where the function can be called.
+    fn definition(&mut self) -> Result<Filter, FilterError> {
+        let name = match self.next()? {
+            Token::Ident(name) => name.clone(),
+            _ => return Err(self.unexpected_previous()),
+        };
+        let mut params = Vec::new();
+        if self.eat(&Token::LeftParen) {
+            loop {
+                params.push(match self.next()? {
+                    Token::Ident(name) => Param::Filter(name.clone()),
+                    Token::Variable(name) => Param::Value(name.clone()),
+                    _ => return Err(self.unexpected_previous()),
+                });
+                if !self.eat(&Token::Semicolon) {
+                    break;
+                }
+            }
+            self.expect(&Token::RightParen)?;
+        }
+        self.expect(&Token::Colon)?;
+
+        let (functions, variables) = (self.functions.len(), self.variables.len());
+        // The body can call the function itself, and its parameters
+        self.functions.push((name.clone(), params.len()));
+        for param in &params {
+            self.functions.push((param.name().to_owned(), 0));
+            if let Param::Value(name) = param {
+                self.variables.push(name.clone());
+            }
+        }
+        let body = self.pipe()?;
+        self.functions.truncate(functions + 1);
+        self.variables.truncate(variables);
+        self.expect(&Token::Semicolon)?;
+        let rest = self.pipe()?;
+        self.functions.truncate(functions);
+
+        let definition = Rc::new(Definition { name, params, body });
+        Ok(scope::define(&rest, &definition))
+    }
+
+    fn comma(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.assign()?;
+        while self.eat(&Token::Comma) {
+            filter = Filter::Comma(Rc::new(filter), Rc::new(self.assign()?));
+        }
+        Ok(filter)
+    }
+
+    /// Assignments don't chain, like comparisons.
+    fn assign(&mut self) -> Result<Filter, FilterError> {
+        let filter = self.or()?;
+        let operator = match self.peek() {
+            Some(Token::Update) => {
+                self.position += 1;
+                return Ok(Filter::Update(Rc::new(filter), Rc::new(self.or()?)));
+            }
+            Some(Token::Assign) => None,
+            Some(Token::PlusAssign) => Some(Operator::Add),
+            Some(Token::MinusAssign) => Some(Operator::Subtract),
+            Some(Token::StarAssign) => Some(Operator::Multiply),
+            Some(Token::SlashAssign) => Some(Operator::Divide),
+            Some(Token::PercentAssign) => Some(Operator::Modulo),
+            _ => return Ok(filter),
+        };
+        self.position += 1;
+        let value = self.or()?;
+        Ok(Filter::Assign(operator, Rc::new(filter), Rc::new(value)))
+    }
+
+    fn or(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.and()?;
+        while self.eat_keyword("or") {
+            filter = Filter::Or(Rc::new(filter), Rc::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.compare()?;
+        while self.eat_keyword("and") {
+            filter = Filter::And(Rc::new(filter), Rc::new(self.compare()?));
+        }
+        Ok(filter)
+    }
+
+    /// Comparisons don't chain, so `1 < 2 < 3` is an error.
+    fn compare(&mut self) -> Result<Filter, FilterError> {
+        let filter = self.sum()?;
+        let operator = match self.peek() {
+            Some(Token::Equal) => Operator::Equal,
+            Some(Token::NotEqual) => Operator::NotEqual,
+            Some(Token::Less) => Operator::Less,
+            Some(Token::LessEqual) => Operator::LessOrEqual,
+            Some(Token::Greater) => Operator::Greater,
+            Some(Token::GreaterEqual) => Operator::GreaterOrEqual,
+            _ => return Ok(filter),
+        };
+        self.position += 1;
+        Ok(binary(operator, filter, self.sum()?))
+    }
+
+    fn sum(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Plus) => Operator::Add,
+                Some(Token::Minus) => Operator::Subtract,
+                _ => return Ok(filter),
+            };
+            self.position += 1;
+            filter = binary(operator, filter, self.product()?);
+        }
+    }
+
+    fn product(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.postfix()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Star) => Operator::Multiply,
+                Some(Token::Slash) => Operator::Divide,
+                Some(Token::Percent) => Operator::Modulo,
+                _ => return Ok(filter),
+            };
+            self.position += 1;
+            filter = binary(operator, filter, self.postfix()?);
+        }
+    }
+
+    /// A term followed by any number of field accesses, brackets, and `?`s,
+    /// like `.items[0].name`, each applied to the outputs of what comes
+    /// before.
+    fn postfix(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.term()?;
+        loop {
+            filter = match (self.peek(), self.token(self.position + 1)) {
+                (Some(Token::Field(name)), _) => {
+                    let name = Value::String(name.clone());
+                    self.position += 1;
+                    index(filter, Filter::Literal(name))
+                }
+                (Some(Token::Dot), Some(Token::Str(key))) => {
+                    let key = Value::String(key.clone());
+                    self.position += 2;
+                    index(filter, Filter::Literal(key))
+                }
+                (Some(Token::Dot), Some(Token::LeftBracket)) => {
+                    self.position += 2;
+                    self.bracket(filter)?
+                }
+                (Some(Token::LeftBracket), _) => {
+                    self.position += 1;
+                    self.bracket(filter)?
+                }
+                (Some(Token::Question), _) => {
+                    self.position += 1;
+                    Filter::Optional(Rc::new(filter))
+                }
+                _ => return Ok(filter),
+            };
+        }
+    }
+
+    fn term(&mut self) -> Result<Filter, FilterError> {
+        match self.next()?.clone() {
+            Token::Dot => match self.peek() {
+                Some(Token::Str(key)) => {
+                    let key = Value::String(key.clone());
+                    self.position += 1;
+                    Ok(index(Filter::Identity, Filter::Literal(key)))
+                }
+                Some(Token::LeftBracket) => {
+                    self.position += 1;
+                    self.bracket(Filter::Identity)
+                }
+                _ => Ok(Filter::Identity),
+            },
+            Token::DotDot => Ok(Filter::Recurse),
+            Token::Field(name) => Ok(index(
+                Filter::Identity,
+                Filter::Literal(Value::String(name)),
+            )),
+            Token::Number(number) => Ok(Filter::Literal(Value::Number(number))),
+            Token::Minus => match self.next()? {
+                Token::Number(number) => Ok(Filter::Literal(negate(number))),
+                _ => Err(self.unexpected_previous()),
+            },
+            Token::Str(string) => Ok(Filter::Literal(Value::String(string))),
+            Token::LeftParen => {
+                let filter = self.pipe()?;
+                self.expect(&Token::RightParen)?;
+                Ok(filter)
+            }
+            Token::LeftBracket => {
+                if self.eat(&Token::RightBracket) {
+                    return Ok(Filter::Literal(Value::Array(Vec::new())));
+                }
+                let filter = self.pipe()?;
+                self.expect(&Token::RightBracket)?;
+                Ok(Filter::Array(Rc::new(filter)))
+            }
+            Token::Variable(name) if self.variables.contains(&name) => Ok(Filter::Variable(name)),
+            Token::Variable(name) if name == "ENV" => Ok(Filter::Call(String::from("env"), vec![])),
+            Token::Variable(name) => {
+                Err(self.error(self.position - 1, &format!("${} is not defined", name)))
+            }
+            Token::Format(name) => {
+                let function = format!("@{}", name);
+                if !functions::is_defined(&function, 0) {
+                    return Err(self.error(
+                        self.position - 1,
+                        &format!("{} is not a valid format", name),
+                    ));
+                }
+                Ok(Filter::Call(function, vec![]))
+            }
+            Token::Ident(name) if name == "if" => self.conditional(),
+            Token::Ident(name) if name == "try" => self.try_catch(),
+            Token::Ident(name) if name == "reduce" => self.reduce(),
+            Token::Ident(name) if name == "foreach" => self.foreach(),
+            Token::Ident(name) => self.call(name),
+            _ => Err(self.unexpected_previous()),
+        }
+    }
+
+    /// Whatever follows `.[`, applied to `target`.
+    fn bracket(&mut self, target: Filter) -> Result<Filter, FilterError> {
+        if self.eat(&Token::RightBracket) {
+            return Ok(Filter::Iterate(Rc::new(target)));
+        }
+        let start = match self.peek() {
+            Some(Token::Colon) => None,
+            _ => Some(Rc::new(self.pipe()?)),
+        };
+        if self.eat(&Token::Colon) {
+            let end = match self.peek() {
+                Some(Token::RightBracket) => None,
+                _ => Some(Rc::new(self.pipe()?)),
+            };
+            self.expect(&Token::RightBracket)?;
+            return Ok(Filter::Slice(Rc::new(target), start, end));
+        }
+        self.expect(&Token::RightBracket)?;
+        let index = start.expect("there's an index when there's no colon");
+        Ok(Filter::Index(Rc::new(target), index))
+    }
+
+    /// Whatever follows `if` or `elif`, up to and including the `end`.
+    fn conditional(&mut self) -> Result<Filter, FilterError> {
+        let condition = self.pipe()?;
+        self.expect_keyword("then")?;
+        let then = self.pipe()?;
+        let otherwise = if self.eat_keyword("elif") {
+            // The rest is parsed like another `if`, which ends at the same
+            // `end` as this one
+            self.conditional()?
+        } else {
+            let otherwise = if self.eat_keyword("else") {
+                self.pipe()?
+            } else {
+                Filter::Identity
+            };
+            self.expect_keyword("end")?;
+            otherwise
+        };
+        Ok(Filter::If(
+            Rc::new(condition),
+            Rc::new(then),
+            Rc::new(otherwise),
+        ))
+    }
+
+    /// Whatever follows `try`, including the `catch` if there is one.
+    fn try_catch(&mut self) -> Result<Filter, FilterError> {
+        let body = Rc::new(self.postfix()?);
+        if self.eat_keyword("catch") {
+            Ok(Filter::Try(body, Rc::new(self.postfix()?)))
+        } else {
+            Ok(Filter::Optional(body))
+        }
+    }
+
+    /// Whatever follows `reduce`, up to and including the closing `)`.
+    fn reduce(&mut self) -> Result<Filter, FilterError> {
+        let (source, name, init) = self.start_fold()?;
+        let update = self.pipe()?;
+        self.variables.pop();
+        self.expect(&Token::RightParen)?;
+        Ok(Filter::Reduce(
+            Rc::new(source),
+            name,
+            Rc::new(init),
+            Rc::new(update),
+        ))
+    }
+
+    /// Whatever follows `foreach`, up to and including the closing `)`.
+    fn foreach(&mut self) -> Result<Filter, FilterError> {
+        let (source, name, init) = self.start_fold()?;
+        let update = self.pipe()?;
+        let extract = if self.eat(&Token::Semicolon) {
+            self.pipe()?
+        } else {
+            Filter::Identity
+        };
+        self.variables.pop();
+        self.expect(&Token::RightParen)?;
+        Ok(Filter::Foreach(
+            Rc::new(source),
+            name,
+            Rc::new(init),
+            Rc::new(update),
+            Rc::new(extract),
+        ))
+    }
+
+    /// The source, the variable, and the `init` of a `reduce` or `foreach`,
+    /// up to the `;` after `init`. The variable stays bound until the
+    /// caller pops it.
+    fn start_fold(&mut self) -> Result<(Filter, String, Filter), FilterError> {
+        let source = self.postfix()?;
+        self.expect_keyword("as")?;
+        let name = match self.next()? {
+            Token::Variable(name) => name.clone(),
+            _ => return Err(self.unexpected_previous()),
+        };
+        self.expect(&Token::LeftParen)?;
+        let init = self.pipe()?;
+        self.expect(&Token::Semicolon)?;
+        self.variables.push(name.clone());
+        Ok((source, name, init))
+    }
+
+    /// A constant like `null`, or a call to a builtin, with its arguments if
+    /// there are any. The name has just been taken.
+    fn call(&mut self, name: String) -> Result<Filter, FilterError> {
+        let start = self.position - 1;
+        match name.as_str() {
+            "null" => return Ok(Filter::Literal(Value::Null)),
+            "true" => return Ok(Filter::Literal(Value::Bool(true))),
+            "false" => return Ok(Filter::Literal(Value::Bool(false))),
+            _ => {}
+        }
+        let mut args = Vec::new();
+        if self.eat(&Token::LeftParen) {
+            args.push(self.pipe()?);
+            while self.eat(&Token::Semicolon) {
+                args.push(self.pipe()?);
+            }
+            self.expect(&Token::RightParen)?;
+        }
+        let arity = args.len();
+        let defined = self
+            .functions
+            .iter()
+            .any(|(function, n)| *function == name && *n == arity);
+        if !defined && !functions::is_defined(&name, arity) {
+            return Err(self.error(start, &format!("{}/{} is not defined", name, args.len())));
+        }
+        Ok(Filter::Call(name, args))
+    }
+}
+
+fn index(target: Filter, index: Filter) -> Filter {
+    Filter::Index(Rc::new(target), Rc::new(index))
+}
+
+fn binary(operator: Operator, lhs: Filter, rhs: Filter) -> Filter {
+    Filter::Binary(operator, Rc::new(lhs), Rc::new(rhs))
+}
+
+fn negate(number: &serde_json::Number) -> Value {
+    match number.as_i64() {
+        Some(int) => Value::from(-int),
+        None => Value::from(-number.as_f64().unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use std::rc::Rc;
+
+    use super::{index, parse, Definition, Filter, Operator, Param};
+    use crate::error::FilterError;
+
+    fn field(name: &str) -> Filter {
+        Filter::Index(
+            Rc::new(Filter::Identity),
+            Rc::new(Filter::Literal(json!(name))),
+        )
+    }
+
+    fn literal(value: Value) -> Rc<Filter> {
+        Rc::new(Filter::Literal(value))
+    }
+
+    fn error(message: &str) -> Result<Filter, FilterError> {
+        Err(FilterError::InvalidFilter(String::from(message)))
+    }
+
+    #[test]
+    fn test_simple_filters() {
+        assert_eq!(parse("."), Ok(Filter::Identity));
+        assert_eq!(parse(".fizz"), Ok(field("fizz")));
+        assert_eq!(parse(".\"fizz\""), Ok(field("fizz")));
+        assert_eq!(
+            parse(".[0]"),
+            Ok(Filter::Index(Rc::new(Filter::Identity), literal(json!(0))))
+        );
+        assert_eq!(
+            parse(".[-1]"),
+            Ok(Filter::Index(Rc::new(Filter::Identity), literal(json!(-1))))
+        );
+        assert_eq!(parse(".[]"), Ok(Filter::Iterate(Rc::new(Filter::Identity))));
+        assert_eq!(parse("(.fizz)"), Ok(field("fizz")));
+        assert_eq!(parse(".."), Ok(Filter::Recurse));
+        assert_eq!(parse("..[]"), Ok(Filter::Iterate(Rc::new(Filter::Recurse))));
+        assert_eq!(parse("null"), Ok(Filter::Literal(Value::Null)));
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(
+            parse(".[1:2]"),
+            Ok(Filter::Slice(
+                Rc::new(Filter::Identity),
+                Some(literal(json!(1))),
+                Some(literal(json!(2)))
+            ))
+        );
+        assert_eq!(
+            parse(".[:2]"),
+            Ok(Filter::Slice(
+                Rc::new(Filter::Identity),
+                None,
+                Some(literal(json!(2)))
+            ))
+        );
+        assert_eq!(
+            parse(".[1:]"),
+            Ok(Filter::Slice(
+                Rc::new(Filter::Identity),
+                Some(literal(json!(1))),
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_chains() {
+        assert_eq!(
+            parse(".a.b"),
+            Ok(Filter::Index(Rc::new(field("a")), literal(json!("b"))))
+        );
+        assert_eq!(parse(".a.\"b\""), parse(".a.b"));
+        assert_eq!(parse(".a[\"b\"]"), parse(".a.b"));
+        assert_eq!(parse(".a.[\"b\"]"), parse(".a.b"));
+        assert_eq!(
+            parse(".items[0].name"),
+            Ok(Filter::Index(
+                Rc::new(Filter::Index(Rc::new(field("items")), literal(json!(0)))),
+                literal(json!("name"))
+            ))
+        );
+        assert_eq!(
+            parse(".[0][1:]"),
+            Ok(Filter::Slice(
+                Rc::new(Filter::Index(Rc::new(Filter::Identity), literal(json!(0)))),
+                Some(literal(json!(1))),
+                None
+            ))
+        );
+        assert_eq!(parse(".a[]"), Ok(Filter::Iterate(Rc::new(field("a")))));
+        assert_eq!(parse("(.a).b"), parse(".a.b"));
+        assert_eq!(
+            parse(".a[]?"),
+            Ok(Filter::Optional(Rc::new(Filter::Iterate(Rc::new(field(
+                "a"
+            ))))))
+        );
+        assert_eq!(
+            parse(".a?.b"),
+            Ok(Filter::Index(
+                Rc::new(Filter::Optional(Rc::new(field("a")))),
+                literal(json!("b"))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_pipes_and_commas() {
+        // Commas bind more tightly than pipes
+        assert_eq!(
+            parse(".a, .b | .c"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Comma(Rc::new(field("a")), Rc::new(field("b")))),
+                Rc::new(field("c"))
+            ))
+        );
+        assert_eq!(
+            parse(".a | .b | .c"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Pipe(Rc::new(field("a")), Rc::new(field("b")))),
+                Rc::new(field("c"))
+            ))
+        );
+        // Whitespace doesn't matter
+        assert_eq!(parse("  .a|.b  "), parse(".a | .b"));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let binary = |operator, lhs, rhs| Filter::Binary(operator, Rc::new(lhs), Rc::new(rhs));
+        let one = || Filter::Literal(json!(1));
+        // Products bind more tightly than sums, which bind more tightly than
+        // commas
+        assert_eq!(
+            parse(".a + 1 * .b, 1"),
+            Ok(Filter::Comma(
+                Rc::new(binary(
+                    Operator::Add,
+                    field("a"),
+                    binary(Operator::Multiply, one(), field("b"))
+                )),
+                Rc::new(one())
+            ))
+        );
+        // Operators of the same precedence go left to right
+        assert_eq!(
+            parse("1 - 1 - 1"),
+            Ok(binary(
+                Operator::Subtract,
+                binary(Operator::Subtract, one(), one()),
+                one()
+            ))
+        );
+        assert_eq!(
+            parse(".a/-1"),
+            Ok(binary(
+                Operator::Divide,
+                field("a"),
+                Filter::Literal(json!(-1))
+            ))
+        );
+        assert!(parse("(1 % .a)[0]").is_ok());
+        assert_eq!(parse(".a +"), error("unexpected end of filter at column 5"));
+        assert_eq!(parse("* 2"), error("unexpected '*' at column 1"));
+    }
+
+    #[test]
+    fn test_comparisons_and_booleans() {
+        let binary = |operator, lhs, rhs| Filter::Binary(operator, Rc::new(lhs), Rc::new(rhs));
+        let one = || Filter::Literal(json!(1));
+        // `and` binds more tightly than `or`, and comparisons more tightly
+        // than both
+        assert_eq!(
+            parse(".a or .b and .c == 1 + 1"),
+            Ok(Filter::Or(
+                Rc::new(field("a")),
+                Rc::new(Filter::And(
+                    Rc::new(field("b")),
+                    Rc::new(binary(
+                        Operator::Equal,
+                        field("c"),
+                        binary(Operator::Add, one(), one())
+                    ))
+                ))
+            ))
+        );
+        assert_eq!(
+            parse(".a >= 1, 2"),
+            Ok(Filter::Comma(
+                Rc::new(binary(Operator::GreaterOrEqual, field("a"), one())),
+                Rc::new(Filter::Literal(json!(2)))
+            ))
+        );
+        assert_eq!(parse("1 < 2 < 3"), error("unexpected '<' at column 7"));
+        assert_eq!(
+            parse(".a and"),
+            error("unexpected end of filter at column 7")
+        );
+    }
+
+    #[test]
+    fn test_assignments() {
+        // Assignments bind more loosely than `or` but more tightly than `,`
+        assert_eq!(
+            parse(".a = .b or .c, 1"),
+            Ok(Filter::Comma(
+                Rc::new(Filter::Assign(
+                    None,
+                    Rc::new(field("a")),
+                    Rc::new(Filter::Or(Rc::new(field("b")), Rc::new(field("c"))))
+                )),
+                literal(json!(1))
+            ))
+        );
+        assert_eq!(
+            parse(".count += 1"),
+            Ok(Filter::Assign(
+                Some(Operator::Add),
+                Rc::new(field("count")),
+                literal(json!(1))
+            ))
+        );
+        assert_eq!(
+            parse(".items[] |= . * 2 | .n"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Update(
+                    Rc::new(Filter::Iterate(Rc::new(field("items")))),
+                    Rc::new(Filter::Binary(
+                        Operator::Multiply,
+                        Rc::new(Filter::Identity),
+                        literal(json!(2))
+                    ))
+                )),
+                Rc::new(field("n"))
+            ))
+        );
+        assert_eq!(parse(".a = .b = 1"), error("unexpected '=' at column 9"));
+        assert_eq!(
+            parse(".a |="),
+            error("unexpected end of filter at column 6")
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(parse("[]"), Ok(Filter::Literal(json!([]))));
+        assert_eq!(
+            parse("[.a, .b]"),
+            Ok(Filter::Array(Rc::new(Filter::Comma(
+                Rc::new(field("a")),
+                Rc::new(field("b"))
+            ))))
+        );
+        assert_eq!(
+            parse("[.a][0]"),
+            Ok(Filter::Index(
+                Rc::new(Filter::Array(Rc::new(field("a")))),
+                literal(json!(0))
+            ))
+        );
+        assert_eq!(parse("[.a"), error("unexpected end of filter at column 4"));
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let conditional = |condition, then, otherwise| {
+            Filter::If(Rc::new(condition), Rc::new(then), Rc::new(otherwise))
+        };
+        assert_eq!(
+            parse("if .a then .b else .c end"),
+            Ok(conditional(field("a"), field("b"), field("c")))
+        );
+        assert_eq!(
+            parse("if .a then .b elif .c then .d else .e end"),
+            Ok(conditional(
+                field("a"),
+                field("b"),
+                conditional(field("c"), field("d"), field("e"))
+            ))
+        );
+        assert_eq!(
+            parse("if .a then .b end"),
+            Ok(conditional(field("a"), field("b"), Filter::Identity))
+        );
+        assert_eq!(
+            parse("if .a then .b else .c end | .d"),
+            Ok(Filter::Pipe(
+                Rc::new(conditional(field("a"), field("b"), field("c"))),
+                Rc::new(field("d"))
+            ))
+        );
+        assert_eq!(
+            parse("if .a then .b"),
+            error("unexpected end of filter at column 14")
+        );
+        assert_eq!(
+            parse("if .a .b end"),
+            error("unexpected 'end' at column 10")
+        );
+        assert_eq!(
+            parse("if .a then .b else .c"),
+            error("unexpected end of filter at column 22")
+        );
+    }
+
+    #[test]
+    fn test_try() {
+        assert_eq!(parse("try .a"), Ok(Filter::Optional(Rc::new(field("a")))));
+        assert_eq!(
+            parse("try .a.b catch . | .c"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Try(
+                    Rc::new(index(field("a"), Filter::Literal(json!("b")))),
+                    Rc::new(Filter::Identity)
+                )),
+                Rc::new(field("c"))
+            ))
+        );
+        assert_eq!(parse("try"), error("unexpected end of filter at column 4"));
+        assert_eq!(
+            parse("try .a catch"),
+            error("unexpected end of filter at column 13")
+        );
+    }
+
+    #[test]
+    fn test_reduce() {
+        let reduce = |source, init, update| {
+            Filter::Reduce(
+                Rc::new(source),
+                String::from("x"),
+                Rc::new(init),
+                Rc::new(update),
+            )
+        };
+        let variable = || Filter::Variable(String::from("x"));
+        assert_eq!(
+            parse("reduce .[] as $x (0; . + $x)"),
+            Ok(reduce(
+                Filter::Iterate(Rc::new(Filter::Identity)),
+                Filter::Literal(json!(0)),
+                Filter::Binary(
+                    Operator::Add,
+                    Rc::new(Filter::Identity),
+                    Rc::new(variable())
+                )
+            ))
+        );
+        assert_eq!(
+            parse("reduce .a as $x (.b; $x) | $y"),
+            error("$y is not defined at column 28")
+        );
+        // `$x` is only bound in the update
+        assert_eq!(
+            parse("reduce .a as $x ($x; .)"),
+            error("$x is not defined at column 18")
+        );
+        assert_eq!(
+            parse("reduce .a as $x (0; .), $x"),
+            error("$x is not defined at column 25")
+        );
+        assert_eq!(
+            parse("reduce .a as x (0; .)"),
+            error("unexpected 'x' at column 14")
+        );
+        assert_eq!(
+            parse("reduce .a as $x (0)"),
+            error("unexpected ')' at column 19")
+        );
+        assert_eq!(
+            parse("reduce .a as $x (0; 1; 2)"),
+            error("unexpected ';' at column 22")
+        );
+    }
+
+    #[test]
+    fn test_foreach() {
+        let foreach = |update, extract| {
+            Filter::Foreach(
+                Rc::new(Filter::Iterate(Rc::new(Filter::Identity))),
+                String::from("x"),
+                Rc::new(Filter::Literal(json!(0))),
+                Rc::new(update),
+                Rc::new(extract),
+            )
+        };
+        let variable = || Rc::new(Filter::Variable(String::from("x")));
+        let sum = Filter::Binary(Operator::Add, Rc::new(Filter::Identity), variable());
+        assert_eq!(
+            parse("foreach .[] as $x (0; . + $x; [$x, .])"),
+            Ok(foreach(
+                sum.clone(),
+                Filter::Array(Rc::new(Filter::Comma(
+                    variable(),
+                    Rc::new(Filter::Identity)
+                )))
+            ))
+        );
+        assert_eq!(
+            parse("foreach .[] as $x (0; . + $x)"),
+            Ok(foreach(sum, Filter::Identity))
+        );
+        assert_eq!(
+            parse("foreach .[] as $x (0; .; .) | $x"),
+            error("$x is not defined at column 31")
+        );
+        assert_eq!(
+            parse("foreach .[] as $x (0; .; .; .)"),
+            error("unexpected ';' at column 27")
+        );
+    }
+
+    #[test]
+    fn test_definitions() {
+        let call = |name: &str| Filter::Call(String::from(name), vec![]);
+        let definition = Rc::new(Definition {
+            name: String::from("inc"),
+            params: vec![
+                Param::Filter(String::from("f")),
+                Param::Value(String::from("n")),
+            ],
+            body: Filter::Binary(
+                Operator::Add,
+                Rc::new(call("f")),
+                Rc::new(Filter::Variable(String::from("n"))),
+            ),
+        });
+        assert_eq!(
+            parse("def inc(f; $n): f + $n; inc(.a; 1)"),
+            Ok(Filter::Function(
+                definition.clone(),
+                vec![field("a"), Filter::Literal(json!(1))]
+            ))
+        );
+        assert_eq!(
+            parse(". | def inc(f; $n): f + $n; inc(.a; 1)"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Identity),
+                Rc::new(Filter::Function(
+                    definition,
+                    vec![field("a"), Filter::Literal(json!(1))]
+                ))
+            ))
+        );
+        // Builtins can be redefined, and functions can call themselves
+        let recursive = Rc::new(Definition {
+            name: String::from("length"),
+            params: vec![],
+            body: call("length"),
+        });
+        assert_eq!(
+            parse("def length: length; length"),
+            Ok(Filter::Function(recursive, vec![]))
+        );
+        assert_eq!(
+            parse("def f(x): x; x"),
+            error("x/0 is not defined at column 14")
+        );
+        assert_eq!(
+            parse("def f($x): $x; $x"),
+            error("$x is not defined at column 16")
+        );
+        assert_eq!(
+            parse("def f: .; f(1)"),
+            error("f/1 is not defined at column 11")
+        );
+        assert_eq!(
+            parse("def f: ."),
+            error("unexpected end of filter at column 9")
+        );
+        assert_eq!(parse("def f(.): ."), error("unexpected '.' at column 7"));
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(
+            parse("length"),
+            Ok(Filter::Call(String::from("length"), vec![]))
+        );
+        assert_eq!(
+            parse("del(.a)"),
+            Ok(Filter::Call(String::from("del"), vec![field("a")]))
+        );
+        assert_eq!(
+            parse("setpath([\"a\"]; 1, 2)"),
+            Ok(Filter::Call(
+                String::from("setpath"),
+                vec![
+                    Filter::Array(Rc::new(Filter::Literal(json!("a")))),
+                    Filter::Comma(literal(json!(1)), literal(json!(2)))
+                ]
+            ))
+        );
+        assert_eq!(
+            parse("setpath(1)"),
+            error("setpath/1 is not defined at column 1")
+        );
+        assert_eq!(
+            parse("del(.a; .b)"),
+            error("del/2 is not defined at column 1")
+        );
+        assert_eq!(parse("nope"), error("nope/0 is not defined at column 1"));
+        assert_eq!(
+            parse(".[] | @csv"),
+            Ok(Filter::Pipe(
+                Rc::new(Filter::Iterate(Rc::new(Filter::Identity))),
+                Rc::new(Filter::Call(String::from("@csv"), vec![]))
+            ))
+        );
+        assert_eq!(
+            parse("@nope"),
+            error("nope is not a valid format at column 1")
+        );
+        assert_eq!(
+            parse("length(1)"),
+            error("length/1 is not defined at column 1")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse(""), error("unexpected end of filter at column 1"));
+        assert_eq!(parse(".["), error("unexpected end of filter at column 3"));
+        assert_eq!(parse(".[0"), error("unexpected end of filter at column 4"));
+        assert_eq!(parse(".a ]"), error("unexpected ']' at column 4"));
+        assert_eq!(parse(".a b"), error("unexpected 'b' at column 4"));
+        assert_eq!(
+            parse(".a.[0"),
+            error("unexpected end of filter at column 6")
+        );
+        assert_eq!(parse("|"), error("unexpected '|' at column 1"));
+        assert_eq!(parse("-.a"), error("unexpected '.a' at column 2"));
+        assert_eq!(parse("..a"), error("unexpected 'a' at column 3"));
+        assert_eq!(parse("?"), error("unexpected '?' at column 1"));
+        assert_eq!(
+            parse("# Every name\n.[] |\n  .name ]"),
+            error("unexpected ']' at line 3, column 9")
+        );
+    }
+}
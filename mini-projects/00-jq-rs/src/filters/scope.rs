@@ -0,0 +1,183 @@
+//! Variables and functions defined with `def`. Both are bound by
+//! replacing what uses them with what they stand for, so a function's body
+//! sees the variables and functions from where it was defined.
+
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use super::parser::{Definition, Filter, Param};
+use super::{collect, evaluate};
+use crate::error::FilterError;
+use crate::input::SharedInputs;
+
+/// `filter` with `$name` replaced by `value`, except inside anything that
+/// binds its own `$name`.
+pub(super) fn bind(filter: &Filter, name: &str, value: &Value) -> Filter {
+    let shared = |filter: &Filter| Rc::new(bind(filter, name, value));
+    match filter {
+        Filter::Variable(variable) if variable == name => Filter::Literal(value.clone()),
+        // Only the source and init are outside the scope of the variable
+        Filter::Reduce(source, variable, init, update) if variable == name => Filter::Reduce(
+            shared(source),
+            variable.clone(),
+            shared(init),
+            update.clone(),
+        ),
+        Filter::Foreach(source, variable, init, update, extract) if variable == name => {
+            Filter::Foreach(
+                shared(source),
+                variable.clone(),
+                shared(init),
+                update.clone(),
+                extract.clone(),
+            )
+        }
+        Filter::Function(definition, args) => {
+            let args = args.iter().map(|arg| bind(arg, name, value)).collect();
+            let shadowed = definition
+                .params
+                .iter()
+                .any(|param| matches!(param, Param::Value(param) if param == name));
+            let definition = if shadowed {
+                definition.clone()
+            } else {
+                with_body(definition, bind(&definition.body, name, value))
+            };
+            Filter::Function(definition, args)
+        }
+        _ => map_children(filter, |child| bind(child, name, value)),
+    }
+}
+
+/// `filter` with every call to `definition` replaced by a
+/// [`Filter::Function`] that runs it.
+pub(super) fn define(filter: &Filter, definition: &Rc<Definition>) -> Filter {
+    replace_calls(filter, &definition.name, definition.params.len(), &|args| {
+        Filter::Function(definition.clone(), args)
+    })
+}
+
+/// The bodies to run for a call to `definition` with `args` on `input`.
+/// Calls to the function itself and to its filter parameters are resolved,
+/// and there's one body for each combination of outputs of the arguments
+/// of its `$` parameters.
+pub(super) fn instantiate(
+    definition: &Rc<Definition>,
+    args: &[Filter],
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+) -> Result<Vec<Filter>, FilterError> {
+    let mut bodies = vec![define(&definition.body, definition)];
+    for (param, arg) in definition.params.iter().zip(args) {
+        bodies = match param {
+            Param::Filter(name) => bodies
+                .iter()
+                .map(|body| replace_calls(body, name, 0, &|_| arg.clone()))
+                .collect(),
+            // `$name` can also be called as `name`
+            Param::Value(name) => {
+                let values = collect(evaluate(arg, input, inputs))?;
+                let mut bound = Vec::new();
+                for body in &bodies {
+                    for value in &values {
+                        let body = bind(body, name, value);
+                        bound.push(replace_calls(&body, name, 0, &|_| {
+                            Filter::Literal(value.clone())
+                        }));
+                    }
+                }
+                bound
+            }
+        };
+    }
+    Ok(bodies)
+}
+
+/// `filter` with every call to `name` with `arity` arguments replaced by
+/// what `replacement` makes from its arguments, except inside a function
+/// that binds that name itself.
+fn replace_calls(
+    filter: &Filter,
+    name: &str,
+    arity: usize,
+    replacement: &dyn Fn(Vec<Filter>) -> Filter,
+) -> Filter {
+    let replace = |filter: &Filter| replace_calls(filter, name, arity, replacement);
+    match filter {
+        Filter::Call(function, args) if function == name && args.len() == arity => {
+            replacement(args.iter().map(replace).collect())
+        }
+        Filter::Function(definition, args) => {
+            let args = args.iter().map(replace).collect();
+            let definition = if definition.binds(name, arity) {
+                definition.clone()
+            } else {
+                with_body(definition, replace(&definition.body))
+            };
+            Filter::Function(definition, args)
+        }
+        _ => map_children(filter, replace),
+    }
+}
+
+fn with_body(definition: &Definition, body: Filter) -> Rc<Definition> {
+    Rc::new(Definition {
+        name: definition.name.clone(),
+        params: definition.params.clone(),
+        body,
+    })
+}
+
+/// `filter` with `map` applied to each filter directly inside it. The body
+/// of a [`Filter::Function`] isn't inside it, since it binds names of its
+/// own.
+fn map_children(filter: &Filter, map: impl Fn(&Filter) -> Filter) -> Filter {
+    let shared = |filter: &Filter| Rc::new(map(filter));
+    match filter {
+        Filter::Identity | Filter::Recurse | Filter::Literal(_) | Filter::Variable(_) => {
+            filter.clone()
+        }
+        Filter::Index(target, index) => Filter::Index(shared(target), shared(index)),
+        Filter::Slice(target, start, end) => Filter::Slice(
+            shared(target),
+            start.as_deref().map(shared),
+            end.as_deref().map(shared),
+        ),
+        Filter::Iterate(target) => Filter::Iterate(shared(target)),
+        Filter::Update(target, update) => Filter::Update(shared(target), shared(update)),
+        Filter::Assign(operator, target, value) => {
+            Filter::Assign(*operator, shared(target), shared(value))
+        }
+        Filter::Pipe(first, second) => Filter::Pipe(shared(first), shared(second)),
+        Filter::Comma(first, second) => Filter::Comma(shared(first), shared(second)),
+        Filter::Optional(filter) => Filter::Optional(shared(filter)),
+        Filter::Try(body, handler) => Filter::Try(shared(body), shared(handler)),
+        Filter::Binary(operator, lhs, rhs) => Filter::Binary(*operator, shared(lhs), shared(rhs)),
+        Filter::And(lhs, rhs) => Filter::And(shared(lhs), shared(rhs)),
+        Filter::Or(lhs, rhs) => Filter::Or(shared(lhs), shared(rhs)),
+        Filter::Array(filter) => Filter::Array(shared(filter)),
+        Filter::If(condition, then, otherwise) => {
+            Filter::If(shared(condition), shared(then), shared(otherwise))
+        }
+        Filter::Call(function, args) => {
+            Filter::Call(function.clone(), args.iter().map(map).collect())
+        }
+        Filter::Function(definition, args) => {
+            Filter::Function(definition.clone(), args.iter().map(map).collect())
+        }
+        Filter::Reduce(source, variable, init, update) => Filter::Reduce(
+            shared(source),
+            variable.clone(),
+            shared(init),
+            shared(update),
+        ),
+        Filter::Foreach(source, variable, init, update, extract) => Filter::Foreach(
+            shared(source),
+            variable.clone(),
+            shared(init),
+            shared(update),
+            shared(extract),
+        ),
+    }
+}
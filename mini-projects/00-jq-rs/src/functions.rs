@@ -0,0 +1,1176 @@
+//! The builtin functions, like `add`, `length`, and `del`.
+
+mod formats;
+mod math;
+mod paths;
+mod strings;
+
+use std::cmp::Ordering;
+use std::env;
+use std::rc::Rc;
+
+use serde_json::{Map, Number, Value};
+
+use crate::error::{type_name, FilterError};
+use crate::filters::{self, as_index, each, is_truthy, optional, single, Filter, Outputs, Path};
+use crate::input::SharedInputs;
+use formats::{base64_function, base64d_function, csv_function, tsv_function};
+use math::{
+    ceil_function, fabs_function, floor_function, pow_function, round_function, sqrt_function,
+};
+use paths::{
+    delete_function, delpaths_function, getpath_function, leaf_paths_function, paths_function,
+    setpath_function,
+};
+pub(crate) use paths::{delete_paths, set_path};
+pub(crate) use strings::split;
+use strings::{
+    ascii_downcase_function, ascii_upcase_function, capture_function, endswith_function,
+    fromjson_function, join_function, ltrimstr_function, match_function, rtrimstr_function,
+    split_function, startswith_function, test_function, tojson_function, tonumber_function,
+    tostring_function,
+};
+
+/// Runs a builtin on `input`, given the filters passed as its arguments and
+/// the rest of the input.
+type Builtin = fn(&[Filter], &Rc<Value>, &SharedInputs) -> Outputs;
+
+/// The builtin called `name` that takes `arity` arguments, if there is one.
+fn builtin(name: &str, arity: usize) -> Option<Builtin> {
+    let builtin: Builtin = match (name, arity) {
+        ("add", 0) => |_, input, _| single(add_function(input)),
+        ("length", 0) => |_, input, _| single(length_function(input)),
+        ("utf8bytelength", 0) => |_, input, _| single(utf8bytelength_function(input)),
+        ("del", 1) => |args, input, inputs| single(delete_function(input, &args[0], inputs)),
+        ("keys", 0) => |_, input, _| single(keys_function(input, true)),
+        ("keys_unsorted", 0) => |_, input, _| single(keys_function(input, false)),
+        ("values", 0) => |_, input, _| single(values_function(input)),
+        ("type", 0) => |_, input, _| single(Ok(Value::from(type_name(input)))),
+        ("not", 0) => |_, input, _| single(Ok(Value::Bool(!is_truthy(input)))),
+        ("select", 1) => select_function,
+        ("map", 1) => |args, input, inputs| single(map_function(input, &args[0], inputs)),
+        ("unique", 0) => |_, input, _| single(unique_function(input)),
+        ("unique_by", 1) => {
+            |args, input, inputs| single(unique_by_function(input, &args[0], inputs))
+        }
+        ("min", 0) => |_, input, inputs| single(min_function(input, None, inputs)),
+        ("max", 0) => |_, input, inputs| single(max_function(input, None, inputs)),
+        ("min_by", 1) => |args, input, inputs| single(min_function(input, args.first(), inputs)),
+        ("max_by", 1) => |args, input, inputs| single(max_function(input, args.first(), inputs)),
+        ("flatten", 0) => |_, input, _| single(flatten_function(input, &Value::from(u32::MAX))),
+        ("flatten", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, flatten_function)
+        }
+        ("reverse", 0) => |_, input, _| single(reverse_function(input)),
+        ("join", 1) => |args, input, inputs| each_output(&args[0], input, inputs, join_function),
+        ("split", 1) => |args, input, inputs| each_output(&args[0], input, inputs, split_function),
+        ("startswith", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, startswith_function)
+        }
+        ("endswith", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, endswith_function)
+        }
+        ("ltrimstr", 1) => |args, input, inputs| {
+            each_output(&args[0], input, inputs, |input, prefix| {
+                Ok(ltrimstr_function(input, prefix))
+            })
+        },
+        ("rtrimstr", 1) => |args, input, inputs| {
+            each_output(&args[0], input, inputs, |input, suffix| {
+                Ok(rtrimstr_function(input, suffix))
+            })
+        },
+        ("ascii_downcase", 0) => |_, input, _| single(ascii_downcase_function(input)),
+        ("ascii_upcase", 0) => |_, input, _| single(ascii_upcase_function(input)),
+        ("floor", 0) => |_, input, _| single(floor_function(input)),
+        ("ceil", 0) => |_, input, _| single(ceil_function(input)),
+        ("round", 0) => |_, input, _| single(round_function(input)),
+        ("sqrt", 0) => |_, input, _| single(sqrt_function(input)),
+        ("fabs", 0) => |_, input, _| single(fabs_function(input)),
+        ("pow", 2) => pow_function,
+        ("tojson", 0) => |_, input, _| single(Ok(tojson_function(input))),
+        ("fromjson", 0) => |_, input, _| single(fromjson_function(input)),
+        ("@csv", 0) => |_, input, _| single(csv_function(input)),
+        ("@tsv", 0) => |_, input, _| single(tsv_function(input)),
+        ("@json", 0) => |_, input, _| single(Ok(tojson_function(input))),
+        ("@base64", 0) => |_, input, _| single(Ok(base64_function(input))),
+        ("@base64d", 0) => |_, input, _| single(base64d_function(input)),
+        ("tostring", 0) => |_, input, _| single(Ok(tostring_function(input))),
+        ("tonumber", 0) => |_, input, _| single(tonumber_function(input)),
+        ("test", 1) => |args, input, inputs| each_output(&args[0], input, inputs, test_function),
+        ("match", 1) => |args, input, inputs| {
+            let input = input.clone();
+            each(filters::evaluate(&args[0], &input, inputs), move |regex| {
+                optional(match_function(&input, &regex))
+            })
+        },
+        ("capture", 1) => |args, input, inputs| {
+            let input = input.clone();
+            each(filters::evaluate(&args[0], &input, inputs), move |regex| {
+                optional(capture_function(&input, &regex))
+            })
+        },
+        ("paths", 0) => |_, input, inputs| paths_function(input, None, inputs),
+        ("paths", 1) => |args, input, inputs| paths_function(input, args.first(), inputs),
+        ("leaf_paths", 0) => |_, input, _| leaf_paths_function(input),
+        ("getpath", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, getpath_function)
+        }
+        ("setpath", 2) => setpath_function,
+        ("delpaths", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, delpaths_function)
+        }
+        ("env", 0) => |_, _, _| single(Ok(env_function())),
+        ("limit", 2) => limit_function,
+        ("first", 1) => {
+            |args, input, inputs| Box::new(filters::evaluate(&args[0], input, inputs).take(1))
+        }
+        ("last", 1) => |args, input, inputs| {
+            let mut last = None;
+            for output in filters::evaluate(&args[0], input, inputs) {
+                let failed = output.is_err();
+                last = Some(output);
+                if failed {
+                    break;
+                }
+            }
+            Box::new(last.into_iter())
+        },
+        ("nth", 2) => nth_function,
+        ("contains", 1) => {
+            |args, input, inputs| each_output(&args[0], input, inputs, contains_function)
+        }
+        ("inside", 1) => |args, input, inputs| {
+            each_output(&args[0], input, inputs, |input, container| {
+                contains_function(container, input)
+            })
+        },
+        ("input", 0) => |_, _, inputs| single(input_function(inputs)),
+        ("inputs", 0) => |_, _, inputs| inputs_function(inputs),
+        ("error", 0) => |_, input, _| single(Err(FilterError::Custom(Value::clone(input)))),
+        ("error", 1) => |args, input, inputs| {
+            Box::new(
+                filters::evaluate(&args[0], input, inputs)
+                    .take(1)
+                    .map(|message| {
+                        Err(match message {
+                            Ok(message) => FilterError::Custom(Rc::unwrap_or_clone(message)),
+                            Err(err) => err,
+                        })
+                    }),
+            )
+        },
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+/// Runs `function` on `input` and each output of `arg`, for builtins whose
+/// argument is a value like `join(", ")`.
+fn each_output(
+    arg: &Filter,
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+    function: fn(&Value, &Value) -> Result<Value, FilterError>,
+) -> Outputs {
+    let outputs = filters::evaluate(arg, input, inputs);
+    let input = input.clone();
+    Box::new(outputs.map(move |arg| function(&input, &*arg?).map(Rc::new)))
+}
+
+/// Whether there's a builtin called `name` that takes `arity` arguments.
+pub fn is_defined(name: &str, arity: usize) -> bool {
+    builtin(name, arity).is_some()
+}
+
+/// Runs the builtin called `name` with `args` on `input`, with `inputs`
+/// left for `input` and `inputs`.
+pub fn call(name: &str, args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    match builtin(name, args.len()) {
+        Some(builtin) => builtin(args, input, inputs),
+        None => single(Err(FilterError::InvalidFilter(format!(
+            "{}/{} is not defined",
+            name,
+            args.len()
+        )))),
+    }
+}
+
+/// The paths to the values the builtin called `name` outputs, for the
+/// builtins that can be used in path expressions, like `select` in
+/// `del(.[] | select(.stale))`. `None` for builtins that make new values.
+pub fn call_paths(
+    name: &str,
+    args: &[Filter],
+    input: &Rc<Value>,
+    inputs: &SharedInputs,
+) -> Option<Result<Vec<Path>, FilterError>> {
+    let paths = match (name, args.len()) {
+        // The input's own path, once for each truthy output
+        ("select", 1) => filters::evaluate(&args[0], input, inputs)
+            .filter(|condition| condition.as_ref().map_or(true, |c| is_truthy(c)))
+            .map(|condition| condition.map(|_| Vec::new()))
+            .collect(),
+        ("first", 1) => filters::paths(&args[0], input, inputs).map(|mut paths| {
+            paths.truncate(1);
+            paths
+        }),
+        ("last", 1) => filters::paths(&args[0], input, inputs)
+            .map(|mut paths| paths.pop().into_iter().collect()),
+        ("getpath", 1) => filters::evaluate(&args[0], input, inputs)
+            .map(|path| match &*path? {
+                Value::Array(path) => Ok(path.clone()),
+                path => Err(FilterError::invalid_input("getpath", path)),
+            })
+            .collect(),
+        _ => return None,
+    };
+    Some(paths)
+}
+
+/// `add`: every value of an array or object added together with `+`, so
+/// numbers are summed, strings and arrays are concatenated, and objects are
+/// merged. `null`s are skipped, and the sum of no values is `null`.
+pub fn add_function(input: &Value) -> Result<Value, FilterError> {
+    let values: Vec<&Value> = match input {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => return Err(FilterError::invalid_input("add", input)),
+    };
+    values
+        .into_iter()
+        .try_fold(Value::Null, |sum, value| filters::add(&sum, value))
+}
+
+/// `length`: how many characters are in a string, elements are in an array,
+/// or keys are in an object. The length of a number is its absolute value,
+/// and the length of `null` is 0.
+pub fn length_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::Null => Ok(Value::from(0)),
+        Value::Number(number) => Ok(abs(number)),
+        Value::String(string) => Ok(Value::from(string.chars().count())),
+        Value::Array(values) => Ok(Value::from(values.len())),
+        Value::Object(map) => Ok(Value::from(map.len())),
+        Value::Bool(_) => Err(FilterError::invalid_input("length", input)),
+    }
+}
+
+/// `utf8bytelength`: how many bytes a string takes up in UTF-8, which is
+/// more than its `length` if it has any non-ASCII characters.
+pub fn utf8bytelength_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::String(string) => Ok(Value::from(string.len())),
+        _ => Err(FilterError::invalid_input("utf8bytelength", input)),
+    }
+}
+
+/// `select(condition)`: the input once for every truthy output of
+/// `condition`, so `.[] | select(.age > 30)` keeps only some elements.
+pub fn select_function(args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    let outputs = filters::evaluate(&args[0], input, inputs);
+    let input = input.clone();
+    Box::new(outputs.filter_map(move |condition| match condition {
+        Ok(condition) => is_truthy(&condition).then(|| Ok(input.clone())),
+        Err(err) => Some(Err(err)),
+    }))
+}
+
+/// `map(f)`: every output of `f` for each value of an array or object, in
+/// an array.
+pub fn map_function(
+    input: &Value,
+    filter: &Filter,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let values: Vec<&Value> = match input {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => return Err(FilterError::CannotIterate(type_name(input))),
+    };
+    let mut mapped = Vec::new();
+    for value in values {
+        let value = Rc::new(value.clone());
+        mapped.extend(filters::collect(filters::evaluate(filter, &value, inputs))?);
+    }
+    Ok(Value::Array(mapped))
+}
+
+/// `limit(n; f)`: the first `n` outputs of `f`, or all of them if `n` is
+/// negative, for each output `n` of the first argument.
+pub fn limit_function(args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    let filter = args[1].clone();
+    let input = input.clone();
+    let inputs = inputs.clone();
+    each(filters::evaluate(&args[0], &input, &inputs), move |n| {
+        let Value::Number(n) = &*n else {
+            return single(Err(FilterError::invalid_input("limit", &n)));
+        };
+        // A negative `n` doesn't limit anything, like jq 1.7
+        let n = usize::try_from(as_index(n)).unwrap_or(usize::MAX);
+        // Nothing past the first `n` outputs is computed
+        Box::new(filters::evaluate(&filter, &input, &inputs).take(n))
+    })
+}
+
+/// `nth(n; f)`: the output of `f` at index `n`, counting from 0, or nothing
+/// if it has fewer outputs.
+pub fn nth_function(args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    let filter = args[1].clone();
+    let input = input.clone();
+    let inputs = inputs.clone();
+    each(filters::evaluate(&args[0], &input, &inputs), move |n| {
+        let Value::Number(n) = &*n else {
+            return single(Err(FilterError::invalid_input("nth", &n)));
+        };
+        let Ok(n) = usize::try_from(as_index(n)) else {
+            return single(Err(FilterError::OutOfBounds));
+        };
+        // An error before the nth output is still an error
+        let nth = filters::evaluate(&filter, &input, &inputs)
+            .enumerate()
+            .find(|(index, output)| *index == n || output.is_err());
+        Box::new(nth.map(|(_, output)| output).into_iter())
+    })
+}
+
+/// `keys` and `keys_unsorted`: the keys of an object, either sorted or in
+/// the order they appear, or the indexes of an array.
+pub fn keys_function(input: &Value, sorted: bool) -> Result<Value, FilterError> {
+    match input {
+        Value::Object(map) => {
+            let mut keys: Vec<String> = map.keys().cloned().collect();
+            if sorted {
+                keys.sort();
+            }
+            Ok(Value::from(keys))
+        }
+        Value::Array(values) => Ok(Value::from_iter(0..values.len())),
+        _ => Err(FilterError::invalid_input(
+            if sorted { "keys" } else { "keys_unsorted" },
+            input,
+        )),
+    }
+}
+
+/// `values`: the values of an object in the order they appear, or the
+/// elements of an array.
+pub fn values_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::Object(map) => Ok(Value::Array(map.values().cloned().collect())),
+        Value::Array(_) => Ok(input.clone()),
+        _ => Err(FilterError::invalid_input("values", input)),
+    }
+}
+
+fn abs(number: &Number) -> Value {
+    if number.is_u64() {
+        return Value::Number(number.clone());
+    }
+    match number.as_i64() {
+        Some(int) => Value::from(int.unsigned_abs()),
+        None => filters::number(number.as_f64().unwrap_or_default().abs()),
+    }
+}
+
+/// The elements of `input`, or an error naming `function` if it isn't an
+/// array.
+fn elements<'a>(function: &'static str, input: &'a Value) -> Result<&'a [Value], FilterError> {
+    match input {
+        Value::Array(values) => Ok(values),
+        _ => Err(FilterError::invalid_input(function, input)),
+    }
+}
+
+/// Pairs each element with its key for the `_by` builtins: an array of the
+/// outputs of `key` on it.
+fn keyed(
+    function: &'static str,
+    input: &Value,
+    key: &Filter,
+    inputs: &SharedInputs,
+) -> Result<Vec<(Value, Value)>, FilterError> {
+    elements(function, input)?
+        .iter()
+        .map(|value| {
+            let key = filters::collect(filters::evaluate(key, &Rc::new(value.clone()), inputs))?;
+            Ok((Value::Array(key), value.clone()))
+        })
+        .collect()
+}
+
+/// `unique`: the distinct elements of an array, sorted.
+pub fn unique_function(input: &Value) -> Result<Value, FilterError> {
+    let mut values = elements("unique", input)?.to_vec();
+    values.sort_by(compare_values);
+    values.dedup_by(|a, b| compare_values(a, b).is_eq());
+    Ok(Value::Array(values))
+}
+
+/// `unique_by(key)`: the first element with each distinct key, sorted by
+/// key.
+pub fn unique_by_function(
+    input: &Value,
+    key: &Filter,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let mut keyed = keyed("unique_by", input, key, inputs)?;
+    keyed.sort_by(|(a, _), (b, _)| compare_values(a, b));
+    keyed.dedup_by(|(a, _), (b, _)| compare_values(a, b).is_eq());
+    Ok(Value::Array(
+        keyed.into_iter().map(|(_, value)| value).collect(),
+    ))
+}
+
+/// `min` and `min_by(key)`: the smallest element of an array, compared by
+/// itself or by its key. Ties go to the first one, and an empty array gives
+/// `null`.
+pub fn min_function(
+    input: &Value,
+    key: Option<&Filter>,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let function = if key.is_some() { "min_by" } else { "min" };
+    extremum(function, input, key, false, inputs)
+}
+
+/// `max` and `max_by(key)`: the largest element of an array, like
+/// [`min_function`], except that ties go to the last one.
+pub fn max_function(
+    input: &Value,
+    key: Option<&Filter>,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let function = if key.is_some() { "max_by" } else { "max" };
+    extremum(function, input, key, true, inputs)
+}
+
+fn extremum(
+    function: &'static str,
+    input: &Value,
+    key: Option<&Filter>,
+    largest: bool,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    let keyed = match key {
+        Some(key) => keyed(function, input, key, inputs)?,
+        None => elements(function, input)?
+            .iter()
+            .map(|value| (value.clone(), value.clone()))
+            .collect(),
+    };
+    let compare = |(a, _): &(Value, Value), (b, _): &(Value, Value)| compare_values(a, b);
+    let extremum = if largest {
+        keyed.into_iter().max_by(compare)
+    } else {
+        keyed.into_iter().min_by(compare)
+    };
+    Ok(extremum.map_or(Value::Null, |(_, value)| value))
+}
+
+/// `flatten` and `flatten(depth)`: an array with every nested array
+/// replaced by its elements, going at most `depth` levels deep.
+pub fn flatten_function(input: &Value, depth: &Value) -> Result<Value, FilterError> {
+    let values = elements("flatten", input)?;
+    let depth = match depth {
+        Value::Number(depth) if depth.as_f64().is_some_and(|depth| depth >= 0.0) => {
+            depth.as_f64().unwrap_or_default() as u32
+        }
+        Value::Number(_) => {
+            return Err(FilterError::InvalidInput {
+                function: "flatten",
+                value: String::from("a negative depth"),
+            })
+        }
+        _ => return Err(FilterError::invalid_input("flatten", depth)),
+    };
+    let mut flattened = Vec::new();
+    flatten(values, depth, &mut flattened);
+    Ok(Value::Array(flattened))
+}
+
+fn flatten(values: &[Value], depth: u32, flattened: &mut Vec<Value>) {
+    for value in values {
+        match value {
+            Value::Array(values) if depth > 0 => flatten(values, depth - 1, flattened),
+            _ => flattened.push(value.clone()),
+        }
+    }
+}
+
+/// `reverse`: an array or a string back to front. Reversing `null` gives an
+/// empty array.
+pub fn reverse_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::Array(values) => Ok(Value::Array(values.iter().rev().cloned().collect())),
+        Value::String(string) => Ok(Value::String(string.chars().rev().collect())),
+        Value::Null => Ok(Value::Array(Vec::new())),
+        _ => Err(FilterError::invalid_input("reverse", input)),
+    }
+}
+
+/// `contains(element)`: whether `element` is part of the input. That's
+/// when a string is a substring of it, when every element of an array is
+/// contained by some element of it, or when every value of an object is
+/// contained by the value at the same key. Anything else has to be equal.
+pub fn contains_function(input: &Value, element: &Value) -> Result<Value, FilterError> {
+    contains(input, element).map(Value::Bool)
+}
+
+fn contains(container: &Value, element: &Value) -> Result<bool, FilterError> {
+    match (container, element) {
+        (Value::String(container), Value::String(element)) => Ok(container.contains(&**element)),
+        (Value::Array(container), Value::Array(elements)) => {
+            for element in elements {
+                if !any_contains(container, element)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Value::Object(container), Value::Object(elements)) => {
+            for (key, element) in elements {
+                match container.get(key) {
+                    Some(value) if contains(value, element)? => {}
+                    _ => return Ok(false),
+                }
+            }
+            Ok(true)
+        }
+        _ if type_name(container) == type_name(element) => {
+            Ok(compare_values(container, element).is_eq())
+        }
+        _ => Err(FilterError::cannot_combine(
+            "checked for containment",
+            container,
+            element,
+        )),
+    }
+}
+
+/// Whether any of `values` contains `element`.
+fn any_contains(values: &[Value], element: &Value) -> Result<bool, FilterError> {
+    for value in values {
+        if contains(value, element)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `env` and `$ENV`: an object of the environment variables jq-rs was run
+/// with. Anything that isn't valid UTF-8 is replaced.
+pub fn env_function() -> Value {
+    let variables: Map<String, Value> = env::vars_os()
+        .map(|(name, value)| {
+            let value = value.to_string_lossy().into_owned();
+            (name.to_string_lossy().into_owned(), Value::String(value))
+        })
+        .collect();
+    Value::Object(variables)
+}
+
+/// `input`: the next document of the input, which the filter then isn't
+/// run on.
+pub fn input_function(inputs: &SharedInputs) -> Result<Value, FilterError> {
+    match inputs.next_input() {
+        Some(document) => document.map_err(|err| FilterError::ReadInput(err.to_string())),
+        None => Err(FilterError::NoMoreInputs),
+    }
+}
+
+/// `inputs`: every document left in the input.
+pub fn inputs_function(inputs: &SharedInputs) -> Outputs {
+    Box::new(inputs.clone().map(|document| match document {
+        Ok(document) => Ok(Rc::new(document)),
+        Err(err) => Err(FilterError::ReadInput(err.to_string())),
+    }))
+}
+
+/// Orders values like jq: `null`, then `false`, `true`, numbers, strings,
+/// arrays, and objects. Arrays compare element by element, and objects
+/// compare their sorted keys first and then their values.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .unwrap_or_default()
+                .total_cmp(&b.as_f64().unwrap_or_default()),
+        },
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => compare_sequences(a, b),
+        (Value::Object(a), Value::Object(b)) => {
+            let mut a_keys: Vec<_> = a.keys().collect();
+            let mut b_keys: Vec<_> = b.keys().collect();
+            a_keys.sort();
+            b_keys.sort();
+            a_keys.cmp(&b_keys).then_with(|| {
+                let a_values: Vec<_> = a_keys.iter().map(|key| a[key.as_str()].clone()).collect();
+                let b_values: Vec<_> = a_keys.iter().map(|key| b[key.as_str()].clone()).collect();
+                compare_sequences(&a_values, &b_values)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn compare_sequences(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| compare_values(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::rc::Rc;
+
+    use serde_json::{json, Value};
+
+    use super::{
+        add_function, compare_values, contains_function, env_function, flatten_function,
+        keys_function, length_function, reverse_function, unique_function, utf8bytelength_function,
+        values_function,
+    };
+    use crate::error::FilterError;
+    use crate::filters::{collect, evaluate, evaluate_all, parse};
+    use crate::input::{read_inputs, InputConfig, SharedInputs};
+
+    #[test]
+    fn test_add() {
+        assert_eq!(add_function(&json!([1, 2, 3])), Ok(json!(6)));
+        assert_eq!(
+            add_function(&json!(["one", "two", "three"])),
+            Ok(json!("onetwothree"))
+        );
+        assert_eq!(add_function(&json!([7])), Ok(json!(7)));
+        assert_eq!(add_function(&json!([])), Ok(Value::Null));
+        assert_eq!(
+            add_function(&json!([1, "a"])),
+            Err(FilterError::cannot_combine("added", &json!(1), &json!("a")))
+        );
+        // Too big for an integer, so it's summed as floats like jq
+        assert_eq!(
+            add_function(&json!([i64::MAX, 1])),
+            Ok(json!(9223372036854775808.0))
+        );
+        assert_eq!(add_function(&json!([1.5, 2, 0.25])), Ok(json!(3.75)));
+        assert_eq!(add_function(&json!([0.5, 0.5])), Ok(json!(1)));
+        assert_eq!(
+            add_function(&json!([[1], [], [2, [3]]])),
+            Ok(json!([1, 2, [3]]))
+        );
+        assert_eq!(
+            add_function(&json!([{"a": 1, "b": 2}, {"b": 3}, {"c": 4}])),
+            Ok(json!({"a": 1, "b": 3, "c": 4}))
+        );
+        assert_eq!(add_function(&json!([null, 1, null, 2])), Ok(json!(3)));
+        assert_eq!(add_function(&json!([null, null])), Ok(Value::Null));
+        assert_eq!(add_function(&json!({"a": 1, "b": 2})), Ok(json!(3)));
+        assert_eq!(add_function(&json!({})), Ok(Value::Null));
+        assert_eq!(
+            add_function(&json!([[1], {"a": 2}])),
+            Err(FilterError::cannot_combine(
+                "added",
+                &json!([1]),
+                &json!({"a": 2})
+            ))
+        );
+        assert_eq!(
+            add_function(&json!("abc")),
+            Err(FilterError::invalid_input("add", &json!("abc")))
+        );
+    }
+
+    #[test]
+    fn test_length() {
+        assert_eq!(
+            length_function(&json!(["one", "two", "three"])),
+            Ok(json!(3))
+        );
+        assert_eq!(length_function(&json!({"a": 1, "b": 2})), Ok(json!(2)));
+        assert_eq!(length_function(&json!("héllo")), Ok(json!(5)));
+        assert_eq!(length_function(&Value::Null), Ok(json!(0)));
+        assert_eq!(length_function(&json!(-5)), Ok(json!(5)));
+        assert_eq!(length_function(&json!(-2.5)), Ok(json!(2.5)));
+        assert_eq!(length_function(&json!(u64::MAX)), Ok(json!(u64::MAX)));
+        assert_eq!(
+            length_function(&json!(i64::MIN)),
+            Ok(json!(9223372036854775808u64))
+        );
+        assert_eq!(length_function(&json!(-2.0)), Ok(json!(2)));
+        assert_eq!(length_function(&json!(-1e300)), Ok(json!(1e300)));
+        assert!(length_function(&json!(true)).is_err());
+    }
+
+    #[test]
+    fn test_utf8bytelength() {
+        assert_eq!(utf8bytelength_function(&json!("abc")), Ok(json!(3)));
+        assert_eq!(utf8bytelength_function(&json!("héllo")), Ok(json!(6)));
+        assert_eq!(utf8bytelength_function(&json!("😀")), Ok(json!(4)));
+        assert_eq!(utf8bytelength_function(&json!("")), Ok(json!(0)));
+        assert_eq!(
+            utf8bytelength_function(&json!([1])),
+            Err(FilterError::invalid_input("utf8bytelength", &json!([1])))
+        );
+    }
+
+    #[test]
+    fn test_limit_first_last_nth() {
+        let run = |filter: &str| evaluate_all(&parse(filter).unwrap(), &json!([1, 2, 3]));
+        assert_eq!(run("limit(2; .[])"), Ok(vec![json!(1), json!(2)]));
+        assert_eq!(run("[limit(5; .[])]"), Ok(vec![json!([1, 2, 3])]));
+        assert_eq!(run("[limit(0; .[])]"), Ok(vec![json!([])]));
+        assert_eq!(run("[limit(-1; .[])]"), Ok(vec![json!([1, 2, 3])]));
+        assert_eq!(
+            run("[limit(1, 2; .[] * 10)]"),
+            Ok(vec![json!([10, 10, 20])])
+        );
+        assert_eq!(run("first(.[])"), Ok(vec![json!(1)]));
+        assert_eq!(run("last(.[])"), Ok(vec![json!(3)]));
+        assert_eq!(run("[first(.[3:][]), last(.[3:][])]"), Ok(vec![json!([])]));
+        assert_eq!(run("nth(1; .[])"), Ok(vec![json!(2)]));
+        assert_eq!(run("nth(1.5, 0; .[])"), Ok(vec![json!(2), json!(1)]));
+        assert_eq!(run("[nth(3; .[])]"), Ok(vec![json!([])]));
+        assert_eq!(run("nth(-1; .[])"), Err(FilterError::OutOfBounds));
+        assert_eq!(
+            run("limit(\"a\"; .[])"),
+            Err(FilterError::invalid_input("limit", &json!("a")))
+        );
+    }
+
+    #[test]
+    fn test_keys() {
+        let input = json!({"b": 1, "a": 2, "c": 3});
+        assert_eq!(keys_function(&input, true), Ok(json!(["a", "b", "c"])));
+        assert_eq!(keys_function(&input, false), Ok(json!(["b", "a", "c"])));
+        assert_eq!(keys_function(&json!(["x", "y"]), true), Ok(json!([0, 1])));
+        assert_eq!(keys_function(&json!({}), true), Ok(json!([])));
+        assert_eq!(
+            keys_function(&json!(1), true),
+            Err(FilterError::invalid_input("keys", &json!(1)))
+        );
+        assert_eq!(
+            keys_function(&Value::Null, false),
+            Err(FilterError::invalid_input("keys_unsorted", &Value::Null))
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(
+            values_function(&json!({"b": 1, "a": [2]})),
+            Ok(json!([1, [2]]))
+        );
+        assert_eq!(values_function(&json!([3, 4])), Ok(json!([3, 4])));
+        assert_eq!(
+            values_function(&json!("abc")),
+            Err(FilterError::invalid_input("values", &json!("abc")))
+        );
+    }
+
+    #[test]
+    fn test_type() {
+        let types = |input: Value| evaluate_all(&parse(".[] | type").unwrap(), &input).unwrap();
+        assert_eq!(
+            types(json!([{}, [], "a", 1, 2.5, true, false, null])),
+            [
+                json!("object"),
+                json!("array"),
+                json!("string"),
+                json!("number"),
+                json!("number"),
+                json!("boolean"),
+                json!("boolean"),
+                json!("null")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_and_select() {
+        let run =
+            |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input).unwrap();
+        assert_eq!(
+            run(".[] | not", json!([null, false, true, 0, ""])),
+            [
+                json!(true),
+                json!(true),
+                json!(false),
+                json!(false),
+                json!(false)
+            ]
+        );
+        let items = json!([
+            {"status": "active", "count": 5},
+            {"status": "active", "count": 2},
+            {"status": "stale", "count": 9}
+        ]);
+        assert_eq!(
+            run(
+                ".[] | select(.status == \"active\" and .count > 3)",
+                items.clone()
+            ),
+            [json!({"status": "active", "count": 5})]
+        );
+        assert_eq!(
+            run(".[] | select(.count > 3 | not) | .count", items),
+            [json!(2)]
+        );
+        assert_eq!(
+            run("select(true, 1, null)", json!("x")),
+            [json!("x"), json!("x")]
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        assert_eq!(run("map(. + 1)", json!([1, 2])), Ok(vec![json!([2, 3])]));
+        assert_eq!(
+            run("map(., .)", json!({"a": 1, "b": 2})),
+            Ok(vec![json!([1, 1, 2, 2])])
+        );
+        assert_eq!(
+            run("map(select(. > 1))", json!([1, 2])),
+            Ok(vec![json!([2])])
+        );
+        assert_eq!(
+            run("map(.)", json!(1)),
+            Err(FilterError::CannotIterate("number"))
+        );
+    }
+
+    #[test]
+    fn test_min_and_max() {
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        assert_eq!(
+            run("min, max", json!([3, 1.5, 7, 2])),
+            Ok(vec![json!(1.5), json!(7)])
+        );
+        assert_eq!(
+            run("min, max", json!([])),
+            Ok(vec![Value::Null, Value::Null])
+        );
+        assert_eq!(
+            run("min, max", json!(["b", null, [1], 2])),
+            Ok(vec![Value::Null, json!([1])])
+        );
+        assert_eq!(
+            run(
+                "[.[] | .latency] | max",
+                json!([{"latency": 20}, {"latency": 35}])
+            ),
+            Ok(vec![json!(35)])
+        );
+        assert!(run("min", json!({"a": 1})).is_err());
+
+        let users = json!([
+            {"name": "a", "age": 30},
+            {"name": "b", "age": 20},
+            {"name": "c", "age": 30},
+            {"name": "d", "age": 20}
+        ]);
+        assert_eq!(
+            run("min_by(.age), max_by(.age) | .name", users.clone()),
+            Ok(vec![json!("b"), json!("c")])
+        );
+        assert_eq!(
+            run("max_by(.name | length) | .name", users),
+            Ok(vec![json!("d")])
+        );
+        assert_eq!(run("min_by(.age)", json!([])), Ok(vec![Value::Null]));
+        assert!(run("max_by(.age)", json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_flatten() {
+        let nested = json!([1, [2, [3, [4]]], [], "a"]);
+        let all = Value::from(u32::MAX);
+        assert_eq!(
+            flatten_function(&nested, &all),
+            Ok(json!([1, 2, 3, 4, "a"]))
+        );
+        assert_eq!(
+            flatten_function(&nested, &json!(1)),
+            Ok(json!([1, 2, [3, [4]], "a"]))
+        );
+        assert_eq!(
+            flatten_function(&nested, &json!(2)),
+            Ok(json!([1, 2, 3, [4], "a"]))
+        );
+        assert_eq!(flatten_function(&nested, &json!(0)), Ok(nested.clone()));
+        assert_eq!(flatten_function(&json!([]), &all), Ok(json!([])));
+        assert!(flatten_function(&nested, &json!(-1)).is_err());
+        assert!(flatten_function(&nested, &json!("1")).is_err());
+        assert!(flatten_function(&json!({"a": [1]}), &all).is_err());
+
+        let run = |filter: &str| evaluate_all(&parse(filter).unwrap(), &nested);
+        assert_eq!(run("flatten | length"), Ok(vec![json!(5)]));
+        assert_eq!(run("flatten(0, 2) | length"), Ok(vec![json!(4), json!(5)]));
+    }
+
+    #[test]
+    fn test_reverse() {
+        assert_eq!(
+            reverse_function(&json!([1, [2, 3], "a"])),
+            Ok(json!(["a", [2, 3], 1]))
+        );
+        assert_eq!(reverse_function(&json!("héllo")), Ok(json!("olléh")));
+        assert_eq!(reverse_function(&json!([])), Ok(json!([])));
+        assert_eq!(reverse_function(&Value::Null), Ok(json!([])));
+        assert_eq!(
+            reverse_function(&json!({"a": 1})),
+            Err(FilterError::invalid_input("reverse", &json!({"a": 1})))
+        );
+        let items = json!({"items": [1, 2, 3]});
+        assert_eq!(
+            evaluate_all(&parse(".items | reverse | .[0]").unwrap(), &items),
+            Ok(vec![json!(3)])
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let input = json!({"name": "foobar", "tags": ["a", "b"], "n": {"x": 1, "y": [1, 2]}});
+        let contains = |element: Value| contains_function(&input, &element);
+        assert_eq!(contains(json!({"name": "bar"})), Ok(json!(true)));
+        assert_eq!(contains(json!({"tags": ["b"]})), Ok(json!(true)));
+        assert_eq!(contains(json!({"n": {"y": [2]}})), Ok(json!(true)));
+        assert_eq!(contains(json!({})), Ok(json!(true)));
+        assert_eq!(contains(json!({"tags": ["c"]})), Ok(json!(false)));
+        assert_eq!(contains(json!({"missing": null})), Ok(json!(false)));
+        assert_eq!(
+            contains_function(&json!(["foobar", "baz"]), &json!(["bar", "baz"])),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            contains_function(&json!([1, 2]), &json!([])),
+            Ok(json!(true))
+        );
+        assert_eq!(contains_function(&json!(1), &json!(1.0)), Ok(json!(true)));
+        assert_eq!(
+            contains_function(&Value::Null, &Value::Null),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            contains_function(&json!("a"), &json!(["a"])),
+            Err(FilterError::cannot_combine(
+                "checked for containment",
+                &json!("a"),
+                &json!(["a"])
+            ))
+        );
+        assert!(contains(json!({"name": 1})).is_err());
+
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        assert_eq!(
+            run("inside(\"foobar\")", json!("bar")),
+            Ok(vec![json!(true)])
+        );
+        assert_eq!(
+            run("inside([\"foobar\", \"x\"])", json!(["bar", "y"])),
+            Ok(vec![json!(false)])
+        );
+        assert_eq!(
+            run(".[] | contains(\"b\")", json!(["abc", "xyz"])),
+            Ok(vec![json!(true), json!(false)])
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let path = env::var("PATH").map_or(Value::Null, Value::String);
+        assert_eq!(env_function()["PATH"], path);
+        assert_eq!(
+            evaluate_all(&parse("env.PATH, $ENV.PATH").unwrap(), &Value::Null),
+            Ok(vec![path.clone(), path])
+        );
+        // `$ENV` can be shadowed like any other variable
+        assert_eq!(
+            evaluate_all(&parse("reduce 1 as $ENV (0; $ENV)").unwrap(), &Value::Null),
+            Ok(vec![json!(1)])
+        );
+    }
+
+    #[test]
+    fn test_inputs() {
+        let inputs =
+            SharedInputs::new(read_inputs("1 [2] 3 4".as_bytes(), &InputConfig::default()));
+        let run = |filter: &str, inputs: &SharedInputs| {
+            collect(evaluate(
+                &parse(filter).unwrap(),
+                &Rc::new(Value::Null),
+                inputs,
+            ))
+        };
+        assert_eq!(run("input", &inputs), Ok(vec![json!(1)]));
+        assert_eq!(run("[input, input]", &inputs), Ok(vec![json!([[2], 3])]));
+        assert_eq!(run("[inputs]", &inputs), Ok(vec![json!([4])]));
+        assert_eq!(run("[inputs]", &inputs), Ok(vec![json!([])]));
+        assert_eq!(run("input", &inputs), Err(FilterError::NoMoreInputs));
+        assert_eq!(
+            run("try input catch .", &inputs),
+            Ok(vec![json!("No more inputs")])
+        );
+
+        let inputs = SharedInputs::new(read_inputs("1 {oops".as_bytes(), &InputConfig::default()));
+        assert!(matches!(
+            run("[inputs]", &inputs),
+            Err(FilterError::ReadInput(_))
+        ));
+
+        // Only as many inputs are read as are used
+        let inputs = SharedInputs::new(read_inputs("1 2 3".as_bytes(), &InputConfig::default()));
+        assert_eq!(run("first(inputs)", &inputs), Ok(vec![json!(1)]));
+        assert_eq!(run("[limit(1; inputs)]", &inputs), Ok(vec![json!([2])]));
+        // Builtins that run their argument on each value still see the input
+        assert_eq!(run("[[0] | map(input)]", &inputs), Ok(vec![json!([[3]])]));
+
+        // Filters run with different inputs don't share them
+        let other = SharedInputs::new(read_inputs("5".as_bytes(), &InputConfig::default()));
+        assert_eq!(run("input", &other), Ok(vec![json!(5)]));
+        assert_eq!(run("input", &inputs), Err(FilterError::NoMoreInputs));
+        assert_eq!(
+            run("input", &SharedInputs::default()),
+            Err(FilterError::NoMoreInputs)
+        );
+    }
+
+    #[test]
+    fn test_join_and_split() {
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        assert_eq!(
+            run("split(\",\") | join(\"; \")", json!("a,b,c")),
+            Ok(vec![json!("a; b; c")])
+        );
+        assert_eq!(
+            run("join(\",\", \"\")", json!(["a", "b"])),
+            Ok(vec![json!("a,b"), json!("ab")])
+        );
+        assert_eq!(
+            run(
+                "[.[] | .name] | join(\", \")",
+                json!([{"name": "Leo"}, {"name": "Max"}])
+            ),
+            Ok(vec![json!("Leo, Max")])
+        );
+    }
+
+    #[test]
+    fn test_regexes() {
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        let lines = json!(["GET /index 200", "POST /login 500", "oops"]);
+        assert_eq!(
+            run(".[] | select(test(\" 5\\\\d\\\\d$\"))", lines.clone()),
+            Ok(vec![json!("POST /login 500")])
+        );
+        assert_eq!(
+            run(".[] | match(\"/\\\\w+\") | .string", lines.clone()),
+            Ok(vec![json!("/index"), json!("/login")])
+        );
+        assert_eq!(
+            run(
+                ".[] | capture(\"(?<method>[A-Z]+) (?<path>\\\\S+)\") | .method",
+                lines
+            ),
+            Ok(vec![json!("GET"), json!("POST")])
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let run = |filter: &str, input: Value| evaluate_all(&parse(filter).unwrap(), &input);
+        let item = json!({"id": "42", "count": 3});
+        assert_eq!(run(".id | tonumber + 1", item.clone()), Ok(vec![json!(43)]));
+        assert_eq!(
+            run("\"count: \" + (.count | tostring)", item.clone()),
+            Ok(vec![json!("count: 3")])
+        );
+        assert_eq!(
+            run(".count | tostring | tonumber", item),
+            Ok(vec![json!(3)])
+        );
+    }
+
+    #[test]
+    fn test_unique() {
+        assert_eq!(
+            unique_function(&json!([3, 1, "a", 1.0, null, 3, [1], "a"])),
+            Ok(json!([null, 1, 3, "a", [1]]))
+        );
+        assert_eq!(unique_function(&json!([])), Ok(json!([])));
+        assert_eq!(
+            unique_function(&json!({"a": 1})),
+            Err(FilterError::invalid_input("unique", &json!({"a": 1})))
+        );
+    }
+
+    #[test]
+    fn test_unique_by() {
+        let run = |filter: &str, input: &Value| evaluate_all(&parse(filter).unwrap(), input);
+        let logs = json!([
+            {"level": "warn", "id": 1},
+            {"level": "error", "id": 2},
+            {"level": "warn", "id": 3},
+            {"id": 4}
+        ]);
+        assert_eq!(
+            run("unique_by(.level)", &logs),
+            Ok(vec![json!([
+                {"id": 4},
+                {"level": "error", "id": 2},
+                {"level": "warn", "id": 1}
+            ])])
+        );
+        assert_eq!(
+            run("unique_by(length)", &json!(["ab", "c", "de", "f"])),
+            Ok(vec![json!(["c", "ab"])])
+        );
+        assert!(run("unique_by(.level)", &json!("warn")).is_err());
+        assert!(run("unique_by(.[0])", &logs).is_err());
+    }
+
+    #[test]
+    fn test_compare_values() {
+        let ordered = [
+            json!(null),
+            json!(false),
+            json!(true),
+            json!(-1),
+            json!(0.5),
+            json!(2),
+            json!("a"),
+            json!("b"),
+            json!([]),
+            json!([1]),
+            json!([1, 0]),
+            json!([2]),
+            json!({}),
+            json!({"a": 2}),
+            json!({"a": 1, "b": 0}),
+            json!({"b": 0}),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(compare_values(a, b), i.cmp(&j), "{} vs {}", a, b);
+            }
+        }
+    }
+}
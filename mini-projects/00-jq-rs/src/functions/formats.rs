@@ -0,0 +1,171 @@
+//! The `@format` builtins, like `@csv` and `@base64`, which turn their
+//! input into a string.
+
+use serde_json::Value;
+
+use crate::error::FilterError;
+
+/// `@csv`: an array as a line of comma-separated values. Strings are
+/// quoted, with quotes inside them doubled, and `null` is left empty.
+pub fn csv_function(input: &Value) -> Result<Value, FilterError> {
+    row("@csv", input, ",", |string| {
+        format!("\"{}\"", string.replace('"', "\"\""))
+    })
+}
+
+/// `@tsv`: an array as a line of tab-separated values. Tabs, newlines,
+/// and backslashes inside strings are escaped with a backslash.
+pub fn tsv_function(input: &Value) -> Result<Value, FilterError> {
+    row("@tsv", input, "\t", |string| {
+        string
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    })
+}
+
+/// The elements of an array joined by `separator`, with strings written by
+/// `quote`. Arrays and objects can't be part of a row.
+fn row(
+    function: &'static str,
+    input: &Value,
+    separator: &str,
+    quote: fn(&str) -> String,
+) -> Result<Value, FilterError> {
+    let Value::Array(values) = input else {
+        return Err(FilterError::invalid_input(function, input));
+    };
+    let fields = values
+        .iter()
+        .map(|value| match value {
+            Value::Null => Ok(String::new()),
+            Value::String(string) => Ok(quote(string)),
+            Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+            _ => Err(FilterError::invalid_input(function, value)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::String(fields.join(separator)))
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `@base64`: a string encoded as base64. Anything else is turned into a
+/// string first, like `tostring`.
+pub fn base64_function(input: &Value) -> Value {
+    let text = text(input);
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0, |bits, (index, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (bits >> (18 - 6 * index)) & 0x3f;
+                encoded.push(char::from(BASE64_ALPHABET[sextet as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    Value::String(encoded)
+}
+
+/// `@base64d`: a base64 string decoded, with the padding at the end
+/// optional. Bytes that aren't valid UTF-8 are replaced.
+pub fn base64d_function(input: &Value) -> Result<Value, FilterError> {
+    let text = text(input);
+    let mut decoded = Vec::new();
+    let (mut bits, mut len) = (0_u32, 0);
+    for byte in text.trim_end_matches('=').bytes() {
+        let sextet = BASE64_ALPHABET
+            .iter()
+            .position(|&c| c == byte)
+            .ok_or_else(|| FilterError::invalid_input("@base64d", input))?;
+        bits = bits << 6 | sextet as u32;
+        len += 6;
+        if len >= 8 {
+            len -= 8;
+            decoded.push((bits >> len) as u8);
+        }
+    }
+    Ok(Value::String(
+        String::from_utf8_lossy(&decoded).into_owned(),
+    ))
+}
+
+/// The text a format works on: a string itself, or anything else as JSON.
+fn text(input: &Value) -> String {
+    match input {
+        Value::String(string) => string.clone(),
+        _ => input.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{base64_function, base64d_function, csv_function, tsv_function};
+    use crate::error::FilterError;
+    use crate::filters::{evaluate_all, parse};
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            csv_function(&json!([1, "a \"quote\"", null, true, 2.5])),
+            Ok(json!("1,\"a \"\"quote\"\"\",,true,2.5"))
+        );
+        assert_eq!(csv_function(&json!([])), Ok(json!("")));
+        assert_eq!(
+            csv_function(&json!([[1]])),
+            Err(FilterError::invalid_input("@csv", &json!([1])))
+        );
+        assert!(csv_function(&json!("a")).is_err());
+    }
+
+    #[test]
+    fn test_tsv() {
+        assert_eq!(
+            tsv_function(&json!(["a\tb", "c\\d\ne", 1, null])),
+            Ok(json!("a\\tb\tc\\\\d\\ne\t1\t"))
+        );
+        assert!(tsv_function(&json!([{"a": 1}])).is_err());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_function(&json!("")), json!(""));
+        assert_eq!(base64_function(&json!("f")), json!("Zg=="));
+        assert_eq!(base64_function(&json!("fo")), json!("Zm8="));
+        assert_eq!(base64_function(&json!("foo")), json!("Zm9v"));
+        assert_eq!(base64_function(&json!("héllo?")), json!("aMOpbGxvPw=="));
+        assert_eq!(base64_function(&json!([1])), json!("WzFd"));
+    }
+
+    #[test]
+    fn test_base64d() {
+        assert_eq!(base64d_function(&json!("Zm9v")), Ok(json!("foo")));
+        assert_eq!(base64d_function(&json!("Zg==")), Ok(json!("f")));
+        assert_eq!(base64d_function(&json!("Zm8")), Ok(json!("fo")));
+        assert_eq!(
+            base64d_function(&json!("aMOpbGxvPw==")),
+            Ok(json!("héllo?"))
+        );
+        assert_eq!(base64d_function(&json!("")), Ok(json!("")));
+        assert_eq!(
+            base64d_function(&json!("Zm9v!")),
+            Err(FilterError::invalid_input("@base64d", &json!("Zm9v!")))
+        );
+    }
+
+    #[test]
+    fn test_format_filters() {
+        let run = |filter: &str| evaluate_all(&parse(filter).unwrap(), &json!(["a", 1]));
+        assert_eq!(run("@csv"), Ok(vec![json!("\"a\",1")]));
+        assert_eq!(run("@tsv"), Ok(vec![json!("a\t1")]));
+        assert_eq!(run("@json"), Ok(vec![json!("[\"a\",1]")]));
+        assert_eq!(run(".[0] | @base64 | @base64d"), Ok(vec![json!("a")]));
+    }
+}
@@ -0,0 +1,128 @@
+//! The math builtins, like `floor` and `pow`.
+
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use crate::error::FilterError;
+use crate::filters::{self, each, number, single, Filter, Outputs};
+use crate::input::SharedInputs;
+
+/// The input of a math builtin called `function` as a float.
+fn float(function: &'static str, input: &Value) -> Result<f64, FilterError> {
+    input
+        .as_f64()
+        .ok_or_else(|| FilterError::invalid_input(function, input))
+}
+
+/// Rounds a number with `round`. Integers are already whole, so they're
+/// left alone rather than losing precision as floats.
+fn rounded(
+    function: &'static str,
+    input: &Value,
+    round: fn(f64) -> f64,
+) -> Result<Value, FilterError> {
+    match input {
+        Value::Number(n) if n.is_i64() || n.is_u64() => Ok(input.clone()),
+        _ => Ok(number(round(float(function, input)?))),
+    }
+}
+
+/// `floor`: the largest whole number that isn't more than the input.
+pub fn floor_function(input: &Value) -> Result<Value, FilterError> {
+    rounded("floor", input, f64::floor)
+}
+
+/// `ceil`: the smallest whole number that isn't less than the input.
+pub fn ceil_function(input: &Value) -> Result<Value, FilterError> {
+    rounded("ceil", input, f64::ceil)
+}
+
+/// `round`: the nearest whole number, with halves rounded away from zero.
+pub fn round_function(input: &Value) -> Result<Value, FilterError> {
+    rounded("round", input, f64::round)
+}
+
+/// `sqrt`: the square root of a number, or `null` for a negative one.
+pub fn sqrt_function(input: &Value) -> Result<Value, FilterError> {
+    Ok(number(float("sqrt", input)?.sqrt()))
+}
+
+/// `fabs`: the absolute value of a number.
+pub fn fabs_function(input: &Value) -> Result<Value, FilterError> {
+    Ok(number(float("fabs", input)?.abs()))
+}
+
+/// `pow(base; exponent)`: `base` raised to `exponent`, for every pair of
+/// their outputs.
+pub fn pow_function(args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    let exponent = args[1].clone();
+    let input = input.clone();
+    let inputs = inputs.clone();
+    each(filters::evaluate(&args[0], &input, &inputs), move |base| {
+        let base = match float("pow", &base) {
+            Ok(base) => base,
+            Err(err) => return single(Err(err)),
+        };
+        Box::new(
+            filters::evaluate(&exponent, &input, &inputs)
+                .map(move |exponent| Ok(Rc::new(number(base.powf(float("pow", &*exponent?)?))))),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{ceil_function, fabs_function, floor_function, round_function, sqrt_function};
+    use crate::error::FilterError;
+    use crate::filters::{evaluate_all, parse};
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(floor_function(&json!(2.7)), Ok(json!(2)));
+        assert_eq!(floor_function(&json!(-2.2)), Ok(json!(-3)));
+        assert_eq!(ceil_function(&json!(2.2)), Ok(json!(3)));
+        assert_eq!(ceil_function(&json!(-2.7)), Ok(json!(-2)));
+        assert_eq!(round_function(&json!(2.5)), Ok(json!(3)));
+        assert_eq!(round_function(&json!(-2.5)), Ok(json!(-3)));
+        assert_eq!(round_function(&json!(2.4)), Ok(json!(2)));
+        // Big integers don't lose precision
+        assert_eq!(
+            floor_function(&json!(9007199254740993_i64)),
+            Ok(json!(9007199254740993_i64))
+        );
+        assert_eq!(
+            floor_function(&json!("1.5")),
+            Err(FilterError::invalid_input("floor", &json!("1.5")))
+        );
+        assert!(round_function(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_sqrt_and_fabs() {
+        assert_eq!(sqrt_function(&json!(16)), Ok(json!(4)));
+        assert_eq!(sqrt_function(&json!(2)), Ok(json!(2_f64.sqrt())));
+        assert_eq!(sqrt_function(&json!(-1)), Ok(Value::Null));
+        assert_eq!(fabs_function(&json!(-2.5)), Ok(json!(2.5)));
+        assert_eq!(fabs_function(&json!(3)), Ok(json!(3)));
+        assert!(sqrt_function(&json!([4])).is_err());
+    }
+
+    #[test]
+    fn test_pow() {
+        let run = |filter: &str| evaluate_all(&parse(filter).unwrap(), &json!({"n": 3}));
+        assert_eq!(run("pow(2; .n)"), Ok(vec![json!(8)]));
+        assert_eq!(
+            run("pow(.n; 0.5, -1)"),
+            Ok(vec![json!(3_f64.sqrt()), json!(1.0 / 3.0)])
+        );
+        assert_eq!(run("pow(2, 3; 2)"), Ok(vec![json!(4), json!(9)]));
+        assert_eq!(run("pow(0; -1)"), Ok(vec![json!(f64::MAX)]));
+        assert_eq!(
+            run("pow(\"2\"; 2)"),
+            Err(FilterError::invalid_input("pow", &json!("2")))
+        );
+    }
+}
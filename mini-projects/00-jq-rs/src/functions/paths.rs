@@ -0,0 +1,451 @@
+//! The builtins that work with paths, like `paths`, `getpath`, and `del`.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use serde_json::{Map, Value};
+
+use super::compare_values;
+use crate::error::{type_name, FilterError};
+use crate::filters::{
+    self, as_index, each, get_path, is_truthy, resolve_index, single, slice_range, try_get_path,
+    Filter, Outputs, Path,
+};
+use crate::input::SharedInputs;
+
+/// `paths` and `paths(condition)`: the path to every value inside the
+/// input, as an array, in the same order as `..`. With a condition, only
+/// the paths to values it's true for.
+pub fn paths_function(input: &Value, condition: Option<&Filter>, inputs: &SharedInputs) -> Outputs {
+    let paths = match inner_paths(input) {
+        Ok(paths) => paths,
+        Err(err) => return single(Err(err)),
+    };
+    let Some(condition) = condition else {
+        return Box::new(
+            paths
+                .into_iter()
+                .map(|path| Ok(Rc::new(Value::Array(path)))),
+        );
+    };
+    let condition = condition.clone();
+    let input = input.clone();
+    let inputs = inputs.clone();
+    Box::new(paths.into_iter().flat_map(move |path| {
+        let outputs = filters::evaluate(&condition, &Rc::new(get_path(&input, &path)), &inputs);
+        outputs.filter_map(move |output| match output {
+            Ok(output) => is_truthy(&output).then(|| Ok(Rc::new(Value::Array(path.clone())))),
+            Err(err) => Some(Err(err)),
+        })
+    }))
+}
+
+/// `leaf_paths`: the paths to every value inside the input that isn't an
+/// array or an object.
+pub fn leaf_paths_function(input: &Value) -> Outputs {
+    let paths = match inner_paths(input) {
+        Ok(paths) => paths,
+        Err(err) => return single(Err(err)),
+    };
+    let leaves: Vec<_> = paths
+        .into_iter()
+        .filter(|path| !matches!(get_path(input, path), Value::Array(_) | Value::Object(_)))
+        .map(|path| Ok(Rc::new(Value::Array(path))))
+        .collect();
+    Box::new(leaves.into_iter())
+}
+
+/// The path to every value inside `input`, leaving out the empty path to
+/// `input` itself.
+fn inner_paths(input: &Value) -> Result<Vec<Path>, FilterError> {
+    Ok(filters::all_paths(input).split_off(1))
+}
+
+/// `getpath(path)`: the value at a path given as an array, like
+/// `["a", 0]`, or `null` if there isn't one.
+pub fn getpath_function(input: &Value, path: &Value) -> Result<Value, FilterError> {
+    match path {
+        Value::Array(path) => try_get_path(input, path),
+        _ => Err(FilterError::invalid_input("getpath", path)),
+    }
+}
+
+/// `setpath(path; value)`: the input with `value` at `path`, once for each
+/// output of both.
+pub fn setpath_function(args: &[Filter], input: &Rc<Value>, inputs: &SharedInputs) -> Outputs {
+    let path = args[0].clone();
+    let input = input.clone();
+    let inputs = inputs.clone();
+    each(filters::evaluate(&args[1], &input, &inputs), move |value| {
+        let input = input.clone();
+        each(filters::evaluate(&path, &input, &inputs), move |path| {
+            let Value::Array(path) = &*path else {
+                return single(Err(FilterError::invalid_input("setpath", &path)));
+            };
+            let mut output = Value::clone(&input);
+            single(set_path(&mut output, path, Value::clone(&value)).map(|()| output))
+        })
+    })
+}
+
+/// Puts `new` at `path` inside `value`, creating any objects and arrays
+/// that are missing along the way. Arrays are padded with `null` to reach
+/// an index past their end.
+pub(crate) fn set_path(value: &mut Value, path: &[Value], new: Value) -> Result<(), FilterError> {
+    let Some((step, rest)) = path.split_first() else {
+        *value = new;
+        return Ok(());
+    };
+    if value.is_null() {
+        *value = match step {
+            Value::String(_) => Value::Object(Map::new()),
+            _ => Value::Array(Vec::new()),
+        };
+    }
+    match (value, step) {
+        (Value::Object(map), Value::String(key)) => {
+            set_path(map.entry(key.clone()).or_insert(Value::Null), rest, new)
+        }
+        (Value::Array(values), Value::Number(index)) => {
+            let index =
+                resolve_index(as_index(index), values.len()).ok_or(FilterError::OutOfBounds)?;
+            if index >= values.len() {
+                values.resize(index + 1, Value::Null);
+            }
+            set_path(&mut values[index], rest, new)
+        }
+        (Value::Array(values), Value::Object(bounds)) => {
+            let bound = |name| bounds.get(name).cloned().unwrap_or(Value::Null);
+            let (start, end) = slice_range(&bound("start"), &bound("end"), values.len())?;
+            let mut slice = Value::Array(values[start..end].to_vec());
+            set_path(&mut slice, rest, new)?;
+            // A slice can only be replaced by another array
+            let Value::Array(replacement) = slice else {
+                return Err(FilterError::invalid_input("setpath", &slice));
+            };
+            values.splice(start..end, replacement);
+            Ok(())
+        }
+        (value, step) => Err(FilterError::CannotIndex {
+            value_type: type_name(value),
+            index: step.to_string(),
+        }),
+    }
+}
+
+/// `del(path)`: `input` without the values `path` picks out, like `.key`,
+/// `.[1, 2]`, or `.[] | .name`.
+pub fn delete_function(
+    input: &Rc<Value>,
+    path: &Filter,
+    inputs: &SharedInputs,
+) -> Result<Value, FilterError> {
+    Ok(delete_paths(input, filters::paths(path, input, inputs)?))
+}
+
+/// `delpaths(paths)`: `input` without the values at each of an array of
+/// paths.
+pub fn delpaths_function(input: &Value, paths: &Value) -> Result<Value, FilterError> {
+    let Value::Array(paths) = paths else {
+        return Err(FilterError::invalid_input("delpaths", paths));
+    };
+    let paths = paths
+        .iter()
+        .map(|path| match path {
+            Value::Array(path) => Ok(path.clone()),
+            _ => Err(FilterError::invalid_input("delpaths", path)),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(delete_paths(input, paths))
+}
+
+pub(crate) fn delete_paths(input: &Value, mut paths: Vec<Path>) -> Value {
+    // Delete from the back, so deleting one element doesn't move the others
+    paths.sort_by(|a, b| compare_paths(b, a));
+    paths.dedup();
+
+    let mut output = input.clone();
+    for path in &paths {
+        delete_path(&mut output, path);
+    }
+    output
+}
+
+fn compare_paths(a: &Path, b: &Path) -> Ordering {
+    compare_values(&Value::Array(a.clone()), &Value::Array(b.clone()))
+}
+
+/// Removes the value at `path`, if there is one.
+fn delete_path(value: &mut Value, path: &[Value]) {
+    let Some((last, parent_path)) = path.split_last() else {
+        // del(.) leaves nothing behind
+        *value = Value::Null;
+        return;
+    };
+    let Some(parent) = get_path_mut(value, parent_path) else {
+        return;
+    };
+    match (parent, last) {
+        (Value::Object(map), Value::String(key)) => {
+            map.shift_remove(key);
+        }
+        (Value::Array(values), Value::Number(index)) => {
+            if let Some(index) = resolve_index(as_index(index), values.len()) {
+                if index < values.len() {
+                    values.remove(index);
+                }
+            }
+        }
+        (Value::Array(values), Value::Object(bounds)) => {
+            let bound = |name| bounds.get(name).cloned().unwrap_or(Value::Null);
+            if let Ok((start, end)) = slice_range(&bound("start"), &bound("end"), values.len()) {
+                values.drain(start..end);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn get_path_mut<'a>(value: &'a mut Value, path: &[Value]) -> Option<&'a mut Value> {
+    path.iter()
+        .try_fold(value, |value, step| match (value, step) {
+            (Value::Object(map), Value::String(key)) => map.get_mut(key),
+            (Value::Array(values), Value::Number(index)) => {
+                let index = resolve_index(as_index(index), values.len())?;
+                values.get_mut(index)
+            }
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{
+        delpaths_function, getpath_function, leaf_paths_function, paths_function, set_path,
+    };
+    use crate::error::FilterError;
+    use crate::filters::{collect, evaluate_all, parse};
+    use crate::input::SharedInputs;
+
+    fn document() -> Value {
+        json!({"a": [1, {"b": null}], "c": "d"})
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(
+            collect(paths_function(&document(), None, &SharedInputs::default())),
+            Ok(vec![
+                json!(["a"]),
+                json!(["a", 0]),
+                json!(["a", 1]),
+                json!(["a", 1, "b"]),
+                json!(["c"])
+            ])
+        );
+        assert_eq!(
+            collect(paths_function(&json!(1), None, &SharedInputs::default())),
+            Ok(vec![])
+        );
+        assert_eq!(
+            collect(paths_function(&json!([]), None, &SharedInputs::default())),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_paths_with_condition() {
+        let condition = parse("type == \"array\" or . == \"d\"").unwrap();
+        assert_eq!(
+            collect(paths_function(
+                &document(),
+                Some(&condition),
+                &SharedInputs::default()
+            )),
+            Ok(vec![json!(["a"]), json!(["c"])])
+        );
+        let failing = parse(".[0]").unwrap();
+        assert!(
+            paths_function(&document(), Some(&failing), &SharedInputs::default())
+                .any(|path| path.is_err())
+        );
+    }
+
+    #[test]
+    fn test_leaf_paths() {
+        assert_eq!(
+            collect(leaf_paths_function(&document())),
+            Ok(vec![json!(["a", 0]), json!(["a", 1, "b"]), json!(["c"])])
+        );
+        assert_eq!(collect(leaf_paths_function(&json!({}))), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_getpath() {
+        let input = document();
+        assert_eq!(
+            getpath_function(&input, &json!(["a", 1])),
+            Ok(json!({"b": null}))
+        );
+        assert_eq!(getpath_function(&input, &json!(["a", -2])), Ok(json!(1)));
+        assert_eq!(getpath_function(&input, &json!([])), Ok(input.clone()));
+        assert_eq!(
+            getpath_function(&input, &json!(["x", "y", 0])),
+            Ok(Value::Null)
+        );
+        assert_eq!(
+            getpath_function(&input, &json!(["a", {"start": 1, "end": null}])),
+            Ok(json!([{"b": null}]))
+        );
+        assert_eq!(
+            getpath_function(&input, &json!(["c", "d"])),
+            Err(FilterError::CannotIndex {
+                value_type: "string",
+                index: String::from("\"d\"")
+            })
+        );
+        assert!(getpath_function(&input, &json!("a")).is_err());
+    }
+
+    fn setpath(input: Value, path: &str, value: &str) -> Result<Value, FilterError> {
+        let setpath = parse(&format!("setpath({}; {})", path, value)).unwrap();
+        evaluate_all(&setpath, &input).map(|mut outputs| outputs.remove(0))
+    }
+
+    #[test]
+    fn test_setpath() {
+        assert_eq!(
+            setpath(document(), "[\"a\", 0]", "5"),
+            Ok(json!({"a": [5, {"b": null}], "c": "d"}))
+        );
+        assert_eq!(
+            setpath(document(), "[\"a\", 1, \"b\"]", ".c"),
+            Ok(json!({"a": [1, {"b": "d"}], "c": "d"}))
+        );
+        assert_eq!(setpath(json!(1), "[]", "2"), Ok(json!(2)));
+        assert_eq!(
+            setpath(Value::Null, "[\"x\", 2, \"y\"]", "true"),
+            Ok(json!({"x": [null, null, {"y": true}]}))
+        );
+        assert_eq!(setpath(json!([1, 2]), "[-1]", "3"), Ok(json!([1, 3])));
+        let mut slice = json!([1, 2, 3]);
+        let path = [json!({"start": 1, "end": 2})];
+        assert_eq!(set_path(&mut slice, &path, json!(["x", "y"])), Ok(()));
+        assert_eq!(slice, json!([1, "x", "y", 3]));
+        assert_eq!(
+            setpath(json!([1]), "[-2]", "0"),
+            Err(FilterError::OutOfBounds)
+        );
+        assert!(setpath(json!([1]), "[\"a\"]", "0").is_err());
+        assert!(setpath(json!([1]), "\"a\"", "0").is_err());
+    }
+
+    #[test]
+    fn test_delpaths() {
+        assert_eq!(
+            delpaths_function(&document(), &json!([["a", 0], ["c"], ["missing"]])),
+            Ok(json!({"a": [{"b": null}]}))
+        );
+        assert_eq!(
+            delpaths_function(&json!([1, 2, 3]), &json!([[0], [2]])),
+            Ok(json!([2]))
+        );
+        assert_eq!(
+            delpaths_function(&document(), &json!([[]])),
+            Ok(Value::Null)
+        );
+        assert_eq!(delpaths_function(&document(), &json!([])), Ok(document()));
+        assert!(delpaths_function(&document(), &json!(["a"])).is_err());
+        assert!(delpaths_function(&document(), &json!({})).is_err());
+    }
+
+    fn delete(input: &Value, path: &str) -> Result<Value, FilterError> {
+        let del = parse(&format!("del({})", path)).unwrap();
+        evaluate_all(&del, input).map(|mut outputs| outputs.remove(0))
+    }
+
+    #[test]
+    fn test_delete_key() {
+        let input = json!({"fizz": "buzz", "baz": null, "fizzes": [1, 2]});
+        assert_eq!(
+            delete(&input, ".fizzes"),
+            Ok(json!({"fizz": "buzz", "baz": null}))
+        );
+        assert_eq!(
+            delete(&input, ".[\"fizz\"]"),
+            Ok(json!({"baz": null, "fizzes": [1, 2]}))
+        );
+        assert_eq!(delete(&input, ".missing"), Ok(input.clone()));
+        assert_eq!(delete(&input, ".fizz, .baz"), Ok(json!({"fizzes": [1, 2]})));
+        assert_eq!(delete(&input, "."), Ok(Value::Null));
+        assert!(delete(&json!([1]), ".fizz").is_err());
+        assert_eq!(
+            delete(&input, "1"),
+            Err(FilterError::InvalidPath(String::from("number (1)")))
+        );
+    }
+
+    #[test]
+    fn test_delete_indexes() {
+        let input = json!(["a", "b", "c", "d"]);
+        assert_eq!(delete(&input, ".[0]"), Ok(json!(["b", "c", "d"])));
+        assert_eq!(delete(&input, ".[1, 3]"), Ok(json!(["a", "c"])));
+        assert_eq!(delete(&input, ".[3, 1]"), Ok(json!(["a", "c"])));
+        assert_eq!(delete(&input, ".[-1]"), Ok(json!(["a", "b", "c"])));
+        assert_eq!(delete(&input, ".[10]"), Ok(input.clone()));
+        assert_eq!(delete(&input, ".[1:3]"), Ok(json!(["a", "d"])));
+        assert_eq!(delete(&input, ".[]"), Ok(json!([])));
+        assert!(delete(&json!({"a": 1}), ".[0]").is_err());
+    }
+
+    #[test]
+    fn test_delete_nested() {
+        let input = json!({"a": {"b": [1, 2], "c": 3}});
+        assert_eq!(delete(&input, ".a | .b"), Ok(json!({"a": {"c": 3}})));
+        assert_eq!(
+            delete(&input, ".a.b[1]"),
+            Ok(json!({"a": {"b": [1], "c": 3}}))
+        );
+        assert_eq!(delete(&input, ".. | .b?"), Ok(json!({"a": {"c": 3}})));
+        assert_eq!(
+            delete(&input, ".a | .b | .[0]"),
+            Ok(json!({"a": {"b": [2], "c": 3}}))
+        );
+    }
+
+    #[test]
+    fn test_delete_selected() {
+        let input = json!({"items": [
+            {"id": 1, "stale": true},
+            {"id": 2},
+            {"id": 3, "stale": true}
+        ]});
+        assert_eq!(
+            delete(&input, ".items[] | select(.stale)"),
+            Ok(json!({"items": [{"id": 2}]}))
+        );
+        assert_eq!(
+            delete(&input, ".items[] | select(.stale) | .stale"),
+            Ok(json!({"items": [{"id": 1}, {"id": 2}, {"id": 3}]}))
+        );
+        assert_eq!(
+            delete(&json!([1, 2, 3, 4, 5, 6]), ".[2:5], .[0]"),
+            Ok(json!([2, 6]))
+        );
+        assert_eq!(
+            delete(&json!([1, 2, 3]), "first(.[]), last(.[])"),
+            Ok(json!([2]))
+        );
+        assert_eq!(
+            delete(&input, "getpath([\"items\", 1], [\"items\", 0, \"id\"])"),
+            Ok(json!({"items": [{"stale": true}, {"id": 3, "stale": true}]}))
+        );
+        assert_eq!(
+            delete(&input, ".items | length"),
+            Err(FilterError::InvalidPath(String::from("number (3)")))
+        );
+        assert!(delete(&json!([1]), ".[] | select(error(\"no\"))").is_err());
+    }
+}
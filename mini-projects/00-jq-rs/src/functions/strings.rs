@@ -0,0 +1,468 @@
+//! The builtins that work on strings.
+
+use regex::{Captures, Regex};
+use serde_json::{json, Map, Number, Value};
+
+use crate::error::FilterError;
+use crate::filters;
+
+/// `join(separator)`: the elements of an array joined into one string, with
+/// `separator` between each of them. `null` becomes an empty string, and
+/// numbers and booleans are turned into strings.
+pub fn join_function(input: &Value, separator: &Value) -> Result<Value, FilterError> {
+    let Value::Array(values) = input else {
+        return Err(FilterError::invalid_input("join", input));
+    };
+    let Value::String(separator) = separator else {
+        return Err(FilterError::invalid_input("join", separator));
+    };
+    let parts = values
+        .iter()
+        .map(|value| match value {
+            Value::Null => Ok(String::new()),
+            Value::String(string) => Ok(string.clone()),
+            Value::Number(_) | Value::Bool(_) => Ok(value.to_string()),
+            _ => Err(FilterError::invalid_input("join", value)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::String(parts.join(separator)))
+}
+
+/// `split(separator)`: the parts of a string between each `separator`.
+pub fn split_function(input: &Value, separator: &Value) -> Result<Value, FilterError> {
+    match (input, separator) {
+        (Value::String(string), Value::String(separator)) => Ok(split(string, separator)),
+        (Value::String(_), _) => Err(FilterError::invalid_input("split", separator)),
+        _ => Err(FilterError::invalid_input("split", input)),
+    }
+}
+
+/// Splits `string` like jq: an empty string has no parts, and an empty
+/// separator splits it into characters.
+pub(crate) fn split(string: &str, separator: &str) -> Value {
+    if string.is_empty() {
+        return Value::Array(Vec::new());
+    }
+    let parts: Vec<&str> = if separator.is_empty() {
+        string
+            .char_indices()
+            .map(|(index, c)| &string[index..index + c.len_utf8()])
+            .collect()
+    } else {
+        string.split(separator).collect()
+    };
+    Value::from(parts)
+}
+
+/// `startswith(prefix)`: whether a string starts with `prefix`.
+pub fn startswith_function(input: &Value, prefix: &Value) -> Result<Value, FilterError> {
+    match (input, prefix) {
+        (Value::String(string), Value::String(prefix)) => {
+            Ok(Value::Bool(string.starts_with(&**prefix)))
+        }
+        (Value::String(_), _) => Err(FilterError::invalid_input("startswith", prefix)),
+        _ => Err(FilterError::invalid_input("startswith", input)),
+    }
+}
+
+/// `endswith(suffix)`: whether a string ends with `suffix`.
+pub fn endswith_function(input: &Value, suffix: &Value) -> Result<Value, FilterError> {
+    match (input, suffix) {
+        (Value::String(string), Value::String(suffix)) => {
+            Ok(Value::Bool(string.ends_with(&**suffix)))
+        }
+        (Value::String(_), _) => Err(FilterError::invalid_input("endswith", suffix)),
+        _ => Err(FilterError::invalid_input("endswith", input)),
+    }
+}
+
+/// `ltrimstr(prefix)`: a string without `prefix` at the start. Anything
+/// else, including a string that doesn't start with it, is left alone.
+pub fn ltrimstr_function(input: &Value, prefix: &Value) -> Value {
+    match (input, prefix) {
+        (Value::String(string), Value::String(prefix)) => string
+            .strip_prefix(&**prefix)
+            .map_or_else(|| input.clone(), Value::from),
+        _ => input.clone(),
+    }
+}
+
+/// `rtrimstr(suffix)`: a string without `suffix` at the end, like
+/// [`ltrimstr_function`].
+pub fn rtrimstr_function(input: &Value, suffix: &Value) -> Value {
+    match (input, suffix) {
+        (Value::String(string), Value::String(suffix)) => string
+            .strip_suffix(&**suffix)
+            .map_or_else(|| input.clone(), Value::from),
+        _ => input.clone(),
+    }
+}
+
+/// `ascii_downcase`: a string with `A` to `Z` made lowercase. Other
+/// characters are left alone.
+pub fn ascii_downcase_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::String(string) => Ok(Value::String(string.to_ascii_lowercase())),
+        _ => Err(FilterError::invalid_input("ascii_downcase", input)),
+    }
+}
+
+/// `ascii_upcase`: a string with `a` to `z` made uppercase, like
+/// [`ascii_downcase_function`].
+pub fn ascii_upcase_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::String(string) => Ok(Value::String(string.to_ascii_uppercase())),
+        _ => Err(FilterError::invalid_input("ascii_upcase", input)),
+    }
+}
+
+/// `tostring`: strings are left alone, and anything else is turned into
+/// its compact JSON text.
+pub fn tostring_function(input: &Value) -> Value {
+    match input {
+        Value::String(_) => input.clone(),
+        _ => Value::String(input.to_string()),
+    }
+}
+
+/// `tonumber`: numbers are left alone, and strings are parsed as JSON
+/// numbers, with whole ones made integers.
+pub fn tonumber_function(input: &Value) -> Result<Value, FilterError> {
+    match input {
+        Value::Number(_) => Ok(input.clone()),
+        Value::String(string) => {
+            let number = serde_json::from_str::<Number>(string.trim())
+                .map_err(|_| FilterError::invalid_input("tonumber", input))?;
+            // `1e3` is read as a float, but whole numbers are integers, like
+            // the results of arithmetic
+            Ok(match number.as_f64() {
+                Some(float) if number.is_f64() => filters::number(float),
+                _ => Value::Number(number),
+            })
+        }
+        _ => Err(FilterError::invalid_input("tonumber", input)),
+    }
+}
+
+/// `tojson`: a value as compact JSON text.
+pub fn tojson_function(input: &Value) -> Value {
+    Value::String(input.to_string())
+}
+
+/// `fromjson`: the value a string of JSON text holds, for payloads that
+/// were encoded twice.
+pub fn fromjson_function(input: &Value) -> Result<Value, FilterError> {
+    let Value::String(text) = input else {
+        return Err(FilterError::invalid_input("fromjson", input));
+    };
+    serde_json::from_str(text).map_err(|err| FilterError::InvalidJson {
+        text: text.clone(),
+        reason: err.to_string(),
+    })
+}
+
+/// The string a regex builtin called `function` runs on, and its compiled
+/// regex.
+fn compile<'a>(
+    function: &'static str,
+    input: &'a Value,
+    regex: &Value,
+) -> Result<(&'a str, Regex), FilterError> {
+    let Value::String(string) = input else {
+        return Err(FilterError::invalid_input(function, input));
+    };
+    let Value::String(regex) = regex else {
+        return Err(FilterError::invalid_input(function, regex));
+    };
+    let compiled = Regex::new(regex).map_err(|err| FilterError::InvalidRegex {
+        regex: regex.clone(),
+        // The last line of a syntax error says what's wrong, after a
+        // diagram of where
+        reason: err
+            .to_string()
+            .lines()
+            .last()
+            .unwrap_or_default()
+            .to_owned(),
+    })?;
+    Ok((string, compiled))
+}
+
+/// `test(regex)`: whether `regex` matches anywhere in a string.
+pub fn test_function(input: &Value, regex: &Value) -> Result<Value, FilterError> {
+    let (string, regex) = compile("test", input, regex)?;
+    Ok(Value::Bool(regex.is_match(string)))
+}
+
+/// `match(regex)`: an object describing the first match of `regex` in a
+/// string, like jq's, or nothing if it doesn't match. Offsets and lengths
+/// count characters, not bytes.
+pub fn match_function(input: &Value, regex: &Value) -> Result<Option<Value>, FilterError> {
+    let (string, regex) = compile("match", input, regex)?;
+    let Some(captures) = regex.captures(string) else {
+        return Ok(None);
+    };
+    let whole = captures.get(0).expect("group 0 is the whole match");
+    let groups: Vec<Value> = regex
+        .capture_names()
+        .enumerate()
+        .skip(1)
+        .map(|(index, name)| match captures.get(index) {
+            Some(group) => json!({
+                "offset": char_offset(string, group.start()),
+                "length": group.as_str().chars().count(),
+                "string": group.as_str(),
+                "name": name,
+            }),
+            None => json!({"offset": -1, "length": 0, "string": null, "name": name}),
+        })
+        .collect();
+    Ok(Some(json!({
+        "offset": char_offset(string, whole.start()),
+        "length": whole.as_str().chars().count(),
+        "string": whole.as_str(),
+        "captures": groups,
+    })))
+}
+
+/// `capture(regex)`: an object with what each named group of `regex`
+/// matched the first time it matches, or nothing if it doesn't.
+pub fn capture_function(input: &Value, regex: &Value) -> Result<Option<Value>, FilterError> {
+    let (string, regex) = compile("capture", input, regex)?;
+    Ok(regex
+        .captures(string)
+        .map(|captures| named_groups(&regex, &captures)))
+}
+
+fn named_groups(regex: &Regex, captures: &Captures) -> Value {
+    let groups: Map<String, Value> = regex
+        .capture_names()
+        .enumerate()
+        .filter_map(|(index, name)| {
+            let group = captures.get(index).map(|group| group.as_str());
+            Some((name?.to_owned(), Value::from(group)))
+        })
+        .collect();
+    Value::Object(groups)
+}
+
+/// The number of characters before `byte` in `string`.
+fn char_offset(string: &str, byte: usize) -> usize {
+    string[..byte].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{
+        ascii_downcase_function, ascii_upcase_function, capture_function, endswith_function,
+        fromjson_function, join_function, ltrimstr_function, match_function, rtrimstr_function,
+        split_function, startswith_function, test_function, tojson_function, tonumber_function,
+        tostring_function,
+    };
+    use crate::error::FilterError;
+
+    #[test]
+    fn test_join() {
+        let comma = json!(", ");
+        assert_eq!(
+            join_function(&json!(["a", "b", "c"]), &comma),
+            Ok(json!("a, b, c"))
+        );
+        assert_eq!(
+            join_function(&json!(["a", 1, null, true, 2.5]), &json!("-")),
+            Ok(json!("a-1--true-2.5"))
+        );
+        assert_eq!(join_function(&json!([]), &comma), Ok(json!("")));
+        assert_eq!(join_function(&json!(["a"]), &comma), Ok(json!("a")));
+        assert_eq!(
+            join_function(&json!(["a", [1]]), &comma),
+            Err(FilterError::invalid_input("join", &json!([1])))
+        );
+        assert!(join_function(&json!("abc"), &comma).is_err());
+        assert!(join_function(&json!(["a"]), &json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let comma = json!(",");
+        assert_eq!(
+            split_function(&json!("a,b,,c"), &comma),
+            Ok(json!(["a", "b", "", "c"]))
+        );
+        assert_eq!(split_function(&json!("abc"), &comma), Ok(json!(["abc"])));
+        assert_eq!(split_function(&json!(""), &comma), Ok(json!([])));
+        assert_eq!(
+            split_function(&json!("héy"), &json!("")),
+            Ok(json!(["h", "é", "y"]))
+        );
+        assert_eq!(
+            split_function(&json!(["a,b"]), &comma),
+            Err(FilterError::invalid_input("split", &json!(["a,b"])))
+        );
+        assert!(split_function(&json!("a"), &Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_startswith_and_endswith() {
+        let input = json!("app.log");
+        assert_eq!(startswith_function(&input, &json!("app")), Ok(json!(true)));
+        assert_eq!(startswith_function(&input, &json!("log")), Ok(json!(false)));
+        assert_eq!(startswith_function(&input, &json!("")), Ok(json!(true)));
+        assert_eq!(endswith_function(&input, &json!(".log")), Ok(json!(true)));
+        assert_eq!(endswith_function(&input, &json!("app")), Ok(json!(false)));
+        assert_eq!(
+            startswith_function(&json!(1), &json!("1")),
+            Err(FilterError::invalid_input("startswith", &json!(1)))
+        );
+        assert_eq!(
+            endswith_function(&input, &Value::Null),
+            Err(FilterError::invalid_input("endswith", &Value::Null))
+        );
+    }
+
+    #[test]
+    fn test_ltrimstr_and_rtrimstr() {
+        let input = json!("app.log");
+        assert_eq!(ltrimstr_function(&input, &json!("app.")), json!("log"));
+        assert_eq!(ltrimstr_function(&input, &json!("log")), input);
+        assert_eq!(rtrimstr_function(&input, &json!(".log")), json!("app"));
+        assert_eq!(rtrimstr_function(&input, &json!("app")), input);
+        assert_eq!(rtrimstr_function(&input, &json!("app.log")), json!(""));
+        // Anything that isn't a string is left alone
+        assert_eq!(ltrimstr_function(&json!(1), &json!("1")), json!(1));
+        assert_eq!(rtrimstr_function(&input, &json!(1)), input);
+    }
+
+    #[test]
+    fn test_ascii_case() {
+        assert_eq!(
+            ascii_downcase_function(&json!("ERROR: Ünï 1")),
+            Ok(json!("error: Ünï 1"))
+        );
+        assert_eq!(
+            ascii_upcase_function(&json!("error: ünï 1")),
+            Ok(json!("ERROR: üNï 1"))
+        );
+        assert_eq!(
+            ascii_downcase_function(&json!(["A"])),
+            Err(FilterError::invalid_input("ascii_downcase", &json!(["A"])))
+        );
+        assert!(ascii_upcase_function(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_tostring() {
+        assert_eq!(tostring_function(&json!("abc")), json!("abc"));
+        assert_eq!(tostring_function(&json!(12)), json!("12"));
+        assert_eq!(tostring_function(&json!(2.5)), json!("2.5"));
+        assert_eq!(tostring_function(&Value::Null), json!("null"));
+        assert_eq!(
+            tostring_function(&json!({"a": [1, "b"]})),
+            json!("{\"a\":[1,\"b\"]}")
+        );
+    }
+
+    #[test]
+    fn test_tonumber() {
+        assert_eq!(tonumber_function(&json!("12")), Ok(json!(12)));
+        assert_eq!(tonumber_function(&json!("-2.5")), Ok(json!(-2.5)));
+        assert_eq!(tonumber_function(&json!("1e3")), Ok(json!(1000)));
+        assert_eq!(tonumber_function(&json!("2.0")), Ok(json!(2)));
+        assert_eq!(tonumber_function(&json!("1.5e1")), Ok(json!(15)));
+        assert_eq!(tonumber_function(&json!(" 7 ")), Ok(json!(7)));
+        assert_eq!(tonumber_function(&json!(3)), Ok(json!(3)));
+        assert_eq!(
+            tonumber_function(&json!("12abc")),
+            Err(FilterError::invalid_input("tonumber", &json!("12abc")))
+        );
+        assert!(tonumber_function(&json!("")).is_err());
+        assert!(tonumber_function(&json!([1])).is_err());
+        assert!(tonumber_function(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_tojson_and_fromjson() {
+        assert_eq!(tojson_function(&json!("abc")), json!("\"abc\""));
+        assert_eq!(
+            tojson_function(&json!({"a": [1, null]})),
+            json!("{\"a\":[1,null]}")
+        );
+        assert_eq!(
+            fromjson_function(&json!("{\"a\": [1, 2.5]}")),
+            Ok(json!({"a": [1, 2.5]}))
+        );
+        assert_eq!(fromjson_function(&json!(" \"x\" ")), Ok(json!("x")));
+        let value = json!({"b": [true, "c"]});
+        assert_eq!(fromjson_function(&tojson_function(&value)), Ok(value));
+        assert_eq!(
+            fromjson_function(&json!("{oops")).map_err(|err| err.to_string()),
+            Err(String::from(
+                "key must be a string at line 1 column 2 (while parsing '{oops')"
+            ))
+        );
+        assert_eq!(
+            fromjson_function(&json!(1)),
+            Err(FilterError::invalid_input("fromjson", &json!(1)))
+        );
+    }
+
+    #[test]
+    fn test_test() {
+        let input = json!("foo bar");
+        assert_eq!(test_function(&input, &json!("ba.")), Ok(json!(true)));
+        assert_eq!(test_function(&input, &json!("^bar")), Ok(json!(false)));
+        assert_eq!(
+            test_function(&json!(1), &json!("1")),
+            Err(FilterError::invalid_input("test", &json!(1)))
+        );
+        assert_eq!(
+            test_function(&input, &json!("(")).map_err(|err| err.to_string()),
+            Err(String::from(
+                "\"(\" is not a valid regex: error: unclosed group"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            match_function(&json!("héllo wörld"), &json!("w(ö)(x)?(?<rest>.+)")),
+            Ok(Some(json!({
+                "offset": 6,
+                "length": 5,
+                "string": "wörld",
+                "captures": [
+                    {"offset": 7, "length": 1, "string": "ö", "name": null},
+                    {"offset": -1, "length": 0, "string": null, "name": null},
+                    {"offset": 8, "length": 3, "string": "rld", "name": "rest"}
+                ]
+            })))
+        );
+        assert_eq!(
+            match_function(&json!("abc"), &json!("b")),
+            Ok(Some(
+                json!({"offset": 1, "length": 1, "string": "b", "captures": []})
+            ))
+        );
+        assert_eq!(match_function(&json!("abc"), &json!("x")), Ok(None));
+        assert!(match_function(&json!("abc"), &Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_capture() {
+        let regex = json!("(?<user>[a-z]+)@(?<domain>[a-z.]+)(?<port>:\\d+)?");
+        assert_eq!(
+            capture_function(&json!("mail to bob@example.com"), &regex),
+            Ok(Some(
+                json!({"user": "bob", "domain": "example.com", "port": null})
+            ))
+        );
+        assert_eq!(capture_function(&json!("nobody"), &regex), Ok(None));
+        assert_eq!(
+            capture_function(&json!("ab"), &json!("(a)(?<b>b)")),
+            Ok(Some(json!({"b": "b"})))
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Reading the JSON documents to filter.
+
+mod stream;
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read};
+use std::iter;
+use std::rc::Rc;
+
+use serde::Deserialize;
+use serde_json::{Deserializer, Value};
+
+/// Values to run the filter on, read as they're needed.
+pub type Inputs = Box<dyn Iterator<Item = io::Result<Value>>>;
+
+/// The rest of the input, shared by the loop that runs the filter on each
+/// value and the `input` and `inputs` builtins, so each value goes to only
+/// one of them. Clones share the same values.
+#[derive(Clone)]
+pub struct SharedInputs(Rc<RefCell<Inputs>>);
+
+impl SharedInputs {
+    pub fn new(inputs: Inputs) -> Self {
+        SharedInputs(Rc::new(RefCell::new(inputs)))
+    }
+
+    /// Takes the next value from the rest of the input.
+    pub(crate) fn next_input(&self) -> Option<io::Result<Value>> {
+        self.0.borrow_mut().next()
+    }
+}
+
+/// No input at all, so `input` fails and `inputs` outputs nothing.
+impl Default for SharedInputs {
+    fn default() -> Self {
+        SharedInputs::new(Box::new(iter::empty()))
+    }
+}
+
+impl Iterator for SharedInputs {
+    type Item = io::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_input()
+    }
+}
+
+/// How the input is turned into the values the filter runs on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputConfig {
+    /// Run the filter once, on an array of every document, instead of on
+    /// each document.
+    pub slurp: bool,
+    /// Treat each line as a string instead of parsing it as JSON. Combined
+    /// with `slurp`, the whole input is one string.
+    pub raw_input: bool,
+    /// Run the filter on each event from [`stream::events`] instead of on
+    /// each whole document.
+    pub stream: bool,
+    /// Parse the input as YAML documents instead of JSON.
+    pub yaml: bool,
+}
+
+/// The values to run the filter on: every document (or streamed event) in
+/// `reader`, or just one array holding them all when slurping. Nothing is
+/// read until the first value is needed.
+pub fn read_inputs<R: BufRead + 'static>(mut reader: R, config: &InputConfig) -> Inputs {
+    if config.raw_input {
+        if config.slurp {
+            return Box::new(iter::once_with(move || {
+                let mut text = String::new();
+                reader
+                    .read_to_string(&mut text)
+                    .map(|_| Value::String(text))
+            }));
+        }
+        // Only `\n` ends a line, so a `\r` before it is kept, like jq
+        return Box::new(reader.split(b'\n').map(|line| {
+            let line = String::from_utf8(line?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            Ok(Value::String(line))
+        }));
+    }
+
+    let documents: Inputs = if config.stream {
+        Box::new(stream::events(reader))
+    } else if config.yaml {
+        Box::new(read_yaml_documents(reader))
+    } else {
+        Box::new(read_documents(reader).map(|document| document.map_err(io::Error::from)))
+    };
+    if config.slurp {
+        Box::new(iter::once_with(move || slurp(documents)))
+    } else {
+        Box::new(documents)
+    }
+}
+
+/// Collects every document into one array, failing if any is invalid.
+fn slurp(documents: impl Iterator<Item = io::Result<Value>>) -> io::Result<Value> {
+    documents.collect::<io::Result<_>>().map(Value::Array)
+}
+
+/// Every top-level JSON value in `reader`, in order. Values can be separated
+/// by whitespace or newlines (as in newline-delimited JSON logs), or not at
+/// all, like `[1][2]`. Values are parsed one at a time as they're needed,
+/// so reading stops at the first invalid one.
+pub fn read_documents<R: Read>(reader: R) -> impl Iterator<Item = serde_json::Result<Value>> {
+    let mut documents = Deserializer::from_reader(reader).into_iter::<Value>();
+    let mut failed = false;
+    iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let document = documents.next()?;
+        failed = document.is_err();
+        Some(document)
+    })
+}
+
+/// Every YAML document in `reader`, in order, separated by `---` lines.
+/// The whole input is read at once, but reading still stops at the first
+/// invalid document.
+pub fn read_yaml_documents<R: Read + 'static>(
+    reader: R,
+) -> impl Iterator<Item = io::Result<Value>> {
+    let mut documents = serde_yaml::Deserializer::from_reader(reader);
+    let mut failed = false;
+    iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let document = Value::deserialize(documents.next()?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        failed = document.is_err();
+        Some(document)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{read_documents, read_inputs, InputConfig, SharedInputs};
+
+    fn read(input: &str) -> Vec<Result<Value, String>> {
+        read_documents(input.as_bytes())
+            .map(|document| document.map_err(|err| err.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_document() {
+        assert_eq!(read("{\"a\": [1, 2]}\n"), [Ok(json!({"a": [1, 2]}))]);
+        assert_eq!(read(""), []);
+        assert_eq!(read("  \n"), []);
+    }
+
+    #[test]
+    fn test_newline_delimited() {
+        assert_eq!(
+            read("{\"level\": \"info\"}\n{\"level\": \"error\"}\n"),
+            [Ok(json!({"level": "info"})), Ok(json!({"level": "error"}))]
+        );
+    }
+
+    #[test]
+    fn test_concatenated() {
+        assert_eq!(
+            read("[1][2]\"three\" 4 null"),
+            [
+                Ok(json!([1])),
+                Ok(json!([2])),
+                Ok(json!("three")),
+                Ok(json!(4)),
+                Ok(Value::Null)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stops_at_invalid_document() {
+        let documents = read("1\n{oops}\n2\n");
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0], Ok(json!(1)));
+        assert!(documents[1].is_err());
+    }
+
+    fn read_slurped(input: &'static str) -> Vec<Result<Value, String>> {
+        let config = InputConfig {
+            slurp: true,
+            ..InputConfig::default()
+        };
+        read_inputs(input.as_bytes(), &config)
+            .map(|value| value.map_err(|err| err.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_slurp() {
+        assert_eq!(
+            read_slurped("{\"a\": 1}\n[2] 3"),
+            [Ok(json!([{"a": 1}, [2], 3]))]
+        );
+        // Still exactly one input with no documents at all
+        assert_eq!(read_slurped(""), [Ok(json!([]))]);
+        let slurped = read_slurped("1 2 {oops}");
+        assert_eq!(slurped.len(), 1);
+        assert!(slurped[0].is_err());
+    }
+
+    #[test]
+    fn test_without_slurp() {
+        let inputs: Vec<_> = read_inputs("1 2".as_bytes(), &InputConfig::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(inputs, [json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn test_raw_input() {
+        let config = InputConfig {
+            raw_input: true,
+            ..InputConfig::default()
+        };
+        let lines: Vec<_> =
+            read_inputs("plain text\n{\"not\": json}\r\n\nlast".as_bytes(), &config)
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(
+            lines,
+            [
+                json!("plain text"),
+                json!("{\"not\": json}\r"),
+                json!(""),
+                json!("last")
+            ]
+        );
+        assert_eq!(read_inputs("".as_bytes(), &config).count(), 0);
+
+        // Lines have to be text
+        let mut lines = read_inputs(&b"\xff\xfe\n"[..], &config);
+        assert!(lines.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_raw_input_slurped() {
+        let config = InputConfig {
+            slurp: true,
+            raw_input: true,
+            ..InputConfig::default()
+        };
+        let inputs: Vec<_> = read_inputs("one\ntwo\n".as_bytes(), &config)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(inputs, [json!("one\ntwo\n")]);
+    }
+
+    #[test]
+    fn test_stream() {
+        let config = InputConfig {
+            stream: true,
+            ..InputConfig::default()
+        };
+        let events: Vec<_> = read_inputs("[1] 2".as_bytes(), &config)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events, [json!([[0], 1]), json!([[0]]), json!([[], 2])]);
+
+        let config = InputConfig {
+            slurp: true,
+            ..config
+        };
+        let slurped: Vec<_> = read_inputs("[1]".as_bytes(), &config)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(slurped, [json!([[[0], 1], [[0]]])]);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let config = InputConfig {
+            yaml: true,
+            ..InputConfig::default()
+        };
+        let read_yaml = |input: &'static str| -> Vec<Result<Value, String>> {
+            read_inputs(input.as_bytes(), &config)
+                .map(|value| value.map_err(|err| err.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            read_yaml("kind: Pod\nspec:\n  containers:\n    - name: web\n      ports: [80, 443]\n"),
+            [Ok(
+                json!({"kind": "Pod", "spec": {"containers": [{"name": "web", "ports": [80, 443]}]}})
+            )]
+        );
+        assert_eq!(
+            read_yaml("a: 1\n---\n- true\n- ~\n- 1.5\n---\nplain\n"),
+            [
+                Ok(json!({"a": 1})),
+                Ok(json!([true, null, 1.5])),
+                Ok(json!("plain"))
+            ]
+        );
+        // Unlike JSON, an empty YAML input is a single null document
+        assert_eq!(read_yaml(""), [Ok(Value::Null)]);
+        let documents = read_yaml("a: 1\n---\na: [\n---\nb: 2\n");
+        assert_eq!(documents.len(), 2);
+        assert!(documents[1].is_err());
+    }
+
+    #[test]
+    fn test_shared_inputs() {
+        assert!(SharedInputs::default().next_input().is_none());
+        let inputs = SharedInputs::new(read_inputs("1 [2]".as_bytes(), &InputConfig::default()));
+        let mut shared = inputs.clone();
+        assert_eq!(inputs.next_input().unwrap().unwrap(), json!(1));
+        // A clone takes from the same values
+        assert_eq!(shared.next().unwrap().unwrap(), json!([2]));
+        assert!(inputs.next_input().is_none());
+    }
+}
@@ -0,0 +1,360 @@
+//! Reading JSON as a stream of events, for `--stream`. Only the path to the
+//! value being read is kept in memory, never the whole document.
+
+use std::io::{self, BufRead, Bytes};
+use std::iter::Peekable;
+
+use serde_json::Value;
+
+/// What the parser expects next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// The start of another top-level document, or the end of the input.
+    Document,
+    /// A value inside an array or object.
+    Value,
+    /// The first value of an array, or `]`.
+    ValueOrEnd,
+    /// The first key of an object, or `}`.
+    KeyOrEnd,
+    /// A key after a `,` in an object.
+    Key,
+    Colon,
+    /// A `,` or the end of the innermost array or object.
+    CommaOrEnd,
+}
+
+/// An array or object being read.
+#[derive(Debug)]
+struct Container {
+    /// The byte that ends it, `]` or `}`.
+    end: u8,
+    /// Whether any values in it have been read, in which case the last item
+    /// of the path is the index or key of the latest one.
+    empty: bool,
+}
+
+/// The events for every document in a reader, like jq's `--stream`: a
+/// `[path, leaf]` for each scalar and empty array or object, and a `[path]`
+/// after the last value in each array or object, where `path` leads to that
+/// last value.
+pub struct Events<R: BufRead> {
+    bytes: Peekable<Bytes<R>>,
+    state: State,
+    containers: Vec<Container>,
+    path: Vec<Value>,
+    line: usize,
+    column: usize,
+    failed: bool,
+}
+
+/// The events for every document in `reader`. Reading stops at the first
+/// invalid document, after the events before the error.
+pub fn events<R: BufRead>(reader: R) -> Events<R> {
+    Events {
+        bytes: reader.bytes().peekable(),
+        state: State::Document,
+        containers: Vec::new(),
+        path: Vec::new(),
+        line: 1,
+        column: 0,
+        failed: false,
+    }
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = io::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let event = self.next_event().transpose();
+        self.failed = matches!(event, Some(Err(_)));
+        event
+    }
+}
+
+impl<R: BufRead> Events<R> {
+    fn next_event(&mut self) -> io::Result<Option<Value>> {
+        loop {
+            self.skip_whitespace()?;
+            let Some(byte) = self.peek()? else {
+                return match self.state {
+                    State::Document => Ok(None),
+                    _ => Err(self.error("EOF while parsing a value")),
+                };
+            };
+            match self.state {
+                State::ValueOrEnd | State::CommaOrEnd | State::KeyOrEnd
+                    if self.containers.last().is_some_and(|c| c.end == byte) =>
+                {
+                    self.next_byte()?;
+                    let container = self.containers.pop().expect("checked above");
+                    let event = if container.empty {
+                        let empty = if byte == b']' {
+                            Value::Array(Vec::new())
+                        } else {
+                            Value::Object(Default::default())
+                        };
+                        Value::Array(vec![self.path_value(), empty])
+                    } else {
+                        let event = Value::Array(vec![self.path_value()]);
+                        self.path.pop();
+                        event
+                    };
+                    self.end_value();
+                    return Ok(Some(event));
+                }
+                State::ValueOrEnd => {
+                    self.path.push(Value::from(0));
+                    self.start_value();
+                    self.state = State::Value;
+                }
+                State::Document | State::Value => match byte {
+                    b'[' | b'{' => {
+                        self.next_byte()?;
+                        let end = if byte == b'[' { b']' } else { b'}' };
+                        self.containers.push(Container { end, empty: true });
+                        self.state = if byte == b'[' {
+                            State::ValueOrEnd
+                        } else {
+                            State::KeyOrEnd
+                        };
+                    }
+                    _ => {
+                        let scalar = self.scalar()?;
+                        self.end_value();
+                        return Ok(Some(Value::Array(vec![self.path_value(), scalar])));
+                    }
+                },
+                State::KeyOrEnd | State::Key => {
+                    if byte != b'"' {
+                        return Err(self.error("key must be a string"));
+                    }
+                    let key = Value::String(self.string()?);
+                    if self.state == State::KeyOrEnd {
+                        self.path.push(key);
+                        self.start_value();
+                    } else if let Some(last) = self.path.last_mut() {
+                        *last = key;
+                    }
+                    self.state = State::Colon;
+                }
+                State::Colon => {
+                    if byte != b':' {
+                        return Err(self.error("expected `:`"));
+                    }
+                    self.next_byte()?;
+                    self.state = State::Value;
+                }
+                State::CommaOrEnd => {
+                    if byte != b',' {
+                        return Err(self.error("expected `,` or the end of the value"));
+                    }
+                    self.next_byte()?;
+                    match self.path.last_mut() {
+                        Some(Value::Number(index)) => {
+                            *index = (index.as_u64().unwrap_or_default() + 1).into();
+                            self.state = State::Value;
+                        }
+                        _ => self.state = State::Key,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Marks the innermost container as having a value in it.
+    fn start_value(&mut self) {
+        if let Some(container) = self.containers.last_mut() {
+            container.empty = false;
+        }
+    }
+
+    /// Moves on after a whole value has been read.
+    fn end_value(&mut self) {
+        self.state = if self.containers.is_empty() {
+            State::Document
+        } else {
+            State::CommaOrEnd
+        };
+    }
+
+    fn path_value(&self) -> Value {
+        Value::Array(self.path.clone())
+    }
+
+    /// A string, number, boolean, or `null`.
+    fn scalar(&mut self) -> io::Result<Value> {
+        match self.peek()? {
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => {
+                let text = self.take_while(|byte| {
+                    byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E')
+                })?;
+                match serde_json::from_slice(&text) {
+                    Ok(Value::Number(number)) => Ok(Value::Number(number)),
+                    _ => Err(self.error("invalid number")),
+                }
+            }
+            _ => match &self.take_while(|byte| byte.is_ascii_alphabetic())?[..] {
+                b"true" => Ok(Value::Bool(true)),
+                b"false" => Ok(Value::Bool(false)),
+                b"null" => Ok(Value::Null),
+                _ => Err(self.error("expected value")),
+            },
+        }
+    }
+
+    /// A string, from its opening quote to its closing one.
+    fn string(&mut self) -> io::Result<String> {
+        let mut text = vec![self.next_byte()?.expect("the opening quote was peeked")];
+        let mut escaped = false;
+        loop {
+            let Some(byte) = self.next_byte()? else {
+                return Err(self.error("EOF while parsing a string"));
+            };
+            text.push(byte);
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => break,
+                _ => {}
+            }
+        }
+        serde_json::from_slice(&text).map_err(|_| self.error("invalid string"))
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(u8) -> bool) -> io::Result<Vec<u8>> {
+        let mut text = Vec::new();
+        while let Some(byte) = self.peek()? {
+            if !predicate(byte) {
+                break;
+            }
+            text.push(byte);
+            self.next_byte()?;
+        }
+        Ok(text)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+            self.next_byte()?;
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        match self.bytes.peek() {
+            Some(Ok(byte)) => Ok(Some(*byte)),
+            Some(Err(_)) => Err(self.bytes.next().expect("peeked").unwrap_err()),
+            None => Ok(None),
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.bytes.next().transpose()?;
+        if byte == Some(b'\n') {
+            self.line += 1;
+            self.column = 0;
+        } else if byte.is_some() {
+            self.column += 1;
+        }
+        Ok(byte)
+    }
+
+    /// An error about the invalid JSON where the parser is, worded like
+    /// [`serde_json`]'s.
+    fn error(&self, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} at line {} column {}", message, self.line, self.column),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::events;
+
+    fn stream(input: &str) -> Vec<Result<Value, String>> {
+        events(input.as_bytes())
+            .map(|event| event.map_err(|err| err.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_document() {
+        assert_eq!(
+            stream("{\"a\": [1, {\"b\": null}], \"c\": \"x\"}"),
+            [
+                Ok(json!([["a", 0], 1])),
+                Ok(json!([["a", 1, "b"], null])),
+                Ok(json!([["a", 1, "b"]])),
+                Ok(json!([["a", 1]])),
+                Ok(json!([["c"], "x"])),
+                Ok(json!([["c"]])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scalars_and_empty_containers() {
+        assert_eq!(
+            stream("3 \"s\" true [] {} [[], {}]"),
+            [
+                Ok(json!([[], 3])),
+                Ok(json!([[], "s"])),
+                Ok(json!([[], true])),
+                Ok(json!([[], []])),
+                Ok(json!([[], {}])),
+                Ok(json!([[0], []])),
+                Ok(json!([[1], {}])),
+                Ok(json!([[1]])),
+            ]
+        );
+        assert_eq!(stream(""), []);
+        assert_eq!(
+            stream("[-1.5e2, \"a\\\"]\"]"),
+            [
+                Ok(json!([[0], -150.0])),
+                Ok(json!([[1], "a\"]"])),
+                Ok(json!([[1]])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        assert_eq!(
+            stream("[1,\n2 3]"),
+            [
+                Ok(json!([[0], 1])),
+                Ok(json!([[1], 2])),
+                Err(String::from(
+                    "expected `,` or the end of the value at line 2 column 2"
+                )),
+            ]
+        );
+        assert_eq!(
+            stream("{1: 2}"),
+            [Err(String::from("key must be a string at line 1 column 1"))]
+        );
+        assert_eq!(
+            stream("[nul]"),
+            [Err(String::from("expected value at line 1 column 4"))]
+        );
+        assert_eq!(
+            stream("{\"a\": [1"),
+            [
+                Ok(json!([["a", 0], 1])),
+                Err(String::from("EOF while parsing a value at line 1 column 8")),
+            ]
+        );
+        // Nothing is read after an error
+        assert_eq!(stream("] 1").len(), 1);
+    }
+}
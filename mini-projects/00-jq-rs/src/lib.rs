@@ -0,0 +1,76 @@
+//! A small clone of jq, for filtering JSON from other Rust programs without
+//! shelling out to the command-line tool.
+//!
+//! ```
+//! use serde_json::json;
+//!
+//! let filter = jq_rs::compile(".players[] | select(.score > 10) | .name").unwrap();
+//! let input = json!({"players": [{"name": "a", "score": 12}, {"name": "b", "score": 3}]});
+//! let names: Result<Vec<_>, _> = filter.run(&input).collect();
+//! assert_eq!(names, Ok(vec![json!("a")]));
+//! ```
+
+mod error;
+mod filters;
+mod functions;
+pub mod input;
+pub mod output;
+
+pub use error::FilterError;
+pub use filters::Filter;
+
+/// Parses a filter program like `.[] | .name`, so it can be run on any
+/// number of inputs with [`Filter::run`].
+pub fn compile(program: &str) -> Result<Filter, FilterError> {
+    filters::parse(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{compile, FilterError};
+    use crate::input::{read_inputs, InputConfig, SharedInputs};
+
+    #[test]
+    fn test_compile_and_run() {
+        let filter = compile(".[] | . * 2").unwrap();
+        for (input, outputs) in [
+            (json!([1, 2]), vec![json!(2), json!(4)]),
+            (json!([]), vec![]),
+        ] {
+            assert_eq!(
+                filter.run(&input).collect::<Result<Vec<_>, _>>(),
+                Ok(outputs)
+            );
+        }
+        assert!(matches!(
+            compile(".a |"),
+            Err(FilterError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_with_inputs() {
+        let filter = compile("[., input]").unwrap();
+        let inputs = SharedInputs::new(read_inputs("2 3".as_bytes(), &InputConfig::default()));
+        let outputs: Result<Vec<_>, _> = filter.run_with_inputs(&json!(1), &inputs).collect();
+        assert_eq!(outputs, Ok(vec![json!([1, 2])]));
+        let outputs: Result<Vec<_>, _> = filter.run_owned(json!(4), &inputs).collect();
+        assert_eq!(outputs, Ok(vec![json!([4, 3])]));
+        // Without inputs, there's nothing for `input` to read
+        assert_eq!(
+            filter.run(&json!(1)).collect::<Vec<_>>(),
+            [Err(FilterError::NoMoreInputs)]
+        );
+    }
+
+    #[test]
+    fn test_run_stops_at_error() {
+        let filter = compile(".[] | .a").unwrap();
+        let outputs: Vec<_> = filter.run(&json!([{"a": 1}, 2, {"a": 3}])).collect();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0], Ok(json!(1)));
+        assert!(outputs[1].is_err());
+    }
+}
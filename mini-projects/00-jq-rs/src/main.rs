@@ -1,3 +1,271 @@
-fn main() {
-    println!("Hello World!");
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::iter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+use serde_json::Value;
+
+use jq_rs::input::{self, InputConfig, Inputs, SharedInputs};
+use jq_rs::output::{self, ColorConfig, Indent, OutputConfig};
+use jq_rs::Filter;
+
+/// A small clone of jq, the command-line JSON processor
+#[derive(Parser, Debug)]
+struct Args {
+    /// The filter to run on each JSON document, like `.[] | .name`. Leave
+    /// it out when using --from-file
+    filter: Option<String>,
+
+    /// The file to read JSON from, or stdin if there isn't one. It can hold
+    /// any number of documents, e.g. one per line
+    file: Option<PathBuf>,
+
+    /// Read the filter from a file instead of the command line. Comments,
+    /// from `#` to the end of a line, are ignored
+    #[clap(short, long, value_name = "PROGRAM")]
+    from_file: Option<PathBuf>,
+
+    /// When to print in color. With `auto`, colors are only used when
+    /// printing to a terminal, so output piped to a file stays plain JSON
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Same as --color=always
+    #[clap(short = 'C', long, conflicts_with_all = ["monochrome_output", "color"])]
+    color_output: bool,
+
+    /// Same as --color=never
+    #[clap(short = 'M', long, conflicts_with = "color")]
+    monochrome_output: bool,
+
+    /// Print the keys of every object in sorted order
+    #[clap(short = 'S', long)]
+    sort_keys: bool,
+
+    /// How many spaces to indent by
+    #[clap(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(0..=7), conflicts_with = "compact_output")]
+    indent: u8,
+
+    /// Print each result on one line
+    #[clap(short = 'c', long)]
+    compact_output: bool,
+
+    /// Indent with a tab per level instead of spaces
+    #[clap(long, conflicts_with_all = ["indent", "compact_output"])]
+    tab: bool,
+
+    /// Print string results without quotes or escapes, for use in shell
+    /// pipelines
+    #[clap(short, long)]
+    raw_output: bool,
+
+    /// Escape every non-ASCII character as \uXXXX
+    #[clap(short, long)]
+    ascii_output: bool,
+
+    /// Start each result with an ASCII record separator (0x1E), so tools
+    /// can split the output into results reliably
+    #[clap(long)]
+    seq: bool,
+
+    /// Run the filter once, on an array of every input document
+    #[clap(short, long)]
+    slurp: bool,
+
+    /// Run the filter on each line of the input as a string, instead of
+    /// parsing it as JSON. With --slurp, the whole input is one string
+    #[clap(short = 'R', long)]
+    raw_input: bool,
+
+    /// Run the filter on `[path, leaf]` events instead of whole documents,
+    /// so huge inputs can be processed without reading them into memory
+    #[clap(long, conflicts_with = "raw_input")]
+    stream: bool,
+
+    /// Read the input as YAML documents, separated by `---` lines, instead
+    /// of JSON
+    #[clap(long, conflicts_with_all = ["raw_input", "stream"])]
+    yaml_input: bool,
+
+    /// Print each result as a YAML document instead of JSON
+    #[clap(long, conflicts_with_all = ["compact_output", "tab", "indent", "ascii_output"])]
+    yaml_output: bool,
+
+    /// Run the filter once, on null, leaving the input for `input` and
+    /// `inputs` to read
+    #[clap(short, long)]
+    null_input: bool,
+}
+
+/// When to print in color.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ColorChoice {
+    /// Only when stdout is a terminal
+    Auto,
+    /// Even when stdout is a file or a pipe
+    Always,
+    /// Never, like --monochrome-output
+    Never,
+}
+
+/// Exit status when a filter fails on some input, like jq.
+const FILTER_ERROR: u8 = 5;
+/// Exit status when the filter can't be parsed.
+const COMPILE_ERROR: u8 = 3;
+/// Exit status when the input can't be read.
+const INPUT_ERROR: u8 = 2;
+
+impl Args {
+    /// The filter to run and the file to run it on. With --from-file there's
+    /// no filter on the command line, so the first positional argument is
+    /// the JSON file.
+    fn filter_and_file(&self) -> io::Result<(String, Option<PathBuf>)> {
+        let Some(program) = &self.from_file else {
+            let filter = self.filter.clone().unwrap_or_else(|| {
+                Args::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        "a filter is required unless --from-file is used",
+                    )
+                    .exit()
+            });
+            return Ok((filter, self.file.clone()));
+        };
+        if self.file.is_some() {
+            Args::command()
+                .error(
+                    ErrorKind::TooManyValues,
+                    "only one JSON file can be given along with --from-file",
+                )
+                .exit()
+        }
+        let filter = fs::read_to_string(program).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("Could not open {}: {}", program.display(), err),
+            )
+        })?;
+        Ok((filter, self.filter.as_ref().map(PathBuf::from)))
+    }
+
+    /// Whether to print in color, going by --color unless -C or -M was
+    /// given instead.
+    fn use_color(&self) -> bool {
+        let choice = if self.color_output {
+            ColorChoice::Always
+        } else if self.monochrome_output {
+            ColorChoice::Never
+        } else {
+            self.color
+        };
+        match choice {
+            ColorChoice::Auto => io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let (program, file) = match args.filter_and_file() {
+        Ok(filter_and_file) => filter_and_file,
+        Err(err) => {
+            eprintln!("jq: error: {}", err);
+            return ExitCode::from(INPUT_ERROR);
+        }
+    };
+    let filter = match jq_rs::compile(&program) {
+        Ok(filter) => filter,
+        Err(err) => {
+            eprintln!("jq: error: {}", err);
+            return ExitCode::from(COMPILE_ERROR);
+        }
+    };
+    let input_config = InputConfig {
+        slurp: args.slurp,
+        raw_input: args.raw_input,
+        stream: args.stream,
+        yaml: args.yaml_input,
+    };
+    let output_config = OutputConfig {
+        colors: args.use_color().then(|| {
+            let (colors, errors) = ColorConfig::from_env();
+            for err in errors {
+                eprintln!("jq: warning: in JQ_COLORS: {}", err);
+            }
+            colors
+        }),
+        sort_keys: args.sort_keys,
+        indent: if args.tab {
+            Indent::Tab
+        } else if args.compact_output {
+            Indent::Spaces(0)
+        } else {
+            Indent::Spaces(usize::from(args.indent))
+        },
+        raw_output: args.raw_output,
+        ascii_output: args.ascii_output,
+        seq: args.seq,
+        yaml: args.yaml_output,
+    };
+
+    let reader: Box<dyn Read> = match &file {
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => {
+                eprintln!("jq: error: Could not open {}: {}", path.display(), err);
+                return ExitCode::from(INPUT_ERROR);
+            }
+        },
+        None => Box::new(io::stdin().lock()),
+    };
+
+    let inputs = SharedInputs::new(input::read_inputs(BufReader::new(reader), &input_config));
+    // With --null-input, every document is left for `input` and `inputs`
+    let values: Inputs = if args.null_input {
+        Box::new(iter::once(Ok(Value::Null)))
+    } else {
+        Box::new(inputs.clone())
+    };
+    match run(&filter, values, &inputs, &output_config) {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("jq: error: {}", err);
+            ExitCode::from(INPUT_ERROR)
+        }
+    }
+}
+
+/// Runs `filter` on each of `values` and prints every result, leaving
+/// `inputs` for `input` and `inputs` in the filter. An input the filter
+/// fails on is reported and skipped, while invalid JSON stops everything.
+fn run(
+    filter: &Filter,
+    values: impl Iterator<Item = io::Result<Value>>,
+    inputs: &SharedInputs,
+    config: &OutputConfig,
+) -> io::Result<ExitCode> {
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let mut status = ExitCode::SUCCESS;
+    for input in values {
+        // Results are printed as they're produced, up to the first error
+        for result in filter.run_owned(input?, inputs) {
+            match result {
+                Ok(result) => output::print_result(&mut stdout, &result, config)?,
+                Err(err) => {
+                    // Keep what's been printed so far in order with the error
+                    stdout.flush()?;
+                    eprintln!("jq: error: {}", err);
+                    status = ExitCode::from(FILTER_ERROR);
+                    break;
+                }
+            }
+        }
+    }
+    stdout.flush()?;
+    Ok(status)
 }
@@ -0,0 +1,650 @@
+//! Printing filter results, pretty or compact, with or without colors.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+/// The escape codes jq uses when `JQ_COLORS` isn't set.
+const DEFAULT_COLORS: &str = "0;90:0;37:0;37:0;37:0;32:1;37:1;37:1;34";
+
+/// ANSI escape codes (like `1;34` for bright blue) for each kind of value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorConfig {
+    pub null: String,
+    pub false_: String,
+    pub true_: String,
+    pub number: String,
+    pub string: String,
+    pub array: String,
+    pub object: String,
+    pub object_key: String,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        let codes: Vec<String> = DEFAULT_COLORS.split(':').map(String::from).collect();
+        let [null, false_, true_, number, string, array, object, object_key]: [String; 8] =
+            codes.try_into().expect("there are 8 default colors");
+        ColorConfig {
+            null,
+            false_,
+            true_,
+            number,
+            string,
+            array,
+            object,
+            object_key,
+        }
+    }
+}
+
+/// What each color in a `JQ_COLORS` spec is for, in order.
+const COLOR_NAMES: [&str; 8] = [
+    "null",
+    "false",
+    "true",
+    "numbers",
+    "strings",
+    "arrays",
+    "objects",
+    "object keys",
+];
+
+/// What was wrong with part of a `JQ_COLORS` spec.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorError {
+    /// More than one color per kind of value. The extra ones are ignored.
+    TooManyColors(usize),
+    /// An entry that isn't an escape code, like `1;3x` or `red`.
+    InvalidColor { name: &'static str, code: String },
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorError::TooManyColors(count) => write!(
+                f,
+                "{} colors were given, but there are only {}",
+                count,
+                COLOR_NAMES.len()
+            ),
+            ColorError::InvalidColor { name, code } => {
+                write!(f, "invalid color {:?} for {}", code, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+impl ColorConfig {
+    /// Reads colon-separated escape codes in the same order as the fields,
+    /// as in the `JQ_COLORS` environment variable. Each code is numbers
+    /// separated by `;`, like `1;34`. Missing or empty entries keep their
+    /// default color, and so do invalid ones, which are returned along with
+    /// the colors so they can be warned about.
+    pub fn parse(spec: &str) -> (Self, Vec<ColorError>) {
+        let codes: Vec<&str> = spec.split(':').collect();
+        let mut errors = Vec::new();
+        if codes.len() > COLOR_NAMES.len() {
+            errors.push(ColorError::TooManyColors(codes.len()));
+        }
+        let mut colors = ColorConfig::default();
+        let fields = [
+            &mut colors.null,
+            &mut colors.false_,
+            &mut colors.true_,
+            &mut colors.number,
+            &mut colors.string,
+            &mut colors.array,
+            &mut colors.object,
+            &mut colors.object_key,
+        ];
+        for ((field, name), code) in fields.into_iter().zip(COLOR_NAMES).zip(codes) {
+            if code.is_empty() {
+                continue;
+            }
+            if code.split(';').all(is_parameter) {
+                *field = String::from(code);
+            } else {
+                errors.push(ColorError::InvalidColor {
+                    name,
+                    code: String::from(code),
+                });
+            }
+        }
+        (colors, errors)
+    }
+
+    /// The colors in `JQ_COLORS`, or the defaults if it isn't set, along
+    /// with anything wrong with it, like [`ColorConfig::parse`].
+    pub fn from_env() -> (Self, Vec<ColorError>) {
+        match std::env::var_os("JQ_COLORS") {
+            Some(spec) => ColorConfig::parse(&spec.to_string_lossy()),
+            None => (ColorConfig::default(), Vec::new()),
+        }
+    }
+}
+
+/// Whether `text` is one number in an ANSI escape code, from 0 to 255.
+fn is_parameter(text: &str) -> bool {
+    text.bytes().all(|byte| byte.is_ascii_digit()) && text.parse::<u8>().is_ok()
+}
+
+/// How nested values are indented.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indent {
+    /// This many spaces per level. 0 prints everything on one line.
+    Spaces(usize),
+    /// One tab per level.
+    Tab,
+}
+
+impl Indent {
+    fn is_compact(self) -> bool {
+        self == Indent::Spaces(0)
+    }
+}
+
+/// How results get printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputConfig {
+    /// `None` for monochrome output.
+    pub colors: Option<ColorConfig>,
+    pub sort_keys: bool,
+    pub indent: Indent,
+    /// Print string results as they are, without quotes or escapes.
+    pub raw_output: bool,
+    /// Escape every non-ASCII character in strings as `\uXXXX`.
+    pub ascii_output: bool,
+    /// Start each result with an ASCII record separator, as in RFC 7464
+    /// JSON text sequences.
+    pub seq: bool,
+    /// Print each result as a YAML document instead of JSON. Colors,
+    /// indents, and ASCII output don't apply.
+    pub yaml: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            colors: Some(ColorConfig::default()),
+            sort_keys: false,
+            indent: Indent::Spaces(2),
+            raw_output: false,
+            ascii_output: false,
+            seq: false,
+            yaml: false,
+        }
+    }
+}
+
+/// The ASCII record separator that starts each result with `--seq`.
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// Writes `value` to `output` as one line (or block, when pretty printing)
+/// of JSON. With raw output, a string result is written as plain text; only
+/// the result itself is raw, not any strings nested inside it.
+pub fn print_result<W: Write>(
+    output: &mut W,
+    value: &Value,
+    config: &OutputConfig,
+) -> io::Result<()> {
+    if config.seq {
+        write!(output, "{}", RECORD_SEPARATOR)?;
+    }
+    match value {
+        _ if config.yaml => return print_yaml(output, value, config),
+        Value::String(string) if config.raw_output && config.ascii_output => {
+            write!(output, "{}", escape_non_ascii(string))?
+        }
+        Value::String(string) if config.raw_output => write!(output, "{}", string)?,
+        _ => Printer { output, config }.print_value(value, 0)?,
+    }
+    writeln!(output)
+}
+
+/// Writes `value` as a YAML document starting with `---`, so several results
+/// make one valid YAML stream. Raw output still prints strings as they are.
+fn print_yaml<W: Write>(output: &mut W, value: &Value, config: &OutputConfig) -> io::Result<()> {
+    writeln!(output, "---")?;
+    if let (Value::String(string), true) = (value, config.raw_output) {
+        return writeln!(output, "{}", string);
+    }
+    let value = Sorted {
+        value,
+        sort_keys: config.sort_keys,
+    };
+    serde_yaml::to_writer(output, &value).map_err(io::Error::other)
+}
+
+/// A value that serializes with its object keys sorted, if `sort_keys` is
+/// set, at every level.
+struct Sorted<'a> {
+    value: &'a Value,
+    sort_keys: bool,
+}
+
+impl Serialize for Sorted<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted = |value| Sorted {
+            value,
+            sort_keys: self.sort_keys,
+        };
+        match self.value {
+            Value::Array(values) => serializer.collect_seq(values.iter().map(sorted)),
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                if self.sort_keys {
+                    entries.sort_by_key(|&(key, _)| key);
+                }
+                let mut object = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    object.serialize_entry(key, &sorted(value))?;
+                }
+                object.end()
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+/// Replaces every non-ASCII character with a `\uXXXX` escape, using a
+/// surrogate pair for characters outside the Basic Multilingual Plane.
+fn escape_non_ascii(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+struct Printer<'a, W> {
+    output: &'a mut W,
+    config: &'a OutputConfig,
+}
+
+impl<W: Write> Printer<'_, W> {
+    /// Writes `text` in the color picked by `color` out of the config's
+    /// colors, or plainly for monochrome output.
+    fn paint(&mut self, color: fn(&ColorConfig) -> &String, text: &str) -> io::Result<()> {
+        match &self.config.colors {
+            Some(colors) => write!(self.output, "\x1b[{}m{}\x1b[0m", color(colors), text),
+            None => write!(self.output, "{}", text),
+        }
+    }
+
+    fn print_value(&mut self, value: &Value, depth: usize) -> io::Result<()> {
+        match value {
+            Value::Null => self.paint(|colors| &colors.null, "null"),
+            Value::Bool(false) => self.paint(|colors| &colors.false_, "false"),
+            Value::Bool(true) => self.paint(|colors| &colors.true_, "true"),
+            Value::Number(number) => self.paint(|colors| &colors.number, &number.to_string()),
+            Value::String(string) => self.print_string(string, |colors| &colors.string),
+            Value::Array(values) => self.print_array(values, depth),
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                if self.config.sort_keys {
+                    entries.sort_by_key(|&(key, _)| key);
+                }
+                self.print_object(&entries, depth)
+            }
+        }
+    }
+
+    fn print_string(&mut self, string: &str, color: fn(&ColorConfig) -> &String) -> io::Result<()> {
+        let mut quoted = serde_json::to_string(string)?;
+        if self.config.ascii_output {
+            quoted = escape_non_ascii(&quoted);
+        }
+        self.paint(color, &quoted)
+    }
+
+    /// Starts a new line at `depth` levels of indentation, unless the output
+    /// is compact.
+    fn newline(&mut self, depth: usize) -> io::Result<()> {
+        match self.config.indent {
+            Indent::Spaces(0) => Ok(()),
+            Indent::Spaces(spaces) => write!(self.output, "\n{:1$}", "", depth * spaces),
+            Indent::Tab => write!(self.output, "\n{}", "\t".repeat(depth)),
+        }
+    }
+
+    fn print_array(&mut self, values: &[Value], depth: usize) -> io::Result<()> {
+        let color: fn(&ColorConfig) -> &String = |colors| &colors.array;
+        if values.is_empty() {
+            return self.paint(color, "[]");
+        }
+        self.paint(color, "[")?;
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                self.paint(color, ",")?;
+            }
+            self.newline(depth + 1)?;
+            self.print_value(value, depth + 1)?;
+        }
+        self.newline(depth)?;
+        self.paint(color, "]")
+    }
+
+    fn print_object(&mut self, entries: &[(&String, &Value)], depth: usize) -> io::Result<()> {
+        let color: fn(&ColorConfig) -> &String = |colors| &colors.object;
+        if entries.is_empty() {
+            return self.paint(color, "{}");
+        }
+        self.paint(color, "{")?;
+        for (index, (key, value)) in entries.iter().enumerate() {
+            if index > 0 {
+                self.paint(color, ",")?;
+            }
+            self.newline(depth + 1)?;
+            self.print_string(key, |colors| &colors.object_key)?;
+            self.paint(color, ":")?;
+            if !self.config.indent.is_compact() {
+                write!(self.output, " ")?;
+            }
+            self.print_value(value, depth + 1)?;
+        }
+        self.newline(depth)?;
+        self.paint(color, "}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{print_result, ColorConfig, ColorError, Indent, OutputConfig};
+
+    fn print(value: &Value, config: &OutputConfig) -> String {
+        let mut output = Vec::new();
+        print_result(&mut output, value, config).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn monochrome(spaces: usize) -> OutputConfig {
+        OutputConfig {
+            colors: None,
+            sort_keys: false,
+            indent: Indent::Spaces(spaces),
+            raw_output: false,
+            ascii_output: false,
+            seq: false,
+            yaml: false,
+        }
+    }
+
+    fn all_types() -> Value {
+        json!({
+            "fizz": "buzz",
+            "baz": null,
+            "fuzz": true,
+            "bizz": 22.0,
+            "biz": 42,
+            "fizzes": ["buzz", null, true, 22.0, 42.0]
+        })
+    }
+
+    #[test]
+    fn test_pretty() {
+        assert_eq!(
+            print(&all_types(), &monochrome(2)),
+            r#"{
+  "fizz": "buzz",
+  "baz": null,
+  "fuzz": true,
+  "bizz": 22.0,
+  "biz": 42,
+  "fizzes": [
+    "buzz",
+    null,
+    true,
+    22.0,
+    42.0
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_scalars_and_empty_containers() {
+        assert_eq!(print(&json!("buzz"), &monochrome(2)), "\"buzz\"\n");
+        assert_eq!(print(&json!("a\"b\n"), &monochrome(2)), "\"a\\\"b\\n\"\n");
+        assert_eq!(print(&Value::Null, &monochrome(2)), "null\n");
+        assert_eq!(
+            print(&json!({"a": [], "b": {}}), &monochrome(2)),
+            "{\n  \"a\": [],\n  \"b\": {}\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        assert_eq!(
+            print(&json!({"a": [1]}), &monochrome(7)),
+            "{\n       \"a\": [\n              1\n       ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_tab() {
+        let config = OutputConfig {
+            indent: Indent::Tab,
+            ..monochrome(2)
+        };
+        assert_eq!(
+            print(&json!({"a": [1, {"b": null}], "c": {}}), &config),
+            "{\n\t\"a\": [\n\t\t1,\n\t\t{\n\t\t\t\"b\": null\n\t\t}\n\t],\n\t\"c\": {}\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(
+            print(&all_types(), &monochrome(0)),
+            "{\"fizz\":\"buzz\",\"baz\":null,\"fuzz\":true,\"bizz\":22.0,\"biz\":42,\"fizzes\":[\"buzz\",null,true,22.0,42.0]}\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let config = OutputConfig {
+            sort_keys: true,
+            ..monochrome(0)
+        };
+        assert_eq!(
+            print(
+                &json!({"b": {"d": 1, "c": 2}, "a": [{"z": 0, "y": 0}]}),
+                &config
+            ),
+            "{\"a\":[{\"y\":0,\"z\":0}],\"b\":{\"c\":2,\"d\":1}}\n"
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        let config = OutputConfig {
+            indent: Indent::Spaces(0),
+            ..OutputConfig::default()
+        };
+        assert_eq!(
+            print(&json!({"a": [null, "b"]}), &config),
+            "\x1b[1;37m{\x1b[0m\x1b[1;34m\"a\"\x1b[0m\x1b[1;37m:\x1b[0m\
+             \x1b[1;37m[\x1b[0m\x1b[0;90mnull\x1b[0m\x1b[1;37m,\x1b[0m\x1b[0;32m\"b\"\x1b[0m\x1b[1;37m]\x1b[0m\
+             \x1b[1;37m}\x1b[0m\n"
+        );
+        assert_eq!(
+            print(&json!([true, false, 1]), &config),
+            "\x1b[1;37m[\x1b[0m\x1b[0;37mtrue\x1b[0m\x1b[1;37m,\x1b[0m\x1b[0;37mfalse\x1b[0m\
+             \x1b[1;37m,\x1b[0m\x1b[0;37m1\x1b[0m\x1b[1;37m]\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let (colors, errors) = ColorConfig::parse("0;31:0;32");
+        assert_eq!(colors.null, "0;31");
+        assert_eq!(colors.false_, "0;32");
+        // Everything else keeps its default
+        assert_eq!(colors.object_key, "1;34");
+        assert!(errors.is_empty());
+        assert_eq!(ColorConfig::parse(""), (ColorConfig::default(), vec![]));
+        assert_eq!(
+            ColorConfig::parse("::::::4;31:"),
+            (
+                ColorConfig {
+                    object: String::from("4;31"),
+                    ..ColorConfig::default()
+                },
+                vec![]
+            )
+        );
+        assert_eq!(
+            ColorConfig::parse("1"),
+            (
+                ColorConfig {
+                    null: String::from("1"),
+                    ..ColorConfig::default()
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_colors() {
+        // Only the invalid entries fall back to their defaults
+        assert_eq!(
+            ColorConfig::parse("1;31:x;y::::::1;3x"),
+            (
+                ColorConfig {
+                    null: String::from("1;31"),
+                    ..ColorConfig::default()
+                },
+                vec![
+                    ColorError::InvalidColor {
+                        name: "false",
+                        code: String::from("x;y")
+                    },
+                    ColorError::InvalidColor {
+                        name: "object keys",
+                        code: String::from("1;3x")
+                    }
+                ]
+            )
+        );
+        for code in ["red", "1;", ";1", "256", "+1", "\u{fffd}"] {
+            let (colors, errors) = ColorConfig::parse(code);
+            assert_eq!(colors, ColorConfig::default(), "{:?}", code);
+            assert_eq!(errors.len(), 1, "{:?}", code);
+        }
+        let (colors, errors) = ColorConfig::parse("1:1:1:1:1:1:1:1:1");
+        assert_eq!(colors.object_key, "1");
+        assert_eq!(errors, [ColorError::TooManyColors(9)]);
+        assert_eq!(
+            errors[0].to_string(),
+            "9 colors were given, but there are only 8"
+        );
+        assert_eq!(
+            ColorConfig::parse("1;3x").1[0].to_string(),
+            "invalid color \"1;3x\" for null"
+        );
+    }
+
+    #[test]
+    fn test_raw_output() {
+        let config = OutputConfig {
+            raw_output: true,
+            ..OutputConfig::default()
+        };
+        assert_eq!(
+            print(&json!("a \"quoted\"\tword"), &config),
+            "a \"quoted\"\tword\n"
+        );
+        assert_eq!(print(&json!("héllo\nworld"), &config), "héllo\nworld\n");
+        // Only top-level strings are raw
+        assert_eq!(
+            print(
+                &json!(["a"]),
+                &OutputConfig {
+                    raw_output: true,
+                    ..monochrome(0)
+                }
+            ),
+            "[\"a\"]\n"
+        );
+        assert_eq!(print(&json!(1), &config), "\x1b[0;37m1\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_ascii_output() {
+        let config = OutputConfig {
+            ascii_output: true,
+            ..monochrome(0)
+        };
+        assert_eq!(
+            print(&json!({"clé": ["héllo", "日本", "😀", "plain\n"]}), &config),
+            "{\"cl\\u00e9\":[\"h\\u00e9llo\",\"\\u65e5\\u672c\",\"\\ud83d\\ude00\",\"plain\\n\"]}\n"
+        );
+
+        let raw = OutputConfig {
+            raw_output: true,
+            ..config
+        };
+        assert_eq!(print(&json!("héllo"), &raw), "h\\u00e9llo\n");
+    }
+
+    #[test]
+    fn test_seq() {
+        let config = OutputConfig {
+            seq: true,
+            ..monochrome(2)
+        };
+        assert_eq!(print(&json!([1]), &config), "\x1e[\n  1\n]\n");
+        assert_eq!(
+            print(
+                &json!("a"),
+                &OutputConfig {
+                    raw_output: true,
+                    ..config
+                }
+            ),
+            "\x1ea\n"
+        );
+    }
+
+    #[test]
+    fn test_yaml() {
+        let config = OutputConfig {
+            yaml: true,
+            ..monochrome(2)
+        };
+        let value = json!({"b": [1, "x", null], "a": {"d": true, "c": 1.5}});
+        assert_eq!(
+            print(&value, &config),
+            "---\nb:\n- 1\n- x\n- null\na:\n  d: true\n  c: 1.5\n"
+        );
+        let sorted = OutputConfig {
+            sort_keys: true,
+            ..config.clone()
+        };
+        assert_eq!(
+            print(&value, &sorted),
+            "---\na:\n  c: 1.5\n  d: true\nb:\n- 1\n- x\n- null\n"
+        );
+        assert_eq!(print(&json!("a: b"), &config), "---\n'a: b'\n");
+        let raw = OutputConfig {
+            raw_output: true,
+            ..config
+        };
+        assert_eq!(print(&json!("a: b"), &raw), "---\na: b\n");
+    }
+}